@@ -1,5 +1,7 @@
 #!/bin/sh
 
+# the shapes and materials are copied to the device as they are, so they have to stay DeviceCopy
+cargo build -p raytracer_lib_no_std --features cuda &&
 cd cuda_kernel_raytracer
 cargo build &&
 cd .. &&
@@ -5,28 +5,36 @@ use raytracer_lib_no_std::basics::ray::{Ray, RayOps};
 use raytracer_lib_no_std::light::light::LightOps;
 use raytracer_lib_no_std::material::material::{Material, MaterialOps};
 use raytracer_lib_no_std::shape::shape::Shape;
-use raytracer_lib_no_std::{assert_valid_color, ColorOps, Light, ShapeOps, DEBUG};
+use raytracer_lib_no_std::{
+    is_valid_color, ColorOps, Light, RenderSettings, RenderSettingsOps, RenderStats, RenderStatsOps, ShapeOps, DEBUG,
+};
 
 use crate::cpu::intersection::Intersection;
 use crate::cpu::intersection::IntersectionOps;
-use crate::cpu::intersection_list::{IntersectionList, IntersectionListOps};
+use crate::cpu::intersection_list::IntersectionListOps;
 
 pub struct CpuKernel {}
 
 impl CpuKernel {
     pub fn color_at(
         shapes: &Vec<Shape>,
+        samples: &Vec<f32>,
         lights: &Vec<Light>,
         r: &Ray,
         remaining: i32,
-        calc_reflection: bool,
-        calc_refraction: bool,
-        calc_shadows: bool,
+        settings: &RenderSettings,
+        stats: &mut RenderStats,
         is_debug_render: bool,
     ) -> Color {
-        let mut color = BLACK;
+        let mut color = *settings.get_background();
 
-        let xs = Intersection::intersect_world(shapes, r);
+        stats.record_depth(settings.get_max_depth() - remaining);
+        // walks all shapes, so only when the statistics are shown
+        if settings.get_statistics() {
+            stats.count_intersection_tests(shapes);
+        }
+        let xs = Intersection::intersect_world(shapes, samples, r);
+        stats.count_intersection_overflows(xs.get_overflows());
 
         let (intersection, is_hit) = xs.hit();
 
@@ -37,7 +45,8 @@ impl CpuKernel {
         }
 
         if is_hit {
-            let comp = Intersection::prepare_computations(intersection, &r, &IntersectionList::new(), shapes);
+            let comp =
+                Intersection::prepare_computations(intersection, &r, &xs, shapes, samples, settings.get_epsilon());
             if is_debug_render {
                 println!("'color_at'   comp   t                        = {:?}", comp.get_t());
                 println!(
@@ -62,78 +71,54 @@ impl CpuKernel {
                     comp.get_under_point()
                 );
             }
-            color = CpuKernel::shade_hit(
-                shapes,
-                lights,
-                &comp,
-                remaining,
-                calc_reflection,
-                calc_refraction,
-                calc_shadows,
-                is_debug_render,
-            );
+            color = CpuKernel::shade_hit(shapes, samples, lights, &comp, remaining, settings, stats, is_debug_render);
         }
         color
     }
 
     fn shade_hit(
         shapes: &Vec<Shape>,
+        samples: &Vec<f32>,
         lights: &Vec<Light>,
         comp: &PrecomputedComponent,
         remaining: i32,
-        calc_reflection: bool,
-        calc_refraction: bool,
-        calc_shadows: bool,
+        settings: &RenderSettings,
+        stats: &mut RenderStats,
         is_debug_render: bool,
     ) -> Color {
         // TODO if there is more than 1 light??? pass that to Material::lightning?
         let light = &lights[0];
 
-        let shape = &shapes[comp.get_object()];
+        let (shape, pattern_point) = CpuKernel::material_source(shapes, comp);
         let material = shape.get_material();
 
         //  let in_shadow = CpuKernel::is_shadowed(w, w.get_light().get_position(), comp.get_over_point());
-        let intensity = CpuKernel::intensity_at(shapes, lights, comp.get_over_point());
+        let intensity = CpuKernel::intensity_at(shapes, samples, lights, comp.get_over_point(), settings, stats);
 
         let surface = CpuKernel::lightning(
             material,
             shape,
             light,
             comp.get_over_point(),
+            &pattern_point,
             comp.get_eye_vector(),
             comp.get_normal_vector(),
             intensity,
-            calc_shadows,
+            settings,
             is_debug_render,
         );
 
-        assert_valid_color(&surface);
+        debug_assert!(is_valid_color(&surface), "invalid color {:?}", surface);
 
         let mut reflected = BLACK;
-        if calc_reflection {
-            reflected = CpuKernel::reflected_color(
-                shapes,
-                lights,
-                comp,
-                remaining,
-                calc_reflection,
-                calc_refraction,
-                calc_shadows,
-                is_debug_render,
-            );
+        if settings.get_calc_reflection() {
+            reflected =
+                CpuKernel::reflected_color(shapes, samples, lights, comp, remaining, settings, stats, is_debug_render);
         }
         let mut refracted = BLACK;
-        if calc_refraction {
-            refracted = CpuKernel::refracted_color(
-                shapes,
-                lights,
-                comp,
-                remaining,
-                calc_reflection,
-                calc_refraction,
-                calc_shadows,
-                is_debug_render,
-            );
+        if settings.get_calc_refraction() {
+            refracted =
+                CpuKernel::refracted_color(shapes, samples, lights, comp, remaining, settings, stats, is_debug_render);
         }
 
         if is_debug_render {
@@ -143,11 +128,11 @@ impl CpuKernel {
             println!("'shade_hit'   refracted        = {:?}", refracted);
         }
 
-        assert_valid_color(&reflected);
-        assert_valid_color(&refracted);
+        debug_assert!(is_valid_color(&reflected), "invalid color {:?}", reflected);
+        debug_assert!(is_valid_color(&refracted), "invalid color {:?}", refracted);
 
         // let material = comp.get_object().get_material();
-        if calc_reflection && material.get_reflective() > 0.0 && material.get_transparency() > 0.0 {
+        if settings.get_calc_reflection() && material.get_reflective() > 0.0 && material.get_transparency() > 0.0 {
             let reflectance = Intersection::schlick(comp);
             if is_debug_render {
                 println!(
@@ -167,7 +152,14 @@ impl CpuKernel {
         &surface + &(&reflected + &refracted)
     }
 
-    fn is_shadowed(shapes: &Vec<Shape>, light_position: &Tuple4D, position: &Tuple4D) -> bool {
+    fn is_shadowed(
+        shapes: &Vec<Shape>,
+        samples: &Vec<f32>,
+        light_position: &Tuple4D,
+        position: &Tuple4D,
+        settings: &RenderSettings,
+        stats: &mut RenderStats,
+    ) -> bool {
         let v = light_position - position;
 
         let distance = Tuple4D::magnitude(&v);
@@ -177,30 +169,53 @@ impl CpuKernel {
         let point = Tuple4D::new_point_from(&position);
         let r = Ray::new(point, direction);
 
-        let intersections = Intersection::intersect_world(shapes, &r);
+        stats.count_shadow_ray();
+        if settings.get_statistics() {
+            stats.count_intersection_tests(shapes);
+        }
+        let intersections = Intersection::intersect_world(shapes, samples, &r);
+        stats.count_intersection_overflows(intersections.get_overflows());
 
         let (intersection, is_hit) = intersections.hit();
 
         if is_hit {
             let s_idx = intersection.get_shape();
             let shape = &shapes[s_idx];
-            if intersection.get_t() - distance < EPSILON_OVER_UNDER && shape.get_casts_shadow() {
+            if intersection.get_t() - distance < settings.get_epsilon() && shape.get_casts_shadow() {
                 return true;
             }
         }
         false
     }
 
-    fn intensity_at(shapes: &Vec<Shape>, lights: &Vec<Light>, point: &Tuple4D) -> f32 {
+    fn intensity_at(
+        shapes: &Vec<Shape>,
+        samples: &Vec<f32>,
+        lights: &Vec<Light>,
+        point: &Tuple4D,
+        settings: &RenderSettings,
+        stats: &mut RenderStats,
+    ) -> f32 {
         let light = &lights[0];
         let res = match light {
-            Light::PointLight(ref _pl) => CpuKernel::intensity_at_point_light(light, point, shapes),
-            Light::AreaLight(ref _al) => CpuKernel::intensity_at_area_light(light, point, shapes),
+            Light::PointLight(ref _pl) => {
+                CpuKernel::intensity_at_point_light(light, point, shapes, samples, settings, stats)
+            }
+            Light::AreaLight(ref _al) => {
+                CpuKernel::intensity_at_area_light(light, point, shapes, samples, settings, stats)
+            }
         };
         res
     }
 
-    fn intensity_at_area_light(light: &Light, point: &Tuple4D, shapes: &Vec<Shape>) -> f32 {
+    fn intensity_at_area_light(
+        light: &Light,
+        point: &Tuple4D,
+        shapes: &Vec<Shape>,
+        samples: &Vec<f32>,
+        settings: &RenderSettings,
+        stats: &mut RenderStats,
+    ) -> f32 {
         let mut total = 0.0;
 
         if DEBUG {
@@ -209,8 +224,8 @@ impl CpuKernel {
         }
         for v in 0..light.get_vsteps() {
             for u in 0..light.get_usteps() {
-                let light_position = light.point_on_light(u, v);
-                if !CpuKernel::is_shadowed(shapes, &light_position, point) {
+                let light_position = light.point_on_light_jittered(u, v, settings.jitter(u, v, point));
+                if !CpuKernel::is_shadowed(shapes, samples, &light_position, point, settings, stats) {
                     total += 1.0;
                 }
             }
@@ -219,8 +234,15 @@ impl CpuKernel {
         total / light.get_samples() as f32
     }
 
-    fn intensity_at_point_light(light: &Light, point: &Tuple4D, shapes: &Vec<Shape>) -> f32 {
-        if CpuKernel::is_shadowed(shapes, light.get_position(), point) {
+    fn intensity_at_point_light(
+        light: &Light,
+        point: &Tuple4D,
+        shapes: &Vec<Shape>,
+        samples: &Vec<f32>,
+        settings: &RenderSettings,
+        stats: &mut RenderStats,
+    ) -> f32 {
+        if CpuKernel::is_shadowed(shapes, samples, light.get_position(), point, settings, stats) {
             return 0.0;
         }
         1.0
@@ -228,18 +250,18 @@ impl CpuKernel {
 
     fn reflected_color(
         shapes: &Vec<Shape>,
+        samples: &Vec<f32>,
         lights: &Vec<Light>,
         comp: &PrecomputedComponent,
         remaining: i32,
-        calc_reflection: bool,
-        calc_refraction: bool,
-        calc_shadows: bool,
+        settings: &RenderSettings,
+        stats: &mut RenderStats,
         is_debug_render: bool,
     ) -> Color {
         if remaining <= 0 {
             return BLACK;
         }
-        let material = shapes[comp.get_object()].get_material();
+        let material = CpuKernel::material_source(shapes, comp).0.get_material();
         if material.get_reflective() == 0.0 {
             return BLACK;
         }
@@ -247,14 +269,15 @@ impl CpuKernel {
             Tuple4D::new_point_from(comp.get_over_point()),
             Tuple4D::new_vector_from(comp.get_reflected_vector()),
         );
+        stats.count_reflection_ray();
         let color = CpuKernel::color_at(
             shapes,
+            samples,
             lights,
             &reflect_ray,
             remaining - 1,
-            calc_reflection,
-            calc_refraction,
-            calc_shadows,
+            settings,
+            stats,
             is_debug_render,
         );
         &color * material.get_reflective()
@@ -262,18 +285,18 @@ impl CpuKernel {
 
     fn refracted_color(
         shapes: &Vec<Shape>,
+        samples: &Vec<f32>,
         lights: &Vec<Light>,
         comp: &PrecomputedComponent,
         remaining: i32,
-        calc_reflection: bool,
-        calc_refraction: bool,
-        calc_shadows: bool,
+        settings: &RenderSettings,
+        stats: &mut RenderStats,
         is_debug_render: bool,
     ) -> Color {
         if remaining <= 0 {
             return BLACK;
         }
-        let material = shapes[comp.get_object()].get_material();
+        let material = CpuKernel::material_source(shapes, comp).0.get_material();
         if material.get_transparency() == 0.0 {
             return BLACK;
         }
@@ -291,33 +314,41 @@ impl CpuKernel {
         direction.w = 0.0;
         let refracted_ray = Ray::new(Tuple4D::new_point_from(comp.get_under_point()), direction);
 
+        stats.count_refraction_ray();
         CpuKernel::color_at(
             shapes,
+            samples,
             lights,
             &refracted_ray,
             remaining - 1,
-            calc_reflection,
-            calc_refraction,
-            calc_shadows,
+            settings,
+            stats,
             is_debug_render,
         ) * material.get_transparency()
     }
 
+    // the shape with the material of the hit and the point to evaluate its pattern at
+    fn material_source<'a>(shapes: &'a Vec<Shape>, comp: &PrecomputedComponent) -> (&'a Shape, Tuple4D) {
+        let prototype = comp.get_prototype().map(|p| &shapes[p]);
+        shapes[comp.get_object()].material_source(prototype, comp.get_over_point())
+    }
+
     fn lightning(
         material: &Material,
         shape: &Shape,
         light: &Light,
         point: &Tuple4D,
+        pattern_point: &Tuple4D,
         eye: &Tuple4D,
         n: &Tuple4D,
         intensity: f32,
-        calc_shadows: bool,
+        settings: &RenderSettings,
         is_debug_render: bool,
     ) -> Color {
         let c: Color;
         // TODO: a lot of color copying here ...
         if material.get_pattern().is_some() {
-            c = material.get_pattern().as_ref().unwrap().color_at_object(shape, point);
+            c = material.get_pattern().as_ref().unwrap().color_at_object(shape, pattern_point);
         } else {
             c = Color::from_color(&material.get_color());
         }
@@ -335,7 +366,7 @@ impl CpuKernel {
             println!("'lightning'           ambient             = {:?} ", ambient);
         }
 
-        if !calc_shadows {
+        if !settings.get_calc_shadows() {
             return ambient;
         }
 
@@ -346,7 +377,7 @@ impl CpuKernel {
 
         for v in 0..light.get_vsteps() {
             for u in 0..light.get_usteps() {
-                samples.push(light.point_on_light(u, v));
+                samples.push(light.point_on_light_jittered(u, v, settings.jitter(u, v, point)));
             }
         }
 
@@ -371,7 +402,7 @@ impl CpuKernel {
                 diffuse = BLACK;
             } else {
                 diffuse = &effective_color * material.get_diffuse() * light_dot_normal;
-                assert_valid_color(&diffuse);
+                debug_assert!(is_valid_color(&diffuse), "invalid color {:?}", diffuse);
                 // diffuse.fix_nan();
                 let reflect_v = Tuple4D::reflect(&(light_v * (-1.0)), &n);
                 let reflect_dot_eye = &reflect_v ^ eye;
@@ -381,12 +412,12 @@ impl CpuKernel {
                     let factor = reflect_dot_eye.powf(material.get_shininess());
                     specular = light.get_intensity() * material.get_specular() * factor;
 
-                    assert_valid_color(&specular);
+                    debug_assert!(is_valid_color(&specular), "invalid color {:?}", specular);
                     //specular.fix_nan();
                 }
             }
-            assert_valid_color(&diffuse);
-            assert_valid_color(&specular);
+            debug_assert!(is_valid_color(&diffuse), "invalid color {:?}", diffuse);
+            debug_assert!(is_valid_color(&specular), "invalid color {:?}", specular);
             if is_debug_render {
                 println!("'lightning'           diffuse     = {:?} ", diffuse);
                 println!("'lightning'           specular     = {:?} ", specular);
@@ -396,8 +427,8 @@ impl CpuKernel {
             sum = &sum + &specular;
         }
 
-        assert_valid_color(&ambient);
-        assert_valid_color(&sum);
+        debug_assert!(is_valid_color(&ambient), "invalid color {:?}", ambient);
+        debug_assert!(is_valid_color(&sum), "invalid color {:?}", sum);
         // sum.replace_inf_with_max();
 
         if intensity == 1.0 {
@@ -413,3 +444,62 @@ impl CpuKernel {
         CpuKernel {}
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::SQRT_2;
+
+    use super::*;
+    use raytracer_lib_no_std::{assert_color, MatrixOps, Plane, PointLight, ShapeEnum, Sphere};
+
+    // page 92
+    fn default_world() -> (Vec<Shape>, Vec<Light>) {
+        let mut s1 = Sphere::new();
+        s1.get_material_mut().set_color(Color::new(0.8, 1.0, 0.6));
+        s1.get_material_mut().set_diffuse(0.7);
+        s1.get_material_mut().set_specular(0.2);
+
+        let mut s2 = Sphere::new();
+        s2.set_transformation(Matrix::scale(0.5, 0.5, 0.5));
+
+        let shapes = vec![Shape::new(ShapeEnum::Sphere(s1)), Shape::new(ShapeEnum::Sphere(s2))];
+        let pl = PointLight::new(Tuple4D::new_point(-10.0, 10.0, -10.0), Color::new(1.0, 1.0, 1.0));
+        (shapes, vec![Light::PointLight(pl)])
+    }
+
+    // page 158, but through color_at: the refractive indices come from the intersections of the ray.
+    // with an empty intersection list n1 and n2 were 0 and the refracted color NaN
+    #[test]
+    fn test_color_at_transparent_material() {
+        let (mut shapes, lights) = default_world();
+
+        let mut floor = Plane::new();
+        floor.set_transformation(Matrix::translation(0.0, -1.0, 0.0));
+        floor.get_material_mut().set_transparency(0.5);
+        floor.get_material_mut().set_refractive_index(1.5);
+        shapes.push(Shape::new(ShapeEnum::Plane(floor)));
+
+        let mut ball = Sphere::new();
+        ball.get_material_mut().set_color(Color::new(1.0, 0.0, 0.0));
+        ball.get_material_mut().set_ambient(0.5);
+        ball.set_transformation(Matrix::translation(0.0, -3.5, -0.5));
+        shapes.push(Shape::new(ShapeEnum::Sphere(ball)));
+
+        let r = Ray::new(
+            Tuple4D::new_point(0.0, 0.0, -3.0),
+            Tuple4D::new_vector(0.0, -SQRT_2 / 2.0, SQRT_2 / 2.0),
+        );
+        let c = CpuKernel::color_at(
+            &shapes,
+            &Vec::new(),
+            &lights,
+            &r,
+            5,
+            &RenderSettings::new(),
+            &mut RenderStats::new(),
+            false,
+        );
+        // the book has (0.93642, 0.68642, 0.68642), EPSILON_OVER_UNDER is larger than the epsilon of the book
+        assert_color(&c, &Color::new(0.936272, 0.686272, 0.686272));
+    }
+}
@@ -10,24 +10,31 @@ use math::prelude::*;
 pub struct Intersection {
     t: f32,
     shape_idx: usize,
+    // the prototype which was hit, if shape_idx is an instance
+    prototype_idx: Option<usize>,
 }
 
 pub trait IntersectionOps {
     fn new(t: f32, shape_idx: usize) -> Intersection;
+    fn new_prototype(t: f32, shape_idx: usize, prototype_idx: usize) -> Intersection;
     fn new_empty() -> Intersection;
 
-    fn intersect(shape_idx: usize, r: &Ray, shapes: &Vec<Shape>) -> IntersectionList;
-    fn intersect_world(shapes: &Vec<Shape>, r: &Ray) -> IntersectionList;
+    // samples is the sample buffer of the world, see World::add_samples
+    fn intersect(shape_idx: usize, r: &Ray, shapes: &Vec<Shape>, samples: &Vec<f32>) -> IntersectionList;
+    fn intersect_world(shapes: &Vec<Shape>, samples: &Vec<f32>, r: &Ray) -> IntersectionList;
 
     fn prepare_computations(
         intersection: &Intersection,
         r: &Ray,
         list: &IntersectionList,
         shapes: &Vec<Shape>,
+        samples: &Vec<f32>,
+        epsilon: f32,
     ) -> PrecomputedComponent;
 
     fn get_t(&self) -> f32;
     fn get_shape(&self) -> usize;
+    fn get_prototype(&self) -> Option<usize>;
     fn schlick(comp: &PrecomputedComponent) -> f32;
 }
 
@@ -36,6 +43,15 @@ impl IntersectionOps for Intersection {
         Intersection {
             t,
             shape_idx: shape_idx,
+            prototype_idx: None,
+        }
+    }
+
+    fn new_prototype(t: f32, shape_idx: usize, prototype_idx: usize) -> Intersection {
+        Intersection {
+            t,
+            shape_idx,
+            prototype_idx: Some(prototype_idx),
         }
     }
 
@@ -43,20 +59,38 @@ impl IntersectionOps for Intersection {
         Intersection {
             t: -99999.9,
             shape_idx: 0,
+            prototype_idx: None,
         }
     }
 
-    fn intersect(shape_idx: usize, r: &Ray, shapes: &Vec<Shape>) -> IntersectionList {
+    fn intersect(shape_idx: usize, r: &Ray, shapes: &Vec<Shape>, samples: &Vec<f32>) -> IntersectionList {
         let mut intersection_list = IntersectionList::new();
         let shape = &shapes[shape_idx];
         let r2 = Ray::transform(r, shape.get_inverse_transformation());
 
+        if let ShapeEnum::Instance(ref instance) = *shape.get_shape() {
+            let first = instance.get_first_prototype();
+            for prototype_idx in first..first + instance.get_prototype_cnt() {
+                let (res, res_cnt) = instance.intersect_prototype(&shapes[prototype_idx], samples, &r2);
+                for t in res[..res_cnt].iter() {
+                    intersection_list.push(Intersection::new_prototype(*t, shape_idx, prototype_idx));
+                }
+            }
+            return intersection_list;
+        }
+
         let (res, res_cnt) = match *shape.get_shape() {
             ShapeEnum::Sphere(ref sphere) => sphere.intersect(&r2),
             ShapeEnum::Plane(ref plane) => plane.intersect(&r2),
             ShapeEnum::Cube(ref cube) => cube.intersect(&r2),
             ShapeEnum::Cylinder(ref cylinder) => cylinder.intersect(&r2),
             ShapeEnum::Triangle(ref triangle) => triangle.intersect(&r2),
+            ShapeEnum::Torus(ref torus) => torus.intersect(&r2),
+            ShapeEnum::Disk(ref disk) => disk.intersect(&r2),
+            ShapeEnum::Rectangle(ref rectangle) => rectangle.intersect(&r2),
+            ShapeEnum::Heightfield(ref heightfield) => heightfield.intersect_samples(samples, &r2),
+            ShapeEnum::Sdf(ref sdf) => sdf.intersect(&r2),
+            ShapeEnum::Instance(_) => unreachable!(),
             // ShapeEnum::Group(ref group) =>
             // let res = Cylinder::intersect(cylinder, &r2);
             //  }
@@ -68,15 +102,16 @@ impl IntersectionOps for Intersection {
         intersection_list
     }
 
-    fn intersect_world(shapes: &Vec<Shape>, r: &Ray) -> IntersectionList {
+    fn intersect_world(shapes: &Vec<Shape>, samples: &Vec<f32>, r: &Ray) -> IntersectionList {
         let mut res = IntersectionList::new();
         for i in 0..shapes.len() {
-            let tmp = Intersection::intersect(i, r, shapes);
-            for idx in 0..tmp.len() {
-                // TODO: something like a drain would be awesome and avoid copying
-                // we want to move all intersections from tmp to res ...
-                res.push(tmp.at(idx));
+            if shapes[i].get_is_prototype() {
+                continue;
             }
+            let tmp = Intersection::intersect(i, r, shapes, samples);
+            // TODO: something like a drain would be awesome and avoid copying
+            // we want to move all intersections from tmp to res ...
+            res.append(&tmp);
         }
         res.sort_intersections();
         res
@@ -87,21 +122,30 @@ impl IntersectionOps for Intersection {
         r: &Ray,
         list: &IntersectionList,
         shapes: &Vec<Shape>,
+        samples: &Vec<f32>,
+        epsilon: f32,
     ) -> PrecomputedComponent {
         let point = Ray::position(r, intersection.get_t());
         let shape = &shapes[intersection.get_shape()];
-        let mut normal_vector = shape.normal_at(&point);
+        let prototype = intersection.get_prototype().map(|p| &shapes[p]);
+        let mut normal_vector = match (shape.get_shape(), prototype) {
+            (ShapeEnum::Instance(ref instance), Some(p)) => instance.normal_at_prototype(p, samples, &point),
+            _ => shape.normal_at_samples(samples, &point),
+        };
+        // shading and the secondary rays use the perturbed normal, over_point and under_point the geometric one
+        let mut shading_normal = shape.perturbed_normal(prototype, samples, &point, &normal_vector);
         let eye_vector = r.get_direction() * (-1.0);
         let mut inside = true;
         if (&normal_vector ^ &eye_vector) < 0.0 {
             normal_vector = normal_vector * (-1.0);
+            shading_normal = shading_normal * (-1.0);
         } else {
             inside = false;
         }
-        let reflected_vector = Tuple4D::reflect(r.get_direction(), &normal_vector);
+        let reflected_vector = Tuple4D::reflect(r.get_direction(), &shading_normal);
 
-        let over_point = &point + &(&normal_vector * EPSILON_OVER_UNDER);
-        let under_point = &point - &(&normal_vector * EPSILON_OVER_UNDER);
+        let over_point = &point + &(&normal_vector * epsilon);
+        let under_point = &point - &(&normal_vector * epsilon);
 
         let mut comp = PrecomputedComponent::new(
             intersection.get_t(),
@@ -110,10 +154,11 @@ impl IntersectionOps for Intersection {
             over_point,
             under_point,
             eye_vector,
-            normal_vector,
+            shading_normal,
             reflected_vector,
             inside,
         );
+        comp.set_prototype(intersection.get_prototype());
 
         let mut container: Vec<&Shape> = Vec::new();
 
@@ -164,6 +209,10 @@ impl IntersectionOps for Intersection {
         self.shape_idx
     }
 
+    fn get_prototype(&self) -> Option<usize> {
+        self.prototype_idx
+    }
+
     fn schlick(comp: &PrecomputedComponent) -> f32 {
         let mut cos = comp.get_eye_vector() ^ comp.get_normal_vector();
         if comp.get_n1() > comp.get_n2() {
@@ -182,16 +231,19 @@ impl IntersectionOps for Intersection {
 
 impl<'a> PartialEq for Intersection {
     fn eq(&self, other: &Self) -> bool {
-        self.shape_idx == other.shape_idx && self.t == other.t
+        self.shape_idx == other.shape_idx && self.prototype_idx == other.prototype_idx && self.t == other.t
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::f32::consts::SQRT_2;
+    use std::f32::consts::{PI, SQRT_2};
 
     use super::*;
-    use raytracer_lib_no_std::{assert_float, assert_tuple, ColorOps, MatrixOps, Sphere};
+    use crate::cpu::intersection_list::MAX_INTERSECTIONLIST_LEN;
+    use raytracer_lib_no_std::{
+        assert_float, assert_tuple, ColorOps, Instance, MaterialOps, MatrixOps, NormalPerturbation, Plane, Sphere,
+    };
 
     // helper
     // page 151
@@ -338,7 +390,7 @@ mod tests {
 
         let shapes = vec![o];
 
-        let i = Intersection::intersect(0, &r, &shapes);
+        let i = Intersection::intersect(0, &r, &shapes, &Vec::new());
         assert_eq!(i.len(), 2);
     }
 
@@ -356,7 +408,14 @@ mod tests {
 
         let shapes = vec![o];
 
-        let c = Intersection::prepare_computations(&i, &r, &IntersectionList::new(), &shapes);
+        let c = Intersection::prepare_computations(
+            &i,
+            &r,
+            &IntersectionList::new(),
+            &shapes,
+            &Vec::new(),
+            EPSILON_OVER_UNDER,
+        );
 
         let point_expected = Tuple4D::new_point(0.0, 0., -1.0);
         let eye_vector_expected = Tuple4D::new_vector(0.0, 0., -1.0);
@@ -381,11 +440,96 @@ mod tests {
 
         let shapes = vec![o];
 
-        let c = Intersection::prepare_computations(&i, &r, &IntersectionList::new(), &shapes);
+        let c = Intersection::prepare_computations(
+            &i,
+            &r,
+            &IntersectionList::new(),
+            &shapes,
+            &Vec::new(),
+            EPSILON_OVER_UNDER,
+        );
 
         assert_eq!(false, c.get_inside());
     }
 
+    #[test]
+    fn test_intersect_instance() {
+        let mut prototype = Shape::new(ShapeEnum::Sphere(Sphere::new()));
+        prototype.set_is_prototype(true);
+        let mut shapes = vec![prototype];
+        let mut instance = Instance::try_new(&shapes, 0, 1).unwrap();
+        instance.set_transformation(Matrix::translation(5.0, 0.0, 0.0));
+        shapes.push(Shape::new(ShapeEnum::Instance(instance)));
+
+        // the prototype itself is not hit
+        let r = Ray::new(Tuple4D::new_point(0.0, 0.0, -5.0), Tuple4D::new_vector(0.0, 0.0, 1.0));
+        assert_eq!(Intersection::intersect_world(&shapes, &Vec::new(), &r).len(), 0);
+
+        let r = Ray::new(Tuple4D::new_point(5.0, 0.0, -5.0), Tuple4D::new_vector(0.0, 0.0, 1.0));
+        let xs = Intersection::intersect_world(&shapes, &Vec::new(), &r);
+        assert_eq!(xs.len(), 2);
+        assert_float(xs.at(0).get_t(), 4.0);
+        assert_eq!(xs.at(0).get_shape(), 1);
+        assert_eq!(xs.at(0).get_prototype(), Some(0));
+
+        let c = Intersection::prepare_computations(&xs.at(0), &r, &xs, &shapes, &Vec::new(), EPSILON_OVER_UNDER);
+        assert_tuple(c.get_point(), &Tuple4D::new_point(5.0, 0.0, -1.0));
+        assert_tuple(c.get_normal_vector(), &Tuple4D::new_vector(0.0, 0.0, -1.0));
+        assert_eq!(c.get_prototype(), Some(0));
+    }
+
+    #[test]
+    fn test_intersect_world_counts_overflows() {
+        // every sphere is hit twice, the farthest sphere doesn't fit into the list
+        let cnt = MAX_INTERSECTIONLIST_LEN / 2 + 1;
+        let shapes: Vec<Shape> = (0..cnt)
+            .map(|i| {
+                let mut s = Sphere::new();
+                s.set_transformation(Matrix::translation(0.0, 0.0, (cnt - 1 - i) as f32 * 3.0));
+                Shape::new(ShapeEnum::Sphere(s))
+            })
+            .collect();
+
+        let r = Ray::new(Tuple4D::new_point(0.0, 0.0, -5.0), Tuple4D::new_vector(0.0, 0.0, 1.0));
+        let xs = Intersection::intersect_world(&shapes, &Vec::new(), &r);
+        assert_eq!(xs.len(), MAX_INTERSECTIONLIST_LEN);
+        assert_eq!(xs.get_overflows(), 2);
+        assert_float(xs.at(0).get_t(), 4.0);
+        assert_eq!(xs.at(0).get_shape(), cnt - 1);
+        assert!(xs.at(MAX_INTERSECTIONLIST_LEN - 1).get_shape() > 0);
+    }
+
+    #[test]
+    fn test_precomputations_perturbed_normal() {
+        let mut plane = Shape::new(ShapeEnum::Plane(Plane::new()));
+        plane.set_transformation(Matrix::translation(1.0, -1.0, 0.0));
+        plane.get_material_mut().set_normal_perturbation(NormalPerturbation::Waves {
+            amplitude: 0.5,
+            frequency: 1.0,
+        });
+        let shapes = vec![plane];
+
+        let r = Ray::new(Tuple4D::new_point(1.0, 0.0, PI / 2.0), Tuple4D::new_vector(0.0, -1.0, 0.0));
+        let i = Intersection::new(1.0, 0);
+        let c = Intersection::prepare_computations(
+            &i,
+            &r,
+            &IntersectionList::new(),
+            &shapes,
+            &Vec::new(),
+            EPSILON_OVER_UNDER,
+        );
+
+        let n = Tuple4D::normalize(&Tuple4D::new_vector(-0.5, 1.0, 0.0));
+        assert_tuple(c.get_normal_vector(), &n);
+        assert_tuple(c.get_reflected_vector(), &Tuple4D::reflect(r.get_direction(), &n));
+        // the offset points use the geometric normal
+        assert_tuple(
+            c.get_over_point(),
+            &Tuple4D::new_point(1.0, -1.0 + EPSILON_OVER_UNDER, PI / 2.0),
+        );
+    }
+
     // page 95 top
     #[test]
     fn test_precomputations_hit_inside() {
@@ -399,7 +543,14 @@ mod tests {
 
         let shapes = vec![o];
 
-        let c = Intersection::prepare_computations(&i, &r, &IntersectionList::new(), &shapes);
+        let c = Intersection::prepare_computations(
+            &i,
+            &r,
+            &IntersectionList::new(),
+            &shapes,
+            &Vec::new(),
+            EPSILON_OVER_UNDER,
+        );
 
         let point_expected = Tuple4D::new_point(0.0, 0.0, 1.0);
         let eye_vector_expected = Tuple4D::new_vector(0.0, 0., -1.0);
@@ -429,7 +580,14 @@ mod tests {
 
         let shapes = vec![sphere];
 
-        let c = Intersection::prepare_computations(&xs.get_intersections()[1], &r, &xs, &shapes);
+        let c = Intersection::prepare_computations(
+            &xs.get_intersections()[1],
+            &r,
+            &xs,
+            &shapes,
+            &Vec::new(),
+            EPSILON_OVER_UNDER,
+        );
 
         let reflectance = Intersection::schlick(&c);
 
@@ -454,7 +612,14 @@ mod tests {
 
         let shapes = vec![sphere];
 
-        let c = Intersection::prepare_computations(&xs.get_intersections()[1], &r, &xs, &shapes);
+        let c = Intersection::prepare_computations(
+            &xs.get_intersections()[1],
+            &r,
+            &xs,
+            &shapes,
+            &Vec::new(),
+            EPSILON_OVER_UNDER,
+        );
 
         let reflectance = Intersection::schlick(&c);
 
@@ -477,7 +642,14 @@ mod tests {
 
         let shapes = vec![sphere];
 
-        let c = Intersection::prepare_computations(&xs.get_intersections()[0], &r, &xs, &shapes);
+        let c = Intersection::prepare_computations(
+            &xs.get_intersections()[0],
+            &r,
+            &xs,
+            &shapes,
+            &Vec::new(),
+            EPSILON_OVER_UNDER,
+        );
 
         let reflectance = Intersection::schlick(&c);
 
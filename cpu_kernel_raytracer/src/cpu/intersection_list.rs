@@ -17,11 +17,16 @@ pub struct IntersectionList {
     // idx: usize,
     len: usize,
     capacity: usize,
+    // intersections, which didn't fit into the list
+    overflows: usize,
 }
 
 pub trait IntersectionListOps {
     fn new() -> IntersectionList;
+    // a full list keeps the nearest intersections and counts the dropped ones
     fn push(&mut self, i: Intersection);
+    // pushes all intersections of other and takes over its overflows
+    fn append(&mut self, other: &IntersectionList);
 
     fn hit(&self) -> (&Intersection, bool);
 
@@ -31,6 +36,7 @@ pub trait IntersectionListOps {
     fn sort_intersections(&mut self);
 
     fn len(&self) -> usize;
+    fn get_overflows(&self) -> usize;
 
     // TODO this is always a  copy, dont know how to borrow ?!
     fn at(&self, idx: usize) -> Intersection;
@@ -43,18 +49,31 @@ impl IntersectionListOps for IntersectionList {
             //    idx: 0,
             capacity: MAX_INTERSECTIONLIST_LEN,
             len: 0,
+            overflows: 0,
         }
     }
 
     fn push(&mut self, i: Intersection) {
-        if !(self.len < self.capacity) {
-            panic!("IntersectionListOps::add  array is full. try increasing MAX_INTERSECTIONLIST_LEN");
+        if self.len == self.capacity {
+            if i.get_t() < self.list_of_intersections[self.len - 1].get_t() {
+                self.list_of_intersections[self.len - 1] = i;
+                self.sort_intersections();
+            }
+            self.overflows += 1;
+            return;
         }
         self.list_of_intersections[self.len] = i;
         self.len += 1;
         self.sort_intersections();
     }
 
+    fn append(&mut self, other: &IntersectionList) {
+        for i in other.list_of_intersections[..other.len].iter() {
+            self.push(*i);
+        }
+        self.overflows += other.overflows;
+    }
+
     fn hit(&self) -> (&Intersection, bool) {
         let mut found = false;
         let mut idx = 0;
@@ -93,6 +112,10 @@ impl IntersectionListOps for IntersectionList {
         self.len
     }
 
+    fn get_overflows(&self) -> usize {
+        self.overflows
+    }
+
     fn at(&self, idx: usize) -> Intersection {
         if !(idx < self.len) {
             panic!("IntersectionListOps::at  idx is out of range . try increasing MAX_INTERSECTIONLIST_LEN");
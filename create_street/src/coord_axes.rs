@@ -16,7 +16,7 @@ pub fn add_floor(world: &mut World) {
     let mut floor = Shape::new(ShapeEnum::Cube(floor));
     floor.set_casts_shadow(false);
 
-    world.add_shape(floor);
+    world.add_shape(floor).unwrap();
 }
 
 pub fn add_borders(world: &mut World) {
@@ -48,8 +48,8 @@ pub fn add_borders(world: &mut World) {
     let mut right_border = Shape::new(ShapeEnum::Cube(right_border));
     right_border.set_casts_shadow(false);
 
-    world.add_shape(left_border);
-    world.add_shape(right_border);
+    world.add_shape(left_border).unwrap();
+    world.add_shape(right_border).unwrap();
 }
 
 pub fn setup_world_coord_axes(width: usize, height: usize, show_axis_shperes: bool) -> (World, Camera) {
@@ -142,14 +142,14 @@ pub fn setup_world_coord_axes(width: usize, height: usize, show_axis_shperes: bo
     sphere_x.set_casts_shadow(false);
 
     let mut w = World::new();
-    w.add_shape(x_axis);
-    w.add_shape(y_axis);
-    w.add_shape(z_axis);
+    w.add_shape(x_axis).unwrap();
+    w.add_shape(y_axis).unwrap();
+    w.add_shape(z_axis).unwrap();
 
     if show_axis_shperes {
-        w.add_shape(sphere_x);
-        w.add_shape(sphere_y);
-        w.add_shape(sphere_z);
+        w.add_shape(sphere_x).unwrap();
+        w.add_shape(sphere_y).unwrap();
+        w.add_shape(sphere_z).unwrap();
     }
 
     let mut c = Camera::new(width, height, 0.6);
@@ -3,20 +3,21 @@ use raytracer::prelude::*;
 use std::f32::consts::PI;
 
 pub fn setup_world_2_walls_and_tilts(width: usize, height: usize) -> (World, Camera) {
-    // ---- CUBE -------
-    let mut c = Cube::new();
-    c.get_material_mut().set_color(Color::new(1., 0.5, 0.2));
-    c.get_material_mut().set_ambient(1.0);
-    c.get_material_mut().set_diffuse(0.0);
-    c.get_material_mut().set_specular(0.0);
-
+    // ---- BILLBOARD -------
+    let mut r = Rectangle::new();
+    r.get_material_mut().set_color(Color::new(1., 0.5, 0.2));
+    r.get_material_mut().set_ambient(1.0);
+    r.get_material_mut().set_diffuse(0.0);
+    r.get_material_mut().set_specular(0.0);
+
+    // upright, facing the camera
     let m_trans = Matrix::translation(0.0, 3.0, 40.0);
-    let m_scale = Matrix::scale(1.0, 1.0, 0.01);
-    let m = &m_trans * &m_scale;
+    let m_rot = Matrix::rotate_x(-PI / 2.0);
+    let m = &m_trans * &m_rot;
 
-    c.set_transformation(m);
-    let mut cube = Shape::new(ShapeEnum::Cube(c));
-    cube.set_casts_shadow(false);
+    r.set_transformation(m);
+    let mut billboard = Shape::new(ShapeEnum::Rectangle(r));
+    billboard.set_casts_shadow(false);
 
     // ---- PLANE -------
     let mut plane = Plane::new();
@@ -134,19 +135,19 @@ pub fn setup_world_2_walls_and_tilts(width: usize, height: usize) -> (World, Cam
     border_right_tilt_right.set_transformation(m);
 
     let mut w = World::new();
-    w.add_shape(sphere1);
-    w.add_shape(cube);
-    w.add_shape(plane);
-    w.add_shape(border_left);
-    w.add_shape(border_right);
-    w.add_shape(border_left_tilt_left);
-    w.add_shape(border_right_tilt_left);
-    w.add_shape(border_left_tilt_right);
-    w.add_shape(border_right_tilt_right);
-
-    w.add_shape(sphere2);
-    w.add_shape(sphere2_clone);
-    w.add_shape(sphere2_clone_clone);
+    w.add_shape(sphere1).unwrap();
+    w.add_shape(billboard).unwrap();
+    w.add_shape(plane).unwrap();
+    w.add_shape(border_left).unwrap();
+    w.add_shape(border_right).unwrap();
+    w.add_shape(border_left_tilt_left).unwrap();
+    w.add_shape(border_right_tilt_left).unwrap();
+    w.add_shape(border_left_tilt_right).unwrap();
+    w.add_shape(border_right_tilt_right).unwrap();
+
+    w.add_shape(sphere2).unwrap();
+    w.add_shape(sphere2_clone).unwrap();
+    w.add_shape(sphere2_clone_clone).unwrap();
 
     let mut c = Camera::new(width, height, 0.50);
     c.set_antialiasing(false);
@@ -3,12 +3,13 @@ use raytracer_lib_no_std::basics::precomputed_component::PrecomputedComponent;
 use raytracer_lib_no_std::basics::ray::{Ray, RayOps};
 use raytracer_lib_no_std::light::light::{Light, LightOps};
 use raytracer_lib_no_std::material::material::{Material, MaterialOps};
+use raytracer_lib_no_std::{RenderSettings, RenderSettingsOps};
 
 use raytracer_lib_no_std::prelude::*;
 
 use crate::cuda::intersection::Intersection;
 use crate::cuda::intersection::IntersectionOps;
-use crate::cuda::intersection_list::{IntersectionList, IntersectionListOps};
+use crate::cuda::intersection_list::IntersectionListOps;
 use crate::cuda::MAX_LIGHT_SAMPLES;
 
 pub struct CudaKernel {}
@@ -19,31 +20,39 @@ impl CudaKernel {
     pub fn color_at(
         shapes: *mut Shape,
         cnt_shapes: usize,
+        samples: *const f32,
+        cnt_samples: usize,
         lights: *const Light,
         cnt_lights: usize,
         r: &Ray,
         remaining: i32,
-        calc_reflection: bool,
-        calc_refraction: bool,
-        calc_shadows: bool,
+        settings: &RenderSettings,
     ) -> Color {
-        let mut color = BLACK;
+        let mut color = *settings.get_background();
 
-        let xs = Intersection::intersect_world(shapes, cnt_shapes, r);
+        let xs = Intersection::intersect_world(shapes, cnt_shapes, samples, cnt_samples, r);
         let (intersection, is_hit) = xs.hit();
         if is_hit {
-            let comp =
-                Intersection::prepare_computations(intersection, &r, &IntersectionList::new(), shapes, cnt_shapes);
+            let comp = Intersection::prepare_computations(
+                intersection,
+                &r,
+                &xs,
+                shapes,
+                cnt_shapes,
+                samples,
+                cnt_samples,
+                settings.get_epsilon(),
+            );
             color = CudaKernel::shade_hit(
                 shapes,
                 cnt_shapes,
+                samples,
+                cnt_samples,
                 lights,
                 cnt_lights,
                 &comp,
                 remaining,
-                calc_reflection,
-                calc_refraction,
-                calc_shadows,
+                settings,
             );
         }
         color
@@ -52,22 +61,31 @@ impl CudaKernel {
     fn shade_hit(
         shapes: *mut Shape,
         cnt_shapes: usize,
+        samples: *const f32,
+        cnt_samples: usize,
         lights: *const Light,
         cnt_lights: usize,
         comp: &PrecomputedComponent,
         remaining: i32,
-        calc_reflection: bool,
-        calc_refraction: bool,
-        calc_shadows: bool,
+        settings: &RenderSettings,
     ) -> Color {
         // TODO if there is more than 1 light??? pass that to Material::lightning?
         let light = unsafe { lights.offset(0).as_ref().unwrap() };
 
-        let shape = unsafe { shapes.offset(comp.get_object() as isize).as_ref().unwrap() };
+        let (shape, pattern_point) = CudaKernel::material_source(shapes, comp);
         let material = shape.get_material();
 
         //  let in_shadow = CudaKernel::is_shadowed(w, w.get_light().get_position(), comp.get_over_point());
-        let intensity = CudaKernel::intensity_at(shapes, cnt_shapes, lights, cnt_lights, comp.get_over_point());
+        let intensity = CudaKernel::intensity_at(
+            shapes,
+            cnt_shapes,
+            samples,
+            cnt_samples,
+            lights,
+            cnt_lights,
+            comp.get_over_point(),
+            settings,
+        );
 
         // TODO: move lightning back to material if mehtod signatures are the same
         let surface = CudaKernel::lightning(
@@ -75,38 +93,39 @@ impl CudaKernel {
             shape,
             light,
             comp.get_over_point(),
+            &pattern_point,
             comp.get_eye_vector(),
             comp.get_normal_vector(),
             intensity,
-            calc_shadows,
+            settings,
         );
         //        assert_valid_color(&surface);
         let mut reflected = BLACK;
-        if calc_reflection {
+        if settings.get_calc_reflection() {
             reflected = CudaKernel::reflected_color(
                 shapes,
                 cnt_shapes,
+                samples,
+                cnt_samples,
                 lights,
                 cnt_lights,
                 comp,
                 remaining,
-                calc_reflection,
-                calc_refraction,
-                calc_shadows,
+                settings,
             );
         }
         let mut refracted = BLACK;
-        if calc_refraction {
+        if settings.get_calc_refraction() {
             refracted = CudaKernel::refracted_color(
                 shapes,
                 cnt_shapes,
+                samples,
+                cnt_samples,
                 lights,
                 cnt_lights,
                 comp,
                 remaining,
-                calc_reflection,
-                calc_refraction,
-                calc_shadows,
+                settings,
             );
         }
 
@@ -114,14 +133,22 @@ impl CudaKernel {
         //        assert_valid_color(&refracted);
 
         // let material = comp.get_object().get_material();
-        if calc_reflection && material.get_reflective() > 0.0 && material.get_transparency() > 0.0 {
+        if settings.get_calc_reflection() && material.get_reflective() > 0.0 && material.get_transparency() > 0.0 {
             let reflectance = Intersection::schlick(comp);
             return &surface + &(&reflected * reflectance + &refracted * (1.0 - reflectance));
         }
         &surface + &(&reflected + &refracted)
     }
 
-    fn is_shadowed(shapes: *mut Shape, cnt_shapes: usize, light_position: &Tuple4D, position: &Tuple4D) -> bool {
+    fn is_shadowed(
+        shapes: *mut Shape,
+        cnt_shapes: usize,
+        samples: *const f32,
+        cnt_samples: usize,
+        light_position: &Tuple4D,
+        position: &Tuple4D,
+        epsilon: f32,
+    ) -> bool {
         let v = light_position - position;
 
         let distance = Tuple4D::magnitude(&v);
@@ -131,7 +158,7 @@ impl CudaKernel {
         let point = Tuple4D::new_point_from(&position);
         let r = Ray::new(point, direction);
 
-        let intersections = Intersection::intersect_world(shapes, cnt_shapes, &r);
+        let intersections = Intersection::intersect_world(shapes, cnt_shapes, samples, cnt_samples, &r);
 
         let (intersection, is_hit) = intersections.hit();
 
@@ -139,7 +166,7 @@ impl CudaKernel {
             let s_idx = intersection.get_shape() as isize;
             let shape = unsafe { shapes.offset(s_idx).as_ref().unwrap() };
 
-            if intersection.get_t() - distance < EPSILON_OVER_UNDER && shape.get_casts_shadow() {
+            if intersection.get_t() - distance < epsilon && shape.get_casts_shadow() {
                 return true;
             }
         }
@@ -149,25 +176,48 @@ impl CudaKernel {
     fn intensity_at(
         shapes: *mut Shape,
         cnt_shapes: usize,
+        samples: *const f32,
+        cnt_samples: usize,
         lights: *const Light,
         _cnt_lights: usize,
         point: &Tuple4D,
+        settings: &RenderSettings,
     ) -> f32 {
         let light = unsafe { lights.offset(0).as_ref().unwrap() };
         let res = match light {
-            Light::PointLight(ref _pl) => CudaKernel::intensity_at_point_light(light, point, shapes, cnt_shapes), //  LightEnum::AreaLight(ref pl) => CudaKernel::intensity_at_area_light(light, point, world),
-            Light::AreaLight(ref _al) => CudaKernel::intensity_at_area_light(light, point, shapes, cnt_shapes),
+            Light::PointLight(ref _pl) => {
+                CudaKernel::intensity_at_point_light(light, point, shapes, cnt_shapes, samples, cnt_samples, settings)
+            } //  LightEnum::AreaLight(ref pl) => CudaKernel::intensity_at_area_light(light, point, world),
+            Light::AreaLight(ref _al) => {
+                CudaKernel::intensity_at_area_light(light, point, shapes, cnt_shapes, samples, cnt_samples, settings)
+            }
         };
         res
     }
 
-    fn intensity_at_area_light(light: &Light, point: &Tuple4D, shapes: *mut Shape, cnt_shapes: usize) -> f32 {
+    fn intensity_at_area_light(
+        light: &Light,
+        point: &Tuple4D,
+        shapes: *mut Shape,
+        cnt_shapes: usize,
+        samples: *const f32,
+        cnt_samples: usize,
+        settings: &RenderSettings,
+    ) -> f32 {
         let mut total = 0.0;
 
         for v in 0..light.get_vsteps() {
             for u in 0..light.get_usteps() {
-                let light_position = light.point_on_light(u, v);
-                if !CudaKernel::is_shadowed(shapes, cnt_shapes, &light_position, point) {
+                let light_position = light.point_on_light_jittered(u, v, settings.jitter(u, v, point));
+                if !CudaKernel::is_shadowed(
+                    shapes,
+                    cnt_shapes,
+                    samples,
+                    cnt_samples,
+                    &light_position,
+                    point,
+                    settings.get_epsilon(),
+                ) {
                     total += 1.0;
                 }
             }
@@ -176,8 +226,24 @@ impl CudaKernel {
         total / light.get_samples() as f32
     }
 
-    fn intensity_at_point_light(light: &Light, point: &Tuple4D, shapes: *mut Shape, cnt_shapes: usize) -> f32 {
-        if CudaKernel::is_shadowed(shapes, cnt_shapes, light.get_position(), point) {
+    fn intensity_at_point_light(
+        light: &Light,
+        point: &Tuple4D,
+        shapes: *mut Shape,
+        cnt_shapes: usize,
+        samples: *const f32,
+        cnt_samples: usize,
+        settings: &RenderSettings,
+    ) -> f32 {
+        if CudaKernel::is_shadowed(
+            shapes,
+            cnt_shapes,
+            samples,
+            cnt_samples,
+            light.get_position(),
+            point,
+            settings.get_epsilon(),
+        ) {
             return 0.0;
         }
         1.0
@@ -186,20 +252,19 @@ impl CudaKernel {
     fn reflected_color(
         shapes: *mut Shape,
         cnt_shapes: usize,
+        samples: *const f32,
+        cnt_samples: usize,
         lights: *const Light,
         cnt_lights: usize,
         comp: &PrecomputedComponent,
         remaining: i32,
-        calc_reflection: bool,
-        calc_refraction: bool,
-        calc_shadows: bool,
+        settings: &RenderSettings,
     ) -> Color {
         if remaining <= 0 {
             return BLACK;
         }
 
-        let shape = unsafe { shapes.offset(comp.get_object() as isize).as_ref().unwrap() };
-        let material = shape.get_material();
+        let material = CudaKernel::material_source(shapes, comp).0.get_material();
 
         if material.get_reflective() == 0.0 {
             return BLACK;
@@ -211,13 +276,13 @@ impl CudaKernel {
         let color = CudaKernel::color_at(
             shapes,
             cnt_shapes,
+            samples,
+            cnt_samples,
             lights,
             cnt_lights,
             &reflect_ray,
             remaining - 1,
-            calc_reflection,
-            calc_refraction,
-            calc_shadows,
+            settings,
         );
         &color * material.get_reflective()
     }
@@ -225,19 +290,18 @@ impl CudaKernel {
     fn refracted_color(
         shapes: *mut Shape,
         cnt_shapes: usize,
+        samples: *const f32,
+        cnt_samples: usize,
         lights: *const Light,
         cnt_lights: usize,
         comp: &PrecomputedComponent,
         remaining: i32,
-        calc_reflection: bool,
-        calc_refraction: bool,
-        calc_shadows: bool,
+        settings: &RenderSettings,
     ) -> Color {
         if remaining <= 0 {
             return BLACK;
         }
-        let shape = unsafe { shapes.offset(comp.get_object() as isize).as_ref().unwrap() };
-        let material = shape.get_material();
+        let material = CudaKernel::material_source(shapes, comp).0.get_material();
         if material.get_transparency() == 0.0 {
             return BLACK;
         }
@@ -258,30 +322,40 @@ impl CudaKernel {
         CudaKernel::color_at(
             shapes,
             cnt_shapes,
+            samples,
+            cnt_samples,
             lights,
             cnt_lights,
             &refracted_ray,
             remaining - 1,
-            calc_reflection,
-            calc_refraction,
-            calc_shadows,
+            settings,
         ) * material.get_transparency()
     }
 
+    // the shape with the material of the hit and the point to evaluate its pattern at
+    fn material_source<'a>(shapes: *mut Shape, comp: &PrecomputedComponent) -> (&'a Shape, Tuple4D) {
+        let shape = unsafe { shapes.offset(comp.get_object() as isize).as_ref().unwrap() };
+        let prototype = comp
+            .get_prototype()
+            .map(|p| unsafe { shapes.offset(p as isize).as_ref().unwrap() });
+        shape.material_source(prototype, comp.get_over_point())
+    }
+
     fn lightning(
         material: &Material,
         shape: &Shape,
         light: &Light,
         point: &Tuple4D,
+        pattern_point: &Tuple4D,
         eye: &Tuple4D,
         n: &Tuple4D,
         intensity: f32,
-        calc_shadow: bool,
+        settings: &RenderSettings,
     ) -> Color {
         let c: Color;
         // TODO: a lot of color copying here ...
         if material.get_pattern().is_some() {
-            c = material.get_pattern().as_ref().unwrap().color_at_object(shape, point);
+            c = material.get_pattern().as_ref().unwrap().color_at_object(shape, pattern_point);
         } else {
             c = Color::from_color(&material.get_color());
         }
@@ -290,7 +364,7 @@ impl CudaKernel {
         let effective_color = &c * light.get_intensity();
         let ambient = &effective_color * material.get_ambient();
 
-        if !calc_shadow {
+        if !settings.get_calc_shadows() {
             return ambient;
         }
         let mut sum = BLACK;
@@ -302,7 +376,7 @@ impl CudaKernel {
         let mut i = 0;
         for v in 0..light.get_vsteps() {
             for u in 0..light.get_vsteps() {
-                samples[i] = light.point_on_light(u, v);
+                samples[i] = light.point_on_light_jittered(u, v, settings.jitter(u, v, point));
                 i += 1;
             }
         }
@@ -0,0 +1,306 @@
+use raytracer_lib_no_std::basics::camera::{Camera, CameraOps};
+use raytracer_lib_no_std::basics::color::Color;
+use raytracer_lib_no_std::light::light::Light;
+use raytracer_lib_no_std::shape::shape::Shape;
+use raytracer_lib_no_std::RenderSettings;
+
+use crate::cuda::pixel::calc_pixel_color;
+
+// runs the kernel for every pixel on the CPU, one pixel after the other like the GPU threads would.
+// returns the colors in row major order. samples is the sample buffer of the world
+pub fn render_host(
+    shapes: &mut [Shape],
+    samples: &[f32],
+    lights: &[Light],
+    c: &Camera,
+    settings: &RenderSettings,
+) -> Vec<Color> {
+    let cnt_shapes = shapes.len();
+    let shapes = shapes.as_mut_ptr();
+
+    let mut pixels = Vec::with_capacity(c.get_hsize() * c.get_vsize());
+    for y in 0..c.get_vsize() {
+        for x in 0..c.get_hsize() {
+            pixels.push(calc_pixel_color(
+                shapes,
+                cnt_shapes,
+                samples.as_ptr(),
+                samples.len(),
+                lights.as_ptr(),
+                lights.len(),
+                c,
+                settings,
+                x,
+                y,
+            ));
+        }
+    }
+    pixels
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use cpu_kernel_raytracer::CpuKernel;
+    use math::prelude::*;
+    use raytracer_lib_no_std::prelude::*;
+
+    use super::*;
+
+    fn camera(w: usize, h: usize, antialiasing: bool) -> Camera {
+        let mut c = Camera::new(w, h, PI / 3.0);
+        c.calc_pixel_size();
+        c.set_antialiasing(antialiasing);
+        c.set_antialiasing_size(2);
+        c.set_transformation(Matrix::view_transform(
+            &Tuple4D::new_point(0.0, 1.5, -5.0),
+            &Tuple4D::new_point(0.0, 1.0, 0.0),
+            &Tuple4D::new_vector(0.0, 1.0, 0.0),
+        ));
+        c
+    }
+
+    fn point_light() -> Light {
+        Light::PointLight(PointLight::new(
+            Tuple4D::new_point(-1.0, 10.0, -10.0),
+            Color::new(1.0, 1.0, 1.0),
+        ))
+    }
+
+    // three spheres on a plane
+    fn spheres() -> Vec<Shape> {
+        let mut floor = Plane::new();
+        floor.get_material_mut().set_color(Color::new(1.0, 0.9, 0.9));
+        floor.get_material_mut().set_specular(0.0);
+
+        let mut middle = Sphere::new();
+        middle.set_transformation(Matrix::translation(-0.5, 1.0, 0.5));
+        middle.get_material_mut().set_color(Color::new(0.1, 1.0, 0.5));
+        middle.get_material_mut().set_diffuse(0.7);
+        middle.get_material_mut().set_specular(0.3);
+
+        let mut right = Sphere::new();
+        right.set_transformation(&Matrix::translation(1.5, 0.5, -0.5) * &Matrix::scale(0.5, 0.5, 0.5));
+        right.get_material_mut().set_color(Color::new(0.5, 1.0, 0.1));
+
+        let mut left = Sphere::new();
+        left.set_transformation(&Matrix::translation(-1.5, 0.33, -0.75) * &Matrix::scale(0.333, 0.333, 0.333));
+        left.get_material_mut().set_color(Color::new(1.0, 0.8, 0.1));
+
+        vec![
+            Shape::new(ShapeEnum::Plane(floor)),
+            Shape::new(ShapeEnum::Sphere(middle)),
+            Shape::new(ShapeEnum::Sphere(right)),
+            Shape::new(ShapeEnum::Sphere(left)),
+        ]
+    }
+
+    // patterns, reflection, refraction, cube and cylinder
+    fn materials() -> Vec<Shape> {
+        let mut checker = Checker3DPattern::new();
+        checker.set_color_a(Color::new(0.9, 0.9, 0.9));
+        checker.set_color_b(Color::new(0.1, 0.2, 0.3));
+        let mut floor = Plane::new();
+        floor.get_material_mut().set_pattern(Pattern::Checker3DPattern(checker));
+        floor.get_material_mut().set_reflective(0.3);
+
+        let mut glass = Sphere::new();
+        glass.set_transformation(Matrix::translation(0.0, 1.0, 0.0));
+        glass.get_material_mut().set_color(Color::new(0.1, 0.1, 0.1));
+        glass.get_material_mut().set_transparency(0.9);
+        glass.get_material_mut().set_reflective(0.9);
+        glass.get_material_mut().set_refractive_index(1.5);
+
+        let mut cube = Cube::new();
+        cube.set_transformation(&Matrix::translation(-2.0, 0.5, 1.0) * &Matrix::scale(0.5, 0.5, 0.5));
+        cube.get_material_mut().set_color(Color::new(0.8, 0.2, 0.2));
+
+        let mut cylinder = Cylinder::new();
+        cylinder.set_minimum(0.0);
+        cylinder.set_maximum(2.0);
+        cylinder.set_closed(true);
+        cylinder.set_transformation(&Matrix::translation(2.0, 0.0, 1.0) * &Matrix::scale(0.5, 1.0, 0.5));
+        cylinder.get_material_mut().set_color(Color::new(0.2, 0.2, 0.8));
+
+        vec![
+            Shape::new(ShapeEnum::Plane(floor)),
+            Shape::new(ShapeEnum::Sphere(glass)),
+            Shape::new(ShapeEnum::Cube(cube)),
+            Shape::new(ShapeEnum::Cylinder(cylinder)),
+        ]
+    }
+
+    fn area_light() -> Light {
+        Light::AreaLight(AreaLight::new(
+            Tuple4D::new_point(-2.0, 5.0, -4.0),
+            Tuple4D::new_vector(2.0, 0.0, 0.0),
+            4,
+            Tuple4D::new_vector(0.0, 2.0, 0.0),
+            4,
+            Color::new(1.0, 1.0, 1.0),
+        ))
+    }
+
+    fn assert_same_as_cpu_kernel(
+        mut shapes: Vec<Shape>,
+        samples: Vec<f32>,
+        lights: Vec<Light>,
+        c: &Camera,
+        settings: &RenderSettings,
+    ) {
+        let colors = render_host(&mut shapes, &samples, &lights, c, settings);
+
+        for y in 0..c.get_vsize() {
+            for x in 0..c.get_hsize() {
+                let r = Camera::ray_for_pixel(c, x, y);
+                let mut expected = CpuKernel::color_at(
+                    &shapes,
+                    &samples,
+                    &lights,
+                    &r,
+                    settings.get_max_depth(),
+                    settings,
+                    &mut RenderStats::new(),
+                    false,
+                );
+                expected.clamp_color();
+                assert_color(&colors[y * c.get_hsize() + x], &expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_host_kernel_spheres() {
+        let c = camera(24, 16, false);
+        assert_same_as_cpu_kernel(
+            spheres(),
+            Vec::new(),
+            vec![point_light()],
+            &c,
+            &RenderSettings::from_camera(&c),
+        );
+    }
+
+    #[test]
+    fn test_host_kernel_materials() {
+        let c = camera(24, 16, false);
+        assert_same_as_cpu_kernel(
+            materials(),
+            Vec::new(),
+            vec![point_light()],
+            &c,
+            &RenderSettings::from_camera(&c),
+        );
+    }
+
+    #[test]
+    fn test_host_kernel_area_light() {
+        let c = camera(16, 12, false);
+        assert_same_as_cpu_kernel(
+            spheres(),
+            Vec::new(),
+            vec![area_light()],
+            &c,
+            &RenderSettings::from_camera(&c),
+        );
+    }
+
+    #[test]
+    fn test_host_kernel_settings() {
+        // jittered area light samples, a background and no reflections
+        let c = camera(16, 12, false);
+        let mut settings = RenderSettings::from_camera(&c);
+        settings.set_seed(7);
+        settings.set_background(Color::new(0.1, 0.2, 0.3));
+        settings.set_calc_reflection(false);
+        settings.set_epsilon(0.01);
+        assert_same_as_cpu_kernel(materials(), Vec::new(), vec![area_light()], &c, &settings);
+    }
+
+    #[test]
+    fn test_host_kernel_heightfield() {
+        // a terrain on the floor, its samples start behind the samples of another heightfield
+        let mut samples = vec![0.5; 4];
+        for z in 0..8 {
+            for x in 0..8 {
+                samples.push(0.5 + 0.5 * ((x as f32 * 0.8).sin() * (z as f32 * 0.6).cos()));
+            }
+        }
+        let mut terrain = Heightfield::try_new(&samples, 4, 8, 8).unwrap();
+        terrain.set_transformation(&Matrix::translation(0.0, 0.0, 1.0) * &Matrix::scale(2.0, 1.0, 2.0));
+        terrain.get_material_mut().set_color(Color::new(0.3, 0.8, 0.3));
+
+        let mut shapes = spheres();
+        shapes.push(Shape::new(ShapeEnum::Heightfield(terrain)));
+        let c = camera(24, 16, false);
+        assert_same_as_cpu_kernel(
+            shapes,
+            samples,
+            vec![point_light()],
+            &c,
+            &RenderSettings::from_camera(&c),
+        );
+    }
+
+    #[test]
+    fn test_host_kernel_normal_map() {
+        // 2 x 2 texels tilted in different directions on the middle sphere
+        let c = 1.0 / 2f32.sqrt();
+        let samples = vec![c, 0.0, c, 0.0, c, c, -c, 0.0, c, 0.0, -c, c];
+        let normal_map = NormalMap::try_new(&samples, 0, 2, 2, UvMapping::Spherical).unwrap();
+
+        let mut shapes = spheres();
+        shapes[1]
+            .get_material_mut()
+            .set_normal_perturbation(NormalPerturbation::NormalMap(normal_map));
+        let c = camera(24, 16, false);
+        assert_same_as_cpu_kernel(
+            shapes,
+            samples,
+            vec![point_light()],
+            &c,
+            &RenderSettings::from_camera(&c),
+        );
+    }
+
+    #[test]
+    fn test_host_kernel_antialiasing() {
+        let c = camera(8, 6, true);
+        let settings = RenderSettings::from_camera(&c);
+        let mut shapes = spheres();
+        let lights = vec![point_light()];
+        let colors = render_host(&mut shapes, &[], &lights, &c, &settings);
+
+        // jitter matrix of the CPU backends for 2x2 samples
+        let jitter = [-0.25, 0.25, 0.25, 0.25, -0.25, -0.25, 0.25, -0.75];
+        for y in 0..c.get_vsize() {
+            for x in 0..c.get_hsize() {
+                let mut expected = BLACK;
+                for sample in 0..4 {
+                    let r = Camera::ray_for_pixel_anti_aliasing(
+                        &c,
+                        x,
+                        y,
+                        jitter[2 * sample] * c.get_pixel_size(),
+                        jitter[2 * sample + 1] * c.get_pixel_size(),
+                    );
+                    expected = expected
+                        + CpuKernel::color_at(
+                            &shapes,
+                            &Vec::new(),
+                            &lights,
+                            &r,
+                            settings.get_max_depth(),
+                            &settings,
+                            &mut RenderStats::new(),
+                            false,
+                        );
+                }
+                let mut expected = expected / 4.0;
+                expected.clamp_color();
+                assert_color(&colors[y * c.get_hsize() + x], &expected);
+            }
+        }
+    }
+}
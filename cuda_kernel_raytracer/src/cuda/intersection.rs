@@ -10,19 +10,35 @@ use raytracer_lib_no_std::{MaterialOps, ShapeIdx, ShapeOps};
 
 use raytracer_lib_no_std::prelude::intri_powi;
 use raytracer_lib_no_std::prelude::intri_sqrt;
-use raytracer_lib_no_std::prelude::EPSILON_OVER_UNDER;
 
 #[derive(Clone, Copy, Debug)]
 pub struct Intersection {
     t: f32,
     shape_idx: ShapeIdx,
+    // the prototype which was hit, if shape_idx is an instance
+    prototype_idx: Option<ShapeIdx>,
 }
 
 pub trait IntersectionOps {
     fn new(t: f32, shape_idx: ShapeIdx) -> Intersection;
+    fn new_prototype(t: f32, shape_idx: ShapeIdx, prototype_idx: ShapeIdx) -> Intersection;
     fn new_empty() -> Intersection;
-    fn intersect(shape_idx: usize, r: &Ray, shapes: *mut Shape, cnt_shapes: usize) -> IntersectionList;
-    fn intersect_world(shapes: *mut Shape, cnt_shapes: usize, r: &Ray) -> IntersectionList;
+    // samples is the sample buffer of the world, see World::add_samples
+    fn intersect(
+        shape_idx: usize,
+        r: &Ray,
+        shapes: *mut Shape,
+        cnt_shapes: usize,
+        samples: *const f32,
+        cnt_samples: usize,
+    ) -> IntersectionList;
+    fn intersect_world(
+        shapes: *mut Shape,
+        cnt_shapes: usize,
+        samples: *const f32,
+        cnt_samples: usize,
+        r: &Ray,
+    ) -> IntersectionList;
 
     fn prepare_computations(
         intersection: &Intersection,
@@ -30,10 +46,14 @@ pub trait IntersectionOps {
         list: &IntersectionList,
         shapes: *mut Shape,
         cnt_shapes: usize,
+        samples: *const f32,
+        cnt_samples: usize,
+        epsilon: f32,
     ) -> PrecomputedComponent;
 
     fn get_t(&self) -> f32;
     fn get_shape(&self) -> ShapeIdx;
+    fn get_prototype(&self) -> Option<ShapeIdx>;
 
     fn schlick(comp: &PrecomputedComponent) -> f32;
 }
@@ -43,6 +63,15 @@ impl IntersectionOps for Intersection {
         Intersection {
             t,
             shape_idx: shape_idx,
+            prototype_idx: None,
+        }
+    }
+
+    fn new_prototype(t: f32, shape_idx: ShapeIdx, prototype_idx: ShapeIdx) -> Intersection {
+        Intersection {
+            t,
+            shape_idx,
+            prototype_idx: Some(prototype_idx),
         }
     }
 
@@ -50,20 +79,47 @@ impl IntersectionOps for Intersection {
         Intersection {
             t: -99999.9,
             shape_idx: 0,
+            prototype_idx: None,
         }
     }
 
-    fn intersect(shape_idx: usize, r: &Ray, shapes: *mut Shape, _cnt_shapes: usize) -> IntersectionList {
+    fn intersect(
+        shape_idx: usize,
+        r: &Ray,
+        shapes: *mut Shape,
+        _cnt_shapes: usize,
+        samples: *const f32,
+        cnt_samples: usize,
+    ) -> IntersectionList {
         let shape = unsafe { shapes.offset(shape_idx as isize).as_ref().unwrap() };
+        let samples = sample_slice(samples, cnt_samples);
         let mut intersection_list = IntersectionList::new();
         let r2 = Ray::transform(r, shape.get_inverse_transformation());
 
+        if let ShapeEnum::Instance(ref instance) = *shape.get_shape() {
+            let first = instance.get_first_prototype();
+            for prototype_idx in first..first + instance.get_prototype_cnt() {
+                let prototype = unsafe { shapes.offset(prototype_idx as isize).as_ref().unwrap() };
+                let (res, res_cnt) = instance.intersect_prototype(prototype, samples, &r2);
+                for i in 0..res_cnt {
+                    intersection_list.push(Intersection::new_prototype(res[i], shape_idx, prototype_idx));
+                }
+            }
+            return intersection_list;
+        }
+
         let (res, res_cnt) = match *shape.get_shape() {
             ShapeEnum::Sphere(ref sphere) => sphere.intersect(&r2),
             ShapeEnum::Plane(ref plane) => plane.intersect(&r2),
             ShapeEnum::Cube(ref cube) => cube.intersect(&r2),
             ShapeEnum::Cylinder(ref cylinder) => cylinder.intersect(&r2),
             ShapeEnum::Triangle(ref triangle) => triangle.intersect(&r2),
+            ShapeEnum::Torus(ref torus) => torus.intersect(&r2),
+            ShapeEnum::Disk(ref disk) => disk.intersect(&r2),
+            ShapeEnum::Rectangle(ref rectangle) => rectangle.intersect(&r2),
+            ShapeEnum::Heightfield(ref heightfield) => heightfield.intersect_samples(samples, &r2),
+            ShapeEnum::Sdf(ref sdf) => sdf.intersect(&r2),
+            ShapeEnum::Instance(_) => unreachable!(),
             // ShapeEnum::Group(ref group) =>
             // let res = Cylinder::intersect(cylinder, &r2);
             //  }
@@ -75,15 +131,23 @@ impl IntersectionOps for Intersection {
         intersection_list
     }
 
-    fn intersect_world(shapes: *mut Shape, cnt_shapes: usize, r: &Ray) -> IntersectionList {
+    fn intersect_world(
+        shapes: *mut Shape,
+        cnt_shapes: usize,
+        samples: *const f32,
+        cnt_samples: usize,
+        r: &Ray,
+    ) -> IntersectionList {
         let mut res = IntersectionList::new();
         for i in 0..cnt_shapes {
-            let tmp = Intersection::intersect(i, r, shapes, cnt_shapes);
-            for idx in 0..tmp.len() {
-                // TODO: something like a drain would be awesome and avoid copying
-                // we want to move all intersections from tmp to res ...
-                res.push(*tmp.at(idx));
+            let shape = unsafe { shapes.offset(i as isize).as_ref().unwrap() };
+            if shape.get_is_prototype() {
+                continue;
             }
+            let tmp = Intersection::intersect(i, r, shapes, cnt_shapes, samples, cnt_samples);
+            // TODO: something like a drain would be awesome and avoid copying
+            // we want to move all intersections from tmp to res ...
+            res.append(&tmp);
         }
         res.sort_intersections();
         res
@@ -95,21 +159,34 @@ impl IntersectionOps for Intersection {
         list: &IntersectionList,
         shapes: *mut Shape,
         _cnt_shapes: usize,
+        samples: *const f32,
+        cnt_samples: usize,
+        epsilon: f32,
     ) -> PrecomputedComponent {
         let point = Ray::position(r, intersection.get_t());
         let shape = unsafe { shapes.offset(intersection.get_shape() as isize).as_ref().unwrap() };
-        let mut normal_vector = shape.normal_at(&point);
+        let prototype = intersection
+            .get_prototype()
+            .map(|p| unsafe { shapes.offset(p as isize).as_ref().unwrap() });
+        let samples = sample_slice(samples, cnt_samples);
+        let mut normal_vector = match (shape.get_shape(), prototype) {
+            (ShapeEnum::Instance(ref instance), Some(p)) => instance.normal_at_prototype(p, samples, &point),
+            _ => shape.normal_at_samples(samples, &point),
+        };
+        // shading and the secondary rays use the perturbed normal, over_point and under_point the geometric one
+        let mut shading_normal = shape.perturbed_normal(prototype, samples, &point, &normal_vector);
         let eye_vector = r.get_direction() * (-1.0);
         let mut inside = true;
         if (&normal_vector ^ &eye_vector) < 0.0 {
             normal_vector = normal_vector * (-1.0);
+            shading_normal = shading_normal * (-1.0);
         } else {
             inside = false;
         }
-        let reflected_vector = Tuple4D::reflect(r.get_direction(), &normal_vector);
+        let reflected_vector = Tuple4D::reflect(r.get_direction(), &shading_normal);
 
-        let over_point = &point + &(&normal_vector * EPSILON_OVER_UNDER);
-        let under_point = &point - &(&normal_vector * EPSILON_OVER_UNDER);
+        let over_point = &point + &(&normal_vector * epsilon);
+        let under_point = &point - &(&normal_vector * epsilon);
 
         let mut comp = PrecomputedComponent::new(
             intersection.get_t(),
@@ -118,10 +195,11 @@ impl IntersectionOps for Intersection {
             over_point,
             under_point,
             eye_vector,
-            normal_vector,
+            shading_normal,
             reflected_vector,
             inside,
         );
+        comp.set_prototype(intersection.get_prototype());
 
         // let mut container: Vec<&'a Shape> ;
         let mut container = ShapeIdxList::new();
@@ -173,6 +251,10 @@ impl IntersectionOps for Intersection {
         self.shape_idx
     }
 
+    fn get_prototype(&self) -> Option<ShapeIdx> {
+        self.prototype_idx
+    }
+
     fn schlick(comp: &PrecomputedComponent) -> f32 {
         let mut cos = comp.get_eye_vector() ^ comp.get_normal_vector();
         if comp.get_n1() > comp.get_n2() {
@@ -191,16 +273,27 @@ impl IntersectionOps for Intersection {
 
 impl<'a> PartialEq for Intersection {
     fn eq(&self, other: &Self) -> bool {
-        self.shape_idx == other.shape_idx && self.t == other.t
+        self.shape_idx == other.shape_idx && self.prototype_idx == other.prototype_idx && self.t == other.t
+    }
+}
+
+// from_raw_parts needs a non null pointer, even for an empty slice
+fn sample_slice<'a>(samples: *const f32, cnt_samples: usize) -> &'a [f32] {
+    if cnt_samples == 0 {
+        return &[];
     }
+    unsafe { core::slice::from_raw_parts(samples, cnt_samples) }
 }
 
 #[cfg(test)]
 mod tests {
     use std::f32::consts::SQRT_2;
+    use std::ptr;
 
     use super::*;
-    use raytracer_lib_no_std::{assert_float, assert_tuple, ColorOps, MatrixOps, Sphere};
+    use crate::cuda::intersection_list::MAX_INTERSECTIONLIST_LEN;
+    use raytracer_lib_no_std::prelude::EPSILON_OVER_UNDER;
+    use raytracer_lib_no_std::{assert_float, assert_tuple, ColorOps, Matrix, MatrixOps, Sphere};
 
     // helper
     // page 151
@@ -347,10 +440,31 @@ mod tests {
 
         let mut shapes = vec![o];
         let shapes = shapes.as_mut_ptr();
-        let i = Intersection::intersect(0, &r, shapes, 1);
+        let i = Intersection::intersect(0, &r, shapes, 1, ptr::null(), 0);
         assert_eq!(i.len(), 2);
     }
 
+    #[test]
+    fn test_intersect_world_counts_overflows() {
+        // every sphere is hit twice, the farthest sphere doesn't fit into the list
+        let cnt = MAX_INTERSECTIONLIST_LEN / 2 + 1;
+        let mut shapes: Vec<Shape> = (0..cnt)
+            .map(|i| {
+                let mut s = Sphere::new();
+                s.set_transformation(Matrix::translation(0.0, 0.0, (cnt - 1 - i) as f32 * 3.0));
+                Shape::new(ShapeEnum::Sphere(s))
+            })
+            .collect();
+
+        let r = Ray::new(Tuple4D::new_point(0.0, 0.0, -5.0), Tuple4D::new_vector(0.0, 0.0, 1.0));
+        let xs = Intersection::intersect_world(shapes.as_mut_ptr(), cnt, ptr::null(), 0, &r);
+        assert_eq!(xs.len(), MAX_INTERSECTIONLIST_LEN);
+        assert_eq!(xs.get_overflows(), 2);
+        assert_float(xs.at(0).get_t(), 4.0);
+        assert_eq!(xs.at(0).get_shape(), cnt - 1);
+        assert!(xs.at(MAX_INTERSECTIONLIST_LEN - 1).get_shape() > 0);
+    }
+
     // page 93
     #[test]
     fn test_prepare_computations() {
@@ -366,7 +480,16 @@ mod tests {
         let mut shapes = vec![o];
         let shapes = shapes.as_mut_ptr();
 
-        let c = Intersection::prepare_computations(&i, &r, &IntersectionList::new(), shapes, 1);
+        let c = Intersection::prepare_computations(
+            &i,
+            &r,
+            &IntersectionList::new(),
+            shapes,
+            1,
+            ptr::null(),
+            0,
+            EPSILON_OVER_UNDER,
+        );
 
         let point_expected = Tuple4D::new_point(0.0, 0., -1.0);
         let eye_vector_expected = Tuple4D::new_vector(0.0, 0., -1.0);
@@ -391,7 +514,16 @@ mod tests {
 
         let mut shapes = vec![o];
         let shapes = shapes.as_mut_ptr();
-        let c = Intersection::prepare_computations(&i, &r, &IntersectionList::new(), shapes, 1);
+        let c = Intersection::prepare_computations(
+            &i,
+            &r,
+            &IntersectionList::new(),
+            shapes,
+            1,
+            ptr::null(),
+            0,
+            EPSILON_OVER_UNDER,
+        );
 
         assert_eq!(false, c.get_inside());
     }
@@ -409,7 +541,16 @@ mod tests {
 
         let mut shapes = vec![o];
         let shapes = shapes.as_mut_ptr();
-        let c = Intersection::prepare_computations(&i, &r, &IntersectionList::new(), shapes, 1);
+        let c = Intersection::prepare_computations(
+            &i,
+            &r,
+            &IntersectionList::new(),
+            shapes,
+            1,
+            ptr::null(),
+            0,
+            EPSILON_OVER_UNDER,
+        );
 
         let point_expected = Tuple4D::new_point(0.0, 0.0, 1.0);
         let eye_vector_expected = Tuple4D::new_vector(0.0, 0., -1.0);
@@ -440,7 +581,16 @@ mod tests {
         let mut shapes = vec![sphere];
         let shapes = shapes.as_mut_ptr();
 
-        let c = Intersection::prepare_computations(&xs.get_intersections()[1], &r, &xs, shapes, 1);
+        let c = Intersection::prepare_computations(
+            &xs.get_intersections()[1],
+            &r,
+            &xs,
+            shapes,
+            1,
+            ptr::null(),
+            0,
+            EPSILON_OVER_UNDER,
+        );
 
         let reflectance = Intersection::schlick(&c);
 
@@ -465,7 +615,16 @@ mod tests {
 
         let mut shapes = vec![sphere];
         let shapes = shapes.as_mut_ptr();
-        let c = Intersection::prepare_computations(&xs.get_intersections()[1], &r, &xs, shapes, 1);
+        let c = Intersection::prepare_computations(
+            &xs.get_intersections()[1],
+            &r,
+            &xs,
+            shapes,
+            1,
+            ptr::null(),
+            0,
+            EPSILON_OVER_UNDER,
+        );
 
         let reflectance = Intersection::schlick(&c);
 
@@ -489,7 +648,16 @@ mod tests {
         let mut shapes = vec![sphere];
         let shapes = shapes.as_mut_ptr();
 
-        let c = Intersection::prepare_computations(&xs.get_intersections()[0], &r, &xs, shapes, 1);
+        let c = Intersection::prepare_computations(
+            &xs.get_intersections()[0],
+            &r,
+            &xs,
+            shapes,
+            1,
+            ptr::null(),
+            0,
+            EPSILON_OVER_UNDER,
+        );
 
         let reflectance = Intersection::schlick(&c);
 
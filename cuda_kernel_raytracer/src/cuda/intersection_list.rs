@@ -8,7 +8,7 @@ use core::fmt;
 use crate::cuda::intersection::Intersection;
 use crate::cuda::intersection::IntersectionOps;
 
-pub const MAX_INTERSECTIONLIST_LEN: usize = 100;
+pub const MAX_INTERSECTIONLIST_LEN: usize = raytracer_lib_no_std::CUDA_MAX_INTERSECTIONLIST_LEN;
 
 type IntersectionContainer = [Intersection; MAX_INTERSECTIONLIST_LEN];
 
@@ -18,15 +18,21 @@ pub struct IntersectionList {
     // idx: usize,
     len: usize,
     capacity: usize,
+    // intersections, which didn't fit into the list
+    overflows: usize,
 }
 
 pub trait IntersectionListOps {
     fn new() -> IntersectionList;
+    // a full list keeps the nearest intersections and counts the dropped ones
     fn push(&mut self, i: Intersection);
+    // pushes all intersections of other and takes over its overflows
+    fn append(&mut self, other: &IntersectionList);
     fn get_intersections(&self) -> &IntersectionContainer;
     fn get_intersections_mut(&mut self) -> &mut IntersectionContainer;
     fn sort_intersections(&mut self);
     fn len(&self) -> usize;
+    fn get_overflows(&self) -> usize;
     fn at(&self, idx: usize) -> &Intersection;
 
     fn hit(&self) -> (&Intersection, bool);
@@ -39,18 +45,31 @@ impl IntersectionListOps for IntersectionList {
             //    idx: 0,
             capacity: MAX_INTERSECTIONLIST_LEN,
             len: 0,
+            overflows: 0,
         }
     }
 
     fn push(&mut self, i: Intersection) {
-        if !(self.len < self.capacity) {
-            panic!("IntersectionListOps::push  array is full. try increasing MAX_INTERSECTIONLIST_LEN");
+        if self.len == self.capacity {
+            if i.get_t() < self.list_of_intersections[self.len - 1].get_t() {
+                self.list_of_intersections[self.len - 1] = i;
+                self.sort_intersections();
+            }
+            self.overflows += 1;
+            return;
         }
         self.list_of_intersections[self.len] = i;
         self.len += 1;
         self.sort_intersections();
     }
 
+    fn append(&mut self, other: &IntersectionList) {
+        for i in other.list_of_intersections[..other.len].iter() {
+            self.push(*i);
+        }
+        self.overflows += other.overflows;
+    }
+
     fn get_intersections(&self) -> &IntersectionContainer {
         &self.list_of_intersections
     }
@@ -76,6 +95,10 @@ impl IntersectionListOps for IntersectionList {
         self.len
     }
 
+    fn get_overflows(&self) -> usize {
+        self.overflows
+    }
+
     fn at(&self, idx: usize) -> &Intersection {
         if !(idx < self.len) {
             panic!("IntersectionListOps::at  idx is out of range . try increasing MAX_INTERSECTIONLIST_LEN");
@@ -1,6 +1,9 @@
 pub mod cuda_kernel;
+#[cfg(feature = "host")]
+pub mod host;
 pub mod intersection;
 pub mod intersection_list;
+pub mod pixel;
 pub mod shape_idx_list;
 
 pub const MAX_LIGHT_SAMPLES: usize = 100;
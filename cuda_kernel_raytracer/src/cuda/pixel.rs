@@ -0,0 +1,95 @@
+use raytracer_lib_no_std::basics::camera::{Camera, CameraOps};
+use raytracer_lib_no_std::basics::color::{Color, BLACK};
+use raytracer_lib_no_std::light::light::Light;
+use raytracer_lib_no_std::shape::shape::Shape;
+use raytracer_lib_no_std::{ColorOps, RenderSettings, RenderSettingsOps};
+
+use crate::cuda::cuda_kernel::CudaKernel;
+
+// color of the pixel (x, y), clamped to [0, 1]. used by the PTX kernel and the host build
+pub fn calc_pixel_color(
+    shapes: *mut Shape,
+    cnt_shapes: usize,
+    samples: *const f32,
+    cnt_samples: usize,
+    lights: *const Light,
+    cnt_lights: usize,
+    c: &Camera,
+    settings: &RenderSettings,
+    x: usize,
+    y: usize,
+) -> Color {
+    let mut color = if settings.get_antialiasing() {
+        let n_samples = settings.get_antialiasing_size();
+
+        let mut jitter_matrix = [0f32; 18];
+        if n_samples == 2 {
+            jitter_matrix[0] = -1.0 / 4.0;
+            jitter_matrix[1] = 1.0 / 4.0;
+            jitter_matrix[2] = 1.0 / 4.;
+            jitter_matrix[3] = 1.0 / 4.0;
+            jitter_matrix[4] = -1.0 / 4.0;
+            jitter_matrix[5] = -1.0 / 4.0;
+            jitter_matrix[6] = 1.0 / 4.0;
+            jitter_matrix[7] = -3.0 / 4.0;
+        }
+        if n_samples == 3 {
+            let two_over_six = 2.0 / 6.0;
+            jitter_matrix[0] = -two_over_six;
+            jitter_matrix[1] = two_over_six;
+            jitter_matrix[2] = 0.0;
+            jitter_matrix[3] = two_over_six;
+            jitter_matrix[4] = two_over_six;
+            jitter_matrix[5] = two_over_six;
+            jitter_matrix[6] = -two_over_six;
+            jitter_matrix[7] = 0.0;
+            jitter_matrix[8] = 0.0;
+            jitter_matrix[9] = 0.0;
+            jitter_matrix[10] = two_over_six;
+            jitter_matrix[11] = 0.0;
+            jitter_matrix[12] = -two_over_six;
+            jitter_matrix[13] = -two_over_six;
+            jitter_matrix[14] = 0.0;
+            jitter_matrix[15] = -two_over_six;
+            jitter_matrix[16] = two_over_six;
+            jitter_matrix[17] = -two_over_six;
+        }
+
+        let mut color = BLACK;
+        for sample in 0..(n_samples * n_samples) {
+            let delta_x = jitter_matrix[2 * sample] * c.get_pixel_size();
+            let delta_y = jitter_matrix[2 * sample + 1] * c.get_pixel_size();
+
+            let r = Camera::ray_for_pixel_anti_aliasing(c, x, y, delta_x, delta_y);
+
+            color = color
+                + CudaKernel::color_at(
+                    shapes,
+                    cnt_shapes,
+                    samples,
+                    cnt_samples,
+                    lights,
+                    cnt_lights,
+                    &r,
+                    settings.get_max_depth(),
+                    settings,
+                );
+        }
+        color / (n_samples * n_samples) as f32
+    } else {
+        let r = Camera::ray_for_pixel(c, x, y);
+        CudaKernel::color_at(
+            shapes,
+            cnt_shapes,
+            samples,
+            cnt_samples,
+            lights,
+            cnt_lights,
+            &r,
+            settings.get_max_depth(),
+            settings,
+        )
+    };
+    color.clamp_color();
+    color
+}
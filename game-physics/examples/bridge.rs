@@ -240,7 +240,7 @@ fn main() {
     let cnt_frames = 5;
     let duration = 0.5;
     for i in 0..cnt_frames {
-        bridge_demo.get_particle_world().render_world(i, &registry);
+        bridge_demo.get_particle_world_mut().render_world(i, &registry);
 
         println!("-----------------------------");
         println!("------frame {}  -----------------------", i);
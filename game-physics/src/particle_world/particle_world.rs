@@ -13,6 +13,8 @@ pub struct ParticleWorld {
     contacts: Vec<ParticleContact>,
     max_contacts: usize,
     iterations: usize,
+    // framed from the particles of the first frame and kept, so the view doesn't follow them
+    camera_transform: Option<Matrix>,
 }
 
 pub trait ParticleWorldOps {
@@ -23,7 +25,7 @@ pub trait ParticleWorldOps {
 
     fn add_contact_generator(&mut self, contact_generator: ParticleContactGenerator) -> usize;
 
-    fn render_world(&self, cnt: usize, registry: &ParticleForceRegistry);
+    fn render_world(&mut self, cnt: usize, registry: &ParticleForceRegistry);
 }
 
 impl ParticleWorldOps for ParticleWorld {
@@ -94,7 +96,7 @@ impl ParticleWorldOps for ParticleWorld {
         self.contact_generators.len() - 1
     }
 
-    fn render_world(&self, cnt: usize, registry: &ParticleForceRegistry) {
+    fn render_world(&mut self, cnt: usize, registry: &ParticleForceRegistry) {
         let particle_scale = 0.1;
         let particle_color = Color::new(1.0, 0.0, 0.0);
 
@@ -116,11 +118,6 @@ impl ParticleWorldOps for ParticleWorld {
 
         let mut c = Camera::new(width, height, PI / 13.5);
         c.calc_pixel_size();
-        c.set_transformation(Matrix::view_transform(
-            &Tuple4D::new_point(0.0, 10.5, -15.0),
-            &Tuple4D::new_point(0.0, 1.0, 0.0),
-            &Tuple4D::new_vector(0.0, 1.0, 0.0),
-        ));
         c.set_antialiasing(false);
         c.set_calc_reflection(false);
         c.set_calc_refraction(false);
@@ -131,7 +128,7 @@ impl ParticleWorldOps for ParticleWorld {
         floor.set_transformation(m);
         floor.get_material_mut().set_color(WHITE);
         let floor = Shape::new(ShapeEnum::Plane(floor));
-        world.add_shape(floor);
+        world.add_shape(floor).unwrap();
 
         for p in registry.get_particles().iter() {
             let mut sphere = Sphere::new();
@@ -142,7 +139,16 @@ impl ParticleWorldOps for ParticleWorld {
             let mut sphere = Shape::new(ShapeEnum::Sphere(sphere));
             sphere.set_casts_shadow(false);
 
-            world.add_shape(sphere);
+            world.add_shape(sphere).unwrap();
+        }
+
+        // the floor is ignored, it has infinite bounds
+        let direction = Tuple4D::new_vector(0.0, 9.5, -15.0);
+        if self.camera_transform.is_none() && c.frame_shapes(world.get_shapes(), &direction) {
+            self.camera_transform = Some(c.get_transform().clone());
+        }
+        if let Some(ref m) = self.camera_transform {
+            c.set_transformation(m.clone());
         }
 
         let filename = format!("bridge_{}.png", cnt);
@@ -161,6 +167,7 @@ impl ParticleWorld {
             contacts: Vec::new(),
             max_contacts,
             iterations,
+            camera_transform: None,
         }
     }
 }
@@ -41,6 +41,31 @@ pub fn intri_tan(x: f32) -> f32 {
     intri_sin(x) / intri_cos(x)
 }
 
+// polynomial approximation, the error is below 1e-5 radians
+pub fn intri_atan2(y: f32, x: f32) -> f32 {
+    let ax = intri_abs(x);
+    let ay = intri_abs(y);
+    if ax == 0.0 && ay == 0.0 {
+        return 0.0;
+    }
+    // atan of a value in [0, 1]
+    let a = intri_min(ax, ay) / intri_max(ax, ay);
+    let s = a * a;
+    let mut r = a
+        * (0.99997726 + s * (-0.33262347 + s * (0.19354346 + s * (-0.11643287 + s * (0.05265332 + s * -0.0117212)))));
+    if ay > ax {
+        r = core::f32::consts::FRAC_PI_2 - r;
+    }
+    if x < 0.0 {
+        r = core::f32::consts::PI - r;
+    }
+    if y < 0.0 {
+        -r
+    } else {
+        r
+    }
+}
+
 #[inline]
 pub fn intri_min(a: f32, b: f32) -> f32 {
     if a < b {
@@ -88,4 +113,14 @@ mod tests {
         assert_eq!(intri_powf(4.0, 0.5), 2.0);
         assert_eq!(intri_powf(1048576.0, 1.0 / 10.), 4.0);
     }
+
+    #[test]
+    fn test_atan2() {
+        for i in 0..64 {
+            let angle = -3.1 + i as f32 * 0.1;
+            let (y, x) = (2.0 * angle.sin(), 2.0 * angle.cos());
+            assert!(fabsf(intri_atan2(y, x) - y.atan2(x)) < 1e-5);
+        }
+        assert_eq!(intri_atan2(0.0, 0.0), 0.0);
+    }
 }
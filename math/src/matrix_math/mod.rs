@@ -8,6 +8,7 @@ pub use self::common::*;
 pub use self::math_ops::*;
 pub use self::matrix::*;
 pub use self::matrix3::*;
+pub use self::polynomial::*;
 pub use self::tuple3d::*;
 pub use self::tuple4d::*;
 
@@ -15,6 +16,7 @@ pub mod common;
 pub mod math_ops;
 pub mod matrix;
 pub mod matrix3;
+pub mod polynomial;
 pub mod tuple3d;
 pub mod tuple4d;
 pub mod libm_striped_to_pow;
@@ -0,0 +1,235 @@
+use core::intrinsics;
+
+// real roots of polynomials up to degree 4, used for the ray - torus intersection.
+// calculations are done in f64 and only need sqrt, so they work in the kernels as well
+
+// roots of c[2] x^2 + c[1] x + c[0], sorted ascending
+pub fn solve_quadratic(c: &[f64; 3]) -> ([f64; 2], usize) {
+    let mut res = [0f64; 2];
+    if c[2] == 0.0 {
+        if c[1] == 0.0 {
+            return (res, 0);
+        }
+        res[0] = -c[0] / c[1];
+        return (res, 1);
+    }
+    let disc = c[1] * c[1] - 4.0 * c[2] * c[0];
+    if disc < 0.0 {
+        return (res, 0);
+    }
+    // avoid the cancellation of -b + sqrt(disc) for b >> 4ac
+    let q = if c[1] < 0.0 {
+        -0.5 * (c[1] - sqrt_f64(disc))
+    } else {
+        -0.5 * (c[1] + sqrt_f64(disc))
+    };
+    if q == 0.0 {
+        // c[1] and c[0] are 0
+        return (res, 1);
+    }
+    let x0 = q / c[2];
+    let x1 = c[0] / q;
+    if x0 < x1 {
+        res = [x0, x1];
+    } else {
+        res = [x1, x0];
+    }
+    (res, 2)
+}
+
+// real roots of c[4] x^4 + c[3] x^3 + c[2] x^2 + c[1] x + c[0], sorted ascending. a double root is returned twice.
+// Ferrari's method with a bracketed newton iteration for the resolvent cubic, the roots are polished
+// with newton steps on the original polynomial. returns no roots if c[4] is 0
+pub fn solve_quartic(c: &[f64; 5]) -> ([f64; 4], usize) {
+    let mut res = [0f64; 4];
+    let mut res_cnt = 0;
+    if c[4] == 0.0 {
+        return (res, res_cnt);
+    }
+
+    // x^4 + a x^3 + b x^2 + c x + d  and  x = y - a/4  gives  y^4 + p y^2 + q y + r
+    let a = c[3] / c[4];
+    let b = c[2] / c[4];
+    let cc = c[1] / c[4];
+    let d = c[0] / c[4];
+    let a2 = a * a;
+    let p = b - 3.0 / 8.0 * a2;
+    let q = cc - 0.5 * a * b + a2 * a / 8.0;
+    let r = d - 0.25 * a * cc + a2 * b / 16.0 - 3.0 / 256.0 * a2 * a2;
+
+    let scale = 1.0 + abs_f64(p) + abs_f64(q) + abs_f64(r);
+    if abs_f64(q) <= 1e-12 * scale {
+        // biquadratic: z^2 + p z + r with z = y^2
+        let (z, z_cnt) = solve_quadratic(&[r, p, 1.0]);
+        for z in z[..z_cnt].iter() {
+            if *z >= 0.0 {
+                let y = sqrt_f64(*z);
+                res[res_cnt] = -y;
+                res[res_cnt + 1] = y;
+                res_cnt += 2;
+            }
+        }
+    } else {
+        // (y^2 + p/2 + m)^2 = (sqrt(2m) y - q / (2 sqrt(2m)))^2 for a root m > 0 of the resolvent
+        // m^3 + p m^2 + (p^2/4 - r) m - q^2/8, which exists because it is negative at m = 0
+        let m = resolvent_root(p, p * p / 4.0 - r, -q * q / 8.0);
+        let s = sqrt_f64(2.0 * m);
+        let t = q / (2.0 * s);
+        for sign in [-1.0, 1.0].iter() {
+            let (y, y_cnt) = solve_quadratic(&[p / 2.0 + m + sign * t, -sign * s, 1.0]);
+            for y in y[..y_cnt].iter() {
+                res[res_cnt] = *y;
+                res_cnt += 1;
+            }
+        }
+    }
+
+    for x in res[..res_cnt].iter_mut() {
+        *x = polish_root(c, *x - a / 4.0);
+    }
+    sort(&mut res[..res_cnt]);
+    (res, res_cnt)
+}
+
+// a positive root of m^3 + b m^2 + c m + d with d < 0
+fn resolvent_root(b: f64, c: f64, d: f64) -> f64 {
+    let f = |m: f64| ((m + b) * m + c) * m + d;
+    let df = |m: f64| (3.0 * m + 2.0 * b) * m + c;
+
+    let mut lo = 0.0;
+    // Cauchy bound for the roots
+    let mut hi = 1.0 + max_f64(abs_f64(b), max_f64(abs_f64(c), abs_f64(d)));
+    let mut m = hi;
+    for _ in 0..100 {
+        let fm = f(m);
+        if fm == 0.0 {
+            return m;
+        }
+        if fm < 0.0 {
+            lo = m;
+        } else {
+            hi = m;
+        }
+        let dfm = df(m);
+        let mut next = m - fm / dfm;
+        // bisection whenever newton leaves the bracket
+        if !(dfm != 0.0 && lo < next && next < hi) {
+            next = 0.5 * (lo + hi);
+        }
+        if abs_f64(next - m) <= 1e-15 * max_f64(1.0, m) {
+            return next;
+        }
+        m = next;
+    }
+    m
+}
+
+fn polish_root(c: &[f64; 5], mut x: f64) -> f64 {
+    for _ in 0..2 {
+        let f = (((c[4] * x + c[3]) * x + c[2]) * x + c[1]) * x + c[0];
+        let df = ((4.0 * c[4] * x + 3.0 * c[3]) * x + 2.0 * c[2]) * x + c[1];
+        if df == 0.0 {
+            break;
+        }
+        x -= f / df;
+    }
+    x
+}
+
+// insertion sort, there are at most 4 roots
+fn sort(x: &mut [f64]) {
+    for i in 1..x.len() {
+        let mut j = i;
+        while j > 0 && x[j - 1] > x[j] {
+            x.swap(j - 1, j);
+            j -= 1;
+        }
+    }
+}
+
+#[inline]
+fn sqrt_f64(x: f64) -> f64 {
+    unsafe { intrinsics::sqrtf64(x) }
+}
+
+#[inline]
+fn abs_f64(x: f64) -> f64 {
+    if x < 0.0 {
+        -x
+    } else {
+        x
+    }
+}
+
+#[inline]
+fn max_f64(a: f64, b: f64) -> f64 {
+    if a < b {
+        b
+    } else {
+        a
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_roots(actual: ([f64; 4], usize), expected: &[f64]) {
+        assert_eq!(actual.1, expected.len());
+        for (a, e) in actual.0.iter().zip(expected.iter()) {
+            assert!(abs_f64(a - e) < 1e-9, "expected {:?}, got {:?}", expected, actual.0);
+        }
+    }
+
+    // expands (x - r0)(x - r1)(x - r2)(x - r3)
+    fn from_roots(r: [f64; 4]) -> [f64; 5] {
+        let mut c = [1.0, 0.0, 0.0, 0.0, 0.0];
+        for root in r.iter() {
+            for i in (1..5).rev() {
+                c[i] = c[i - 1] - root * c[i];
+            }
+            c[0] *= -root;
+        }
+        c
+    }
+
+    #[test]
+    fn test_solve_quadratic() {
+        assert_eq!(solve_quadratic(&[-2.0, -1.0, 1.0]), ([-1.0, 2.0], 2));
+        assert_eq!(solve_quadratic(&[1.0, 0.0, 1.0]).1, 0);
+        assert_eq!(solve_quadratic(&[3.0, -1.5, 0.0]), ([2.0, 0.0], 1));
+        // no cancellation for roots of very different magnitude
+        let (x, cnt) = solve_quadratic(&[1.0, -1e8, 1.0]);
+        assert_eq!(cnt, 2);
+        assert!(abs_f64(x[0] - 1e-8) < 1e-20);
+    }
+
+    #[test]
+    fn test_solve_quartic() {
+        assert_roots(
+            solve_quartic(&from_roots([1.0, -2.0, 3.0, 0.5])),
+            &[-2.0, 0.5, 1.0, 3.0],
+        );
+        // biquadratic
+        assert_roots(
+            solve_quartic(&from_roots([-2.0, -1.0, 1.0, 2.0])),
+            &[-2.0, -1.0, 1.0, 2.0],
+        );
+        // not monic, with two complex roots: 2 (x - 1)(x - 4)(x^2 + 1)
+        assert_roots(solve_quartic(&[8.0, -10.0, 10.0, -10.0, 2.0]), &[1.0, 4.0]);
+        // no real roots: x^4 + 1
+        assert_roots(solve_quartic(&[1.0, 0.0, 0.0, 0.0, 1.0]), &[]);
+        // double roots, like a ray touching a torus
+        assert_roots(solve_quartic(&from_roots([3.0, 3.0, 5.0, 7.0])), &[3.0, 3.0, 5.0, 7.0]);
+        // clustered roots of a ray through a thin torus, after the ray origin was moved next to it
+        assert_roots(
+            solve_quartic(&from_roots([-1.1, -0.9, 0.9, 1.1])),
+            &[-1.1, -0.9, 0.9, 1.1],
+        );
+        assert_roots(
+            solve_quartic(&from_roots([-0.01, 0.01, 0.99, 1.01])),
+            &[-0.01, 0.01, 0.99, 1.01],
+        );
+        assert_eq!(solve_quartic(&[1.0, 2.0, 3.0, 4.0, 0.0]).1, 0);
+    }
+}
@@ -36,7 +36,7 @@ fn add_cylinder(world: &mut World, points: &Vec<&Tuple4D>) {
         let c = cylinder_between_two_points(points[i], points[i + 1], radius);
         let mut c = Shape::new(ShapeEnum::Cylinder(c));
         c.set_casts_shadow(false);
-        world.add_shape(c);
+        world.add_shape(c).unwrap();
     }
 }
 
@@ -67,7 +67,7 @@ fn create_spheres(world: &mut World, points: &Vec<&Tuple4D>) {
         s.set_transformation(m);
         s.get_material_mut().set_color(Color::new(1.0, 0.0, 0.0));
         let s = Shape::new(ShapeEnum::Sphere(s));
-        world.add_shape(s);
+        world.add_shape(s).unwrap();
     });
 }
 
@@ -116,7 +116,7 @@ pub fn add_floor(world: &mut World) {
     let mut floor = Shape::new(ShapeEnum::Cube(floor));
     floor.set_casts_shadow(false);
 
-    world.add_shape(floor);
+    world.add_shape(floor).unwrap();
 }
 
 pub fn setup_world_coord_axes(width: usize, height: usize, show_axis_shperes: bool) -> (World, Camera) {
@@ -209,14 +209,14 @@ pub fn setup_world_coord_axes(width: usize, height: usize, show_axis_shperes: bo
     sphere_x.set_casts_shadow(false);
 
     let mut w = World::new();
-    w.add_shape(x_axis);
-    w.add_shape(y_axis);
-    w.add_shape(z_axis);
+    w.add_shape(x_axis).unwrap();
+    w.add_shape(y_axis).unwrap();
+    w.add_shape(z_axis).unwrap();
 
     if show_axis_shperes {
-        w.add_shape(sphere_x);
-        w.add_shape(sphere_y);
-        w.add_shape(sphere_z);
+        w.add_shape(sphere_x).unwrap();
+        w.add_shape(sphere_y).unwrap();
+        w.add_shape(sphere_z).unwrap();
     }
 
     let mut c = Camera::new(width, height, 1.0);
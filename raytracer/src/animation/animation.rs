@@ -0,0 +1,420 @@
+use core::fmt;
+use std::error::Error;
+
+#[cfg(feature = "use_serde")]
+use serde::{Deserialize, Serialize};
+
+use raytracer_lib_no_std::prelude::*;
+use raytracer_lib_std::{Scene, SceneOps, WorldOps};
+
+use crate::animation::{Interpolation, Track};
+
+// keyframed transformations of one shape of the world. the resulting transformation is
+// translation * rotation * scale * (transformation of the shape in the base scene)
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+pub struct ShapeAnimation {
+    shape_idx: usize,
+    translation: Track<Tuple4D>,
+    // euler angles in radians, applied in the order x, y, z
+    rotation: Track<Tuple4D>,
+    scale: Track<Tuple4D>,
+    color: Track<Color>,
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+pub struct Animation {
+    camera_from: Track<Tuple4D>,
+    camera_to: Track<Tuple4D>,
+    light_position: Track<Tuple4D>,
+    light_intensity: Track<Color>,
+    shapes: Vec<ShapeAnimation>,
+}
+
+#[derive(Debug)]
+pub enum AnimationError {
+    ShapeNotFound(usize),
+    NonInvertibleTransformation(String),
+    // frames per second must be positive and finite, otherwise there is no frame count
+    InvalidFps(f32),
+}
+
+pub trait ShapeAnimationOps {
+    fn new(shape_idx: usize) -> ShapeAnimation;
+
+    fn get_shape_idx(&self) -> usize;
+
+    fn add_translation(&mut self, time: f32, translation: Tuple4D, interpolation: Interpolation);
+    fn add_rotation(&mut self, time: f32, rotation: Tuple4D, interpolation: Interpolation);
+    fn add_scale(&mut self, time: f32, scale: Tuple4D, interpolation: Interpolation);
+    fn add_color(&mut self, time: f32, color: Color, interpolation: Interpolation);
+
+    fn get_duration(&self) -> f32;
+
+    fn transformation_at(&self, time: f32) -> Matrix;
+    fn color_at(&self, time: f32) -> Option<Color>;
+}
+
+pub trait AnimationOps {
+    fn new() -> Animation;
+
+    fn add_camera_from(&mut self, time: f32, from: Tuple4D, interpolation: Interpolation);
+    fn add_camera_to(&mut self, time: f32, to: Tuple4D, interpolation: Interpolation);
+    fn add_light_position(&mut self, time: f32, position: Tuple4D, interpolation: Interpolation);
+    fn add_light_intensity(&mut self, time: f32, intensity: Color, interpolation: Interpolation);
+
+    fn add_shape_animation(&mut self, shape_animation: ShapeAnimation);
+    fn get_shape_animations(&self) -> &Vec<ShapeAnimation>;
+
+    fn get_duration(&self) -> f32;
+    fn frame_count(&self, fps: f32) -> usize;
+
+    // evaluates all tracks at the given time and applies them to a copy of the scene
+    fn scene_at(&self, scene: &Scene, time: f32) -> Result<Scene, AnimationError>;
+}
+
+impl ShapeAnimationOps for ShapeAnimation {
+    fn new(shape_idx: usize) -> ShapeAnimation {
+        ShapeAnimation {
+            shape_idx,
+            translation: Track::new(),
+            rotation: Track::new(),
+            scale: Track::new(),
+            color: Track::new(),
+        }
+    }
+
+    fn get_shape_idx(&self) -> usize {
+        self.shape_idx
+    }
+
+    fn add_translation(&mut self, time: f32, translation: Tuple4D, interpolation: Interpolation) {
+        self.translation.add_keyframe(time, translation, interpolation);
+    }
+
+    fn add_rotation(&mut self, time: f32, rotation: Tuple4D, interpolation: Interpolation) {
+        self.rotation.add_keyframe(time, rotation, interpolation);
+    }
+
+    fn add_scale(&mut self, time: f32, scale: Tuple4D, interpolation: Interpolation) {
+        self.scale.add_keyframe(time, scale, interpolation);
+    }
+
+    fn add_color(&mut self, time: f32, color: Color, interpolation: Interpolation) {
+        self.color.add_keyframe(time, color, interpolation);
+    }
+
+    fn get_duration(&self) -> f32 {
+        self.translation
+            .get_duration()
+            .max(self.rotation.get_duration())
+            .max(self.scale.get_duration())
+            .max(self.color.get_duration())
+    }
+
+    fn transformation_at(&self, time: f32) -> Matrix {
+        let mut m = Matrix::new_identity_4x4();
+        if let Some(t) = self.translation.value_at(time) {
+            m = m * Matrix::translation(t.x, t.y, t.z);
+        }
+        if let Some(r) = self.rotation.value_at(time) {
+            m = m * Matrix::rotate_z(r.z) * Matrix::rotate_y(r.y) * Matrix::rotate_x(r.x);
+        }
+        if let Some(s) = self.scale.value_at(time) {
+            m = m * Matrix::scale(s.x, s.y, s.z);
+        }
+        m
+    }
+
+    fn color_at(&self, time: f32) -> Option<Color> {
+        self.color.value_at(time)
+    }
+}
+
+impl AnimationOps for Animation {
+    fn new() -> Animation {
+        Animation {
+            camera_from: Track::new(),
+            camera_to: Track::new(),
+            light_position: Track::new(),
+            light_intensity: Track::new(),
+            shapes: Vec::new(),
+        }
+    }
+
+    fn add_camera_from(&mut self, time: f32, from: Tuple4D, interpolation: Interpolation) {
+        self.camera_from.add_keyframe(time, from, interpolation);
+    }
+
+    fn add_camera_to(&mut self, time: f32, to: Tuple4D, interpolation: Interpolation) {
+        self.camera_to.add_keyframe(time, to, interpolation);
+    }
+
+    fn add_light_position(&mut self, time: f32, position: Tuple4D, interpolation: Interpolation) {
+        self.light_position.add_keyframe(time, position, interpolation);
+    }
+
+    fn add_light_intensity(&mut self, time: f32, intensity: Color, interpolation: Interpolation) {
+        self.light_intensity.add_keyframe(time, intensity, interpolation);
+    }
+
+    fn add_shape_animation(&mut self, shape_animation: ShapeAnimation) {
+        self.shapes.push(shape_animation);
+    }
+
+    fn get_shape_animations(&self) -> &Vec<ShapeAnimation> {
+        &self.shapes
+    }
+
+    fn get_duration(&self) -> f32 {
+        self.shapes.iter().fold(
+            self.camera_from
+                .get_duration()
+                .max(self.camera_to.get_duration())
+                .max(self.light_position.get_duration())
+                .max(self.light_intensity.get_duration()),
+            |d, s| d.max(s.get_duration()),
+        )
+    }
+
+    // first frame at time 0, last frame at the end of the longest track
+    fn frame_count(&self, fps: f32) -> usize {
+        (self.get_duration() * fps).floor() as usize + 1
+    }
+
+    fn scene_at(&self, scene: &Scene, time: f32) -> Result<Scene, AnimationError> {
+        let mut scene = scene.clone();
+
+        let from = self.camera_from.value_at(time);
+        let to = self.camera_to.value_at(time);
+        if from.is_some() || to.is_some() {
+            // whatever is not animated is taken from the view transformation of the base scene
+            let inv = Matrix::invert(scene.get_camera().get_transform())
+                .ok_or_else(|| AnimationError::NonInvertibleTransformation("camera".to_string()))?;
+            let base_from = &inv * &Tuple4D::new_point(0.0, 0.0, 0.0);
+            let base_to = base_from + &inv * &Tuple4D::new_vector(0.0, 0.0, -1.0);
+            let up = &inv * &Tuple4D::new_vector(0.0, 1.0, 0.0);
+
+            let m = Matrix::view_transform(&from.unwrap_or(base_from), &to.unwrap_or(base_to), &up);
+            if Matrix::invert(&m).is_none() {
+                return Err(AnimationError::NonInvertibleTransformation(format!(
+                    "camera at time {}",
+                    time
+                )));
+            }
+            scene.get_camera_mut().set_transformation(m);
+        }
+
+        let mut light = scene.get_world().get_light().clone();
+        if let Some(position) = self.light_position.value_at(time) {
+            light = match light {
+                Light::PointLight(mut pl) => {
+                    pl.set_position(position);
+                    Light::PointLight(pl)
+                }
+                // move the whole area, not just its center
+                Light::AreaLight(al) => {
+                    let corner = al.get_corner() + &(&position - al.get_position());
+                    Light::AreaLight(AreaLight::new(
+                        corner,
+                        al.get_uvec() * al.get_usteps() as f32,
+                        al.get_usteps(),
+                        al.get_vvec() * al.get_vsteps() as f32,
+                        al.get_vsteps(),
+                        *al.get_intensity(),
+                    ))
+                }
+            };
+        }
+        if let Some(intensity) = self.light_intensity.value_at(time) {
+            light.set_intensity(intensity);
+        }
+        scene.get_world_mut().set_light(light);
+
+        let shapes = scene.get_world_mut().get_shapes_mut();
+        for a in self.shapes.iter() {
+            let shape = shapes
+                .get_mut(a.shape_idx)
+                .ok_or(AnimationError::ShapeNotFound(a.shape_idx))?;
+
+            let m = a.transformation_at(time) * shape.get_transformation().clone();
+            if Matrix::invert(&m).is_none() {
+                return Err(AnimationError::NonInvertibleTransformation(format!(
+                    "shape {} at time {}",
+                    a.shape_idx, time
+                )));
+            }
+            shape.set_transformation(m);
+
+            if let Some(c) = a.color_at(time) {
+                shape.get_material_mut().set_color(c);
+            }
+        }
+
+        Ok(scene)
+    }
+}
+
+impl Default for Animation {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Error for AnimationError {}
+
+impl fmt::Display for AnimationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AnimationError::ShapeNotFound(idx) => write!(f, "animated shape {} does not exist in the scene", idx),
+            AnimationError::NonInvertibleTransformation(what) => {
+                write!(f, "animation produces a non invertible transformation for {}", what)
+            }
+            AnimationError::InvalidFps(fps) => write!(f, "frames per second must be positive and finite, got {}", fps),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use raytracer_lib_std::World;
+
+    use super::*;
+
+    fn test_scene() -> Scene {
+        let mut world = World::new();
+        world.add_shape(Shape::new(ShapeEnum::Sphere(Sphere::new()))).unwrap();
+        let mut plane = Shape::new(ShapeEnum::Plane(Plane::new()));
+        plane.set_transformation(Matrix::translation(0.0, -1.0, 0.0));
+        world.add_shape(plane).unwrap();
+
+        let mut camera = Camera::new(4, 3, PI / 3.0);
+        camera.set_transformation(Matrix::view_transform(
+            &Tuple4D::new_point(0.0, 0.0, -5.0),
+            &Tuple4D::new_point(0.0, 0.0, 0.0),
+            &Tuple4D::new_vector(0.0, 1.0, 0.0),
+        ));
+        Scene::new(world, camera)
+    }
+
+    #[test]
+    fn test_animation_duration_and_frames() {
+        let mut a = Animation::new();
+        assert_eq!(a.frame_count(25.0), 1);
+
+        a.add_camera_from(0.0, Tuple4D::new_point(0.0, 0.0, -5.0), Interpolation::Linear);
+        a.add_camera_from(1.0, Tuple4D::new_point(0.0, 0.0, -10.0), Interpolation::Linear);
+        let mut s = ShapeAnimation::new(0);
+        s.add_scale(2.0, Tuple4D::new_vector(2.0, 2.0, 2.0), Interpolation::Linear);
+        a.add_shape_animation(s);
+
+        assert_float(a.get_duration(), 2.0);
+        assert_eq!(a.frame_count(10.0), 21);
+    }
+
+    #[test]
+    fn test_animation_camera() {
+        let scene = test_scene();
+        let mut a = Animation::new();
+        a.add_camera_from(0.0, Tuple4D::new_point(0.0, 0.0, -5.0), Interpolation::Linear);
+        a.add_camera_from(2.0, Tuple4D::new_point(0.0, 0.0, -9.0), Interpolation::Linear);
+
+        let s = a.scene_at(&scene, 1.0).unwrap();
+        let expected = Matrix::view_transform(
+            &Tuple4D::new_point(0.0, 0.0, -7.0),
+            &Tuple4D::new_point(0.0, 0.0, -6.0),
+            &Tuple4D::new_vector(0.0, 1.0, 0.0),
+        );
+        assert_matrix(s.get_camera().get_transform(), &expected);
+        // the base scene is not touched
+        assert_matrix(
+            scene.get_camera().get_transform(),
+            test_scene().get_camera().get_transform(),
+        );
+    }
+
+    #[test]
+    fn test_animation_light() {
+        let scene = test_scene();
+        let mut a = Animation::new();
+        a.add_light_position(0.0, Tuple4D::new_point(0.0, 10.0, 0.0), Interpolation::Linear);
+        a.add_light_position(1.0, Tuple4D::new_point(10.0, 10.0, 0.0), Interpolation::Linear);
+        a.add_light_intensity(0.0, Color::new(1.0, 1.0, 1.0), Interpolation::Linear);
+        a.add_light_intensity(1.0, Color::new(0.0, 0.0, 0.0), Interpolation::Linear);
+
+        let s = a.scene_at(&scene, 0.5).unwrap();
+        let light = s.get_world().get_light();
+        assert_tuple(light.get_position(), &Tuple4D::new_point(5.0, 10.0, 0.0));
+        assert_color(light.get_intensity(), &Color::new(0.5, 0.5, 0.5));
+    }
+
+    #[test]
+    fn test_animation_area_light_moves_corner() {
+        let mut scene = test_scene();
+        let al = AreaLight::new(
+            Tuple4D::new_point(-1.0, 2.0, 4.0),
+            Tuple4D::new_vector(2.0, 0.0, 0.0),
+            4,
+            Tuple4D::new_vector(0.0, 2.0, 0.0),
+            2,
+            Color::new(1.0, 1.0, 1.0),
+        );
+        scene.get_world_mut().set_light(Light::AreaLight(al));
+
+        let mut a = Animation::new();
+        a.add_light_position(0.0, Tuple4D::new_point(1.0, 3.0, 4.0), Interpolation::Linear);
+
+        let s = a.scene_at(&scene, 0.0).unwrap();
+        let light = s.get_world().get_light();
+        assert_tuple(light.get_corner(), &Tuple4D::new_point(0.0, 2.0, 4.0));
+        assert_tuple(light.get_uvec(), &Tuple4D::new_vector(0.5, 0.0, 0.0));
+        assert_eq!(light.get_samples(), 8);
+    }
+
+    #[test]
+    fn test_animation_shape() {
+        let scene = test_scene();
+        let mut sa = ShapeAnimation::new(1);
+        sa.add_translation(0.0, Tuple4D::new_vector(0.0, 0.0, 0.0), Interpolation::EaseInOut);
+        sa.add_translation(1.0, Tuple4D::new_vector(2.0, 0.0, 0.0), Interpolation::EaseInOut);
+        sa.add_rotation(0.0, Tuple4D::new_vector(0.0, PI / 2.0, 0.0), Interpolation::Linear);
+        sa.add_scale(0.0, Tuple4D::new_vector(2.0, 2.0, 2.0), Interpolation::Linear);
+        sa.add_color(0.0, Color::new(1.0, 0.0, 0.0), Interpolation::Linear);
+        sa.add_color(1.0, Color::new(0.0, 0.0, 1.0), Interpolation::Linear);
+        let mut a = Animation::new();
+        a.add_shape_animation(sa);
+
+        let s = a.scene_at(&scene, 0.5).unwrap();
+        let shape = &s.get_world().get_shapes()[1];
+        let expected = Matrix::translation(1.0, 0.0, 0.0)
+            * Matrix::rotate_y(PI / 2.0)
+            * Matrix::scale(2.0, 2.0, 2.0)
+            * Matrix::translation(0.0, -1.0, 0.0);
+        assert_matrix(shape.get_transformation(), &expected);
+        assert_matrix(shape.get_inverse_transformation(), &Matrix::invert(&expected).unwrap());
+        assert_color(shape.get_material().get_color(), &Color::new(0.5, 0.0, 0.5));
+    }
+
+    #[test]
+    fn test_animation_errors() {
+        let scene = test_scene();
+        let mut a = Animation::new();
+        a.add_shape_animation(ShapeAnimation::new(5));
+        match a.scene_at(&scene, 0.0) {
+            Err(AnimationError::ShapeNotFound(5)) => {}
+            _ => panic!("expected ShapeNotFound"),
+        }
+
+        let mut sa = ShapeAnimation::new(0);
+        sa.add_scale(0.0, Tuple4D::new_vector(0.0, 1.0, 1.0), Interpolation::Linear);
+        let mut a = Animation::new();
+        a.add_shape_animation(sa);
+        match a.scene_at(&scene, 0.0) {
+            Err(AnimationError::NonInvertibleTransformation(_)) => {}
+            _ => panic!("expected NonInvertibleTransformation"),
+        }
+    }
+}
@@ -0,0 +1,152 @@
+use std::error::Error;
+
+use raytracer_lib_no_std::RenderSettings;
+use raytracer_lib_std::{Canvas, CanvasOpsStd, RenderedSceneOps, Scene, SceneOps};
+
+use crate::animation::{Animation, AnimationError, AnimationOps};
+use crate::BackendOps;
+
+// renders every frame of the animation with the given backend and settings and hands the
+// canvas together with the frame index to on_frame
+pub fn render_animation<F>(
+    backend: &dyn BackendOps,
+    scene: &Scene,
+    animation: &Animation,
+    fps: f32,
+    settings: &RenderSettings,
+    mut on_frame: F,
+) -> Result<(), Box<dyn Error>>
+where
+    F: FnMut(usize, Canvas) -> Result<(), Box<dyn Error>>,
+{
+    if !(fps > 0.0) || !fps.is_finite() {
+        return Err(Box::new(AnimationError::InvalidFps(fps)));
+    }
+    for frame in 0..animation.frame_count(fps) {
+        let mut s = animation.scene_at(scene, frame as f32 / fps)?;
+        let camera = s.get_camera().clone();
+        let canvas = backend
+            .render_scene(s.get_world_mut(), &camera, settings)?
+            .into_canvas();
+        on_frame(frame, canvas)?;
+    }
+    Ok(())
+}
+
+// writes the frames as numbered PNG files "{filename_prefix}_{frame:0>8}.png"
+// and returns the filenames in order
+pub fn render_frame_sequence(
+    backend: &dyn BackendOps,
+    scene: &Scene,
+    animation: &Animation,
+    fps: f32,
+    settings: &RenderSettings,
+    filename_prefix: &str,
+) -> Result<Vec<String>, Box<dyn Error>> {
+    let mut filenames = Vec::new();
+    render_animation(backend, scene, animation, fps, settings, |frame, canvas| {
+        let filename = format!("{}_{:0>8}.png", filename_prefix, frame);
+        canvas.write_png(&filename)?;
+        filenames.push(filename);
+        Ok(())
+    })?;
+    Ok(filenames)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::f32::consts::PI;
+    use std::fs;
+
+    use raytracer_lib_no_std::prelude::*;
+    use raytracer_lib_std::{CanvasOps, World, WorldOps};
+
+    use crate::animation::Interpolation;
+    use crate::Backend;
+
+    use super::*;
+
+    fn test_scene() -> Scene {
+        let mut world = World::new();
+        world.add_shape(Shape::new(ShapeEnum::Sphere(Sphere::new()))).unwrap();
+
+        let mut camera = Camera::new(8, 6, PI / 3.0);
+        camera.calc_pixel_size();
+        camera.set_transformation(Matrix::view_transform(
+            &Tuple4D::new_point(0.0, 0.0, -5.0),
+            &Tuple4D::new_point(0.0, 0.0, 0.0),
+            &Tuple4D::new_vector(0.0, 1.0, 0.0),
+        ));
+        Scene::new(world, camera)
+    }
+
+    #[test]
+    fn test_render_animation() {
+        let scene = test_scene();
+        let mut a = Animation::new();
+        a.add_light_intensity(0.0, Color::new(1.0, 1.0, 1.0), Interpolation::Linear);
+        a.add_light_intensity(1.0, Color::new(0.0, 0.0, 0.0), Interpolation::Linear);
+
+        let backend = Backend::new();
+        let b = backend.get_backend(&backend.get_available_backends()[0]).unwrap();
+        let settings = RenderSettings::from_camera(scene.get_camera());
+        let mut centers = Vec::new();
+        render_animation(b.as_ref(), &scene, &a, 2.0, &settings, |frame, canvas| {
+            assert_eq!(frame, centers.len());
+            assert_eq!(canvas.get_width(), 8);
+            // the rays miss the sphere in the corners
+            assert_color(&canvas.pixel_at(0, 0).color, &BLACK);
+            centers.push(canvas.pixel_at(4, 3).color);
+            Ok(())
+        })
+        .unwrap();
+
+        assert_eq!(centers.len(), 3);
+        assert!(centers[0].r > centers[1].r);
+        assert!(centers[1].r > centers[2].r);
+        // even the ambient part depends on the light intensity
+        assert_color(&centers[2], &BLACK);
+    }
+
+    #[test]
+    fn test_render_frame_sequence() {
+        let scene = test_scene();
+        let mut a = Animation::new();
+        a.add_camera_from(0.0, Tuple4D::new_point(0.0, 0.0, -5.0), Interpolation::Linear);
+        a.add_camera_from(1.0, Tuple4D::new_point(0.0, 0.0, -8.0), Interpolation::Linear);
+
+        let prefix = env::temp_dir().join("raytracer_test_frame_sequence");
+        let backend = Backend::new();
+        let b = backend.get_backend(&backend.get_available_backends()[0]).unwrap();
+        let settings = RenderSettings::from_camera(scene.get_camera());
+        let filenames =
+            render_frame_sequence(b.as_ref(), &scene, &a, 1.0, &settings, prefix.to_str().unwrap()).unwrap();
+
+        assert_eq!(filenames.len(), 2);
+        assert!(filenames[1].ends_with("raytracer_test_frame_sequence_00000001.png"));
+        for f in filenames.iter() {
+            assert!(fs::metadata(f).is_ok());
+            fs::remove_file(f).unwrap();
+        }
+    }
+    #[test]
+    fn test_render_animation_rejects_invalid_fps() {
+        let scene = test_scene();
+        let a = Animation::new();
+        let settings = RenderSettings::from_camera(scene.get_camera());
+        let backend = Backend::new();
+        let b = backend.get_backend(&backend.get_available_backends()[0]).unwrap();
+        for fps in [0.0, -1.0, std::f32::NAN, std::f32::INFINITY].iter() {
+            let err = render_animation(b.as_ref(), &scene, &a, *fps, &settings, |_, _| {
+                panic!("no frame expected")
+            })
+            .err()
+            .unwrap();
+            match err.downcast_ref::<AnimationError>() {
+                Some(AnimationError::InvalidFps(_)) => {}
+                _ => panic!("expected InvalidFps, got {}", err),
+            }
+        }
+    }
+}
@@ -0,0 +1,7 @@
+pub use self::animation::*;
+pub use self::frame_renderer::*;
+pub use self::track::*;
+
+mod animation;
+mod frame_renderer;
+mod track;
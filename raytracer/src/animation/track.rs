@@ -0,0 +1,226 @@
+#[cfg(feature = "use_serde")]
+use serde::{Deserialize, Serialize};
+
+use raytracer_lib_no_std::prelude::*;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+pub enum Interpolation {
+    // keep the value of the keyframe until the next one
+    Step,
+    Linear,
+    // smoothstep: slow start, slow end
+    EaseInOut,
+    // Catmull-Rom spline through the neighbouring keyframes
+    Spline,
+}
+
+// values which can be interpolated: a weighted sum with weights adding up to 1.0
+pub trait Animatable: Copy {
+    fn weighted_sum(values: &[(Self, f32)]) -> Self;
+}
+
+impl Animatable for f32 {
+    fn weighted_sum(values: &[(f32, f32)]) -> f32 {
+        values.iter().fold(0.0, |acc, (v, w)| acc + v * w)
+    }
+}
+
+impl Animatable for Tuple4D {
+    fn weighted_sum(values: &[(Tuple4D, f32)]) -> Tuple4D {
+        values
+            .iter()
+            .fold(Tuple4D::new(0.0, 0.0, 0.0, 0.0), |acc, (v, w)| acc + *v * *w)
+    }
+}
+
+impl Animatable for Color {
+    fn weighted_sum(values: &[(Color, f32)]) -> Color {
+        values.iter().fold(BLACK, |acc, (v, w)| acc + *v * *w)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+pub struct Keyframe<T> {
+    time: f32,
+    value: T,
+    // interpolation used between this keyframe and the next one
+    interpolation: Interpolation,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+pub struct Track<T> {
+    keyframes: Vec<Keyframe<T>>,
+}
+
+impl<T: Animatable> Keyframe<T> {
+    pub fn new(time: f32, value: T, interpolation: Interpolation) -> Keyframe<T> {
+        Keyframe {
+            time,
+            value,
+            interpolation,
+        }
+    }
+
+    pub fn get_time(&self) -> f32 {
+        self.time
+    }
+
+    pub fn get_value(&self) -> &T {
+        &self.value
+    }
+
+    pub fn get_interpolation(&self) -> Interpolation {
+        self.interpolation
+    }
+}
+
+impl<T: Animatable> Track<T> {
+    pub fn new() -> Track<T> {
+        Track { keyframes: Vec::new() }
+    }
+
+    // keyframes are kept sorted by time, a keyframe at an existing time replaces the old one
+    pub fn add_keyframe(&mut self, time: f32, value: T, interpolation: Interpolation) {
+        let k = Keyframe::new(time, value, interpolation);
+        match self.keyframes.iter().position(|k| k.time >= time) {
+            Some(idx) if self.keyframes[idx].time == time => self.keyframes[idx] = k,
+            Some(idx) => self.keyframes.insert(idx, k),
+            None => self.keyframes.push(k),
+        }
+    }
+
+    pub fn get_keyframes(&self) -> &Vec<Keyframe<T>> {
+        &self.keyframes
+    }
+
+    pub fn get_duration(&self) -> f32 {
+        self.keyframes.last().map(|k| k.time).unwrap_or(0.0)
+    }
+
+    // before the first and after the last keyframe the track holds the first / last value
+    pub fn value_at(&self, time: f32) -> Option<T> {
+        let first = self.keyframes.first()?;
+        let last = self.keyframes.last()?;
+        if time <= first.time {
+            return Some(first.value);
+        }
+        if time >= last.time {
+            return Some(last.value);
+        }
+
+        let idx = self.keyframes.iter().rposition(|k| k.time <= time)?;
+        let k1 = &self.keyframes[idx];
+        let k2 = &self.keyframes[idx + 1];
+        let t = (time - k1.time) / (k2.time - k1.time);
+
+        let value = match k1.interpolation {
+            Interpolation::Step => k1.value,
+            Interpolation::Linear => T::weighted_sum(&[(k1.value, 1.0 - t), (k2.value, t)]),
+            Interpolation::EaseInOut => {
+                let s = t * t * (3.0 - 2.0 * t);
+                T::weighted_sum(&[(k1.value, 1.0 - s), (k2.value, s)])
+            }
+            Interpolation::Spline => {
+                let k0 = &self.keyframes[if idx > 0 { idx - 1 } else { idx }];
+                let k3 = &self.keyframes[if idx + 2 < self.keyframes.len() {
+                    idx + 2
+                } else {
+                    idx + 1
+                }];
+                let t2 = t * t;
+                let t3 = t2 * t;
+                T::weighted_sum(&[
+                    (k0.value, 0.5 * (-t3 + 2.0 * t2 - t)),
+                    (k1.value, 0.5 * (3.0 * t3 - 5.0 * t2 + 2.0)),
+                    (k2.value, 0.5 * (-3.0 * t3 + 4.0 * t2 + t)),
+                    (k3.value, 0.5 * (t3 - t2)),
+                ])
+            }
+        };
+        Some(value)
+    }
+}
+
+impl<T: Animatable> Default for Track<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_track_empty() {
+        let track: Track<f32> = Track::new();
+        assert_eq!(track.value_at(1.0), None);
+        assert_float(track.get_duration(), 0.0);
+    }
+
+    #[test]
+    fn test_track_keyframes_sorted() {
+        let mut track = Track::new();
+        track.add_keyframe(2.0, 20.0, Interpolation::Linear);
+        track.add_keyframe(0.0, 0.0, Interpolation::Linear);
+        track.add_keyframe(1.0, 10.0, Interpolation::Linear);
+        track.add_keyframe(1.0, 15.0, Interpolation::Linear);
+
+        let times: Vec<f32> = track.get_keyframes().iter().map(|k| k.get_time()).collect();
+        assert_eq!(times, vec![0.0, 1.0, 2.0]);
+        assert_float(*track.get_keyframes()[1].get_value(), 15.0);
+        assert_float(track.get_duration(), 2.0);
+    }
+
+    #[test]
+    fn test_track_linear() {
+        let mut track = Track::new();
+        track.add_keyframe(1.0, 10.0, Interpolation::Linear);
+        track.add_keyframe(3.0, 20.0, Interpolation::Linear);
+
+        assert_float(track.value_at(0.0).unwrap(), 10.0);
+        assert_float(track.value_at(2.0).unwrap(), 15.0);
+        assert_float(track.value_at(2.5).unwrap(), 17.5);
+        assert_float(track.value_at(5.0).unwrap(), 20.0);
+    }
+
+    #[test]
+    fn test_track_step_and_ease() {
+        let mut track = Track::new();
+        track.add_keyframe(0.0, 0.0, Interpolation::Step);
+        track.add_keyframe(1.0, 1.0, Interpolation::EaseInOut);
+        track.add_keyframe(2.0, 2.0, Interpolation::Linear);
+
+        assert_float(track.value_at(0.9).unwrap(), 0.0);
+        assert_float(track.value_at(1.5).unwrap(), 1.5);
+        assert_float(track.value_at(1.25).unwrap(), 1.15625);
+    }
+
+    #[test]
+    fn test_track_spline_passes_through_keyframes() {
+        let mut track = Track::new();
+        track.add_keyframe(0.0, 0.0, Interpolation::Spline);
+        track.add_keyframe(1.0, 1.0, Interpolation::Spline);
+        track.add_keyframe(2.0, 0.0, Interpolation::Spline);
+        track.add_keyframe(3.0, 1.0, Interpolation::Spline);
+
+        assert_float(track.value_at(1.0).unwrap(), 1.0);
+        assert_float(track.value_at(2.0).unwrap(), 0.0);
+        // symmetric around the middle segment
+        assert_float(track.value_at(1.5).unwrap(), 0.5);
+        assert!(track.value_at(0.9).unwrap() > 0.9);
+    }
+
+    #[test]
+    fn test_track_tuple_keeps_point() {
+        let mut track = Track::new();
+        track.add_keyframe(0.0, Tuple4D::new_point(0.0, 0.0, 0.0), Interpolation::Spline);
+        track.add_keyframe(1.0, Tuple4D::new_point(2.0, 4.0, 6.0), Interpolation::Spline);
+
+        let p = track.value_at(0.5).unwrap();
+        assert_tuple(&p, &Tuple4D::new_point(1.0, 2.0, 3.0));
+    }
+}
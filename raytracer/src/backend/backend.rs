@@ -1,11 +1,12 @@
 use core::fmt;
 use std::error::Error;
+use std::str::FromStr;
 
 #[cfg(feature = "use_serde")]
 use serde::{Deserialize, Serialize};
 
-use raytracer_lib_no_std::Camera;
-use raytracer_lib_std::{Canvas, World};
+use raytracer_lib_no_std::{Camera, RenderSettings, RenderSettingsOps, ShapeEnum};
+use raytracer_lib_std::{Canvas, RaytracerError, RenderedScene, RenderedSceneOps, World, WorldOps};
 
 #[cfg(feature = "cpu_multi_core")]
 use crate::BackendCpuMultiCore;
@@ -18,7 +19,7 @@ use crate::BackendWasm;
 
 // TODO: use Vec<Light> if multiple light sources should be supported
 
-#[derive(Clone, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 #[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
 pub enum BackendEnum {
     #[cfg(feature = "cpu_single_core")]
@@ -40,10 +41,89 @@ pub struct Backend {
     available_backends: BackendVec,
 }
 
+// what a backend is able to render. used to pick a backend which fits a scene
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BackendCapabilities {
+    pub antialiasing: bool,
+    pub area_lights: bool,
+    // None if the number of shapes is only limited by memory
+    pub max_shapes: Option<usize>,
+    // the image is computed pixel by pixel on the host, so it can be split
+    // into tiles and the progress reported in between
+    pub supports_progress: bool,
+    // the kernel counts rays and intersection tests, see RenderSettings::set_statistics
+    pub statistics: bool,
+}
+
+impl BackendCapabilities {
+    // fails with CapacityExceeded if the world has more shapes than max_shapes. a ray is tested against every
+    // prototype of an instance, so an instance counts as its prototypes and the prototypes themselves don't count
+    pub fn check_shapes(&self, world: &World) -> Result<(), RaytracerError> {
+        let max_shapes = match self.max_shapes {
+            Some(max_shapes) => max_shapes,
+            None => return Ok(()),
+        };
+        let cnt: usize = world
+            .get_shapes()
+            .iter()
+            .filter(|s| !s.get_is_prototype())
+            .map(|s| match s.get_shape() {
+                ShapeEnum::Instance(ref instance) => instance.get_prototype_cnt(),
+                _ => 1,
+            })
+            .sum();
+        if cnt > max_shapes {
+            return Err(RaytracerError::CapacityExceeded(max_shapes));
+        }
+        Ok(())
+    }
+}
+
 pub trait BackendOps {
-    fn render_world(&self, world: &mut World, c: &Camera) -> Result<Canvas, Box<dyn Error>>;
+    // renders with the settings stored in the camera
+    fn render_world(&self, world: &mut World, c: &Camera) -> Result<Canvas, RaytracerError> {
+        self.render_world_with_settings(world, c, &RenderSettings::from_camera(c))
+    }
+
+    fn render_world_with_settings(
+        &self,
+        world: &mut World,
+        c: &Camera,
+        settings: &RenderSettings,
+    ) -> Result<Canvas, RaytracerError> {
+        Ok(self.render_scene(world, c, settings)?.into_canvas())
+    }
+
+    // like render_world_with_settings, but also returns the duration and, if
+    // requested by the settings and supported by the backend, the statistics
+    fn render_scene(
+        &self,
+        world: &mut World,
+        c: &Camera,
+        settings: &RenderSettings,
+    ) -> Result<RenderedScene, RaytracerError>;
+
+    fn get_capabilities(&self) -> BackendCapabilities;
+
+    // checks that the backend can actually be used on this machine, e.g. that
+    // a CUDA device is present. backends running on the CPU are always usable
+    fn init(&self) -> Result<(), RaytracerError> {
+        Ok(())
+    }
 }
 
+// order in which best_available() tries the backends
+const PREFERENCE_ORDER: &[BackendEnum] = &[
+    #[cfg(feature = "cuda")]
+    BackendEnum::Cuda,
+    #[cfg(feature = "cpu_multi_core")]
+    BackendEnum::CpuMultiCore,
+    #[cfg(feature = "cpu_single_core")]
+    BackendEnum::CpuSingleCore,
+    #[cfg(feature = "wasm")]
+    BackendEnum::Wasm,
+];
+
 impl Backend {
     pub fn new() -> Backend {
         let mut list = Vec::new();
@@ -70,9 +150,10 @@ impl Backend {
     }
 
     pub fn get_backend(&self, backend_type: &BackendEnum) -> Result<Box<dyn BackendOps>, Box<dyn Error>> {
-        if !self.available_backends.contains(&backend_type) {
-            // TODO: erro from string or custom error ?!
-            return Err(Box::new(BackendError::BackendNotAvailable));
+        if !self.available_backends.contains(backend_type) {
+            return Err(Box::new(BackendError::BackendNotAvailable(
+                backend_type.get_name().to_string(),
+            )));
         }
         match backend_type {
             #[cfg(feature = "cpu_single_core")]
@@ -88,23 +169,113 @@ impl Backend {
             BackendEnum::Wasm => Ok(Box::new(BackendWasm::new())),
         }
     }
+
+    // looks up a backend by the name used on the command line and in config
+    // files, e.g. "cpu_multi_core"
+    pub fn get_backend_by_name(&self, name: &str) -> Result<(BackendEnum, Box<dyn BackendOps>), Box<dyn Error>> {
+        let backend_type = self
+            .available_backends
+            .iter()
+            .find(|b| b.get_name() == name)
+            .ok_or_else(|| BackendError::UnknownBackend {
+                name: name.to_string(),
+                available: self.get_available_backend_names(),
+            })?;
+        Ok((*backend_type, self.get_backend(backend_type)?))
+    }
+
+    pub fn get_available_backend_names(&self) -> Vec<String> {
+        self.available_backends
+            .iter()
+            .map(|b| b.get_name().to_string())
+            .collect()
+    }
+
+    // returns the fastest backend which initializes successfully: CUDA, CPU multi
+    // core, CPU single core and WASM - in that order. a backend failing init()
+    // is skipped, so a missing GPU falls back to the CPU
+    pub fn best_available(&self) -> Result<(BackendEnum, Box<dyn BackendOps>), BackendError> {
+        let mut failures = Vec::new();
+        for backend_type in PREFERENCE_ORDER.iter().filter(|b| self.available_backends.contains(b)) {
+            let backend = match self.get_backend(backend_type) {
+                Ok(backend) => backend,
+                Err(e) => {
+                    failures.push(format!("{}: {}", backend_type.get_name(), e));
+                    continue;
+                }
+            };
+            match backend.init() {
+                Ok(()) => return Ok((*backend_type, backend)),
+                Err(e) => failures.push(format!("{}: {}", backend_type.get_name(), e)),
+            }
+        }
+        Err(BackendError::NoBackendAvailable(failures))
+    }
 }
 
-#[derive(Debug)]
-enum BackendError {
-    BackendNotAvailable,
+impl BackendEnum {
+    // the name used on the command line and in config files
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            #[cfg(feature = "cpu_single_core")]
+            BackendEnum::CpuSingleCore => "cpu_single_core",
+
+            #[cfg(feature = "cpu_multi_core")]
+            BackendEnum::CpuMultiCore => "cpu_multi_core",
+
+            #[cfg(feature = "cuda")]
+            BackendEnum::Cuda => "cuda",
+
+            #[cfg(feature = "wasm")]
+            BackendEnum::Wasm => "wasm",
+        }
+    }
 }
 
-// TODO: error handling =!=! the display trait ?!
-impl Error for BackendError {
-    fn description(&self) -> &str {
-        "I'm the superhero of errors"
+impl FromStr for BackendEnum {
+    type Err = BackendError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Backend::new()
+            .get_available_backends()
+            .iter()
+            .find(|b| b.get_name() == name)
+            .cloned()
+            .ok_or_else(|| BackendError::UnknownBackend {
+                name: name.to_string(),
+                available: Backend::new().get_available_backend_names(),
+            })
     }
 }
 
+#[derive(Debug, PartialEq)]
+pub enum BackendError {
+    BackendNotAvailable(String),
+    UnknownBackend { name: String, available: Vec<String> },
+    NoBackendAvailable(Vec<String>),
+}
+
+impl Error for BackendError {}
+
 impl fmt::Display for BackendError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "BackendError!")
+        match self {
+            BackendError::BackendNotAvailable(name) => {
+                write!(f, "backend '{}' is not enabled in this build", name)
+            }
+            BackendError::UnknownBackend { name, available } => write!(
+                f,
+                "unknown or unavailable backend '{}', available backends: {}",
+                name,
+                available.join(", ")
+            ),
+            BackendError::NoBackendAvailable(failures) if failures.is_empty() => {
+                write!(f, "no backend enabled in this build")
+            }
+            BackendError::NoBackendAvailable(failures) => {
+                write!(f, "no backend could be initialized ({})", failures.join("; "))
+            }
+        }
     }
 }
 
@@ -125,3 +296,104 @@ impl fmt::Display for BackendEnum {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use raytracer_lib_no_std::{CameraOps, Instance, Shape, Sphere};
+
+    use super::*;
+
+    #[test]
+    fn test_backend_names() {
+        let b = Backend::new();
+        for backend_type in b.get_available_backends() {
+            let parsed: BackendEnum = backend_type.get_name().parse().unwrap();
+            assert_eq!(parsed, *backend_type);
+
+            let (found, _) = b.get_backend_by_name(backend_type.get_name()).unwrap();
+            assert_eq!(found, *backend_type);
+        }
+    }
+
+    #[test]
+    fn test_unknown_backend_name() {
+        let b = Backend::new();
+        let err = b.get_backend_by_name("opencl").err().unwrap();
+        let msg = err.to_string();
+        assert!(msg.contains("'opencl'"));
+        for name in b.get_available_backend_names() {
+            assert!(msg.contains(&name));
+        }
+
+        assert!("opencl".parse::<BackendEnum>().is_err());
+    }
+
+    #[test]
+    fn test_best_available() {
+        let b = Backend::new();
+        let (backend_type, backend) = b.best_available().unwrap();
+        assert!(b.get_available_backends().contains(&backend_type));
+        assert!(backend.init().is_ok());
+    }
+
+    #[test]
+    fn test_no_backend_available() {
+        let b = Backend {
+            available_backends: Vec::new(),
+        };
+        let err = b.best_available().err().unwrap();
+        assert_eq!(err, BackendError::NoBackendAvailable(Vec::new()));
+        assert_eq!(err.to_string(), "no backend enabled in this build");
+    }
+
+    #[test]
+    fn test_capabilities() {
+        let b = Backend::new();
+        for backend_type in b.get_available_backends() {
+            let caps = b.get_backend(backend_type).unwrap().get_capabilities();
+            assert!(caps.antialiasing);
+            assert!(caps.area_lights);
+        }
+        #[cfg(feature = "cpu_single_core")]
+        assert_eq!(BackendCpuSingleCore::new().get_capabilities().max_shapes, None);
+    }
+
+    #[test]
+    fn test_render_scene_rejects_invalid_settings() {
+        let mut settings = RenderSettings::new();
+        settings.set_antialiasing(true);
+        settings.set_antialiasing_size(4);
+        let mut w = World::new();
+        let c = Camera::new(4, 4, 1.0);
+        let b = Backend::new();
+        for backend_type in b.get_available_backends() {
+            match b.get_backend(backend_type).unwrap().render_scene(&mut w, &c, &settings) {
+                Err(RaytracerError::InvalidSettings("antialiasing_size")) => {}
+                r => panic!("expected InvalidSettings, got {:?}", r.map(|_| ())),
+            }
+        }
+    }
+
+    #[test]
+    fn test_check_shapes() {
+        let mut caps = Backend::new().best_available().unwrap().1.get_capabilities();
+        caps.max_shapes = Some(3);
+
+        let mut w = World::new();
+        let prototypes = vec![Shape::new(ShapeEnum::Sphere(Sphere::new())); 2];
+        let first = w.add_prototypes(prototypes).unwrap();
+        let instance = Instance::try_new(w.get_shapes(), first, 2).unwrap();
+        w.add_shape(Shape::new(ShapeEnum::Instance(instance))).unwrap();
+        w.add_shape(Shape::new(ShapeEnum::Sphere(Sphere::new()))).unwrap();
+        assert!(caps.check_shapes(&w).is_ok());
+
+        w.add_shape(Shape::new(ShapeEnum::Sphere(Sphere::new()))).unwrap();
+        match caps.check_shapes(&w) {
+            Err(RaytracerError::CapacityExceeded(3)) => {}
+            r => panic!("expected CapacityExceeded, got {:?}", r),
+        }
+
+        caps.max_shapes = None;
+        assert!(caps.check_shapes(&w).is_ok());
+    }
+}
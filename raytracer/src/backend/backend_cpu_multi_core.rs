@@ -1,45 +1,91 @@
-use std::error::Error;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use rayon::iter::ParallelIterator;
-use rayon::prelude::IntoParallelIterator;
+use rayon::slice::ParallelSliceMut;
+use rayon::ThreadPoolBuilder;
 
 use cpu_kernel_raytracer::CpuKernel;
-use raytracer_lib_no_std::{BLACK, Color, ColorOps, Light, Pixel, Ray, Shape};
+use raytracer_lib_no_std::{
+    BLACK, Color, ColorOps, Light, Pixel, Ray, RenderSettings, RenderSettingsOps, RenderStats, RenderStatsOps, Shape,
+};
 use raytracer_lib_no_std::camera::{Camera, CameraOps};
-use raytracer_lib_no_std::MAX_REFLECTION_RECURSION_DEPTH;
-use raytracer_lib_std::{Canvas, CanvasOps, World, WorldOps};
+use raytracer_lib_std::{
+    Canvas, CanvasOps, RaytracerError, RenderStatistics, RenderStatisticsOps, RenderedScene, RenderedSceneOps, World,
+    WorldOps,
+};
 
-use crate::backend::backend_helper::{calc_pixel, get_antialiasing_params};
-use crate::BackendOps;
+use crate::backend::backend_helper::{calc_tile, get_antialiasing_params, rendered_scene};
+use crate::{BackendCapabilities, BackendOps};
 
 pub struct BackendCpuMultiCore {}
 
 impl BackendOps for BackendCpuMultiCore {
-    fn render_world(&self, world: &mut World, c: &Camera) -> Result<Canvas, Box<dyn Error>> {
+    fn render_scene(
+        &self,
+        world: &mut World,
+        c: &Camera,
+        settings: &RenderSettings,
+    ) -> Result<RenderedScene, RaytracerError> {
+        settings.validate()?;
         let start = Instant::now();
-        let canvas = render_world_multi_core(world, c, CpuKernel::color_at);
-        let stopped = Instant::now();
-        println!("cpu multicore       duration  {:?}  \n ", stopped.duration_since(start));
-        Ok(canvas)
+        let (canvas, statistics) = render_world_multi_core(world, c, settings, CpuKernel::color_at);
+        let rendered = rendered_scene(canvas, start, statistics, settings);
+        println!("cpu multicore       duration  {:?}  \n ", rendered.get_duration());
+        Ok(rendered)
+    }
+
+    fn get_capabilities(&self) -> BackendCapabilities {
+        BackendCapabilities {
+            antialiasing: true,
+            area_lights: true,
+            // the intersection lists keep the nearest hits and count the rest, see IntersectionList::get_overflows
+            max_shapes: None,
+            supports_progress: true,
+            statistics: true,
+        }
     }
 }
 
-pub fn render_world_multi_core<F: Sync + Send>(world: &mut World, c: &Camera, f: F) -> Canvas
+// every row of the image is a tile, the tiles are rendered in parallel and
+// count into their own RenderStats
+pub fn render_world_multi_core<F: Sync + Send>(
+    world: &mut World,
+    c: &Camera,
+    settings: &RenderSettings,
+    f: F,
+) -> (Canvas, RenderStatistics)
 where
-    F: Fn(&Vec<Shape>, &Vec<Light>, &Ray, i32, bool, bool, bool, bool) -> Color,
+    F: Fn(&Vec<Shape>, &Vec<f32>, &Vec<Light>, &Ray, i32, &RenderSettings, &mut RenderStats, bool) -> Color,
 {
-    let (n_samples, jitter_matrix) = get_antialiasing_params(c);
+    let (n_samples, jitter_matrix) = get_antialiasing_params(settings);
 
     let mut canvas = Canvas::new(c.get_hsize(), c.get_vsize());
     // TODO: remove, when WOrld has lights vector
     let mut lights = Vec::new();
     lights.push(world.get_light().clone());
-    canvas
-        .get_pixels_mut()
-        .into_par_iter()
-        .for_each(|p| calc_pixel(world, c, &f, n_samples, &jitter_matrix, &lights, p));
-    canvas
+
+    let width = c.get_hsize().max(1);
+    let mut render = || -> Vec<(RenderStats, Duration)> {
+        canvas
+            .get_pixels_mut()
+            .par_chunks_mut(width)
+            .map(|row| calc_tile(world, c, settings, &f, n_samples, &jitter_matrix, &lights, row))
+            .collect()
+    };
+    // 0 threads: use the global pool, which has one thread per core
+    let tiles = match settings.get_threads() {
+        0 => render(),
+        threads => match ThreadPoolBuilder::new().num_threads(threads).build() {
+            Ok(pool) => pool.install(render),
+            Err(_) => render(),
+        },
+    };
+
+    let mut statistics = RenderStatistics::new();
+    for (stats, duration) in tiles.iter() {
+        statistics.add_tile(stats, *duration);
+    }
+    (canvas, statistics)
 }
 
 impl BackendCpuMultiCore {
@@ -53,22 +99,23 @@ impl BackendCpuMultiCore {
         c: &Camera,
         x: usize,
         y: usize,
-    ) -> Result<Canvas, Box<dyn Error>> {
+    ) -> Result<Canvas, RaytracerError> {
         let mut canvas = Canvas::new(c.get_hsize(), c.get_vsize());
 
         // TODO: remove, when WOrld has lights vector
         let mut lights = Vec::new();
         lights.push(world.get_light().clone());
 
+        let settings = RenderSettings::from_camera(c);
         let r = Camera::ray_for_pixel(c, x, y);
         let mut color = CpuKernel::color_at(
             world.get_shapes(),
+            world.get_samples(),
             &lights,
             &r,
-            MAX_REFLECTION_RECURSION_DEPTH,
-            c.get_calc_reflection(),
-            c.get_calc_refraction(),
-            c.get_calc_shadows(),
+            settings.get_max_depth(),
+            &settings,
+            &mut RenderStats::new(),
             true,
         );
         println!("'render_world_debug'   color   = {:?}", color);
@@ -1,23 +1,42 @@
-use std::error::Error;
 use std::time::Instant;
 
 use cpu_kernel_raytracer::CpuKernel;
-use raytracer_lib_no_std::{CameraOps, Color, Light, Ray, Shape};
+use raytracer_lib_no_std::{CameraOps, Color, Light, Ray, RenderSettings, RenderSettingsOps, RenderStats, Shape};
 use raytracer_lib_no_std::camera::Camera;
-use raytracer_lib_std::{Canvas, CanvasOps, World, WorldOps};
+use raytracer_lib_std::{
+    Canvas, CanvasOps, RaytracerError, RenderStatistics, RenderStatisticsOps, RenderedScene, RenderedSceneOps, World,
+    WorldOps,
+};
 
-use crate::backend::backend_helper::{calc_pixel, get_antialiasing_params};
-use crate::BackendOps;
+use crate::backend::backend_helper::{calc_tile, get_antialiasing_params, rendered_scene};
+use crate::{BackendCapabilities, BackendOps};
 
 pub struct BackendCpuSingleCore {}
 
 impl BackendOps for BackendCpuSingleCore {
-    fn render_world(&self, world: &mut World, c: &Camera) -> Result<Canvas, Box<dyn Error>> {
+    fn render_scene(
+        &self,
+        world: &mut World,
+        c: &Camera,
+        settings: &RenderSettings,
+    ) -> Result<RenderedScene, RaytracerError> {
+        settings.validate()?;
         let start = Instant::now();
-        let canvas = render_world_single_core(world, c, CpuKernel::color_at);
-        let stopped = Instant::now();
-        println!("cpu single core     duration: {:?} ", stopped.duration_since(start));
-        Ok(canvas)
+        let (canvas, statistics) = render_world_single_core(world, c, settings, CpuKernel::color_at);
+        let rendered = rendered_scene(canvas, start, statistics, settings);
+        println!("cpu single core     duration: {:?} ", rendered.get_duration());
+        Ok(rendered)
+    }
+
+    fn get_capabilities(&self) -> BackendCapabilities {
+        BackendCapabilities {
+            antialiasing: true,
+            area_lights: true,
+            // the intersection lists keep the nearest hits and count the rest, see IntersectionList::get_overflows
+            max_shapes: None,
+            supports_progress: true,
+            statistics: true,
+        }
     }
 }
 
@@ -27,19 +46,27 @@ impl BackendCpuSingleCore {
     }
 }
 
-pub fn render_world_single_core<F>(world: &mut World, c: &Camera, f: F) -> Canvas
+// every row of the image is a tile
+pub fn render_world_single_core<F>(
+    world: &mut World,
+    c: &Camera,
+    settings: &RenderSettings,
+    f: F,
+) -> (Canvas, RenderStatistics)
 where
-    F: Fn(&Vec<Shape>, &Vec<Light>, &Ray, i32, bool, bool, bool, bool) -> Color,
+    F: Fn(&Vec<Shape>, &Vec<f32>, &Vec<Light>, &Ray, i32, &RenderSettings, &mut RenderStats, bool) -> Color,
 {
-    let (n_samples, jitter_matrix) = get_antialiasing_params(c);
+    let (n_samples, jitter_matrix) = get_antialiasing_params(settings);
 
     let mut canvas = Canvas::new(c.get_hsize(), c.get_vsize());
     // TODO: remove, when WOrld has lights vector
     let mut lights = Vec::new();
     lights.push(world.get_light().clone());
-    canvas
-        .get_pixels_mut()
-        .into_iter()
-        .for_each(|p| calc_pixel(world, c, &f, n_samples, &jitter_matrix, &mut lights, p));
-    canvas
+
+    let mut statistics = RenderStatistics::new();
+    for row in canvas.get_pixels_mut().chunks_mut(c.get_hsize().max(1)) {
+        let (stats, duration) = calc_tile(world, c, settings, &f, n_samples, &jitter_matrix, &lights, row);
+        statistics.add_tile(&stats, duration);
+    }
+    (canvas, statistics)
 }
@@ -1,77 +1,98 @@
 extern crate rustacuda;
 
-use std::error::Error;
 use std::ffi::CString;
 use std::time::Instant;
 
+use rustacuda::error::CudaError;
 use rustacuda::memory::{cuda_device_get_limit_stacksize, cuda_device_set_limit_stacksize, DeviceBox};
 use rustacuda::prelude::{
     Context, ContextFlags, CopyDestination, CudaFlags, Device, DeviceBuffer, Module, Stream, StreamFlags,
 };
 
-use crate::BackendOps;
-use raytracer_lib_no_std::{Camera, CameraOps, ColorOps, BLACK};
-use raytracer_lib_std::{Canvas, CanvasOps, World, WorldOps};
+use crate::{BackendCapabilities, BackendOps};
+use raytracer_lib_no_std::{
+    Camera, CameraOps, ColorOps, RenderSettings, RenderSettingsOps, BLACK, CUDA_MAX_INTERSECTIONLIST_LEN,
+    SHAPE_MAX_INTERSECTIONS,
+};
+use raytracer_lib_std::{Canvas, CanvasOps, RaytracerError, RenderedScene, RenderedSceneOps, World, WorldOps};
 
 pub struct BackendCuda {}
 
+// the kernel stores the intersections of a ray in fixed size lists and a shape contributes
+// up to SHAPE_MAX_INTERSECTIONS of them
+const CUDA_MAX_SHAPES: usize = CUDA_MAX_INTERSECTIONLIST_LEN / SHAPE_MAX_INTERSECTIONS;
+
 impl BackendOps for BackendCuda {
-    fn render_world(&self, world: &mut World, c: &Camera) -> Result<Canvas, Box<dyn Error>> {
+    fn render_scene(
+        &self,
+        world: &mut World,
+        c: &Camera,
+        settings: &RenderSettings,
+    ) -> Result<RenderedScene, RaytracerError> {
         let start = Instant::now();
 
+        settings.validate()?;
+        // the intersection lists of the kernel have a fixed length
+        self.get_capabilities().check_shapes(world)?;
+
         // CUDA setup
-        rustacuda::init(CudaFlags::empty())?;
+        rustacuda::init(CudaFlags::empty()).map_err(cuda_error)?;
 
-        let device = Device::get_device(0)?;
+        let device = Device::get_device(0).map_err(cuda_error)?;
 
-        let _context = Context::create_and_push(ContextFlags::MAP_HOST | ContextFlags::SCHED_AUTO, device)?;
+        let _context =
+            Context::create_and_push(ContextFlags::MAP_HOST | ContextFlags::SCHED_AUTO, device).map_err(cuda_error)?;
 
         // Load the module containing the function we want to call
         // let ptx = env!("KERNEL_PTX_PATH_RUST_RENDER");
         // println!("ptx = {}", ptx);
         // let ptx_content = include_str!(ptx);
         //    let module_data = CString::new(ptx_content)?;
-        let module_data = CString::new(include_str!("../../../cuda_kernel_raytracer/ptx/cuda_kernel_raytracer.ptx")).expect("Unable to load ptx file ");
-        let module = Module::load_from_string(&module_data).expect("Unable to create kernel name string");
+        let module_data = CString::new(include_str!(
+            "../../../cuda_kernel_raytracer/ptx/cuda_kernel_raytracer.ptx"
+        ))
+        .map_err(|e| RaytracerError::Backend(format!("invalid ptx file: {}", e)))?;
+        let module = Module::load_from_string(&module_data).map_err(cuda_error)?;
 
         //  Create a stream to submit work to
-        let stream = Stream::new(StreamFlags::NON_BLOCKING, None)?;
+        let stream = Stream::new(StreamFlags::NON_BLOCKING, None).map_err(cuda_error)?;
 
-        let a = unsafe { cuda_device_get_limit_stacksize() }?;
+        let a = unsafe { cuda_device_get_limit_stacksize() }.map_err(cuda_error)?;
         // println!(" cudaLimitSTackSize   = {}", a);
         let a = a * 80;
         // println!(" set stack size to 20x the size   = {}", a);
         let _b = unsafe { cuda_device_set_limit_stacksize(a) };
 
-        let _a = unsafe { cuda_device_get_limit_stacksize() }?;
+        let _a = unsafe { cuda_device_get_limit_stacksize() }.map_err(cuda_error)?;
         // println!(" new  stack size    = {}", a);
 
         // width and height
         let w = c.get_hsize();
         let h = c.get_vsize();
-        let mut width = DeviceBox::new(&(w as f32)).expect("DeviceBox::new(w)   image save expect in 'backend_cuda' ");
-        let mut height = DeviceBox::new(&(h as f32)).expect("DeviceBox::new(h)   image save expect in 'backend_cuda' ");
+        let mut width = DeviceBox::new(&(w as f32)).map_err(cuda_error)?;
+        let mut height = DeviceBox::new(&(h as f32)).map_err(cuda_error)?;
 
         // PIXELS
         let mut pixels_vec = vec![BLACK; c.get_vsize() as usize * c.get_hsize() as usize];
-        let mut pixels = DeviceBuffer::from_slice(&pixels_vec)
-            .expect("DeviceBuffer::from_slice(&pixels_vec)    image save expect in 'backend_cuda' ");
+        let mut pixels = DeviceBuffer::from_slice(&pixels_vec).map_err(cuda_error)?;
 
-        let mut shapes_device = DeviceBuffer::from_slice(world.get_shapes_mut())
-            .expect("DeviceBuffer::from_slice(&shapes)    image save expect in 'backend_cuda' ");
+        let mut shapes_device = DeviceBuffer::from_slice(world.get_shapes_mut()).map_err(cuda_error)?;
         let cnt_shapes = world.get_shapes().len();
 
+        // the sample buffer of the heightfields
+        let mut samples_device = DeviceBuffer::from_slice(world.get_samples()).map_err(cuda_error)?;
+        let cnt_samples = world.get_samples().len();
+
         // we are using a vec of lights, world has only 1 light
         let mut lights_vec = Vec::new();
         lights_vec.push(world.get_light().clone());
-        let mut lights_device = DeviceBuffer::from_slice(&lights_vec)
-            .expect("DeviceBuffer::from_slice(&lights)    image save expect in 'backend_cuda' ");
+        let mut lights_device = DeviceBuffer::from_slice(&lights_vec).map_err(cuda_error)?;
         let cnt_lights = lights_vec.len();
 
         // CAMERA
         let camera_clone = c.clone();
-        let mut camera_device =
-            DeviceBox::new(&camera_clone).expect("DeviceBox::new(camera_clone)   image save expect in 'backend_cuda' ");
+        let mut camera_device = DeviceBox::new(&camera_clone).map_err(cuda_error)?;
+        let mut settings_device = DeviceBox::new(settings).map_err(cuda_error)?;
 
         // CUDA setup block/grid
         let b = (256, 1, 1);
@@ -90,22 +111,22 @@ impl BackendOps for BackendCuda {
                 pixels.as_device_ptr(),
                 shapes_device.as_device_ptr(),
                 cnt_shapes,
+                samples_device.as_device_ptr(),
+                cnt_samples,
                 lights_device.as_device_ptr(),
                 cnt_lights,
                 camera_device.as_device_ptr(),
+                settings_device.as_device_ptr(),
                 width.as_device_ptr(),
                 height.as_device_ptr(),
                 block.0,
                 block.1
-            ))?;
+            ))
+            .map_err(cuda_error)?;
         }
-        stream
-            .synchronize()
-            .expect("----     stream.synchronize()       expect in 'backend_cuda' ");
+        stream.synchronize().map_err(cuda_error)?;
 
-        pixels
-            .copy_to(&mut pixels_vec)
-            .expect(" pixels.copy_to(&mut pixels_vec)             expect in 'backend_cuda' ");
+        pixels.copy_to(&mut pixels_vec).map_err(cuda_error)?;
 
         let stopped = Instant::now();
         println!("\ncuda   {:?} \n", stopped.duration_since(start));
@@ -127,8 +148,32 @@ impl BackendOps for BackendCuda {
                 x = 0;
             }
         }
-        Ok(c)
+        Ok(RenderedScene::new(c, stopped.duration_since(start)))
     }
+
+    fn get_capabilities(&self) -> BackendCapabilities {
+        BackendCapabilities {
+            antialiasing: true,
+            area_lights: true,
+            max_shapes: Some(CUDA_MAX_SHAPES),
+            // the whole image is rendered by a single kernel launch
+            supports_progress: false,
+            // the GPU threads have no counters
+            statistics: false,
+        }
+    }
+
+    fn init(&self) -> Result<(), RaytracerError> {
+        rustacuda::init(CudaFlags::empty()).map_err(cuda_error)?;
+        let device = Device::get_device(0).map_err(cuda_error)?;
+        let _context =
+            Context::create_and_push(ContextFlags::MAP_HOST | ContextFlags::SCHED_AUTO, device).map_err(cuda_error)?;
+        Ok(())
+    }
+}
+
+fn cuda_error(e: CudaError) -> RaytracerError {
+    RaytracerError::Backend(format!("CUDA: {}", e))
 }
 
 impl BackendCuda {
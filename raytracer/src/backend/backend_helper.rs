@@ -1,10 +1,61 @@
-use raytracer_lib_no_std::{BLACK, Camera, CameraOps, Color, ColorOps, Light, Pixel, Ray, Shape};
-use raytracer_lib_no_std::MAX_REFLECTION_RECURSION_DEPTH;
-use raytracer_lib_std::{World, WorldOps};
+use std::time::{Duration, Instant};
+
+use raytracer_lib_no_std::{
+    Camera, CameraOps, Color, ColorOps, Light, Pixel, Ray, RenderSettings, RenderSettingsOps, RenderStats,
+    RenderStatsOps, Shape, BLACK,
+};
+use raytracer_lib_std::{
+    Canvas, RenderStatistics, RenderStatisticsOps, RenderedScene, RenderedSceneOps, World, WorldOps,
+};
+
+// the statistics are only handed out, if the settings ask for them
+pub fn rendered_scene(
+    canvas: Canvas,
+    start: Instant,
+    statistics: RenderStatistics,
+    settings: &RenderSettings,
+) -> RenderedScene {
+    let mut rendered = RenderedScene::new(canvas, Instant::now().duration_since(start));
+    rendered.set_intersection_overflows(statistics.get_counters().get_intersection_overflows());
+    if settings.get_statistics() {
+        rendered.set_statistics(Some(statistics));
+    }
+    rendered
+}
+
+// renders a tile of pixels with its own counters, so tiles can be rendered in parallel
+pub fn calc_tile<F>(
+    world: &World,
+    c: &Camera,
+    settings: &RenderSettings,
+    f: &F,
+    n_samples: usize,
+    jitter_matrix: &Vec<f32>,
+    lights: &Vec<Light>,
+    pixels: &mut [Pixel],
+) -> (RenderStats, Duration)
+where
+    F: Fn(&Vec<Shape>, &Vec<f32>, &Vec<Light>, &Ray, i32, &RenderSettings, &mut RenderStats, bool) -> Color,
+{
+    // the clock is only read, if the statistics are requested
+    let start = if settings.get_statistics() {
+        Some(Instant::now())
+    } else {
+        None
+    };
+    let mut stats = RenderStats::new();
+    pixels
+        .iter_mut()
+        .for_each(|p| calc_pixel(world, c, settings, &mut stats, f, n_samples, jitter_matrix, lights, p));
+    let duration = start.map(|s| Instant::now().duration_since(s)).unwrap_or_default();
+    (stats, duration)
+}
 
 pub fn calc_pixel<F>(
     world: &World,
     c: &Camera,
+    settings: &RenderSettings,
+    stats: &mut RenderStats,
     f: &F,
     n_samples: usize,
     jitter_matrix: &Vec<f32>,
@@ -12,15 +63,15 @@ pub fn calc_pixel<F>(
     p: &mut Pixel,
 ) -> ()
     where
-        F: Fn(&Vec<Shape>, &Vec<Light>, &Ray, i32, bool, bool, bool, bool) -> Color,
+        F: Fn(&Vec<Shape>, &Vec<f32>, &Vec<Light>, &Ray, i32, &RenderSettings, &mut RenderStats, bool) -> Color,
 {
     let x = p.x;
     let y = p.y;
     let mut color;
-    if c.get_antialiasing() {
-        color = calc_pixel_antialiasing(world, c, f, n_samples, jitter_matrix, &lights, x, y);
+    if settings.get_antialiasing() {
+        color = calc_pixel_antialiasing(world, c, settings, stats, f, n_samples, jitter_matrix, &lights, x, y);
     } else {
-        color = calc_pixel_no_antialiasing(world, c, f, &lights, x, y);
+        color = calc_pixel_no_antialiasing(world, c, settings, stats, f, &lights, x, y);
     }
     set_pixel_color(p, &mut color);
 }
@@ -35,21 +86,24 @@ fn set_pixel_color(p: &mut Pixel, color: &mut Color) {
 fn calc_pixel_no_antialiasing<F>(
     world: &World,
     c: &Camera,
+    settings: &RenderSettings,
+    stats: &mut RenderStats,
     f: &F,
     lights: &&Vec<Light>,
     x: usize,
     y: usize,
 ) -> Color
-    where F: Fn(&Vec<Shape>, &Vec<Light>, &Ray, i32, bool, bool, bool, bool) -> Color {
+    where F: Fn(&Vec<Shape>, &Vec<f32>, &Vec<Light>, &Ray, i32, &RenderSettings, &mut RenderStats, bool) -> Color {
     let r = Camera::ray_for_pixel(c, x, y);
+    stats.count_primary_ray();
     let color = f(
         world.get_shapes(),
+        world.get_samples(),
         &lights,
         &r,
-        MAX_REFLECTION_RECURSION_DEPTH,
-        c.get_calc_reflection(),
-        c.get_calc_refraction(),
-        c.get_calc_shadows(),
+        settings.get_max_depth(),
+        settings,
+        stats,
         false,
     );
     color
@@ -58,27 +112,30 @@ fn calc_pixel_no_antialiasing<F>(
 fn calc_pixel_antialiasing<F>(
     world: &World,
     c: &Camera,
+    settings: &RenderSettings,
+    stats: &mut RenderStats,
     f: &F,
     n_samples: usize,
     jitter_matrix: &Vec<f32>,
     lights: &&Vec<Light>,
     x: usize,
     y: usize,
-) -> Color where F: Fn(&Vec<Shape>, &Vec<Light>, &Ray, i32, bool, bool, bool, bool) -> Color  {
+) -> Color where F: Fn(&Vec<Shape>, &Vec<f32>, &Vec<Light>, &Ray, i32, &RenderSettings, &mut RenderStats, bool) -> Color  {
     let mut color = BLACK;
     // Accumulate light for N samples.
     for sample in 0..(n_samples * n_samples) {
         let delta_x = jitter_matrix[2 * sample] * c.get_pixel_size();
         let delta_y = jitter_matrix[2 * sample + 1] * c.get_pixel_size();
         let r = Camera::ray_for_pixel_anti_aliasing(c, x, y, delta_x, delta_y);
+        stats.count_primary_ray();
         let c = f(
             world.get_shapes(),
+            world.get_samples(),
             &lights,
             &r,
-            MAX_REFLECTION_RECURSION_DEPTH,
-            c.get_calc_reflection(),
-            c.get_calc_refraction(),
-            c.get_calc_shadows(),
+            settings.get_max_depth(),
+            settings,
+            stats,
             false,
         );
         color = c + color;
@@ -87,8 +144,8 @@ fn calc_pixel_antialiasing<F>(
     color
 }
 
-pub fn get_antialiasing_params(c: &Camera) -> (usize, Vec<f32>) {
-    let n_samples = c.get_antialiasing_size();
+pub fn get_antialiasing_params(settings: &RenderSettings) -> (usize, Vec<f32>) {
+    let n_samples = settings.get_antialiasing_size();
     let mut jitter_matrix = Vec::new();
     if n_samples == 2 {
         jitter_matrix = vec![
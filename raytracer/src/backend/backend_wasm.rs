@@ -1,18 +1,38 @@
-use std::error::Error;
+use std::time::Duration;
 
 use cpu_kernel_raytracer::CpuKernel;
-use raytracer_lib_no_std::Camera;
-use raytracer_lib_std::{Canvas, World};
+use raytracer_lib_no_std::{Camera, RenderSettings, RenderSettingsOps};
+use raytracer_lib_std::{RaytracerError, RenderedScene, RenderedSceneOps, World};
 
-use crate::BackendOps;
+use crate::{BackendCapabilities, BackendOps};
 use crate::backend::backend_cpu_single_core::render_world_single_core;
 
 pub struct BackendWasm {}
 
 impl BackendOps for BackendWasm {
-    fn render_world(&self, world: &mut World, c: &Camera) -> Result<Canvas, Box<dyn Error>> {
-        let canvas = render_world_single_core(world, c, CpuKernel::color_at);
-        Ok(canvas)
+    fn render_scene(
+        &self,
+        world: &mut World,
+        c: &Camera,
+        settings: &RenderSettings,
+    ) -> Result<RenderedScene, RaytracerError> {
+        settings.validate()?;
+        // there is no clock in the browser sandbox, so neither the image nor the tiles are timed
+        let mut settings = settings.clone();
+        settings.set_statistics(false);
+        let (canvas, _) = render_world_single_core(world, c, &settings, CpuKernel::color_at);
+        Ok(RenderedScene::new(canvas, Duration::default()))
+    }
+
+    fn get_capabilities(&self) -> BackendCapabilities {
+        BackendCapabilities {
+            antialiasing: true,
+            area_lights: true,
+            // the intersection lists keep the nearest hits and count the rest, see IntersectionList::get_overflows
+            max_shapes: None,
+            supports_progress: true,
+            statistics: false,
+        }
     }
 }
 
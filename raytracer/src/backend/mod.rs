@@ -21,4 +21,4 @@ mod backend_cuda;
 mod backend_wasm;
 
 mod backend;
-mod backend_helper;
\ No newline at end of file
+pub(crate) mod backend_helper;
\ No newline at end of file
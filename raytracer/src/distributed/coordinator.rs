@@ -0,0 +1,480 @@
+use std::collections::VecDeque;
+use std::io::{BufReader, BufWriter, ErrorKind};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use raytracer_lib_no_std::{CameraOps, Color, RenderSettings};
+use raytracer_lib_std::{Canvas, CanvasOps, Scene, SceneOps};
+
+use crate::distributed::{
+    read_message, split_into_tiles, write_message, CoordinatorMessage, DistributedError, Tile, WorkerMessage,
+    PROTOCOL_VERSION,
+};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+pub struct RenderCoordinator {
+    tile_size: usize,
+    // a worker which does not answer within this time is treated as disconnected
+    read_timeout: Duration,
+    // the whole render, None waits forever for workers
+    timeout: Option<Duration>,
+}
+
+#[derive(Clone, Debug)]
+pub struct WorkerStats {
+    name: String,
+    addr: SocketAddr,
+    threads: usize,
+    tiles: usize,
+    pixels: usize,
+    failed_tiles: usize,
+    busy: Duration,
+}
+
+#[derive(Clone, Debug)]
+pub struct DistributedRender {
+    canvas: Canvas,
+    duration: Duration,
+    worker_stats: Vec<WorkerStats>,
+}
+
+// tiles not handed out yet, failed tiles go back to the front
+struct TileQueue {
+    pending: Mutex<VecDeque<Tile>>,
+    finished: AtomicBool,
+}
+
+impl RenderCoordinator {
+    pub fn new() -> RenderCoordinator {
+        RenderCoordinator {
+            tile_size: 32,
+            read_timeout: Duration::from_secs(60),
+            timeout: None,
+        }
+    }
+
+    pub fn set_tile_size(&mut self, tile_size: usize) {
+        self.tile_size = tile_size;
+    }
+
+    pub fn get_tile_size(&self) -> usize {
+        self.tile_size
+    }
+
+    pub fn set_read_timeout(&mut self, read_timeout: Duration) {
+        self.read_timeout = read_timeout;
+    }
+
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    // accepts workers on the listener until every tile of the scene is rendered.
+    // workers may connect and disconnect at any time
+    pub fn render(
+        &self,
+        scene: &Scene,
+        settings: &RenderSettings,
+        listener: &TcpListener,
+    ) -> Result<DistributedRender, DistributedError> {
+        if self.tile_size == 0 {
+            return Err(DistributedError::Protocol("tile size must not be 0".to_string()));
+        }
+        let start = Instant::now();
+        let c = scene.get_camera();
+        let tiles = split_into_tiles(c.get_hsize(), c.get_vsize(), self.tile_size);
+        let mut done = vec![false; tiles.len()];
+        let mut remaining = tiles.len();
+
+        let queue = Arc::new(TileQueue {
+            pending: Mutex::new(tiles.into_iter().collect()),
+            finished: AtomicBool::new(false),
+        });
+        let scene = Arc::new(scene.clone());
+        let settings = Arc::new(settings.clone());
+        let (results_tx, results_rx) = channel::<(Tile, Vec<Color>)>();
+        let mut handles: Vec<JoinHandle<Option<WorkerStats>>> = Vec::new();
+        let mut canvas = Canvas::new(c.get_hsize(), c.get_vsize());
+
+        listener.set_nonblocking(true)?;
+        let result = loop {
+            if remaining == 0 {
+                break Ok(());
+            }
+            if let Some(timeout) = self.timeout {
+                if start.elapsed() > timeout {
+                    break Err(DistributedError::Timeout);
+                }
+            }
+
+            match listener.accept() {
+                Ok((stream, addr)) => {
+                    let queue = queue.clone();
+                    let scene = scene.clone();
+                    let settings = settings.clone();
+                    let results_tx = results_tx.clone();
+                    let read_timeout = self.read_timeout;
+                    handles.push(thread::spawn(move || {
+                        handle_worker(stream, addr, &scene, &settings, &queue, results_tx, read_timeout)
+                    }));
+                }
+                Err(ref e) if e.kind() == ErrorKind::WouldBlock => {}
+                Err(e) => break Err(DistributedError::Io(e)),
+            }
+
+            while let Ok((tile, colors)) = results_rx.recv_timeout(POLL_INTERVAL) {
+                if done[tile.id] {
+                    continue;
+                }
+                for (idx, color) in colors.into_iter().enumerate() {
+                    canvas.write_pixel(tile.x + idx % tile.width, tile.y + idx / tile.width, color);
+                }
+                done[tile.id] = true;
+                remaining -= 1;
+            }
+        };
+
+        queue.finished.store(true, Ordering::SeqCst);
+        listener.set_nonblocking(false)?;
+        result?;
+
+        // all tiles are done, so no worker is waiting for a tile result anymore
+        let worker_stats = handles.into_iter().filter_map(|h| h.join().ok().flatten()).collect();
+        Ok(DistributedRender {
+            canvas,
+            duration: start.elapsed(),
+            worker_stats,
+        })
+    }
+}
+
+impl Default for RenderCoordinator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// returns None if the connection is not a valid worker
+fn handle_worker(
+    stream: TcpStream,
+    addr: SocketAddr,
+    scene: &Scene,
+    settings: &RenderSettings,
+    queue: &TileQueue,
+    results: Sender<(Tile, Vec<Color>)>,
+    read_timeout: Duration,
+) -> Option<WorkerStats> {
+    // accepted sockets may inherit the non blocking mode of the listener
+    stream.set_nonblocking(false).ok()?;
+    stream.set_read_timeout(Some(read_timeout)).ok()?;
+    stream.set_nodelay(true).ok()?;
+    let mut reader = BufReader::new(stream.try_clone().ok()?);
+    let mut writer = BufWriter::new(stream);
+
+    let (name, threads) = match read_message(&mut reader) {
+        Ok(WorkerMessage::Hello { version, name, threads }) if version == PROTOCOL_VERSION => (name, threads),
+        Ok(WorkerMessage::Hello { version, .. }) => {
+            println!(
+                "worker {} uses protocol version {}, expected {}",
+                addr, version, PROTOCOL_VERSION
+            );
+            let _ = write_message(&mut writer, &CoordinatorMessage::Shutdown);
+            return None;
+        }
+        _ => return None,
+    };
+    let mut stats = WorkerStats {
+        name,
+        addr,
+        threads,
+        tiles: 0,
+        pixels: 0,
+        failed_tiles: 0,
+        busy: Duration::from_secs(0),
+    };
+
+    let job = CoordinatorMessage::Job {
+        scene: Box::new(scene.clone()),
+        settings: settings.clone(),
+    };
+    if write_message(&mut writer, &job).is_err() {
+        return Some(stats);
+    }
+
+    loop {
+        let tile = match next_tile(queue) {
+            Some(tile) => tile,
+            None => {
+                let _ = write_message(&mut writer, &CoordinatorMessage::Shutdown);
+                return Some(stats);
+            }
+        };
+
+        let start = Instant::now();
+        let result =
+            write_message(&mut writer, &CoordinatorMessage::Render(tile)).and_then(|_| read_message(&mut reader));
+        match result {
+            Ok(WorkerMessage::TileDone { tile: t, colors }) if t == tile && colors.len() == tile.pixels() => {
+                stats.busy += start.elapsed();
+                stats.tiles += 1;
+                stats.pixels += tile.pixels();
+                if results.send((tile, colors)).is_err() {
+                    return Some(stats);
+                }
+            }
+            result => {
+                // disconnected, timed out or garbage: somebody else has to render the tile
+                queue.pending.lock().unwrap().push_front(tile);
+                stats.failed_tiles += 1;
+                match result {
+                    Ok(WorkerMessage::Failed(msg)) => println!("worker {} failed: {}", addr, msg),
+                    Err(e) => println!("worker {} dropped: {}", addr, e),
+                    _ => println!("worker {} sent an unexpected answer for tile {}", addr, tile.id),
+                }
+                return Some(stats);
+            }
+        }
+    }
+}
+
+// waits while other workers still have tiles in flight, they might fail
+fn next_tile(queue: &TileQueue) -> Option<Tile> {
+    loop {
+        if queue.finished.load(Ordering::SeqCst) {
+            return None;
+        }
+        if let Some(tile) = queue.pending.lock().unwrap().pop_front() {
+            return Some(tile);
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+impl WorkerStats {
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_addr(&self) -> &SocketAddr {
+        &self.addr
+    }
+
+    pub fn get_threads(&self) -> usize {
+        self.threads
+    }
+
+    pub fn get_tiles(&self) -> usize {
+        self.tiles
+    }
+
+    pub fn get_pixels(&self) -> usize {
+        self.pixels
+    }
+
+    pub fn get_failed_tiles(&self) -> usize {
+        self.failed_tiles
+    }
+
+    // time between sending a tile and receiving its pixels, including the network
+    pub fn get_busy(&self) -> &Duration {
+        &self.busy
+    }
+
+    pub fn pixels_per_second(&self) -> f32 {
+        if self.busy.as_secs_f32() > 0.0 {
+            self.pixels as f32 / self.busy.as_secs_f32()
+        } else {
+            0.0
+        }
+    }
+}
+
+impl DistributedRender {
+    pub fn get_canvas(&self) -> &Canvas {
+        &self.canvas
+    }
+
+    pub fn get_duration(&self) -> &Duration {
+        &self.duration
+    }
+
+    pub fn get_worker_stats(&self) -> &Vec<WorkerStats> {
+        &self.worker_stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use raytracer_lib_no_std::prelude::*;
+    use raytracer_lib_std::{World, WorldOps};
+
+    use crate::distributed::{render_tile, run_worker};
+
+    use super::*;
+
+    fn test_scene() -> Scene {
+        let mut world = World::new();
+        world.add_shape(Shape::new(ShapeEnum::Sphere(Sphere::new()))).unwrap();
+        let mut plane = Shape::new(ShapeEnum::Plane(Plane::new()));
+        plane.set_transformation(Matrix::translation(0.0, -1.0, 0.0));
+        world.add_shape(plane).unwrap();
+
+        let mut camera = Camera::new(21, 13, PI / 3.0);
+        camera.set_transformation(Matrix::view_transform(
+            &Tuple4D::new_point(0.0, 1.0, -5.0),
+            &Tuple4D::new_point(0.0, 0.0, 0.0),
+            &Tuple4D::new_vector(0.0, 1.0, 0.0),
+        ));
+        camera.calc_pixel_size();
+        Scene::new(world, camera)
+    }
+
+    fn expected_canvas(scene: &Scene, settings: &RenderSettings) -> Canvas {
+        let c = scene.get_camera();
+        let tile = Tile {
+            id: 0,
+            x: 0,
+            y: 0,
+            width: c.get_hsize(),
+            height: c.get_vsize(),
+        };
+        let mut canvas = Canvas::new(c.get_hsize(), c.get_vsize());
+        for (idx, color) in render_tile(scene.get_world(), c, settings, &tile).into_iter().enumerate() {
+            canvas.write_pixel(idx % tile.width, idx / tile.width, color);
+        }
+        canvas
+    }
+
+    fn assert_same_canvas(actual: &Canvas, expected: &Canvas) {
+        assert_eq!(actual.get_width(), expected.get_width());
+        assert_eq!(actual.get_height(), expected.get_height());
+        for (a, e) in actual.get_pixels().iter().zip(expected.get_pixels().iter()) {
+            assert_color(&a.color, &e.color);
+        }
+    }
+
+    #[test]
+    fn test_distributed_render_with_several_workers() {
+        let scene = test_scene();
+        // the workers must render with these settings and not with the ones of the camera
+        let mut settings = RenderSettings::from_camera(scene.get_camera());
+        settings.set_calc_shadows(false);
+        settings.set_background(Color::new(0.2, 0.3, 0.4));
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let workers: Vec<_> = (0..3)
+            .map(|i| thread::spawn(move || run_worker(addr, &format!("worker {}", i)).ok()))
+            .collect();
+
+        let mut coordinator = RenderCoordinator::new();
+        coordinator.set_tile_size(4);
+        coordinator.set_timeout(Some(Duration::from_secs(60)));
+        let result = coordinator.render(&scene, &settings, &listener).unwrap();
+        // workers still waiting in the backlog of the listener get disconnected
+        drop(listener);
+        let rendered: usize = workers.into_iter().map(|w| w.join().unwrap().unwrap_or(0)).sum();
+
+        assert_same_canvas(result.get_canvas(), &expected_canvas(&scene, &settings));
+        // the top left corner sees the sky
+        assert_color(&result.get_canvas().get_pixels()[0].color, &Color::new(0.2, 0.3, 0.4));
+        assert_eq!(rendered, 24);
+        let stats = result.get_worker_stats();
+        assert!(!stats.is_empty());
+        assert_eq!(stats.iter().map(|s| s.get_tiles()).sum::<usize>(), 24);
+        assert_eq!(stats.iter().map(|s| s.get_pixels()).sum::<usize>(), 21 * 13);
+        assert!(stats.iter().all(|s| s.get_failed_tiles() == 0));
+    }
+
+    #[test]
+    fn test_distributed_render_retries_tiles_of_dropped_worker() {
+        let scene = test_scene();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        // takes the scene and one tile, then disconnects without answering
+        let broken = thread::spawn(move || {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            write_message(
+                &mut stream,
+                &WorkerMessage::Hello {
+                    version: PROTOCOL_VERSION,
+                    name: "broken".to_string(),
+                    threads: 1,
+                },
+            )
+            .unwrap();
+            match read_message(&mut stream).unwrap() {
+                CoordinatorMessage::Job { .. } => {}
+                m => panic!("unexpected message {:?}", m),
+            }
+            match read_message(&mut stream).unwrap() {
+                CoordinatorMessage::Render(_) => {}
+                m => panic!("unexpected message {:?}", m),
+            }
+        });
+        let good = thread::spawn(move || {
+            // give the broken worker a head start
+            broken.join().unwrap();
+            run_worker(addr, "good").unwrap()
+        });
+
+        let settings = RenderSettings::from_camera(scene.get_camera());
+        let result = RenderCoordinator::new().render(&scene, &settings, &listener).unwrap();
+        good.join().unwrap();
+
+        assert_same_canvas(result.get_canvas(), &expected_canvas(&scene, &settings));
+        let stats = result.get_worker_stats();
+        let broken = stats.iter().find(|s| s.get_name() == "broken").unwrap();
+        assert_eq!(broken.get_tiles(), 0);
+        assert_eq!(broken.get_failed_tiles(), 1);
+        let good = stats.iter().find(|s| s.get_name() == "good").unwrap();
+        assert_eq!(good.get_tiles(), 1);
+        assert!(good.pixels_per_second() > 0.0);
+    }
+
+    #[test]
+    fn test_worker_rejects_invalid_settings() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let worker = thread::spawn(move || run_worker(addr, "worker"));
+
+        let (mut stream, _) = listener.accept().unwrap();
+        match read_message(&mut stream).unwrap() {
+            WorkerMessage::Hello { .. } => {}
+            m => panic!("unexpected message {:?}", m),
+        }
+        let mut settings = RenderSettings::new();
+        settings.set_antialiasing(true);
+        settings.set_antialiasing_size(4);
+        let job = CoordinatorMessage::Job {
+            scene: Box::new(test_scene()),
+            settings,
+        };
+        write_message(&mut stream, &job).unwrap();
+        match read_message(&mut stream).unwrap() {
+            WorkerMessage::Failed(msg) => assert!(msg.contains("antialiasing_size")),
+            m => panic!("unexpected message {:?}", m),
+        }
+        assert!(worker.join().unwrap().is_err());
+    }
+
+    #[test]
+    fn test_distributed_render_timeout_without_workers() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut coordinator = RenderCoordinator::new();
+        coordinator.set_timeout(Some(Duration::from_millis(50)));
+        match coordinator.render(&test_scene(), &RenderSettings::new(), &listener) {
+            Err(DistributedError::Timeout) => {}
+            _ => panic!("expected a timeout"),
+        }
+    }
+}
@@ -0,0 +1,7 @@
+pub use self::coordinator::*;
+pub use self::protocol::*;
+pub use self::worker::*;
+
+mod coordinator;
+mod protocol;
+mod worker;
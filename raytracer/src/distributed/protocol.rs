@@ -0,0 +1,202 @@
+use core::fmt;
+use std::error::Error;
+use std::io;
+use std::io::{Read, Write};
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use raytracer_lib_no_std::{Color, RenderSettings};
+use raytracer_lib_std::Scene;
+
+// bumped whenever a message changes, coordinator and worker must agree
+pub const PROTOCOL_VERSION: u32 = 2;
+
+// a scene with a few hundred triangles is a few MB, anything much larger is a broken stream
+const MAX_MESSAGE_SIZE: usize = 256 * 1024 * 1024;
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Tile {
+    pub id: usize,
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+// coordinator -> worker
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum CoordinatorMessage {
+    // every tile of the job is rendered with the settings of the coordinator
+    Job { scene: Box<Scene>, settings: RenderSettings },
+    Render(Tile),
+    Shutdown,
+}
+
+// worker -> coordinator
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum WorkerMessage {
+    Hello { version: u32, name: String, threads: usize },
+    // colors of the tile in row major order
+    TileDone { tile: Tile, colors: Vec<Color> },
+    Failed(String),
+}
+
+#[derive(Debug)]
+pub enum DistributedError {
+    Io(io::Error),
+    Serialization(bincode::Error),
+    Protocol(String),
+    Timeout,
+}
+
+impl Tile {
+    pub fn pixels(&self) -> usize {
+        self.width * self.height
+    }
+}
+
+// splits the image into tiles of at most tile_size x tile_size pixels, row by row
+pub fn split_into_tiles(width: usize, height: usize, tile_size: usize) -> Vec<Tile> {
+    let mut tiles = Vec::new();
+    for y in (0..height).step_by(tile_size) {
+        for x in (0..width).step_by(tile_size) {
+            tiles.push(Tile {
+                id: tiles.len(),
+                x,
+                y,
+                width: tile_size.min(width - x),
+                height: tile_size.min(height - y),
+            });
+        }
+    }
+    tiles
+}
+
+// every message is sent as a big endian u32 length followed by the bincode encoded message
+pub fn write_message<W: Write, T: Serialize>(w: &mut W, msg: &T) -> Result<(), DistributedError> {
+    let data = bincode::serialize(msg)?;
+    if data.len() > MAX_MESSAGE_SIZE {
+        return Err(DistributedError::Protocol(format!(
+            "message of {} bytes exceeds the maximum of {} bytes",
+            data.len(),
+            MAX_MESSAGE_SIZE
+        )));
+    }
+    w.write_all(&(data.len() as u32).to_be_bytes())?;
+    w.write_all(&data)?;
+    w.flush()?;
+    Ok(())
+}
+
+pub fn read_message<R: Read, T: DeserializeOwned>(r: &mut R) -> Result<T, DistributedError> {
+    let mut len = [0u8; 4];
+    r.read_exact(&mut len)?;
+    let len = u32::from_be_bytes(len) as usize;
+    if len > MAX_MESSAGE_SIZE {
+        return Err(DistributedError::Protocol(format!(
+            "message of {} bytes exceeds the maximum of {} bytes",
+            len, MAX_MESSAGE_SIZE
+        )));
+    }
+    let mut data = vec![0u8; len];
+    r.read_exact(&mut data)?;
+    Ok(bincode::deserialize(&data)?)
+}
+
+impl Error for DistributedError {}
+
+impl fmt::Display for DistributedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DistributedError::Io(e) => write!(f, "network error: {}", e),
+            DistributedError::Serialization(e) => write!(f, "invalid message: {}", e),
+            DistributedError::Protocol(msg) => write!(f, "protocol error: {}", msg),
+            DistributedError::Timeout => write!(f, "render did not finish in time"),
+        }
+    }
+}
+
+impl From<io::Error> for DistributedError {
+    fn from(e: io::Error) -> Self {
+        DistributedError::Io(e)
+    }
+}
+
+impl From<bincode::Error> for DistributedError {
+    fn from(e: bincode::Error) -> Self {
+        DistributedError::Serialization(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    fn test_split_into_tiles() {
+        let tiles = split_into_tiles(10, 5, 4);
+        assert_eq!(tiles.len(), 6);
+        assert_eq!(
+            tiles[2],
+            Tile {
+                id: 2,
+                x: 8,
+                y: 0,
+                width: 2,
+                height: 4
+            }
+        );
+        assert_eq!(
+            tiles[5],
+            Tile {
+                id: 5,
+                x: 8,
+                y: 4,
+                width: 2,
+                height: 1
+            }
+        );
+        assert_eq!(tiles.iter().map(|t| t.pixels()).sum::<usize>(), 50);
+    }
+
+    #[test]
+    fn test_message_round_trip() {
+        let tile = Tile {
+            id: 3,
+            x: 1,
+            y: 2,
+            width: 1,
+            height: 1,
+        };
+        let mut buf = Vec::new();
+        write_message(&mut buf, &CoordinatorMessage::Render(tile)).unwrap();
+        write_message(&mut buf, &CoordinatorMessage::Shutdown).unwrap();
+
+        let mut r = Cursor::new(buf);
+        match read_message(&mut r).unwrap() {
+            CoordinatorMessage::Render(t) => assert_eq!(t, tile),
+            m => panic!("unexpected message {:?}", m),
+        }
+        match read_message(&mut r).unwrap() {
+            CoordinatorMessage::Shutdown => {}
+            m => panic!("unexpected message {:?}", m),
+        }
+        // end of stream
+        match read_message::<_, CoordinatorMessage>(&mut r) {
+            Err(DistributedError::Io(_)) => {}
+            r => panic!("expected an I/O error, got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_message_too_large() {
+        let mut r = Cursor::new(vec![0xFF, 0xFF, 0xFF, 0xFF]);
+        match read_message::<_, WorkerMessage>(&mut r) {
+            Err(DistributedError::Protocol(_)) => {}
+            r => panic!("expected a protocol error, got {:?}", r),
+        }
+    }
+}
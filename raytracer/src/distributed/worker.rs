@@ -0,0 +1,91 @@
+use std::io::{BufReader, BufWriter};
+use std::net::{TcpStream, ToSocketAddrs};
+
+use rayon::iter::ParallelIterator;
+use rayon::slice::ParallelSliceMut;
+
+use cpu_kernel_raytracer::CpuKernel;
+use raytracer_lib_no_std::{Camera, Color, Pixel, RenderSettings, RenderSettingsOps};
+use raytracer_lib_std::{Scene, SceneOps, World, WorldOps};
+
+use crate::backend::backend_helper::{calc_tile, get_antialiasing_params};
+use crate::distributed::PROTOCOL_VERSION;
+use crate::distributed::{read_message, write_message, CoordinatorMessage, DistributedError, Tile, WorkerMessage};
+
+// connects to the coordinator and renders tiles until the coordinator sends Shutdown.
+// returns the number of rendered tiles
+pub fn run_worker<A: ToSocketAddrs>(addr: A, name: &str) -> Result<usize, DistributedError> {
+    let stream = TcpStream::connect(addr)?;
+    stream.set_nodelay(true)?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = BufWriter::new(stream);
+
+    write_message(
+        &mut writer,
+        &WorkerMessage::Hello {
+            version: PROTOCOL_VERSION,
+            name: name.to_string(),
+            threads: rayon::current_num_threads(),
+        },
+    )?;
+
+    let mut job: Option<(Scene, RenderSettings)> = None;
+    let mut rendered = 0;
+    loop {
+        match read_message(&mut reader)? {
+            CoordinatorMessage::Job { scene: s, settings } => {
+                let mut s = *s;
+                // the inverse transformations are not transferred
+                if let Err(e) = s.validate() {
+                    write_message(&mut writer, &WorkerMessage::Failed(format!("invalid scene: {}", e)))?;
+                    return Err(DistributedError::Protocol(format!("invalid scene: {}", e)));
+                }
+                // a wrong antialiasing size would panic in the middle of a tile
+                if let Err(e) = settings.validate() {
+                    write_message(&mut writer, &WorkerMessage::Failed(format!("invalid settings: {}", e)))?;
+                    return Err(DistributedError::Protocol(format!("invalid settings: {}", e)));
+                }
+                job = Some((s, settings));
+            }
+            CoordinatorMessage::Render(tile) => {
+                let (s, settings) = job
+                    .as_ref()
+                    .ok_or_else(|| DistributedError::Protocol(format!("tile {} sent before the scene", tile.id)))?;
+                let colors = render_tile(s.get_world(), s.get_camera(), settings, &tile);
+                write_message(&mut writer, &WorkerMessage::TileDone { tile, colors })?;
+                rendered += 1;
+            }
+            CoordinatorMessage::Shutdown => return Ok(rendered),
+        }
+    }
+}
+
+// renders the pixels of one tile on all cores, in row major order
+pub fn render_tile(world: &World, c: &Camera, settings: &RenderSettings, tile: &Tile) -> Vec<Color> {
+    let (n_samples, jitter_matrix) = get_antialiasing_params(settings);
+    // TODO: remove, when WOrld has lights vector
+    let lights = vec![world.get_light().clone()];
+
+    let mut pixels: Vec<Pixel> = (0..tile.pixels())
+        .map(|idx| {
+            let mut p = Pixel::new();
+            p.x = tile.x + idx % tile.width;
+            p.y = tile.y + idx / tile.width;
+            p
+        })
+        .collect();
+    // the statistics are not sent to the coordinator
+    pixels.par_chunks_mut(tile.width.max(1)).for_each(|row| {
+        calc_tile(
+            world,
+            c,
+            settings,
+            &CpuKernel::color_at,
+            n_samples,
+            &jitter_matrix,
+            &lights,
+            row,
+        );
+    });
+    pixels.into_iter().map(|p| p.color).collect()
+}
@@ -14,14 +14,20 @@ extern crate serde;
 extern crate cpu_kernel_raytracer;
 extern crate raytracer_lib_std;
 
+pub use self::animation::*;
 pub use self::backend::*;
+#[cfg(feature = "use_serde")]
+pub use self::distributed::*;
 
 pub use self::cpu_kernel_raytracer::*;
 pub use self::raytracer_lib_no_std::*;
 pub use self::raytracer_lib_std::*;
 pub use self::utils::*;
 
+mod animation;
 mod backend;
+#[cfg(feature = "use_serde")]
+mod distributed;
 mod utils;
 
 pub mod prelude {
@@ -37,8 +43,11 @@ pub mod prelude {
     #[cfg(feature = "cpu_multi_core")]
     pub use super::BackendCpuMultiCore;
 
+    pub use super::animation::*;
     pub use super::backend::Backend;
     pub use super::backend::BackendOps;
+    #[cfg(feature = "use_serde")]
+    pub use super::distributed::*;
     pub use super::cpu_kernel_raytracer::*;
     pub use super::raytracer_lib_no_std::*;
     pub use super::raytracer_lib_std::*;
@@ -0,0 +1,363 @@
+// renders small versions of the book's chapter scenes and compares them with the images in tests/golden.
+//
+// after an intended change of the output regenerate the images with
+//     UPDATE_GOLDEN_IMAGES=1 cargo test -p raytracer --test golden_images
+// and check the new images before committing them
+#![cfg(feature = "cpu_multi_core")]
+
+use std::env;
+use std::f32::consts::PI;
+use std::path::PathBuf;
+
+use raytracer::prelude::*;
+
+const WIDTH: usize = 48;
+const HEIGHT: usize = 32;
+
+// the images are written with 8 bit per channel, so a few pixels on edges are allowed to flip,
+// everything else has to stay within a PSNR of 40 dB and look structurally the same
+const MIN_PSNR: f32 = 40.0;
+const MIN_SSIM: f32 = 0.98;
+const MAX_DIFFERING_PIXELS: f32 = 0.01;
+
+#[test]
+fn test_golden_chapter07() {
+    let (mut w, c) = setup_chapter07(WIDTH, HEIGHT);
+    assert_golden_image("chapter07", &mut w, &c);
+}
+
+#[test]
+fn test_golden_chapter09() {
+    let (mut w, c) = setup_chapter09(WIDTH, HEIGHT);
+    assert_golden_image("chapter09", &mut w, &c);
+}
+
+#[test]
+fn test_golden_chapter10() {
+    let (mut w, c) = setup_chapter10(WIDTH, HEIGHT);
+    assert_golden_image("chapter10", &mut w, &c);
+}
+
+#[test]
+fn test_golden_chapter12() {
+    let (mut w, c) = setup_chapter12(WIDTH, HEIGHT);
+    assert_golden_image("chapter12", &mut w, &c);
+}
+
+#[test]
+fn test_golden_chapter14() {
+    let (mut w, c) = setup_chapter14(WIDTH, HEIGHT);
+    assert_golden_image("chapter14", &mut w, &c);
+}
+
+// the counters don't depend on the order, in which the tiles are rendered
+#[cfg(feature = "cpu_single_core")]
+#[test]
+fn test_statistics_chapter12() {
+    let (mut w, c) = setup_chapter12(WIDTH, HEIGHT);
+    let mut settings = RenderSettings::from_camera(&c);
+    settings.set_statistics(true);
+
+    let single = BackendCpuSingleCore::new().render_scene(&mut w, &c, &settings).unwrap();
+    let multi = BackendCpuMultiCore::new().render_scene(&mut w, &c, &settings).unwrap();
+    let stats = multi.get_statistics().unwrap();
+    assert_eq!(single.get_statistics().unwrap().get_counters(), stats.get_counters());
+    assert_eq!(stats.get_tile_durations().len(), HEIGHT);
+
+    let counters = stats.get_counters();
+    assert_eq!(counters.get_primary_rays(), (WIDTH * HEIGHT) as u64);
+    assert!(counters.get_shadow_rays() > 0);
+    assert!(counters.get_reflection_rays() > 0);
+    assert!(counters.get_refraction_rays() > 0);
+    assert!(counters.get_max_depth_reached() > 1);
+    assert!(counters.get_max_depth_reached() <= settings.get_max_depth());
+
+    // every ray is tested against all 3 spheres, 3 planes and the cube
+    let rays = counters.get_total_rays();
+    assert_eq!(counters.get_intersection_tests(), &[3 * rays, 3 * rays, rays, 0, 0, 0, 0, 0, 0, 0, 0]);
+
+    settings.set_statistics(false);
+    let rendered = BackendCpuMultiCore::new().render_scene(&mut w, &c, &settings).unwrap();
+    assert!(rendered.get_statistics().is_none());
+}
+
+fn assert_golden_image(name: &str, w: &mut World, c: &Camera) {
+    let actual = quantize(&BackendCpuMultiCore::new().render_world(w, c).unwrap());
+
+    let golden_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("golden");
+    let golden = golden_dir.join(format!("{}.png", name));
+    let golden = golden.to_str().unwrap();
+    if env::var("UPDATE_GOLDEN_IMAGES").is_ok() {
+        std::fs::create_dir_all(&golden_dir).unwrap();
+        actual.write_png(golden).unwrap();
+        return;
+    }
+
+    let expected = read_png(golden).unwrap_or_else(|e| {
+        panic!(
+            "{}\nrun the test with UPDATE_GOLDEN_IMAGES=1 to create the golden image",
+            e
+        )
+    });
+    let d = compare_canvas(&expected, &actual).unwrap();
+    let ssim = compare_ssim(&expected, &actual).unwrap();
+    let differing = d.get_differing_pixels() as f32 / d.get_pixels() as f32;
+    if d.get_psnr() >= MIN_PSNR && ssim >= MIN_SSIM && differing <= MAX_DIFFERING_PIXELS {
+        return;
+    }
+
+    let out = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("golden_images");
+    std::fs::create_dir_all(&out).unwrap();
+    let file = |suffix: &str| {
+        out.join(format!("{}_{}.png", name, suffix))
+            .to_string_lossy()
+            .into_owned()
+    };
+    actual.write_png(&file("actual")).unwrap();
+    expected.write_png(&file("expected")).unwrap();
+    compare_flip(&expected, &actual)
+        .unwrap()
+        .to_heatmap()
+        .write_png(&file("diff"))
+        .unwrap();
+    panic!(
+        "'{}' differs from the golden image: psnr {:.2} dB, ssim {:.4}, max abs diff {:.4}, {} of {} pixels differ. \
+         see {}",
+        name,
+        d.get_psnr(),
+        ssim,
+        d.get_max_abs_diff(),
+        d.get_differing_pixels(),
+        d.get_pixels(),
+        out.display()
+    );
+}
+
+// the same rounding as write_png, so a freshly rendered image matches its own PNG exactly
+fn quantize(canvas: &Canvas) -> Canvas {
+    let q = |v: f32| (v * 255.0) as u8 as f32 / 255.0;
+    let mut res = Canvas::new(canvas.get_width(), canvas.get_height());
+    for (idx, p) in canvas.get_pixels().iter().enumerate() {
+        let (x, y) = (idx % canvas.get_width(), idx / canvas.get_width());
+        res.write_pixel(x, y, Color::new(q(p.color.r), q(p.color.g), q(p.color.b)));
+    }
+    res
+}
+
+fn setup_camera(width: usize, height: usize, field_of_view: f32, from: Tuple4D) -> Camera {
+    let mut c = Camera::new(width, height, field_of_view);
+    c.calc_pixel_size();
+    c.set_transformation(Matrix::view_transform(
+        &from,
+        &Tuple4D::new_point(0.0, 1.0, 0.0),
+        &Tuple4D::new_vector(0.0, 1.0, 0.0),
+    ));
+    c
+}
+
+fn three_spheres() -> (Sphere, Sphere, Sphere) {
+    let mut middle = Sphere::new();
+    middle.set_transformation(Matrix::translation(-0.5, 1.0, 0.5));
+    middle.get_material_mut().set_color(Color::new(0.1, 1.0, 0.5));
+    middle.get_material_mut().set_diffuse(0.7);
+    middle.get_material_mut().set_specular(0.3);
+
+    let mut right = Sphere::new();
+    right.set_transformation(&Matrix::translation(1.5, 0.5, -0.5) * &Matrix::scale(0.5, 0.5, 0.5));
+    right.get_material_mut().set_color(Color::new(0.5, 1.0, 0.1));
+    right.get_material_mut().set_diffuse(0.7);
+    right.get_material_mut().set_specular(0.3);
+
+    let mut left = Sphere::new();
+    left.set_transformation(&Matrix::translation(-1.5, 0.33, -0.75) * &Matrix::scale(0.333, 0.333, 0.333));
+    left.get_material_mut().set_color(Color::new(1.0, 0.8, 0.1));
+    left.get_material_mut().set_diffuse(0.7);
+    left.get_material_mut().set_specular(0.3);
+
+    (middle, left, right)
+}
+
+fn wall_transformation(angle: f32) -> Matrix {
+    &(&Matrix::translation(0.0, 0.0, 5.0) * &Matrix::rotate_y(angle)) * &Matrix::rotate_x(PI / 2.0)
+}
+
+fn new_world(shapes: Vec<Shape>) -> World {
+    let pl = PointLight::new(Tuple4D::new_point(-1.0, 10.0, -10.0), Color::new(1.0, 1.0, 1.0));
+    let mut w = World::new();
+    w.set_light(Light::PointLight(pl));
+    for s in shapes {
+        w.add_shape(s).unwrap();
+    }
+    w
+}
+
+// chapter 7: spheres as floor and walls
+fn setup_chapter07(width: usize, height: usize) -> (World, Camera) {
+    let mut floor = Sphere::new();
+    floor.set_transformation(Matrix::scale(10.0, 0.01, 10.0));
+    floor.get_material_mut().set_color(Color::new(1.0, 0.9, 0.9));
+    floor.get_material_mut().set_specular(0.0);
+
+    let mut left_wall = Sphere::new();
+    left_wall.set_transformation(&wall_transformation(-PI / 4.0) * &Matrix::scale(10.0, 0.01, 10.0));
+    left_wall.get_material_mut().set_color(Color::new(1.0, 0.9, 0.9));
+    left_wall.get_material_mut().set_specular(0.0);
+
+    let mut right_wall = Sphere::new();
+    right_wall.set_transformation(&wall_transformation(PI / 4.0) * &Matrix::scale(10.0, 0.01, 10.0));
+    right_wall.get_material_mut().set_color(Color::new(1.0, 0.9, 0.9));
+    right_wall.get_material_mut().set_specular(0.0);
+
+    let (middle, left, right) = three_spheres();
+    let w = new_world(vec![
+        Shape::new(ShapeEnum::Sphere(floor)),
+        Shape::new(ShapeEnum::Sphere(left_wall)),
+        Shape::new(ShapeEnum::Sphere(right_wall)),
+        Shape::new(ShapeEnum::Sphere(middle)),
+        Shape::new(ShapeEnum::Sphere(left)),
+        Shape::new(ShapeEnum::Sphere(right)),
+    ]);
+    let c = setup_camera(width, height, PI / 3.0, Tuple4D::new_point(0.0, 1.5, -5.0));
+    (w, c)
+}
+
+// chapter 9: planes as floor and walls
+fn setup_chapter09(width: usize, height: usize) -> (World, Camera) {
+    let mut floor = Plane::new();
+    floor.get_material_mut().set_color(Color::new(1.0, 0.0, 0.0));
+    floor.get_material_mut().set_specular(0.0);
+
+    let mut left_wall = Plane::new();
+    left_wall.set_transformation(wall_transformation(-PI / 4.0));
+    left_wall.get_material_mut().set_color(Color::new(0.0, 1., 0.0));
+    left_wall.get_material_mut().set_specular(0.5);
+
+    let mut right_wall = Plane::new();
+    right_wall.set_transformation(wall_transformation(PI / 4.0));
+    right_wall.get_material_mut().set_color(Color::new(0.0, 0.0, 1.));
+    right_wall.get_material_mut().set_specular(1.0);
+
+    let (middle, left, right) = three_spheres();
+    let w = new_world(vec![
+        Shape::new(ShapeEnum::Plane(floor)),
+        Shape::new(ShapeEnum::Plane(left_wall)),
+        Shape::new(ShapeEnum::Plane(right_wall)),
+        Shape::new(ShapeEnum::Sphere(middle)),
+        Shape::new(ShapeEnum::Sphere(left)),
+        Shape::new(ShapeEnum::Sphere(right)),
+    ]);
+    let c = setup_camera(width, height, PI / 3.0, Tuple4D::new_point(0.0, 1.5, -5.0));
+    (w, c)
+}
+
+// floor and walls with a gradient, ring and checker pattern, used from chapter 10 on
+fn patterned_planes() -> (Plane, Plane, Plane) {
+    let mut p = GradientPattern::new();
+    p.set_color_a(Color::new(1.0, 0.0, 0.0));
+    p.set_color_b(Color::new(1.0, 0.0, 1.0));
+    p.set_transformation(Matrix::rotate_y(PI / 4.0));
+    let mut floor = Plane::new();
+    floor.get_material_mut().set_pattern(Pattern::GradientPattern(p));
+
+    let mut p = RingPattern::new();
+    p.set_color_a(Color::new(0.5, 0.0, 0.0));
+    p.set_color_b(Color::new(0.5, 0.0, 0.8));
+    p.set_transformation(Matrix::rotate_x(PI / 4.0));
+    let mut left_wall = Plane::new();
+    left_wall.set_transformation(wall_transformation(-PI / 4.0));
+    left_wall.get_material_mut().set_pattern(Pattern::RingPattern(p));
+
+    let mut p = Checker3DPattern::new();
+    p.set_color_a(Color::new(0.1, 0.8, 0.4));
+    p.set_color_b(Color::new(0.8, 0.2, 0.2));
+    let mut right_wall = Plane::new();
+    right_wall.set_transformation(wall_transformation(PI / 4.0));
+    right_wall.get_material_mut().set_pattern(Pattern::Checker3DPattern(p));
+
+    (floor, left_wall, right_wall)
+}
+
+// chapter 10: patterns
+fn setup_chapter10(width: usize, height: usize) -> (World, Camera) {
+    let (floor, left_wall, right_wall) = patterned_planes();
+    let (middle, left, right) = three_spheres();
+    let w = new_world(vec![
+        Shape::new(ShapeEnum::Plane(floor)),
+        Shape::new(ShapeEnum::Plane(left_wall)),
+        Shape::new(ShapeEnum::Plane(right_wall)),
+        Shape::new(ShapeEnum::Sphere(middle)),
+        Shape::new(ShapeEnum::Sphere(left)),
+        Shape::new(ShapeEnum::Sphere(right)),
+    ]);
+    let c = setup_camera(width, height, PI / 3.0, Tuple4D::new_point(0.0, 1.5, -5.0));
+    (w, c)
+}
+
+// chapter 11 and 12: reflection, refraction and a cube
+fn setup_chapter12(width: usize, height: usize) -> (World, Camera) {
+    let (floor, left_wall, right_wall) = patterned_planes();
+    let (mut middle, mut left, right) = three_spheres();
+    middle.get_material_mut().set_reflective(0.5);
+    middle.get_material_mut().set_transparency(0.5);
+    middle.get_material_mut().set_refractive_index(1.3);
+    left.get_material_mut().set_color(Color::new(1.0, 0.0, 0.0));
+
+    let mut p = Checker3DPattern::new();
+    p.set_color_a(Color::new(1.0, 0.0, 1.0));
+    p.set_color_b(Color::new(0.1, 0.1, 1.0));
+    let mut cube = Cube::new();
+    cube.set_transformation(&Matrix::translation(-2.5, 0.33, -0.75) * &Matrix::scale(0.5, 0.5, 0.25));
+    cube.get_material_mut().set_pattern(Pattern::Checker3DPattern(p));
+
+    let w = new_world(vec![
+        Shape::new(ShapeEnum::Plane(floor)),
+        Shape::new(ShapeEnum::Plane(left_wall)),
+        Shape::new(ShapeEnum::Plane(right_wall)),
+        Shape::new(ShapeEnum::Sphere(middle)),
+        Shape::new(ShapeEnum::Sphere(left)),
+        Shape::new(ShapeEnum::Sphere(right)),
+        Shape::new(ShapeEnum::Cube(cube)),
+    ]);
+    let c = setup_camera(width, height, PI / 3.0, Tuple4D::new_point(0.0, 1.5, -5.0));
+    (w, c)
+}
+
+// chapter 13 and 14: cylinders and the complete scene with a wider field of view
+fn setup_chapter14(width: usize, height: usize) -> (World, Camera) {
+    let (floor, left_wall, right_wall) = patterned_planes();
+    let (mut middle, mut left, right) = three_spheres();
+    middle.get_material_mut().set_reflective(0.8);
+    left.get_material_mut().set_color(Color::new(1.0, 0.0, 0.0));
+
+    let mut p = Checker3DPattern::new();
+    p.set_color_a(Color::new(1.0, 0.0, 1.0));
+    p.set_color_b(Color::new(0.1, 0.1, 1.0));
+    let mut cube = Cube::new();
+    cube.set_transformation(
+        &(&Matrix::translation(-2.0, 2.0, -1.75) * &Matrix::rotate_y(PI / 5.0)) * &Matrix::scale(0.5, 0.5, 0.25),
+    );
+    cube.get_material_mut().set_pattern(Pattern::Checker3DPattern(p));
+
+    let mut p = Checker3DPattern::new();
+    p.set_color_a(Color::new(1.0, 0.0, 0.0));
+    p.set_color_b(Color::new(0.0, 1.0, 1.0));
+    let mut cylinder = Cylinder::new();
+    cylinder.set_transformation(&Matrix::translation(1.5, 0.0, -0.75) * &Matrix::scale(0.5, 1.0, 0.5));
+    cylinder.set_minimum(0.0);
+    cylinder.set_maximum(1.0);
+    cylinder.set_closed(true);
+    cylinder.get_material_mut().set_pattern(Pattern::Checker3DPattern(p));
+
+    let w = new_world(vec![
+        Shape::new(ShapeEnum::Plane(floor)),
+        Shape::new(ShapeEnum::Plane(left_wall)),
+        Shape::new(ShapeEnum::Plane(right_wall)),
+        Shape::new(ShapeEnum::Sphere(middle)),
+        Shape::new(ShapeEnum::Sphere(left)),
+        Shape::new(ShapeEnum::Sphere(right)),
+        Shape::new(ShapeEnum::Cube(cube)),
+        Shape::new(ShapeEnum::Cylinder(cylinder)),
+    ]);
+    let c = setup_camera(width, height, PI / 2.0, Tuple4D::new_point(0.0, 1.5, -6.0));
+    (w, c)
+}
@@ -16,11 +16,11 @@ fn main() -> Result<(), Box<dyn Error>> {
     println!("single core duration: {:?}", dur);
 
     // multi core
-    //    let start = Instant::now();
-    //    let canvas_std = Camera::render_multi_core(&c, &w, 4);
-    //    canvas_std.write_ppm("chapter12_multi_core.ppm")?;
-    //    let dur = Instant::now() - start;
-    //    println!("multi core duration: {:?}", dur);
+    let start = Instant::now();
+    let canvas = Camera::render_multi_core(&c, &w, 4);
+    canvas.write_ppm("chapter12_multi_core.ppm")?;
+    let dur = Instant::now() - start;
+    println!("multi core duration: {:?}", dur);
 
     Ok(())
 }
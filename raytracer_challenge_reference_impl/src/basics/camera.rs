@@ -1,5 +1,9 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+
 use crate::basics::canvas::{Canvas, CanvasOps};
-use crate::basics::color::BLACK;
+use crate::basics::color::{Color, BLACK};
 use crate::basics::ray::Ray;
 use crate::basics::ray::RayOps;
 use crate::math::matrix::Matrix;
@@ -171,71 +175,12 @@ impl CameraOps for Camera {
     }
 
     fn render(c: &Camera, w: &World) -> Canvas {
-        //  https://computergraphics.stackexchange.com/questions/4248/how-is-anti-aliasing-implemented-in-ray-tracing
-        let n_samples = c.get_antialiasing_size();
-        let mut jitter_matrix = Vec::new();
-        if n_samples == 2 {
-            jitter_matrix = vec![
-                -1.0 / 4.0,
-                1.0 / 4.0,
-                1.0 / 4.0,
-                1.0 / 4.0,
-                -1.0 / 4.0,
-                -1.0 / 4.0,
-                1.0 / 4.0,
-                -3.0 / 4.0,
-            ];
-        }
-
-        if n_samples == 3 {
-            let two_over_six = 2.0 / 6.0;
-            jitter_matrix = vec![
-                -two_over_six,
-                two_over_six,
-                0.0,
-                two_over_six,
-                two_over_six,
-                two_over_six,
-                -two_over_six,
-                0.0,
-                0.0,
-                0.0,
-                two_over_six,
-                0.0,
-                -two_over_six,
-                -two_over_six,
-                0.0,
-                -two_over_six,
-                two_over_six,
-                -two_over_six,
-            ];
-        }
-
+        let jitter_matrix = jitter_matrix(c.get_antialiasing_size());
         let mut canvas = Canvas::new(c.get_hsize(), c.get_vsize());
 
         for y in 0..c.get_vsize() {
             for x in 0..c.get_hsize() {
-                if c.get_antialiasing() {
-                    let mut color = BLACK;
-
-                    // Accumulate light for N samples.
-                    for sample in 0..n_samples {
-                        let delta_x = jitter_matrix[2 * sample] * c.get_pixel_size();
-                        let delta_y = jitter_matrix[2 * sample + 1] * c.get_pixel_size();
-
-                        let r = Camera::ray_for_pixel_anti_aliasing(c, x, y, delta_x, delta_y);
-
-                        color = color + World::color_at(w, &r, MAX_REFLECTION_RECURSION_DEPTH);
-                    }
-                    color = color / n_samples as f32;
-                    // println!("with AA    color at ({}/{}): {:?}", x, y, color);
-                    canvas.write_pixel(x, y, color);
-                } else {
-                    let r = Camera::ray_for_pixel(c, x, y);
-                    let color = World::color_at(w, &r, MAX_REFLECTION_RECURSION_DEPTH);
-                    // println!("no AA    color at ({}/{}): {:?}", x, y, color);
-                    canvas.write_pixel(x, y, color);
-                }
+                canvas.write_pixel(x, y, color_at_pixel(c, w, &jitter_matrix, x, y));
             }
             // println!("render line  {}", y);
         }
@@ -243,50 +188,49 @@ impl CameraOps for Camera {
     }
 
     fn render_multi_core(c: &Camera, w: &World, num_cores: i32) -> Canvas {
-        //        let mut canvas_std = Canvas::new(c.get_hsize(), c.get_vsize());
-        //
-        //        let data = Arc::new(Mutex::new(canvas_std));
-        //        let mut children = vec![];
-        //        let act_y: usize = 0;
-        //        let act_y_mutex = Arc::new(Mutex::new(act_y));
-        //
-        //        for _i in 0..num_cores {
-        //            let cloned_data = Arc::clone(&data);
-        //            let cloned_act_y = Arc::clone(&act_y_mutex);
-        //            let height = c.get_hsize();
-        //            let width = c.get_vsize();
-        //
-        //            let c_clone = c.clone();
-        //            let w_clone = w.clone();
-        //
-        //            children.push(thread::spawn(move || {
-        //                let mut y: usize = 0;
-        //                while *cloned_act_y.lock().unwrap() < height {
-        //                    if y < height {
-        //                        let mut acty = cloned_act_y.lock().unwrap();
-        //                        y = *acty;
-        //                        *acty = *acty + 1;
-        //                    }
-        //                    for x in 0..width {
-        //                        let r = Camera::ray_for_pixel(&c_clone, x, y);
-        //                        println!("render point  {}/{}", x, y);
-        //                        let color = World::color_at(&w_clone, &r, MAX_REFLECTION_RECURSION_DEPTH);
-        //                        // TODO: wtf ?!
-        //                        if color.r != 0.0 || color.g != 0.0 || color.b != 0.0 {}
-        //                        let mut canvas_std = cloned_data.lock().unwrap();
-        //                        canvas_std.write_pixel(x, y, color);
-        //                    }
-        //                }
-        //            }));
-        //        }
-        //
-        //        for child in children {
-        //            let _ = child.join();
-        //        }
-        //
-        //        let c = data.lock().unwrap();
-        //        *c
-        Canvas::new(c.get_hsize(), c.get_vsize())
+        let num_threads = if num_cores > 0 {
+            num_cores as usize
+        } else {
+            num_cpus::get()
+        };
+        let jitter_matrix = Arc::new(jitter_matrix(c.get_antialiasing_size()));
+        let next_y = Arc::new(AtomicUsize::new(0));
+
+        // every thread takes the next row, until all rows are done. the rows are
+        // collected per thread, so no thread has to wait for the canvas
+        let mut children = vec![];
+        for _i in 0..num_threads.min(c.get_vsize()) {
+            let c_clone = c.clone();
+            let w_clone = w.clone();
+            let jitter_matrix = Arc::clone(&jitter_matrix);
+            let next_y = Arc::clone(&next_y);
+
+            children.push(thread::spawn(move || {
+                let mut rows = vec![];
+                loop {
+                    let y = next_y.fetch_add(1, Ordering::SeqCst);
+                    if y >= c_clone.get_vsize() {
+                        break;
+                    }
+                    let row: Vec<Color> = (0..c_clone.get_hsize())
+                        .map(|x| color_at_pixel(&c_clone, &w_clone, &jitter_matrix, x, y))
+                        .collect();
+                    rows.push((y, row));
+                }
+                rows
+            }));
+        }
+
+        let mut canvas = Canvas::new(c.get_hsize(), c.get_vsize());
+        for child in children {
+            let rows = child.join().expect("render thread panicked");
+            for (y, row) in rows {
+                for (x, color) in row.into_iter().enumerate() {
+                    canvas.write_pixel(x, y, color);
+                }
+            }
+        }
+        canvas
     }
 
     fn render_debug(c: &Camera, w: &World, x: usize, y: usize) -> Canvas {
@@ -317,6 +261,70 @@ impl CameraOps for Camera {
     }
 }
 
+//  https://computergraphics.stackexchange.com/questions/4248/how-is-anti-aliasing-implemented-in-ray-tracing
+fn jitter_matrix(n_samples: usize) -> Vec<f32> {
+    let mut jitter_matrix = Vec::new();
+    if n_samples == 2 {
+        jitter_matrix = vec![
+            -1.0 / 4.0,
+            1.0 / 4.0,
+            1.0 / 4.0,
+            1.0 / 4.0,
+            -1.0 / 4.0,
+            -1.0 / 4.0,
+            1.0 / 4.0,
+            -3.0 / 4.0,
+        ];
+    }
+
+    if n_samples == 3 {
+        let two_over_six = 2.0 / 6.0;
+        jitter_matrix = vec![
+            -two_over_six,
+            two_over_six,
+            0.0,
+            two_over_six,
+            two_over_six,
+            two_over_six,
+            -two_over_six,
+            0.0,
+            0.0,
+            0.0,
+            two_over_six,
+            0.0,
+            -two_over_six,
+            -two_over_six,
+            0.0,
+            -two_over_six,
+            two_over_six,
+            -two_over_six,
+        ];
+    }
+    jitter_matrix
+}
+
+// used by render and render_multi_core, so both produce the same image
+fn color_at_pixel(c: &Camera, w: &World, jitter_matrix: &[f32], x: usize, y: usize) -> Color {
+    if c.get_antialiasing() {
+        let n_samples = c.get_antialiasing_size();
+        let mut color = BLACK;
+
+        // Accumulate light for N samples.
+        for sample in 0..n_samples {
+            let delta_x = jitter_matrix[2 * sample] * c.get_pixel_size();
+            let delta_y = jitter_matrix[2 * sample + 1] * c.get_pixel_size();
+
+            let r = Camera::ray_for_pixel_anti_aliasing(c, x, y, delta_x, delta_y);
+
+            color = color + World::color_at(w, &r, MAX_REFLECTION_RECURSION_DEPTH);
+        }
+        color / n_samples as f32
+    } else {
+        let r = Camera::ray_for_pixel(c, x, y);
+        World::color_at(w, &r, MAX_REFLECTION_RECURSION_DEPTH)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::f32::consts::{PI, SQRT_2};
@@ -434,6 +442,32 @@ mod tests {
         assert_color(color, &c_expected);
     }
 
+    #[test]
+    fn test_camera_render_multi_core() {
+        let w = default_world();
+        let mut c = Camera::new(21, 13, PI / 2.0);
+        c.calc_pixel_size();
+        c.set_transformation(Matrix::view_transform(
+            &Tuple4D::new_point(0.0, 0.0, -5.0),
+            &Tuple4D::new_point(0.0, 0.0, 0.0),
+            &Tuple4D::new_vector(0.0, 1.0, 0.0),
+        ));
+
+        for aa in [false, true].iter() {
+            c.set_antialiasing(*aa);
+            c.set_antialiasing_size(3);
+            let expected = Camera::render(&c, &w);
+            for num_cores in [1, 4, 64].iter() {
+                let image = Camera::render_multi_core(&c, &w, *num_cores);
+                for y in 0..c.get_vsize() {
+                    for x in 0..c.get_hsize() {
+                        assert_eq!(image.pixel_at(x, y), expected.pixel_at(x, y));
+                    }
+                }
+            }
+        }
+    }
+
     //
     // copy of sphere::test_ray_sphere_intersection()  but uses the render method
     //    #[test]
@@ -0,0 +1,93 @@
+use std::fs::File;
+use std::io::{BufWriter, Error, Write};
+
+use crate::basics::color::{Color, ColorOps, BLACK};
+
+#[derive(Clone, Debug)]
+pub struct Canvas {
+    width: usize,
+    height: usize,
+    pixel: Vec<Color>,
+}
+
+pub trait CanvasOps {
+    fn new(width: usize, height: usize) -> Canvas;
+    fn write_pixel(&mut self, x: usize, y: usize, c: Color);
+    fn pixel_at(&self, x: usize, y: usize) -> &Color;
+
+    fn get_width(&self) -> usize;
+    fn get_height(&self) -> usize;
+
+    fn write_ppm(&self, filename: &str) -> Result<(), Error>;
+}
+
+impl CanvasOps for Canvas {
+    fn new(width: usize, height: usize) -> Canvas {
+        Canvas {
+            width,
+            height,
+            pixel: vec![BLACK; width * height],
+        }
+    }
+
+    fn write_pixel(&mut self, x: usize, y: usize, c: Color) {
+        assert!(x < self.width);
+        assert!(y < self.height);
+        self.pixel[y * self.width + x] = c;
+    }
+
+    fn pixel_at(&self, x: usize, y: usize) -> &Color {
+        assert!(x < self.width);
+        assert!(y < self.height);
+        &self.pixel[y * self.width + x]
+    }
+
+    fn get_width(&self) -> usize {
+        self.width
+    }
+
+    fn get_height(&self) -> usize {
+        self.height
+    }
+
+    fn write_ppm(&self, filename: &str) -> Result<(), Error> {
+        let mut file = BufWriter::new(File::create(filename)?);
+        write!(file, "P3\n{} {}\n255\n", self.width, self.height)?;
+        for row in self.pixel.chunks(self.width) {
+            let mut line = String::new();
+            for c in row.iter() {
+                let mut c = c.clone();
+                c.clamp_color();
+                line += &format!(
+                    "{} {} {} ",
+                    (c.r * 255.0) as u8,
+                    (c.g * 255.0) as u8,
+                    (c.b * 255.0) as u8
+                );
+            }
+            writeln!(file, "{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_canvas_new() {
+        let c = Canvas::new(10, 20);
+        assert_eq!(c.get_width(), 10);
+        assert_eq!(c.get_height(), 20);
+        assert_eq!(c.pixel_at(9, 19), &BLACK);
+    }
+
+    #[test]
+    fn test_canvas_write_pixel() {
+        let mut c = Canvas::new(10, 20);
+        let red = Color::new(1.0, 0.0, 0.0);
+        c.write_pixel(2, 3, red.clone());
+        assert_eq!(c.pixel_at(2, 3), &red);
+    }
+}
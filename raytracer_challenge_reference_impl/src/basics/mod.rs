@@ -1,10 +1,12 @@
 pub mod camera;
+pub mod canvas;
 pub mod color;
 pub mod intersection;
 pub mod precomputed_component;
 pub mod ray;
 
 pub use self::camera::*;
+pub use self::canvas::*;
 pub use self::color::*;
 pub use self::intersection::*;
 pub use self::precomputed_component::*;
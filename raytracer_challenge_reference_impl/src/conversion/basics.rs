@@ -0,0 +1,179 @@
+use std::convert::TryFrom;
+
+use math::prelude::{Matrix as LibMatrix, MatrixOps as LibMatrixOps, Tuple4D as LibTuple4D};
+use raytracer_lib_no_std::{Camera as LibCamera, CameraOps as LibCameraOps, Color as LibColor};
+use raytracer_lib_std::{Canvas as LibCanvas, CanvasOps as LibCanvasOps, RaytracerError};
+
+use crate::basics::camera::{Camera, CameraOps};
+use crate::basics::canvas::{Canvas, CanvasOps};
+use crate::basics::color::Color;
+use crate::math::matrix::{Matrix, MatrixOps};
+use crate::math::tuple4d::Tuple4D;
+use crate::world::world::MAX_REFLECTION_RECURSION_DEPTH;
+
+impl From<&Color> for LibColor {
+    fn from(c: &Color) -> Self {
+        LibColor { r: c.r, g: c.g, b: c.b }
+    }
+}
+
+impl From<&LibColor> for Color {
+    fn from(c: &LibColor) -> Self {
+        Color { r: c.r, g: c.g, b: c.b }
+    }
+}
+
+impl From<&Tuple4D> for LibTuple4D {
+    fn from(t: &Tuple4D) -> Self {
+        LibTuple4D {
+            x: t.x,
+            y: t.y,
+            z: t.z,
+            w: t.w,
+        }
+    }
+}
+
+impl From<&LibTuple4D> for Tuple4D {
+    fn from(t: &LibTuple4D) -> Self {
+        Tuple4D {
+            x: t.x,
+            y: t.y,
+            z: t.z,
+            w: t.w,
+        }
+    }
+}
+
+// the matrices of the raytracer crates have a fixed storage of 4x4 entries
+impl TryFrom<&Matrix> for LibMatrix {
+    type Error = RaytracerError;
+
+    fn try_from(m: &Matrix) -> Result<Self, Self::Error> {
+        if m.rows > 4 || m.cols > 4 {
+            return Err(RaytracerError::Unsupported("matrices larger than 4x4"));
+        }
+        let mut res = LibMatrix::new(m.rows, m.cols);
+        for row in 0..m.rows {
+            for col in 0..m.cols {
+                res[row][col] = m.m[row][col];
+            }
+        }
+        Ok(res)
+    }
+}
+
+impl From<&LibMatrix> for Matrix {
+    fn from(m: &LibMatrix) -> Self {
+        let mut res = Matrix::new(m.rows, m.cols);
+        for row in 0..m.rows {
+            for col in 0..m.cols {
+                res.m[row][col] = m[row][col];
+            }
+        }
+        res
+    }
+}
+
+// the reference implementation always calculates reflection, refraction and shadows
+// up to MAX_REFLECTION_RECURSION_DEPTH, which are the defaults of the raytracer crates
+impl TryFrom<&Camera> for LibCamera {
+    type Error = RaytracerError;
+
+    fn try_from(c: &Camera) -> Result<Self, Self::Error> {
+        let mut res = LibCamera::new(c.get_hsize(), c.get_vsize(), c.get_field_of_view());
+        res.set_transformation(LibMatrix::try_from(c.get_transform())?);
+        res.set_antialiasing(c.get_antialiasing());
+        res.set_antialiasing_size(c.get_antialiasing_size());
+        res.calc_pixel_size();
+        Ok(res)
+    }
+}
+
+impl TryFrom<&LibCamera> for Camera {
+    type Error = RaytracerError;
+
+    fn try_from(c: &LibCamera) -> Result<Self, Self::Error> {
+        if !c.get_calc_reflection() || !c.get_calc_refraction() || !c.get_calc_shadows() {
+            return Err(RaytracerError::Unsupported(
+                "disabling reflection, refraction or shadows in the reference implementation",
+            ));
+        }
+        if c.get_max_recursion_depth() != MAX_REFLECTION_RECURSION_DEPTH {
+            return Err(RaytracerError::Unsupported(
+                "a max recursion depth other than MAX_REFLECTION_RECURSION_DEPTH in the reference implementation",
+            ));
+        }
+        let mut res = Camera::new(c.get_hsize(), c.get_vsize(), c.get_field_of_view());
+        res.set_transformation(Matrix::from(c.get_transform()));
+        res.set_antialiasing(c.get_antialiasing());
+        res.set_antialiasing_size(c.get_antialiasing_size());
+        res.calc_pixel_size();
+        Ok(res)
+    }
+}
+
+impl From<&Canvas> for LibCanvas {
+    fn from(c: &Canvas) -> Self {
+        let mut res = LibCanvas::new(c.get_width(), c.get_height());
+        for y in 0..c.get_height() {
+            for x in 0..c.get_width() {
+                res.write_pixel(x, y, LibColor::from(c.pixel_at(x, y)));
+            }
+        }
+        res
+    }
+}
+
+impl From<&LibCanvas> for Canvas {
+    fn from(c: &LibCanvas) -> Self {
+        let mut res = Canvas::new(c.get_width(), c.get_height());
+        for y in 0..c.get_height() {
+            for x in 0..c.get_width() {
+                res.write_pixel(x, y, Color::from(&c.pixel_at(x, y).color));
+            }
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use crate::math::common::assert_float;
+    use crate::math::tuple4d::Tuple;
+
+    use super::*;
+
+    #[test]
+    fn test_convert_camera() {
+        let mut c = Camera::new(160, 90, PI / 3.0);
+        c.set_transformation(Matrix::view_transform(
+            &Tuple4D::new_point(0.0, 1.5, -5.0),
+            &Tuple4D::new_point(0.0, 1.0, 0.0),
+            &Tuple4D::new_vector(0.0, 1.0, 0.0),
+        ));
+        c.set_antialiasing(true);
+        c.set_antialiasing_size(3);
+        c.calc_pixel_size();
+
+        let lib = LibCamera::try_from(&c).unwrap();
+        assert_eq!(lib.get_hsize(), 160);
+        assert_eq!(lib.get_vsize(), 90);
+        assert!(lib.get_antialiasing());
+        assert_eq!(lib.get_antialiasing_size(), 3);
+        assert_float(lib.get_pixel_size(), c.get_pixel_size());
+
+        let back = Camera::try_from(&lib).unwrap();
+        assert_eq!(back.get_transform(), c.get_transform());
+        assert_eq!(back.get_field_of_view(), c.get_field_of_view());
+
+        let mut lib = lib;
+        lib.set_calc_shadows(false);
+        match Camera::try_from(&lib) {
+            Err(RaytracerError::Unsupported(_)) => {}
+            r => panic!("expected Unsupported, got {:?}", r.map(|_| ())),
+        }
+    }
+}
@@ -0,0 +1,59 @@
+use math::prelude::Tuple4D as LibTuple4D;
+use raytracer_lib_no_std::{
+    AreaLight as LibAreaLight, Color as LibColor, Light as LibLight, LightOps as LibLightOps,
+    PointLight as LibPointLight,
+};
+
+use crate::basics::color::Color;
+use crate::light::arealight::AreaLight;
+use crate::light::light::{Light, LightOps};
+use crate::light::pointlight::PointLight;
+use crate::math::tuple4d::Tuple4D;
+
+// AreaLight::new takes the full edges of the light and calculates the position from them,
+// so the position is copied afterwards
+impl From<&Light> for LibLight {
+    fn from(l: &Light) -> Self {
+        match l {
+            Light::PointLight(pl) => LibLight::PointLight(LibPointLight::new(
+                LibTuple4D::from(pl.get_position()),
+                LibColor::from(pl.get_intensity()),
+            )),
+            Light::AreaLight(al) => {
+                let mut res = LibAreaLight::new(
+                    LibTuple4D::from(al.get_corner()),
+                    LibTuple4D::from(&(al.get_uvec() * al.get_usteps() as f32)),
+                    al.get_usteps(),
+                    LibTuple4D::from(&(al.get_vvec() * al.get_vsteps() as f32)),
+                    al.get_vsteps(),
+                    LibColor::from(al.get_intensity()),
+                );
+                res.set_position(LibTuple4D::from(al.get_position()));
+                LibLight::AreaLight(res)
+            }
+        }
+    }
+}
+
+impl From<&LibLight> for Light {
+    fn from(l: &LibLight) -> Self {
+        match l {
+            LibLight::PointLight(pl) => Light::PointLight(PointLight::new(
+                Tuple4D::from(pl.get_position()),
+                Color::from(pl.get_intensity()),
+            )),
+            LibLight::AreaLight(al) => {
+                let mut res = AreaLight::new(
+                    Tuple4D::from(al.get_corner()),
+                    Tuple4D::from(&(al.get_uvec() * al.get_usteps() as f32)),
+                    al.get_usteps(),
+                    Tuple4D::from(&(al.get_vvec() * al.get_vsteps() as f32)),
+                    al.get_vsteps(),
+                    Color::from(al.get_intensity()),
+                );
+                res.set_position(Tuple4D::from(al.get_position()));
+                Light::AreaLight(res)
+            }
+        }
+    }
+}
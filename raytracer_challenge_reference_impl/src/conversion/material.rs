@@ -0,0 +1,108 @@
+use std::convert::TryFrom;
+
+use math::prelude::Matrix as LibMatrix;
+use raytracer_lib_no_std::{
+    Checker3DPattern as LibChecker3DPattern, GradientPattern as LibGradientPattern, Material as LibMaterial,
+    MaterialOps as LibMaterialOps, Pattern as LibPattern, RingPattern as LibRingPattern,
+    StripePattern as LibStripePattern, TestPattern as LibTestPattern,
+};
+use raytracer_lib_std::RaytracerError;
+
+use crate::material::material::{Material, MaterialOps};
+use crate::math::matrix::Matrix;
+use crate::patterns::checker3d_patterns::Checker3DPattern;
+use crate::patterns::gradient_patterns::GradientPattern;
+use crate::patterns::patterns::Pattern;
+use crate::patterns::ring_patterns::RingPattern;
+use crate::patterns::stripe_patterns::StripePattern;
+use crate::patterns::test_patterns::TestPattern;
+
+// all patterns consist of two colors and a transformation
+macro_rules! convert_pattern {
+    ($target:ident, $pattern:expr, $transformation:expr) => {{
+        let mut p = $target::new();
+        p.set_color_a($pattern.get_color_a().into());
+        p.set_color_b($pattern.get_color_b().into());
+        p.set_transformation($transformation);
+        p
+    }};
+}
+
+impl TryFrom<&Pattern> for LibPattern {
+    type Error = RaytracerError;
+
+    fn try_from(p: &Pattern) -> Result<Self, Self::Error> {
+        let m = LibMatrix::try_from(p.get_transformation())?;
+        let res = match p {
+            Pattern::StripePattern(p) => LibPattern::StripePattern(convert_pattern!(LibStripePattern, p, m)),
+            Pattern::GradientPattern(p) => LibPattern::GradientPattern(convert_pattern!(LibGradientPattern, p, m)),
+            Pattern::RingPattern(p) => LibPattern::RingPattern(convert_pattern!(LibRingPattern, p, m)),
+            Pattern::Checker3DPattern(p) => LibPattern::Checker3DPattern(convert_pattern!(LibChecker3DPattern, p, m)),
+            Pattern::TestPattern(p) => LibPattern::TestPattern(convert_pattern!(LibTestPattern, p, m)),
+        };
+        Ok(res)
+    }
+}
+
+impl TryFrom<&LibPattern> for Pattern {
+    type Error = RaytracerError;
+
+    fn try_from(p: &LibPattern) -> Result<Self, Self::Error> {
+        let m = Matrix::from(p.get_transformation());
+        let res = match p {
+            LibPattern::StripePattern(p) => Pattern::StripePattern(convert_pattern!(StripePattern, p, m)),
+            LibPattern::GradientPattern(p) => Pattern::GradientPattern(convert_pattern!(GradientPattern, p, m)),
+            LibPattern::RingPattern(p) => Pattern::RingPattern(convert_pattern!(RingPattern, p, m)),
+            LibPattern::Checker3DPattern(p) => Pattern::Checker3DPattern(convert_pattern!(Checker3DPattern, p, m)),
+            LibPattern::TestPattern(p) => Pattern::TestPattern(convert_pattern!(TestPattern, p, m)),
+            LibPattern::NestedPattern(_) => {
+                return Err(RaytracerError::Unsupported("nested patterns in the reference implementation"))
+            }
+        };
+        Ok(res)
+    }
+}
+
+// the values are not checked here, Shape::try_from rejects invalid materials
+impl TryFrom<&Material> for LibMaterial {
+    type Error = RaytracerError;
+
+    fn try_from(m: &Material) -> Result<Self, Self::Error> {
+        let mut res = LibMaterial::new();
+        res.set_color(m.get_color().into());
+        res.set_ambient(m.get_ambient());
+        res.set_diffuse(m.get_diffuse());
+        res.set_specular(m.get_specular());
+        res.set_shininess(m.get_shininess());
+        res.set_reflective(m.get_reflective());
+        res.set_transparency(m.get_transparency());
+        res.set_refractive_index(m.get_refractive_index());
+        if let Some(p) = m.get_pattern() {
+            res.set_pattern(LibPattern::try_from(p)?);
+        }
+        Ok(res)
+    }
+}
+
+impl TryFrom<&LibMaterial> for Material {
+    type Error = RaytracerError;
+
+    fn try_from(m: &LibMaterial) -> Result<Self, Self::Error> {
+        if m.get_normal_perturbation().is_some() {
+            return Err(RaytracerError::Unsupported("normal perturbation in the reference implementation"));
+        }
+        let mut res = Material::new();
+        res.set_color(m.get_color().into());
+        res.set_ambient(m.get_ambient());
+        res.set_diffuse(m.get_diffuse());
+        res.set_specular(m.get_specular());
+        res.set_shininess(m.get_shininess());
+        res.set_reflective(m.get_reflective());
+        res.set_transparency(m.get_transparency());
+        res.set_refractive_index(m.get_refractive_index());
+        if let Some(p) = m.get_pattern() {
+            res.set_pattern(Pattern::try_from(p)?);
+        }
+        Ok(res)
+    }
+}
@@ -0,0 +1,9 @@
+// From / TryFrom conversions between the types of the reference implementation and the
+// types of raytracer_lib_std and raytracer_lib_no_std, so a scene has to be described only
+// once and can be rendered by both. conversions, which can not represent every feature of
+// the source (e.g. a Group), fail with RaytracerError::Unsupported
+mod basics;
+mod light;
+mod material;
+mod shape;
+mod world;
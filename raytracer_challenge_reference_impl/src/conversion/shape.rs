@@ -0,0 +1,107 @@
+use std::convert::TryFrom;
+
+use math::prelude::{Matrix as LibMatrix, Tuple4D as LibTuple4D};
+use raytracer_lib_no_std::{
+    Cube as LibCube, Cylinder as LibCylinder, Material as LibMaterial, Plane as LibPlane, Shape as LibShape,
+    ShapeEnum as LibShapeEnum, ShapeOps as LibShapeOps, Sphere as LibSphere, Triangle as LibTriangle,
+};
+use raytracer_lib_std::RaytracerError;
+
+use crate::material::material::Material;
+use crate::math::matrix::Matrix;
+use crate::math::tuple4d::Tuple4D;
+use crate::shape::cube::Cube;
+use crate::shape::cylinder::Cylinder;
+use crate::shape::plane::Plane;
+use crate::shape::shape::{Shape, ShapeEnum, ShapeOps};
+use crate::shape::sphere::Sphere;
+use crate::shape::triangle::Triangle;
+
+// the raytracer crates have no groups, the children of a group are ordinary shapes of the
+// World in the reference implementation, so only the Group itself is rejected
+impl TryFrom<&Shape> for LibShape {
+    type Error = RaytracerError;
+
+    fn try_from(s: &Shape) -> Result<Self, Self::Error> {
+        let shape = match s.get_shape() {
+            ShapeEnum::Sphere(_) => LibShapeEnum::Sphere(LibSphere::new()),
+            ShapeEnum::Plane(_) => LibShapeEnum::Plane(LibPlane::new()),
+            ShapeEnum::Cube(_) => LibShapeEnum::Cube(LibCube::new()),
+            ShapeEnum::Cylinder(c) => {
+                let mut cylinder = LibCylinder::new();
+                cylinder.set_minimum(c.get_minimum());
+                cylinder.set_maximum(c.get_maximum());
+                cylinder.set_closed(c.get_closed());
+                LibShapeEnum::Cylinder(cylinder)
+            }
+            ShapeEnum::Triangle(t) => LibShapeEnum::Triangle(LibTriangle::new(
+                LibTuple4D::from(t.get_p1()),
+                LibTuple4D::from(t.get_p2()),
+                LibTuple4D::from(t.get_p3()),
+            )),
+            ShapeEnum::Group(_) => return Err(RaytracerError::Unsupported("groups in the raytracer crates")),
+        };
+        let mut res = LibShape::new(shape);
+        res.try_set_transformation(LibMatrix::try_from(s.get_transformation())?)?;
+        res.set_material(LibMaterial::try_from(s.get_material())?);
+        res.validate()?;
+        res.set_casts_shadow(s.get_casts_shadow());
+        Ok(res)
+    }
+}
+
+// the reference implementation has no tori, disks, rectangles, heightfields, sdf shapes and instances
+impl TryFrom<&LibShape> for Shape {
+    type Error = RaytracerError;
+
+    fn try_from(s: &LibShape) -> Result<Self, Self::Error> {
+        if s.get_is_prototype() {
+            return Err(RaytracerError::Unsupported("instances in the reference implementation"));
+        }
+        let m = Matrix::from(s.get_transformation());
+        let material = Material::try_from(s.get_material())?;
+        let shape = match s.get_shape() {
+            LibShapeEnum::Sphere(_) => ShapeEnum::Sphere(with_transformation_and_material(Sphere::new(), m, material)),
+            LibShapeEnum::Plane(_) => ShapeEnum::Plane(with_transformation_and_material(Plane::new(), m, material)),
+            LibShapeEnum::Cube(_) => ShapeEnum::Cube(with_transformation_and_material(Cube::new(), m, material)),
+            LibShapeEnum::Cylinder(c) => {
+                let mut cylinder = Cylinder::new();
+                cylinder.set_minimum(c.get_minimum());
+                cylinder.set_maximum(c.get_maximum());
+                cylinder.set_closed(c.get_closed());
+                ShapeEnum::Cylinder(with_transformation_and_material(cylinder, m, material))
+            }
+            LibShapeEnum::Triangle(t) => {
+                let triangle = Triangle::new(
+                    Tuple4D::from(t.get_p1()),
+                    Tuple4D::from(t.get_p2()),
+                    Tuple4D::from(t.get_p3()),
+                );
+                ShapeEnum::Triangle(with_transformation_and_material(triangle, m, material))
+            }
+            LibShapeEnum::Torus(_)
+            | LibShapeEnum::Disk(_)
+            | LibShapeEnum::Rectangle(_)
+            | LibShapeEnum::Heightfield(_)
+            | LibShapeEnum::Sdf(_) => {
+                return Err(RaytracerError::Unsupported(
+                    "tori, disks, rectangles, heightfields and sdf shapes in the reference implementation",
+                ));
+            }
+            LibShapeEnum::Instance(_) => {
+                return Err(RaytracerError::Unsupported("instances in the reference implementation"));
+            }
+        };
+        let mut res = Shape::new(shape);
+        res.set_casts_shadow(s.get_casts_shadow());
+        Ok(res)
+    }
+}
+
+// Shape::set_transformation and Shape::set_material are not implemented in the reference
+// implementation, so they are set on the individual shape before it is wrapped
+fn with_transformation_and_material<S: ShapeOps>(mut shape: S, m: Matrix, material: Material) -> S {
+    shape.set_transformation(m);
+    shape.set_material(material);
+    shape
+}
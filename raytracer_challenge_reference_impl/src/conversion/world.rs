@@ -0,0 +1,123 @@
+use std::convert::TryFrom;
+
+use raytracer_lib_no_std::{Light as LibLight, Shape as LibShape};
+use raytracer_lib_std::{RaytracerError, World as LibWorld, WorldOps as LibWorldOps};
+
+use crate::light::light::Light;
+use crate::shape::shape::Shape;
+use crate::world::world::{World, WorldOps};
+
+impl TryFrom<&World> for LibWorld {
+    type Error = RaytracerError;
+
+    fn try_from(w: &World) -> Result<Self, Self::Error> {
+        let mut res = LibWorld::new();
+        res.set_light(LibLight::from(w.get_light()));
+        for s in w.get_shapes().iter() {
+            res.add_shape(LibShape::try_from(s)?)?;
+        }
+        Ok(res)
+    }
+}
+
+impl TryFrom<&LibWorld> for World {
+    type Error = RaytracerError;
+
+    fn try_from(w: &LibWorld) -> Result<Self, Self::Error> {
+        let mut res = World::new();
+        res.set_light(Light::from(w.get_light()));
+        for s in w.get_shapes().iter() {
+            res.add_shape(Shape::try_from(s)?);
+        }
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use raytracer_lib_no_std::{ShapeEnum as LibShapeEnum, Torus as LibTorus};
+
+    use crate::prelude::*;
+
+    use super::*;
+
+    fn all_shapes_world() -> World {
+        let mut w = default_world();
+        w.set_light(Light::AreaLight(AreaLight::new(
+            Tuple4D::new_point(-1.0, 2.0, 4.0),
+            Tuple4D::new_vector(2.0, 0.0, 0.0),
+            4,
+            Tuple4D::new_vector(0.0, 2.0, 0.0),
+            3,
+            Color::new(1.0, 0.9, 0.8),
+        )));
+
+        let mut p = RingPattern::new();
+        p.set_color_a(Color::new(0.1, 0.2, 0.3));
+        p.set_color_b(Color::new(0.7, 0.8, 0.9));
+        p.set_transformation(Matrix::rotate_y(0.3));
+        let mut plane = Plane::new();
+        plane.get_material_mut().set_pattern(Pattern::RingPattern(p));
+        plane.get_material_mut().set_reflective(0.4);
+        w.add_shape(Shape::new(ShapeEnum::Plane(plane)));
+
+        let mut cube = Cube::new();
+        cube.set_transformation(Matrix::translation(1.0, 2.0, 3.0));
+        cube.get_material_mut().set_transparency(0.5);
+        cube.get_material_mut().set_refractive_index(REFRACTION_GLASS);
+        let mut cube = Shape::new(ShapeEnum::Cube(cube));
+        cube.set_casts_shadow(false);
+        w.add_shape(cube);
+
+        let mut cylinder = Cylinder::new();
+        cylinder.set_minimum(-1.0);
+        cylinder.set_maximum(2.0);
+        cylinder.set_closed(true);
+        w.add_shape(Shape::new(ShapeEnum::Cylinder(cylinder)));
+
+        let triangle = Triangle::new(
+            Tuple4D::new_point(0.0, 1.0, 0.0),
+            Tuple4D::new_point(-1.0, 0.0, 0.0),
+            Tuple4D::new_point(1.0, 0.0, 0.0),
+        );
+        w.add_shape(Shape::new(ShapeEnum::Triangle(triangle)));
+        w
+    }
+
+    #[test]
+    fn test_convert_world_round_trip() {
+        let w = all_shapes_world();
+        let lib = LibWorld::try_from(&w).unwrap();
+        assert_eq!(lib.get_shapes().len(), w.get_shapes().len());
+
+        let back = World::try_from(&lib).unwrap();
+        assert_eq!(back.get_shapes(), w.get_shapes());
+        let (expected, actual) = (w.get_light(), back.get_light());
+        assert_tuple(actual.get_position(), expected.get_position());
+        assert_tuple(actual.get_corner(), expected.get_corner());
+        assert_tuple(actual.get_uvec(), expected.get_uvec());
+        assert_tuple(actual.get_vvec(), expected.get_vvec());
+        assert_eq!(actual.get_samples(), expected.get_samples());
+        assert_color(actual.get_intensity(), expected.get_intensity());
+    }
+
+    #[test]
+    fn test_convert_world_rejects_groups() {
+        let mut w = default_world();
+        w.add_shape(Shape::new(ShapeEnum::Group(Group::new())));
+        match LibWorld::try_from(&w) {
+            Err(RaytracerError::Unsupported(_)) => {}
+            r => panic!("expected Unsupported, got {:?}", r.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn test_convert_world_rejects_tori() {
+        let mut lib = LibWorld::try_from(&default_world()).unwrap();
+        lib.add_shape(LibShape::new(LibShapeEnum::Torus(LibTorus::new()))).unwrap();
+        match World::try_from(&lib) {
+            Err(RaytracerError::Unsupported(_)) => {}
+            r => panic!("expected Unsupported, got {:?}", r.map(|_| ())),
+        }
+    }
+}
@@ -1,6 +1,7 @@
 #![feature(stmt_expr_attributes)]
 
 pub mod basics;
+mod conversion;
 pub mod light;
 pub mod material;
 pub mod math;
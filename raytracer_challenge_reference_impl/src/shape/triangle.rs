@@ -98,15 +98,15 @@ impl Triangle {
         Some(res)
     }
 
-    fn get_p1(&self) -> &Tuple4D {
+    pub fn get_p1(&self) -> &Tuple4D {
         &self.p1
     }
 
-    fn get_p2(&self) -> &Tuple4D {
+    pub fn get_p2(&self) -> &Tuple4D {
         &self.p2
     }
 
-    fn get_p3(&self) -> &Tuple4D {
+    pub fn get_p3(&self) -> &Tuple4D {
         &self.p3
     }
 
@@ -4,6 +4,8 @@ use crate::prelude::test_patterns::TestPattern;
 use crate::prelude::*;
 use crate::DEBUG;
 
+pub const MAX_REFLECTION_RECURSION_DEPTH: i32 = 5;
+
 #[derive(Clone, Debug)]
 pub struct World {
     shapes: Vec<Shape>,
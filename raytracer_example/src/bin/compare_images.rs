@@ -0,0 +1,103 @@
+use std::env;
+use std::process;
+
+use raytracer::prelude::*;
+
+const USAGE: &str = "usage: compare_images <expected image> <actual image> [options]
+
+prints MSE, PSNR, SSIM and a FLIP-style perceptual error of the two images.
+exits with 1 when one of the given limits is exceeded.
+
+options:
+    -d, --diff <file>         write a false color image of the perceptual error
+    --min-psnr <dB>           fail when the PSNR is below the value
+    --min-ssim <x>            fail when the SSIM is below the value
+    --max-flip <x>            fail when the mean perceptual error is above the value";
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.is_empty() || args.iter().any(|a| a == "-h" || a == "--help") {
+        println!("{}", USAGE);
+        return;
+    }
+
+    match run(&args) {
+        Ok(true) => {}
+        Ok(false) => process::exit(1),
+        Err(e) => {
+            eprintln!("error: {}", e);
+            process::exit(2);
+        }
+    }
+}
+
+fn run(args: &[String]) -> Result<bool, String> {
+    let mut files = Vec::new();
+    let mut diff_file = None;
+    let mut min_psnr = None;
+    let mut min_ssim = None;
+    let mut max_flip = None;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let mut value = || iter.next().ok_or_else(|| format!("option '{}' needs a value", arg));
+        match arg.as_str() {
+            "-d" | "--diff" => diff_file = Some(value()?.to_string()),
+            "--min-psnr" => min_psnr = Some(parse_limit(arg, value()?)?),
+            "--min-ssim" => min_ssim = Some(parse_limit(arg, value()?)?),
+            "--max-flip" => max_flip = Some(parse_limit(arg, value()?)?),
+            _ if arg.starts_with('-') => return Err(format!("unknown option '{}'\n\n{}", arg, USAGE)),
+            _ => files.push(arg.to_string()),
+        }
+    }
+    if files.len() != 2 {
+        return Err(format!("expected two image files\n\n{}", USAGE));
+    }
+
+    let expected = read_png(&files[0]).map_err(|e| e.to_string())?;
+    let actual = read_png(&files[1]).map_err(|e| e.to_string())?;
+
+    let difference = compare_canvas(&expected, &actual).map_err(|e| e.to_string())?;
+    let ssim = compare_ssim(&expected, &actual).map_err(|e| e.to_string())?;
+    let flip = compare_flip(&expected, &actual).map_err(|e| e.to_string())?;
+
+    println!("size:             {}x{}", expected.get_width(), expected.get_height());
+    println!("max abs diff:     {:.5}", difference.get_max_abs_diff());
+    println!("mse:              {:.7}", difference.get_mse());
+    println!("psnr:             {:.2} dB", difference.get_psnr());
+    println!("ssim:             {:.5}", ssim);
+    println!("flip mean / max:  {:.5} / {:.5}", flip.get_mean(), flip.get_max());
+    println!(
+        "differing pixels: {} of {}",
+        difference.get_differing_pixels(),
+        difference.get_pixels()
+    );
+
+    if let Some(diff_file) = diff_file {
+        flip.to_heatmap()
+            .write_png(&diff_file)
+            .map_err(|e| format!("can't write '{}': {}", diff_file, e))?;
+        println!("diff image:       {}", diff_file);
+    }
+
+    let mut ok = true;
+    if let Some(min) = min_psnr.filter(|min| difference.get_psnr() < *min) {
+        eprintln!("PSNR is below {}", min);
+        ok = false;
+    }
+    if let Some(min) = min_ssim.filter(|min| ssim < *min) {
+        eprintln!("SSIM is below {}", min);
+        ok = false;
+    }
+    if let Some(max) = max_flip.filter(|max| flip.get_mean() > *max) {
+        eprintln!("mean perceptual error is above {}", max);
+        ok = false;
+    }
+    Ok(ok)
+}
+
+fn parse_limit(option: &str, value: &str) -> Result<f32, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid value '{}' for option '{}'", value, option))
+}
@@ -0,0 +1,261 @@
+use std::convert::TryFrom;
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use std::process;
+
+use raytracer::prelude::*;
+use raytracer::BackendEnum;
+
+// the scenes are described with the types of the reference implementation and converted
+// for the backends
+mod scenes;
+
+const USAGE: &str = "usage: parity [options] [scene...]
+
+renders every scene with the reference implementation and all available backends
+and compares the backends against the reference image.
+
+options:
+    -W, --width <n>         image width (default: 160)
+    -H, --height <n>        image height (default: 100)
+    -a, --antialiasing <n>  render with n x n antialiasing samples, 2 or 3 (default: off)
+    -o, --output <dir>      directory for the images and diff heatmaps (default: parity)
+    --max-rmse <x>          fail when the RMSE of a backend exceeds x (default: 0.01)
+    --max-abs <x>           fail when a channel of a pixel differs by more than x (default: off)
+
+scenes: spheres, shapes (default: all)";
+
+// differences at or above this value are white in the heatmaps
+const HEATMAP_MAX_DIFF: f32 = 0.1;
+
+struct Options {
+    width: usize,
+    height: usize,
+    antialiasing: Option<usize>,
+    output: String,
+    max_rmse: f32,
+    max_abs: Option<f32>,
+    scenes: Vec<String>,
+}
+
+struct ParityResult {
+    scene: String,
+    backend: String,
+    difference: CanvasDifference,
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.iter().any(|a| a == "-h" || a == "--help") {
+        println!("{}", USAGE);
+        return;
+    }
+
+    let options = match parse_args(&args) {
+        Ok(o) => o,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            process::exit(2);
+        }
+    };
+
+    match run(&options) {
+        Ok(results) => {
+            print_results(&results);
+            let failed: Vec<&ParityResult> = results.iter().filter(|r| !passes(&options, &r.difference)).collect();
+            if !failed.is_empty() {
+                for r in failed.iter() {
+                    eprintln!(
+                        "FAILED: backend '{}' differs from the reference in scene '{}'",
+                        r.backend, r.scene
+                    );
+                }
+                process::exit(1);
+            }
+            println!("all backends match the reference implementation");
+        }
+        Err(e) => {
+            eprintln!("error: {}", e);
+            process::exit(2);
+        }
+    }
+}
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut options = Options {
+        width: 160,
+        height: 100,
+        antialiasing: None,
+        output: "parity".to_string(),
+        max_rmse: 0.01,
+        max_abs: None,
+        scenes: Vec::new(),
+    };
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let mut value = || iter.next().ok_or_else(|| format!("option '{}' needs a value", arg));
+        match arg.as_str() {
+            "-W" | "--width" => options.width = parse_value(arg, value()?)?,
+            "-H" | "--height" => options.height = parse_value(arg, value()?)?,
+            "-a" | "--antialiasing" => {
+                let v = value()?;
+                match v.parse::<usize>() {
+                    Ok(n) if n == 2 || n == 3 => options.antialiasing = Some(n),
+                    _ => return Err(format!("antialiasing must be 2 or 3, got '{}'", v)),
+                }
+            }
+            "-o" | "--output" => options.output = value()?.to_string(),
+            "--max-rmse" => options.max_rmse = parse_value(arg, value()?)?,
+            "--max-abs" => options.max_abs = Some(parse_value(arg, value()?)?),
+            _ if arg.starts_with('-') => return Err(format!("unknown option '{}'\n\n{}", arg, USAGE)),
+            _ if scenes::SCENE_NAMES.contains(&arg.as_str()) => options.scenes.push(arg.to_string()),
+            _ => return Err(format!("unknown scene '{}'\n\n{}", arg, USAGE)),
+        }
+    }
+    if options.width == 0 || options.height == 0 {
+        return Err("width and height must be positive".to_string());
+    }
+    if options.scenes.is_empty() {
+        options.scenes = scenes::SCENE_NAMES.iter().map(|s| s.to_string()).collect();
+    }
+    Ok(options)
+}
+
+fn parse_value<T: std::str::FromStr>(option: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid value '{}' for option '{}'", value, option))
+}
+
+fn run(options: &Options) -> Result<Vec<ParityResult>, Box<dyn Error>> {
+    fs::create_dir_all(&options.output)?;
+    let output = Path::new(&options.output);
+
+    let backend = Backend::new();
+    let mut results = Vec::new();
+    for scene in options.scenes.iter() {
+        println!("rendering scene '{}' with the reference implementation", scene);
+        let expected = render_reference(scene, options.width, options.height, options.antialiasing);
+        expected.write_png(&output.join(format!("{}_reference.png", scene)).to_string_lossy())?;
+
+        for backend_type in backend.get_available_backends().iter() {
+            println!("rendering scene '{}' with backend '{}'", scene, backend_type);
+            let actual = render_backend(&backend, backend_type, scene, options)?;
+            let difference = compare_canvas(&expected, &actual)?;
+
+            let backend_name = backend_type.to_string().trim().to_string();
+            let name = format!("{}_{}", scene, backend_name).replace(' ', "_").to_lowercase();
+            actual.write_png(&output.join(format!("{}.png", name)).to_string_lossy())?;
+            diff_heatmap(&expected, &actual, HEATMAP_MAX_DIFF)?
+                .write_png(&output.join(format!("{}_diff.png", name)).to_string_lossy())?;
+
+            results.push(ParityResult {
+                scene: scene.to_string(),
+                backend: backend_name,
+                difference,
+            });
+        }
+    }
+    Ok(results)
+}
+
+fn render_reference(scene: &str, width: usize, height: usize, antialiasing: Option<usize>) -> Canvas {
+    use raytracer_challenge_reference_impl::prelude::CameraOps as ReferenceCameraOps;
+
+    let (world, mut camera) = scenes::setup_scene(scene, width, height).expect("unknown scene");
+    if let Some(n) = antialiasing {
+        camera.set_antialiasing(true);
+        camera.set_antialiasing_size(n);
+    }
+    let rendered = raytracer_challenge_reference_impl::prelude::Camera::render(&camera, &world);
+    Canvas::from(&rendered)
+}
+
+fn render_backend(
+    backend: &Backend,
+    backend_type: &BackendEnum,
+    scene: &str,
+    options: &Options,
+) -> Result<Canvas, Box<dyn Error>> {
+    let (world, camera) = scenes::setup_scene(scene, options.width, options.height).expect("unknown scene");
+    let mut world = World::try_from(&world)?;
+    let mut camera = Camera::try_from(&camera)?;
+    if let Some(n) = options.antialiasing {
+        camera.set_antialiasing(true);
+        camera.set_antialiasing_size(n);
+    }
+    // the over and under points of the backends are further away from the surface by default, which
+    // moves the shadow borders. use the offset of the reference implementation to compare the images
+    let mut settings = RenderSettings::from_camera(&camera);
+    settings.set_epsilon(raytracer_challenge_reference_impl::prelude::EPSILON_OVER_UNDER);
+    Ok(backend
+        .get_backend(backend_type)?
+        .render_world_with_settings(&mut world, &camera, &settings)?)
+}
+
+fn passes(options: &Options, d: &CanvasDifference) -> bool {
+    d.get_rmse() <= options.max_rmse && options.max_abs.map_or(true, |max| d.get_max_abs_diff() <= max)
+}
+
+fn print_results(results: &[ParityResult]) {
+    println!();
+    println!(
+        "{:<10} {:<20} {:>10} {:>10} {:>10} {:>18}",
+        "scene", "backend", "max abs", "rmse", "psnr [dB]", "differing pixels"
+    );
+    for r in results.iter() {
+        let d = &r.difference;
+        println!(
+            "{:<10} {:<20} {:>10.5} {:>10.5} {:>10.2} {:>10} / {:<6}",
+            r.scene,
+            r.backend,
+            d.get_max_abs_diff(),
+            d.get_rmse(),
+            d.get_psnr(),
+            d.get_differing_pixels(),
+            d.get_pixels()
+        );
+    }
+    println!();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_options(scene: &str) -> Options {
+        Options {
+            width: 40,
+            height: 25,
+            antialiasing: None,
+            output: String::new(),
+            max_rmse: 0.01,
+            max_abs: None,
+            scenes: vec![scene.to_string()],
+        }
+    }
+
+    #[test]
+    fn test_backends_match_reference() {
+        let backend = Backend::new();
+        for scene in scenes::SCENE_NAMES.iter() {
+            let options = test_options(scene);
+            let expected = render_reference(scene, options.width, options.height, None);
+            let canvases: Vec<Canvas> = backend
+                .get_available_backends()
+                .iter()
+                .map(|b| render_backend(&backend, b, scene, &options).unwrap())
+                .collect();
+
+            for actual in canvases.iter() {
+                let d = compare_canvas(&expected, actual).unwrap();
+                assert!(passes(&options, &d), "backend differs from the reference in scene '{}'", scene);
+                let d = compare_canvas(&canvases[0], actual).unwrap();
+                assert!(passes(&options, &d), "backends differ in scene '{}'", scene);
+            }
+        }
+    }
+}
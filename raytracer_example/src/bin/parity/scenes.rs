@@ -0,0 +1,133 @@
+use raytracer_challenge_reference_impl::prelude::*;
+
+pub const SCENE_NAMES: [&str; 2] = ["spheres", "shapes"];
+
+pub fn setup_scene(name: &str, w: usize, h: usize) -> Option<(World, Camera)> {
+    match name {
+        "spheres" => Some(setup_spheres(w, h)),
+        "shapes" => Some(setup_shapes(w, h)),
+        _ => None,
+    }
+}
+
+fn setup_camera(w: usize, h: usize, from: Tuple4D, to: Tuple4D) -> Camera {
+    let mut c = Camera::new(w, h, std::f32::consts::PI / 3.0);
+    c.calc_pixel_size();
+    c.set_antialiasing(false);
+    c.set_transformation(Matrix::view_transform(&from, &to, &Tuple4D::new_vector(0.0, 1.0, 0.0)));
+    c
+}
+
+fn setup_spheres(w: usize, h: usize) -> (World, Camera) {
+    let pi = std::f32::consts::PI;
+
+    // the floor and the walls are flattened spheres like in chapter 7, but thick enough to not show shadow acne
+    let mut floor = Sphere::new();
+    floor.set_transformation(Matrix::scale(10.0, 0.1, 10.0));
+    floor.get_material_mut().set_color(Color::new(1.0, 0.9, 0.9));
+    floor.get_material_mut().set_specular(0.0);
+
+    let mut left_wall = Sphere::new();
+    left_wall.set_transformation(
+        &(&(&Matrix::translation(0.0, 0.0, 5.0) * &Matrix::rotate_y(-pi / 4.0)) * &Matrix::rotate_x(pi / 2.0))
+            * &Matrix::scale(10.0, 0.1, 10.0),
+    );
+    left_wall.get_material_mut().set_color(Color::new(1.0, 0.9, 0.9));
+    left_wall.get_material_mut().set_specular(0.0);
+
+    let mut right_wall = Sphere::new();
+    right_wall.set_transformation(
+        &(&(&Matrix::translation(0.0, 0.0, 5.0) * &Matrix::rotate_y(pi / 4.0)) * &Matrix::rotate_x(pi / 2.0))
+            * &Matrix::scale(10.0, 0.1, 10.0),
+    );
+    right_wall.get_material_mut().set_color(Color::new(1.0, 0.9, 0.9));
+    right_wall.get_material_mut().set_specular(0.0);
+
+    let mut middle = Sphere::new();
+    middle.set_transformation(Matrix::translation(-0.5, 1.0, 0.5));
+    middle.get_material_mut().set_color(Color::new(0.1, 1.0, 0.5));
+    middle.get_material_mut().set_diffuse(0.7);
+    middle.get_material_mut().set_specular(0.3);
+
+    let mut right = Sphere::new();
+    right.set_transformation(&Matrix::translation(1.5, 0.5, -0.5) * &Matrix::scale(0.5, 0.5, 0.5));
+    right.get_material_mut().set_color(Color::new(0.5, 1.0, 0.1));
+    right.get_material_mut().set_diffuse(0.7);
+    right.get_material_mut().set_specular(0.3);
+
+    let mut left = Sphere::new();
+    left.set_transformation(&Matrix::translation(-1.5, 0.33, -0.75) * &Matrix::scale(0.333, 0.333, 0.333));
+    left.get_material_mut().set_color(Color::new(1.0, 0.8, 0.1));
+    left.get_material_mut().set_diffuse(0.7);
+    left.get_material_mut().set_specular(0.3);
+
+    let pl = PointLight::new(Tuple4D::new_point(-1.0, 10.0, -10.0), Color::new(1.0, 1.0, 1.0));
+
+    let mut world = World::new();
+    world.set_light(Light::PointLight(pl));
+    world.add_shape(Shape::new(ShapeEnum::Sphere(floor)));
+    world.add_shape(Shape::new(ShapeEnum::Sphere(left_wall)));
+    world.add_shape(Shape::new(ShapeEnum::Sphere(right_wall)));
+    world.add_shape(Shape::new(ShapeEnum::Sphere(middle)));
+    world.add_shape(Shape::new(ShapeEnum::Sphere(left)));
+    world.add_shape(Shape::new(ShapeEnum::Sphere(right)));
+
+    let c = setup_camera(
+        w,
+        h,
+        Tuple4D::new_point(0.0, 1.5, -5.0),
+        Tuple4D::new_point(0.0, 1.0, 0.0),
+    );
+    (world, c)
+}
+
+// plane, cube and cylinder with a pattern and reflections
+fn setup_shapes(w: usize, h: usize) -> (World, Camera) {
+    let mut checker = Checker3DPattern::new();
+    checker.set_color_a(Color::new(0.9, 0.9, 0.9));
+    checker.set_color_b(Color::new(0.2, 0.3, 0.4));
+
+    let mut floor = Plane::new();
+    floor.get_material_mut().set_pattern(Pattern::Checker3DPattern(checker));
+    floor.get_material_mut().set_reflective(0.2);
+    floor.get_material_mut().set_specular(0.0);
+
+    let mut cube = Cube::new();
+    cube.set_transformation(
+        &(&Matrix::translation(-1.5, 0.5, 0.5) * &Matrix::rotate_y(0.5)) * &Matrix::scale(0.5, 0.5, 0.5),
+    );
+    cube.get_material_mut().set_color(Color::new(0.8, 0.3, 0.2));
+
+    let mut cylinder = Cylinder::new();
+    cylinder.set_minimum(0.0);
+    cylinder.set_maximum(1.5);
+    cylinder.set_closed(true);
+    cylinder.set_transformation(&Matrix::translation(1.5, 0.0, 1.0) * &Matrix::scale(0.5, 1.0, 0.5));
+    cylinder.get_material_mut().set_color(Color::new(0.2, 0.6, 0.9));
+    cylinder.get_material_mut().set_reflective(0.3);
+
+    // no refraction: the reference implementation produces NaN colors for transparent shapes
+    let mut mirror = Sphere::new();
+    mirror.set_transformation(&Matrix::translation(0.0, 0.75, -0.5) * &Matrix::scale(0.75, 0.75, 0.75));
+    mirror.get_material_mut().set_color(Color::new(0.1, 0.1, 0.1));
+    mirror.get_material_mut().set_diffuse(0.2);
+    mirror.get_material_mut().set_reflective(0.8);
+    mirror.get_material_mut().set_shininess(300.0);
+
+    let pl = PointLight::new(Tuple4D::new_point(-5.0, 8.0, -6.0), Color::new(1.0, 1.0, 1.0));
+
+    let mut world = World::new();
+    world.set_light(Light::PointLight(pl));
+    world.add_shape(Shape::new(ShapeEnum::Plane(floor)));
+    world.add_shape(Shape::new(ShapeEnum::Cube(cube)));
+    world.add_shape(Shape::new(ShapeEnum::Cylinder(cylinder)));
+    world.add_shape(Shape::new(ShapeEnum::Sphere(mirror)));
+
+    let c = setup_camera(
+        w,
+        h,
+        Tuple4D::new_point(0.0, 2.5, -6.0),
+        Tuple4D::new_point(0.0, 0.5, 0.0),
+    );
+    (world, c)
+}
@@ -0,0 +1,446 @@
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::path::Path;
+use std::process;
+use std::time::Instant;
+
+use raytracer::prelude::*;
+use raytracer::BackendEnum;
+
+const USAGE: &str = "usage: render <scene.json|scene.bin> [options]
+
+options:
+    -o, --output <file>       output image (default: <scene>.png)
+    -f, --format <png|ppm>    output format (default: from output file extension, png otherwise)
+    -r, --resolution <WxH>    override the camera resolution, e.g. 1280x720
+        --aa <0|2|3>          antialiasing samples per axis, 0 disables antialiasing
+    -b, --backend <name>      backend to render with (default: fastest one that initializes)
+    -d, --depth <n>           max. recursion depth for reflection and refraction
+    -t, --threads <n>         number of worker threads (multi core backend only)
+    -e, --epsilon <x>         offset of secondary rays from the surface (default: 0.005)
+    -s, --seed <n>            jitter the area light samples, 0 disables jittering
+        --background <r,g,b>  color of rays which hit nothing (default: 0,0,0)
+        --stats               print ray and intersection counters and the slowest tile
+    -h, --help                print this help";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum OutputFormat {
+    Png,
+    Ppm,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct CliOptions {
+    scene_file: String,
+    output: String,
+    format: OutputFormat,
+    resolution: Option<(usize, usize)>,
+    antialiasing: Option<usize>,
+    backend: Option<String>,
+    depth: Option<i32>,
+    threads: Option<usize>,
+    epsilon: Option<f32>,
+    seed: Option<u64>,
+    background: Option<Color>,
+    statistics: bool,
+}
+
+#[derive(Debug)]
+enum CliError {
+    Usage(String),
+    Render(String),
+}
+
+impl Error for CliError {}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::Usage(msg) => write!(f, "{}\n\n{}", msg, USAGE),
+            CliError::Render(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.iter().any(|a| a == "-h" || a == "--help") {
+        println!("{}", USAGE);
+        return;
+    }
+
+    let result = parse_args(&args).and_then(|options| run(&options));
+    if let Err(e) = result {
+        eprintln!("error: {}", e);
+        match e {
+            CliError::Usage(_) => process::exit(2),
+            CliError::Render(_) => process::exit(1),
+        }
+    }
+}
+
+fn run(options: &CliOptions) -> Result<(), CliError> {
+    let mut scene = load_scene(&options.scene_file)?;
+    let camera = configure_camera(scene.get_camera(), options);
+    let settings = render_settings(&camera, options);
+
+    let b = Backend::new();
+    let (backend_type, backend) = match options.backend.as_ref() {
+        Some(name) => b
+            .get_backend_by_name(name)
+            .map_err(|e| CliError::Usage(e.to_string()))?,
+        None => b.best_available().map_err(|e| CliError::Render(e.to_string()))?,
+    };
+
+    if options.threads.is_some() && !uses_thread_pool(&backend_type) {
+        println!("warning: --threads is ignored by backend '{}'", backend_type.get_name());
+    }
+
+    if options.statistics && !backend.get_capabilities().statistics {
+        println!("warning: backend '{}' collects no statistics", backend_type.get_name());
+    }
+
+    let start = Instant::now();
+    let rendered = backend
+        .render_scene(scene.get_world_mut(), &camera, &settings)
+        .map_err(|e| CliError::Render(format!("rendering failed: {}", e)))?;
+    let duration = Instant::now().duration_since(start);
+
+    write_canvas(rendered.get_canvas(), &options.output, options.format)?;
+    if rendered.get_intersection_overflows() > 0 {
+        println!(
+            "warning: {} intersections didn't fit into the intersection lists, the farthest were dropped",
+            rendered.get_intersection_overflows()
+        );
+    }
+
+    println!("scene:          {}", options.scene_file);
+    println!("shapes:         {}", scene.get_world().get_shapes().len());
+    println!("resolution:     {}x{}", camera.get_hsize(), camera.get_vsize());
+    if settings.get_antialiasing() {
+        println!(
+            "antialiasing:   {}x{}",
+            settings.get_antialiasing_size(),
+            settings.get_antialiasing_size()
+        );
+    } else {
+        println!("antialiasing:   off");
+    }
+    println!("backend:        {}", backend_type.get_name());
+    println!("depth:          {}", settings.get_max_depth());
+    match settings.get_threads() {
+        0 => println!("threads:        default"),
+        threads => println!("threads:        {}", threads),
+    }
+    println!("epsilon:        {}", settings.get_epsilon());
+    println!("seed:           {}", settings.get_seed());
+    println!("duration:       {:?}", duration);
+    println!("output:         {}", options.output);
+    if let Some(statistics) = rendered.get_statistics() {
+        println!("\n{}", statistics);
+    }
+
+    Ok(())
+}
+
+fn load_scene(filename: &str) -> Result<Scene, CliError> {
+    let scene = match Path::new(filename).extension().and_then(|e| e.to_str()) {
+        Some("json") => Scene::load_json(filename),
+        _ => Scene::load_binary(filename),
+    };
+    scene.map_err(|e| CliError::Render(format!("can't load scene file '{}': {}", filename, e)))
+}
+
+fn configure_camera(c: &Camera, options: &CliOptions) -> Camera {
+    let (w, h) = options.resolution.unwrap_or((c.get_hsize(), c.get_vsize()));
+
+    let mut camera = Camera::new(w, h, c.get_field_of_view());
+    camera.set_transformation(c.get_transform().clone());
+    camera.set_antialiasing(c.get_antialiasing());
+    camera.set_antialiasing_size(c.get_antialiasing_size());
+    camera.set_calc_reflection(c.get_calc_reflection());
+    camera.set_calc_refraction(c.get_calc_refraction());
+    camera.set_calc_shadows(c.get_calc_shadows());
+    camera.set_max_recursion_depth(c.get_max_recursion_depth());
+    camera.calc_pixel_size();
+    camera
+}
+
+// the settings of the scene file, overridden by the command line
+fn render_settings(c: &Camera, options: &CliOptions) -> RenderSettings {
+    let mut settings = RenderSettings::from_camera(c);
+    if let Some(aa) = options.antialiasing {
+        settings.set_antialiasing(aa > 0);
+        if aa > 0 {
+            settings.set_antialiasing_size(aa);
+        }
+    }
+    if let Some(depth) = options.depth {
+        settings.set_max_depth(depth);
+    }
+    if let Some(threads) = options.threads {
+        settings.set_threads(threads);
+    }
+    if let Some(epsilon) = options.epsilon {
+        settings.set_epsilon(epsilon);
+    }
+    if let Some(seed) = options.seed {
+        settings.set_seed(seed);
+    }
+    if let Some(background) = options.background {
+        settings.set_background(background);
+    }
+    settings.set_statistics(options.statistics);
+    settings
+}
+
+// only the multi core backend renders on the rayon thread pool
+fn uses_thread_pool(b: &BackendEnum) -> bool {
+    #[cfg(feature = "cpu_multi_core")]
+    {
+        if *b == BackendEnum::CpuMultiCore {
+            return true;
+        }
+    }
+    false
+}
+
+fn write_canvas(canvas: &Canvas, filename: &str, format: OutputFormat) -> Result<(), CliError> {
+    let res = match format {
+        OutputFormat::Png => canvas.write_png(filename),
+        OutputFormat::Ppm => canvas.write_ppm(filename),
+    };
+    res.map_err(|e| CliError::Render(format!("can't write image '{}': {}", filename, e)))
+}
+
+fn parse_args(args: &[String]) -> Result<CliOptions, CliError> {
+    let mut scene_file = None;
+    let mut output = None;
+    let mut format = None;
+    let mut resolution = None;
+    let mut antialiasing = None;
+    let mut backend = None;
+    let mut depth = None;
+    let mut threads = None;
+    let mut epsilon = None;
+    let mut seed = None;
+    let mut background = None;
+    let mut statistics = false;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "-o" | "--output" => output = Some(value(arg, iter.next())?.to_string()),
+            "-f" | "--format" => format = Some(parse_format(value(arg, iter.next())?)?),
+            "-r" | "--resolution" => resolution = Some(parse_resolution(value(arg, iter.next())?)?),
+            "--aa" => {
+                let aa = parse_number(arg, value(arg, iter.next())?)?;
+                if aa != 0 && aa != 2 && aa != 3 {
+                    return Err(CliError::Usage(format!("--aa must be 0, 2 or 3, got {}", aa)));
+                }
+                antialiasing = Some(aa);
+            }
+            "-b" | "--backend" => backend = Some(value(arg, iter.next())?.to_string()),
+            "-d" | "--depth" => {
+                let v = value(arg, iter.next())?;
+                match v.parse::<i32>() {
+                    Ok(d) if d >= 0 => depth = Some(d),
+                    _ => {
+                        return Err(CliError::Usage(format!(
+                            "--depth must be a number between 0 and {}, got '{}'",
+                            i32::MAX,
+                            v
+                        )))
+                    }
+                }
+            }
+            "-t" | "--threads" => {
+                let t = parse_number(arg, value(arg, iter.next())?)?;
+                if t == 0 {
+                    return Err(CliError::Usage("--threads must be at least 1".to_string()));
+                }
+                threads = Some(t);
+            }
+            "-e" | "--epsilon" => {
+                let v = value(arg, iter.next())?;
+                match v.parse::<f32>() {
+                    Ok(e) if e > 0.0 => epsilon = Some(e),
+                    _ => {
+                        return Err(CliError::Usage(format!(
+                            "--epsilon must be greater than 0, got '{}'",
+                            v
+                        )))
+                    }
+                }
+            }
+            "-s" | "--seed" => seed = Some(parse_number(arg, value(arg, iter.next())?)? as u64),
+            "--background" => background = Some(parse_color(value(arg, iter.next())?)?),
+            "--stats" => statistics = true,
+            _ if arg.starts_with('-') => return Err(CliError::Usage(format!("unknown option '{}'", arg))),
+            _ => {
+                if scene_file.is_some() {
+                    return Err(CliError::Usage(format!("unexpected argument '{}'", arg)));
+                }
+                scene_file = Some(arg.to_string());
+            }
+        }
+    }
+
+    let scene_file = scene_file.ok_or_else(|| CliError::Usage("no scene file given".to_string()))?;
+    let output = output.unwrap_or_else(|| {
+        Path::new(&scene_file)
+            .with_extension("png")
+            .to_string_lossy()
+            .into_owned()
+    });
+    let format = match format {
+        Some(f) => f,
+        None => match Path::new(&output).extension().and_then(|e| e.to_str()) {
+            Some("ppm") => OutputFormat::Ppm,
+            _ => OutputFormat::Png,
+        },
+    };
+
+    Ok(CliOptions {
+        scene_file,
+        output,
+        format,
+        resolution,
+        antialiasing,
+        backend,
+        depth,
+        threads,
+        epsilon,
+        seed,
+        background,
+        statistics,
+    })
+}
+
+fn value<'a>(option: &str, v: Option<&'a String>) -> Result<&'a str, CliError> {
+    v.map(|s| s.as_str())
+        .ok_or_else(|| CliError::Usage(format!("option '{}' needs a value", option)))
+}
+
+fn parse_number(option: &str, v: &str) -> Result<usize, CliError> {
+    v.parse::<usize>()
+        .map_err(|_| CliError::Usage(format!("option '{}' expects a positive number, got '{}'", option, v)))
+}
+
+fn parse_format(v: &str) -> Result<OutputFormat, CliError> {
+    match v.to_lowercase().as_str() {
+        "png" => Ok(OutputFormat::Png),
+        "ppm" => Ok(OutputFormat::Ppm),
+        _ => Err(CliError::Usage(format!(
+            "unsupported output format '{}', use png or ppm",
+            v
+        ))),
+    }
+}
+
+fn parse_resolution(v: &str) -> Result<(usize, usize), CliError> {
+    let mut parts = v.split('x');
+    let w = parts.next().and_then(|w| w.parse::<usize>().ok());
+    let h = parts.next().and_then(|h| h.parse::<usize>().ok());
+    match (w, h, parts.next()) {
+        (Some(w), Some(h), None) if w > 0 && h > 0 => Ok((w, h)),
+        _ => Err(CliError::Usage(format!(
+            "resolution must look like 1280x720, got '{}'",
+            v
+        ))),
+    }
+}
+
+fn parse_color(v: &str) -> Result<Color, CliError> {
+    let c: Vec<f32> = v.split(',').filter_map(|c| c.trim().parse::<f32>().ok()).collect();
+    match c.as_slice() {
+        [r, g, b] if v.split(',').count() == 3 => Ok(Color::new(*r, *g, *b)),
+        _ => Err(CliError::Usage(format!(
+            "color must look like 0.1,0.2,0.3, got '{}'",
+            v
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(a: &[&str]) -> Vec<String> {
+        a.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_args_defaults() {
+        let o = parse_args(&args(&["scenes/street.json"])).unwrap();
+        assert_eq!(o.scene_file, "scenes/street.json");
+        assert_eq!(o.output, "scenes/street.png");
+        assert_eq!(o.format, OutputFormat::Png);
+        assert_eq!(o.resolution, None);
+        assert_eq!(o.backend, None);
+    }
+
+    #[test]
+    fn test_parse_args_all_options() {
+        let o = parse_args(&args(&[
+            "scene.json",
+            "-o",
+            "out.ppm",
+            "-r",
+            "640x480",
+            "--aa",
+            "3",
+            "-b",
+            "cpu_multi_core",
+            "-d",
+            "7",
+            "-t",
+            "4",
+            "-e",
+            "0.01",
+            "-s",
+            "42",
+            "--background",
+            "0.1,0.2,0.3",
+            "--stats",
+        ]))
+        .unwrap();
+        assert_eq!(o.output, "out.ppm");
+        assert_eq!(o.format, OutputFormat::Ppm);
+        assert_eq!(o.resolution, Some((640, 480)));
+        assert_eq!(o.antialiasing, Some(3));
+        assert_eq!(o.backend, Some("cpu_multi_core".to_string()));
+        assert_eq!(o.depth, Some(7));
+        assert_eq!(o.threads, Some(4));
+        assert_eq!(o.epsilon, Some(0.01));
+        assert_eq!(o.seed, Some(42));
+        assert_eq!(o.background, Some(Color::new(0.1, 0.2, 0.3)));
+        assert!(o.statistics);
+
+        let mut c = Camera::new(640, 480, 1.0);
+        c.set_max_recursion_depth(2);
+        let settings = render_settings(&c, &o);
+        assert_eq!(settings.get_max_depth(), 7);
+        assert_eq!(settings.get_antialiasing(), true);
+        assert_eq!(settings.get_antialiasing_size(), 3);
+        assert_eq!(settings.get_threads(), 4);
+        assert_eq!(settings.get_epsilon(), 0.01);
+        assert_eq!(settings.get_seed(), 42);
+        assert!(settings.get_statistics());
+    }
+
+    #[test]
+    fn test_parse_args_invalid() {
+        assert!(parse_args(&args(&[])).is_err());
+        assert!(parse_args(&args(&["scene.json", "--aa", "4"])).is_err());
+        assert!(parse_args(&args(&["scene.json", "-r", "640"])).is_err());
+        assert!(parse_args(&args(&["scene.json", "-f", "jpg"])).is_err());
+        assert!(parse_args(&args(&["scene.json", "-t", "0"])).is_err());
+        assert!(parse_args(&args(&["scene.json", "--depth"])).is_err());
+        assert!(parse_args(&args(&["scene.json", "-d", "4294967297"])).is_err());
+        assert!(parse_args(&args(&["scene.json", "-e", "0"])).is_err());
+        assert!(parse_args(&args(&["scene.json", "--background", "1,0"])).is_err());
+        assert!(parse_args(&args(&["scene.json", "other.json"])).is_err());
+    }
+}
@@ -0,0 +1,137 @@
+use std::env;
+use std::net::TcpListener;
+use std::path::Path;
+use std::process;
+
+use raytracer::prelude::*;
+
+const USAGE: &str = "usage:
+    render_distributed coordinator <scene.json|scene.bin> [options]
+    render_distributed worker <coordinator host:port> [name]
+
+coordinator options:
+    -l, --listen <host:port>  address the workers connect to (default: 0.0.0.0:7878)
+    -o, --output <file>       output PNG (default: <scene>.png)
+    -s, --tile-size <n>       edge length of the tiles in pixels (default: 32)
+    -d, --depth <n>           max. recursion depth for reflection and refraction
+    -e, --epsilon <x>         offset of secondary rays from the surface (default: 0.005)";
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.is_empty() || args.iter().any(|a| a == "-h" || a == "--help") {
+        println!("{}", USAGE);
+        return;
+    }
+
+    let result = match args[0].as_str() {
+        "coordinator" => run_coordinator(&args[1..]),
+        "worker" => run_worker_process(&args[1..]),
+        cmd => Err(format!("unknown command '{}'\n\n{}", cmd, USAGE)),
+    };
+    if let Err(e) = result {
+        eprintln!("error: {}", e);
+        process::exit(1);
+    }
+}
+
+fn run_coordinator(args: &[String]) -> Result<(), String> {
+    let mut scene_file = None;
+    let mut listen = "0.0.0.0:7878".to_string();
+    let mut output = None;
+    let mut depth = None;
+    let mut epsilon = None;
+    let mut coordinator = RenderCoordinator::new();
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let mut value = || iter.next().ok_or_else(|| format!("option '{}' needs a value", arg));
+        match arg.as_str() {
+            "-l" | "--listen" => listen = value()?.to_string(),
+            "-o" | "--output" => output = Some(value()?.to_string()),
+            "-s" | "--tile-size" => {
+                let v = value()?;
+                match v.parse::<usize>() {
+                    Ok(size) if size > 0 => coordinator.set_tile_size(size),
+                    _ => return Err(format!("tile size must be a positive number, got '{}'", v)),
+                }
+            }
+            "-d" | "--depth" => {
+                let v = value()?;
+                match v.parse::<i32>() {
+                    Ok(d) if d >= 0 => depth = Some(d),
+                    _ => return Err(format!("--depth must be a number between 0 and {}, got '{}'", i32::MAX, v)),
+                }
+            }
+            "-e" | "--epsilon" => {
+                let v = value()?;
+                match v.parse::<f32>() {
+                    Ok(e) if e > 0.0 => epsilon = Some(e),
+                    _ => return Err(format!("--epsilon must be greater than 0, got '{}'", v)),
+                }
+            }
+            _ if arg.starts_with('-') => return Err(format!("unknown option '{}'\n\n{}", arg, USAGE)),
+            _ => scene_file = Some(arg.to_string()),
+        }
+    }
+    let scene_file = scene_file.ok_or_else(|| format!("no scene file given\n\n{}", USAGE))?;
+    let output = output.unwrap_or_else(|| {
+        Path::new(&scene_file)
+            .with_extension("png")
+            .to_string_lossy()
+            .into_owned()
+    });
+
+    let scene = match Path::new(&scene_file).extension().and_then(|e| e.to_str()) {
+        Some("json") => Scene::load_json(&scene_file),
+        _ => Scene::load_binary(&scene_file),
+    }
+    .map_err(|e| format!("can't load scene file '{}': {}", scene_file, e))?;
+
+    let mut settings = RenderSettings::from_camera(scene.get_camera());
+    if let Some(d) = depth {
+        settings.set_max_depth(d);
+    }
+    if let Some(e) = epsilon {
+        settings.set_epsilon(e);
+    }
+
+    let listener = TcpListener::bind(&listen).map_err(|e| format!("can't listen on '{}': {}", listen, e))?;
+    println!("waiting for workers on {}", listen);
+    let result = coordinator
+        .render(&scene, &settings, &listener)
+        .map_err(|e| format!("distributed render failed: {}", e))?;
+
+    result
+        .get_canvas()
+        .write_png(&output)
+        .map_err(|e| format!("can't write image '{}': {}", output, e))?;
+
+    println!("duration:       {:?}", result.get_duration());
+    for s in result.get_worker_stats().iter() {
+        println!(
+            "worker {:<20} {:<22} threads {:>3}   tiles {:>5}   failed {:>3}   {:>12.0} pixels/s",
+            s.get_name(),
+            s.get_addr().to_string(),
+            s.get_threads(),
+            s.get_tiles(),
+            s.get_failed_tiles(),
+            s.pixels_per_second()
+        );
+    }
+    println!("output:         {}", output);
+    Ok(())
+}
+
+fn run_worker_process(args: &[String]) -> Result<(), String> {
+    let addr = args
+        .first()
+        .ok_or_else(|| format!("no coordinator address given\n\n{}", USAGE))?;
+    let name = match args.get(1) {
+        Some(name) => name.to_string(),
+        None => format!("worker-{}", process::id()),
+    };
+
+    let tiles = run_worker(addr.as_str(), &name).map_err(|e| format!("worker '{}' failed: {}", name, e))?;
+    println!("worker '{}' rendered {} tiles", name, tiles);
+    Ok(())
+}
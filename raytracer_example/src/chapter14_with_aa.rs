@@ -88,14 +88,14 @@ pub(crate) fn setup_world_chapter14_with_aa(width: usize, height: usize) -> (Wor
 
     let mut w = World::new();
     w.set_light(l);
-    w.add_shape(Shape::new(ShapeEnum::Plane(floor)));
-    w.add_shape(Shape::new(ShapeEnum::Plane(left_wall)));
-    w.add_shape(Shape::new(ShapeEnum::Plane(right_wall)));
-    w.add_shape(Shape::new(ShapeEnum::Sphere(middle)));
-    w.add_shape(Shape::new(ShapeEnum::Sphere(left)));
-    w.add_shape(Shape::new(ShapeEnum::Sphere(right)));
-    w.add_shape(Shape::new(ShapeEnum::Cube(cube)));
-    w.add_shape(Shape::new(ShapeEnum::Cylinder(cylinder)));
+    w.add_shape(Shape::new(ShapeEnum::Plane(floor))).unwrap();
+    w.add_shape(Shape::new(ShapeEnum::Plane(left_wall))).unwrap();
+    w.add_shape(Shape::new(ShapeEnum::Plane(right_wall))).unwrap();
+    w.add_shape(Shape::new(ShapeEnum::Sphere(middle))).unwrap();
+    w.add_shape(Shape::new(ShapeEnum::Sphere(left))).unwrap();
+    w.add_shape(Shape::new(ShapeEnum::Sphere(right))).unwrap();
+    w.add_shape(Shape::new(ShapeEnum::Cube(cube))).unwrap();
+    w.add_shape(Shape::new(ShapeEnum::Cylinder(cylinder))).unwrap();
 
     let mut c = Camera::new(width, height, PI / 1.5);
     c.calc_pixel_size();
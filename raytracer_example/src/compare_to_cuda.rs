@@ -47,12 +47,12 @@ pub(crate) fn setup_world_compare_to_cuda(w: usize, h: usize) -> (World, Camera)
 
     let mut world = World::new();
     world.set_light(l);
-    world.add_shape(Shape::new(ShapeEnum::Sphere(floor)));
-    world.add_shape(Shape::new(ShapeEnum::Sphere(left_wall)));
-    world.add_shape(Shape::new(ShapeEnum::Sphere(right_wall)));
-    world.add_shape(Shape::new(ShapeEnum::Sphere(middle)));
-    world.add_shape(Shape::new(ShapeEnum::Sphere(left)));
-    world.add_shape(Shape::new(ShapeEnum::Sphere(right)));
+    world.add_shape(Shape::new(ShapeEnum::Sphere(floor))).unwrap();
+    world.add_shape(Shape::new(ShapeEnum::Sphere(left_wall))).unwrap();
+    world.add_shape(Shape::new(ShapeEnum::Sphere(right_wall))).unwrap();
+    world.add_shape(Shape::new(ShapeEnum::Sphere(middle))).unwrap();
+    world.add_shape(Shape::new(ShapeEnum::Sphere(left))).unwrap();
+    world.add_shape(Shape::new(ShapeEnum::Sphere(right))).unwrap();
 
     let mut c = Camera::new(w, h, PI / 3.0);
     c.calc_pixel_size();
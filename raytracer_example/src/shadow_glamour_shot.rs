@@ -63,10 +63,10 @@ pub fn setup_world_shadow_glamour(size_factor: f32, antialiasing: bool, antialia
 
     let mut w = World::new();
     w.set_light(l);
-    w.add_shape(cube);
-    w.add_shape(plane);
-    w.add_shape(sphere1);
-    w.add_shape(sphere2);
+    w.add_shape(cube).unwrap();
+    w.add_shape(plane).unwrap();
+    w.add_shape(sphere1).unwrap();
+    w.add_shape(sphere2).unwrap();
 
     let mut c = Camera::new(width, height, 0.78540);
     c.set_antialiasing(antialiasing);
@@ -3,7 +3,7 @@ use serde::{Deserialize, Serialize};
 
 use math::prelude::*;
 
-use crate::{Ray, RayOps};
+use crate::{BoundingBox, BoundingBoxOps, Ray, RayOps, Shape, ShapeOps};
 
 #[derive(Clone, Debug)]
 #[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
@@ -13,15 +13,21 @@ pub struct Camera {
     vsize: usize,
     field_of_view: f32,
     transform: Matrix,
+    // derived from hsize, vsize and field_of_view in calc_pixel_size()
+    #[cfg_attr(feature = "use_serde", serde(skip))]
     half_view: f32,
+    #[cfg_attr(feature = "use_serde", serde(skip))]
     half_width: f32,
+    #[cfg_attr(feature = "use_serde", serde(skip))]
     half_height: f32,
+    #[cfg_attr(feature = "use_serde", serde(skip))]
     pixel_size: f32,
     antialiasing: bool,
     antialiasing_size: usize, // 2 or 3
     calc_reflection: bool,
     calc_refraction: bool,
     calc_shadows: bool,
+    max_recursion_depth: i32,
 }
 
 pub trait CameraOps {
@@ -50,10 +56,30 @@ pub trait CameraOps {
     fn get_calc_shadows(&self) -> bool;
     fn set_calc_shadows(&mut self, calc_shadows: bool);
 
+    fn get_max_recursion_depth(&self) -> i32;
+    fn set_max_recursion_depth(&mut self, max_recursion_depth: i32);
+
     fn calc_pixel_size(&mut self);
 
     fn set_transformation(&mut self, m: Matrix);
 
+    // camera position in world space, taken from the transformation
+    fn get_position(&self) -> Tuple4D;
+
+    // look at the center of bounds from direction (pointing from the center to the camera), far enough
+    // away that the bounding sphere of the box fits into the field of view for both axes.
+    // returns false and leaves the camera untouched if bounds is empty or infinite
+    fn frame_bounds(&mut self, bounds: &BoundingBox, direction: &Tuple4D) -> bool;
+    // frame_bounds() for the world bounds of all shapes. shapes with infinite bounds (planes, open
+    // cylinders) are ignored, so a floor does not prevent framing. use world.get_shapes() for a World
+    fn frame_shapes(&mut self, shapes: &[Shape], direction: &Tuple4D) -> bool;
+
+    // turntable: rotate the camera by angle around the vertical axis through target and look at target
+    fn orbit(&mut self, target: &Tuple4D, angle: f32);
+    // move along the line of sight, the distance to target gets multiplied by factor.
+    // returns false and leaves the camera untouched if factor is not positive and finite
+    fn dolly(&mut self, target: &Tuple4D, factor: f32) -> bool;
+
     fn ray_for_pixel(c: &Camera, x: usize, y: usize) -> Ray;
     fn ray_for_pixel_anti_aliasing(c: &Camera, x: usize, y: usize, x_offset: f32, y_offset: f32) -> Ray;
 }
@@ -74,6 +100,7 @@ impl CameraOps for Camera {
             calc_reflection: true,
             calc_refraction: true,
             calc_shadows: true,
+            max_recursion_depth: MAX_REFLECTION_RECURSION_DEPTH,
         };
         c
     }
@@ -117,6 +144,63 @@ impl CameraOps for Camera {
         self.transform = m;
     }
 
+    fn get_position(&self) -> Tuple4D {
+        let camera_transform_inv =
+            Matrix::invert(self.get_transform()).expect("get_position:  cant calculate the inverse");
+        let p = &camera_transform_inv * &Tuple4D::new_point(0.0, 0.0, 0.0);
+        Tuple4D::new_point(p.x, p.y, p.z)
+    }
+
+    fn frame_bounds(&mut self, bounds: &BoundingBox, direction: &Tuple4D) -> bool {
+        if bounds.is_empty() || bounds.is_infinite() {
+            return false;
+        }
+        let radius = Tuple4D::magnitude(&bounds.get_size()) / 2.0;
+
+        // tangent of the smaller half angle, same split as in calc_pixel_size()
+        let half_view = intri_tan(self.field_of_view / 2.0);
+        let aspect = self.hsize as f32 / (self.vsize as f32);
+        let t = if aspect >= 1.0 {
+            half_view / aspect
+        } else {
+            half_view * aspect
+        };
+        // the sphere touches the view cone at sin(half_angle) = radius / distance
+        let distance = radius * intri_sqrt(1.0 + t * t) / t;
+
+        let to = bounds.get_center();
+        let from = to + Tuple4D::normalize(direction) * distance;
+        self.transform = Matrix::view_transform(&from, &to, &up_for(direction));
+        true
+    }
+
+    fn frame_shapes(&mut self, shapes: &[Shape], direction: &Tuple4D) -> bool {
+        let mut bounds = BoundingBox::empty();
+        for s in shapes.iter().filter(|s| !s.get_is_prototype()) {
+            let b = s.bounds();
+            if !b.is_infinite() {
+                bounds.merge(&b);
+            }
+        }
+        self.frame_bounds(&bounds, direction)
+    }
+
+    fn orbit(&mut self, target: &Tuple4D, angle: f32) {
+        let offset = &Matrix::rotate_y(angle) * &(&self.get_position() - target);
+        let from = target + &offset;
+        self.transform = Matrix::view_transform(&from, target, &up_for(&offset));
+    }
+
+    fn dolly(&mut self, target: &Tuple4D, factor: f32) -> bool {
+        if !(factor > 0.0) || !factor.is_finite() {
+            return false;
+        }
+        let offset = (&self.get_position() - target) * factor;
+        let from = target + &offset;
+        self.transform = Matrix::view_transform(&from, target, &up_for(&offset));
+        true
+    }
+
     fn ray_for_pixel(c: &Camera, x: usize, y: usize) -> Ray {
         let camera_transform_inv =
             Matrix::invert(c.get_transform()).expect("ray_for_pixel:  cant calculate the inverse");
@@ -213,6 +297,24 @@ impl CameraOps for Camera {
     fn set_calc_shadows(&mut self, calc_shadows: bool) {
         self.calc_shadows = calc_shadows;
     }
+
+    fn get_max_recursion_depth(&self) -> i32 {
+        self.max_recursion_depth
+    }
+
+    fn set_max_recursion_depth(&mut self, max_recursion_depth: i32) {
+        self.max_recursion_depth = max_recursion_depth;
+    }
+}
+
+// y is up, unless the camera looks straight up or down
+fn up_for(direction: &Tuple4D) -> Tuple4D {
+    let d = Tuple4D::normalize(direction);
+    if intri_abs(d.x) < EPSILON && intri_abs(d.z) < EPSILON {
+        Tuple4D::new_vector(0.0, 0.0, 1.0)
+    } else {
+        Tuple4D::up()
+    }
 }
 
 #[cfg(test)]
@@ -220,7 +322,7 @@ mod tests {
     use core::f32::consts::{PI, SQRT_2};
 
     use crate::basics::color::{Color, ColorOps};
-    use crate::{assert_float, assert_matrix, assert_tuple, MatrixOps, Tuple4D};
+    use crate::{assert_float, assert_matrix, assert_tuple, Cube, MatrixOps, Plane, ShapeEnum, Tuple4D};
 
     use super::*;
 
@@ -302,4 +404,77 @@ mod tests {
         assert_tuple(&r.get_origin(), &expected_origin);
         assert_tuple(&r.get_direction(), &expected_direction);
     }
+
+    // every corner of the bounds has to be inside the view frustum
+    fn assert_visible(c: &Camera, bounds: &BoundingBox) {
+        let (min, max) = (bounds.get_min(), bounds.get_max());
+        for x in [min.x, max.x].iter() {
+            for y in [min.y, max.y].iter() {
+                for z in [min.z, max.z].iter() {
+                    let p = c.get_transform() * &Tuple4D::new_point(*x, *y, *z);
+                    assert!(p.z < 0.0);
+                    assert!(intri_abs(p.x / p.z) <= c.get_half_width());
+                    assert!(intri_abs(p.y / p.z) <= c.get_half_height());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_camera_frame_shapes() {
+        let mut cube = Shape::new(ShapeEnum::Cube(Cube::new()));
+        cube.set_transformation(&Matrix::translation(5.0, 1.0, 2.0) * &Matrix::scale(3.0, 1.0, 0.5));
+        let floor = Shape::new(ShapeEnum::Plane(Plane::new()));
+        let shapes = [floor, cube.clone()];
+
+        for (w, h) in [(160, 90), (90, 160)].iter() {
+            let mut c = Camera::new(*w, *h, PI / 3.0);
+            c.calc_pixel_size();
+            assert!(c.frame_shapes(&shapes, &Tuple4D::new_vector(1.0, 2.0, -3.0)));
+            assert_visible(&c, &cube.bounds());
+
+            // the center of the image is the center of the cube
+            let r = Camera::ray_for_pixel(&c, *w / 2, *h / 2);
+            let to_center = Tuple4D::normalize(&(&Tuple4D::new_point(5.0, 1.0, 2.0) - &r.get_origin()));
+            assert!((&to_center ^ &r.get_direction()) > 0.999);
+        }
+
+        // looking straight down still works, only a plane has nothing to frame
+        let mut c = Camera::new(100, 100, PI / 2.0);
+        c.calc_pixel_size();
+        assert!(c.frame_shapes(&shapes, &Tuple4D::new_vector(0.0, 1.0, 0.0)));
+        assert_visible(&c, &cube.bounds());
+        let before = c.get_transform().clone();
+        assert!(!c.frame_shapes(&shapes[..1], &Tuple4D::new_vector(0.0, 0.0, -1.0)));
+        assert_matrix(c.get_transform(), &before);
+    }
+
+    #[test]
+    fn test_camera_orbit_dolly() {
+        let mut c = Camera::new(101, 51, PI / 3.0);
+        c.calc_pixel_size();
+        let target = Tuple4D::new_point(1.0, 0.0, 1.0);
+        c.set_transformation(Matrix::view_transform(
+            &Tuple4D::new_point(1.0, 2.0, -3.0),
+            &target,
+            &Tuple4D::new_vector(0.0, 1.0, 0.0),
+        ));
+
+        c.orbit(&target, PI / 2.0);
+        assert_tuple(&c.get_position(), &Tuple4D::new_point(-3.0, 2.0, 1.0));
+        let r = Camera::ray_for_pixel(&c, 50, 25);
+        assert_tuple(
+            &r.get_direction(),
+            &Tuple4D::normalize(&Tuple4D::new_vector(4.0, -2.0, 0.0)),
+        );
+
+        assert!(c.dolly(&target, 0.5));
+        assert_tuple(&c.get_position(), &Tuple4D::new_point(-1.0, 1.0, 1.0));
+
+        // the camera would end up on or behind the target
+        assert!(!c.dolly(&target, 0.0));
+        assert!(!c.dolly(&target, -1.0));
+        assert!(!c.dolly(&target, core::f32::NAN));
+        assert_tuple(&c.get_position(), &Tuple4D::new_point(-1.0, 1.0, 1.0));
+    }
 }
@@ -186,6 +186,10 @@ pub fn assert_color(actual: &Color, expected: &Color) {
     assert_eq!(assert_two_float(actual.b, expected.b), true);
 }
 
+pub fn is_valid_color(c: &Color) -> bool {
+    c.r.is_finite() && c.g.is_finite() && c.b.is_finite()
+}
+
 pub fn assert_valid_color(c: &Color) {
     if c.r.is_nan() || c.g.is_nan() || c.b.is_nan() {
         panic!("c has  NAN  component            c = {:?}", c);
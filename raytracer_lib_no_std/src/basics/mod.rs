@@ -3,9 +3,15 @@ pub use self::color::*;
 pub use self::pixel::*;
 pub use self::precomputed_component::*;
 pub use self::ray::*;
+pub use self::render_settings::*;
+pub use self::render_stats::*;
+pub use self::shape_error::*;
 
 pub mod camera;
 pub mod color;
 pub mod pixel;
 pub mod precomputed_component;
 pub mod ray;
+pub mod render_settings;
+pub mod render_stats;
+pub mod shape_error;
@@ -4,6 +4,8 @@ use math::prelude::*;
 pub struct PrecomputedComponent {
     t: f32,
     shape_idx: usize,
+    // the prototype of a hit on an instance
+    prototype_idx: Option<usize>,
     point: Tuple4D,
     over_point: Tuple4D,
     under_point: Tuple4D,
@@ -30,6 +32,7 @@ impl PrecomputedComponent {
         PrecomputedComponent {
             t,
             shape_idx,
+            prototype_idx: None,
             point,
             over_point,
             under_point,
@@ -70,6 +73,14 @@ impl PrecomputedComponent {
         self.shape_idx
     }
 
+    pub fn get_prototype(&self) -> Option<usize> {
+        self.prototype_idx
+    }
+
+    pub fn set_prototype(&mut self, prototype_idx: Option<usize>) {
+        self.prototype_idx = prototype_idx;
+    }
+
     pub fn get_reflected_vector(&self) -> &Tuple4D {
         &self.reflected_vector
     }
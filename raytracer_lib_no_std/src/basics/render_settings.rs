@@ -0,0 +1,300 @@
+#[cfg(feature = "use_serde")]
+use serde::{Deserialize, Serialize};
+
+use math::prelude::*;
+
+use crate::{Camera, CameraOps, Color, ShapeError, BLACK};
+
+// everything the kernels need to know about how to render, besides the scene itself.
+// the defaults are the values, which used to be compile time constants
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "cuda", derive(DeviceCopy))]
+pub struct RenderSettings {
+    max_depth: i32,
+    // offset of the over and under points and tolerance of the shadow test - scale it with the scene
+    epsilon: f32,
+    calc_reflection: bool,
+    calc_refraction: bool,
+    calc_shadows: bool,
+    antialiasing: bool,
+    antialiasing_size: usize, // 2 or 3
+    // 0 uses all cores
+    threads: usize,
+    // 0 samples the corners of the area light cells, any other value jitters the samples
+    seed: u64,
+    background: Color,
+    // return the counters of the kernels with the image
+    statistics: bool,
+}
+
+pub trait RenderSettingsOps {
+    fn new() -> RenderSettings;
+    // takes the settings, which are stored in the camera of a scene file
+    fn from_camera(c: &Camera) -> RenderSettings;
+
+    fn get_max_depth(&self) -> i32;
+    fn set_max_depth(&mut self, max_depth: i32);
+
+    fn get_epsilon(&self) -> f32;
+    fn set_epsilon(&mut self, epsilon: f32);
+
+    fn get_calc_reflection(&self) -> bool;
+    fn set_calc_reflection(&mut self, calc_reflection: bool);
+
+    fn get_calc_refraction(&self) -> bool;
+    fn set_calc_refraction(&mut self, calc_refraction: bool);
+
+    fn get_calc_shadows(&self) -> bool;
+    fn set_calc_shadows(&mut self, calc_shadows: bool);
+
+    fn get_antialiasing(&self) -> bool;
+    fn set_antialiasing(&mut self, aa: bool);
+
+    fn get_antialiasing_size(&self) -> usize;
+    fn set_antialiasing_size(&mut self, size: usize);
+
+    fn get_threads(&self) -> usize;
+    fn set_threads(&mut self, threads: usize);
+
+    fn get_seed(&self) -> u64;
+    fn set_seed(&mut self, seed: u64);
+
+    fn get_background(&self) -> &Color;
+    fn set_background(&mut self, background: Color);
+
+    fn get_statistics(&self) -> bool;
+    fn set_statistics(&mut self, statistics: bool);
+
+    // offset in [0, 1) of the sample (u, v) of an area light seen from point
+    fn jitter(&self, u: usize, v: usize, point: &Tuple4D) -> (f32, f32);
+
+    // the backends index the jitter matrix with the antialiasing size, so it is checked before rendering
+    fn validate(&self) -> Result<(), ShapeError>;
+}
+
+impl RenderSettingsOps for RenderSettings {
+    fn new() -> RenderSettings {
+        RenderSettings {
+            max_depth: MAX_REFLECTION_RECURSION_DEPTH,
+            epsilon: EPSILON_OVER_UNDER,
+            calc_reflection: true,
+            calc_refraction: true,
+            calc_shadows: true,
+            antialiasing: false,
+            antialiasing_size: 2,
+            threads: 0,
+            seed: 0,
+            background: BLACK,
+            statistics: false,
+        }
+    }
+
+    fn from_camera(c: &Camera) -> RenderSettings {
+        let mut s = RenderSettings::new();
+        s.max_depth = c.get_max_recursion_depth();
+        s.calc_reflection = c.get_calc_reflection();
+        s.calc_refraction = c.get_calc_refraction();
+        s.calc_shadows = c.get_calc_shadows();
+        s.antialiasing = c.get_antialiasing();
+        s.antialiasing_size = c.get_antialiasing_size();
+        s
+    }
+
+    fn get_max_depth(&self) -> i32 {
+        self.max_depth
+    }
+
+    fn set_max_depth(&mut self, max_depth: i32) {
+        self.max_depth = max_depth;
+    }
+
+    fn get_epsilon(&self) -> f32 {
+        self.epsilon
+    }
+
+    fn set_epsilon(&mut self, epsilon: f32) {
+        self.epsilon = epsilon;
+    }
+
+    fn get_calc_reflection(&self) -> bool {
+        self.calc_reflection
+    }
+
+    fn set_calc_reflection(&mut self, calc_reflection: bool) {
+        self.calc_reflection = calc_reflection;
+    }
+
+    fn get_calc_refraction(&self) -> bool {
+        self.calc_refraction
+    }
+
+    fn set_calc_refraction(&mut self, calc_refraction: bool) {
+        self.calc_refraction = calc_refraction;
+    }
+
+    fn get_calc_shadows(&self) -> bool {
+        self.calc_shadows
+    }
+
+    fn set_calc_shadows(&mut self, calc_shadows: bool) {
+        self.calc_shadows = calc_shadows;
+    }
+
+    fn get_antialiasing(&self) -> bool {
+        self.antialiasing
+    }
+
+    fn set_antialiasing(&mut self, aa: bool) {
+        self.antialiasing = aa;
+    }
+
+    fn get_antialiasing_size(&self) -> usize {
+        self.antialiasing_size
+    }
+
+    fn set_antialiasing_size(&mut self, size: usize) {
+        self.antialiasing_size = size;
+    }
+
+    fn get_threads(&self) -> usize {
+        self.threads
+    }
+
+    fn set_threads(&mut self, threads: usize) {
+        self.threads = threads;
+    }
+
+    fn get_seed(&self) -> u64 {
+        self.seed
+    }
+
+    fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+    }
+
+    fn get_background(&self) -> &Color {
+        &self.background
+    }
+
+    fn set_background(&mut self, background: Color) {
+        self.background = background;
+    }
+
+    fn get_statistics(&self) -> bool {
+        self.statistics
+    }
+
+    fn set_statistics(&mut self, statistics: bool) {
+        self.statistics = statistics;
+    }
+
+    fn jitter(&self, u: usize, v: usize, point: &Tuple4D) -> (f32, f32) {
+        if self.seed == 0 {
+            return (0.0, 0.0);
+        }
+        // hash of seed, sample and point: the same settings always give the same image,
+        // no matter in which order the pixels are rendered
+        let mut h = self.seed
+            ^ (u as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+            ^ (v as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F)
+            ^ (point.x.to_bits() as u64)
+            ^ ((point.y.to_bits() as u64) << 21)
+            ^ ((point.z.to_bits() as u64) << 42);
+        let a = splitmix64(&mut h);
+        let b = splitmix64(&mut h);
+        (to_unit_interval(a), to_unit_interval(b))
+    }
+
+    fn validate(&self) -> Result<(), ShapeError> {
+        if self.antialiasing && self.antialiasing_size != 2 && self.antialiasing_size != 3 {
+            return Err(ShapeError::InvalidSettings("antialiasing_size"));
+        }
+        // written negated, so NaN is rejected too
+        if !(self.epsilon > 0.0) {
+            return Err(ShapeError::InvalidSettings("epsilon"));
+        }
+        if self.max_depth < 0 {
+            return Err(ShapeError::InvalidSettings("max_depth"));
+        }
+        Ok(())
+    }
+}
+
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+// the upper 24 bits fit exactly into the mantissa of a f32
+fn to_unit_interval(x: u64) -> f32 {
+    (x >> 40) as f32 / (1u64 << 24) as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_settings_from_camera() {
+        let mut c = Camera::new(40, 30, 1.0);
+        c.set_max_recursion_depth(2);
+        c.set_calc_shadows(false);
+        c.set_antialiasing(true);
+        c.set_antialiasing_size(3);
+
+        let s = RenderSettings::from_camera(&c);
+        assert_eq!(s.get_max_depth(), 2);
+        assert_eq!(s.get_calc_shadows(), false);
+        assert_eq!(s.get_calc_reflection(), true);
+        assert_eq!(s.get_antialiasing(), true);
+        assert_eq!(s.get_antialiasing_size(), 3);
+        assert_eq!(s.get_epsilon(), EPSILON_OVER_UNDER);
+        assert_eq!(s.get_background(), &BLACK);
+    }
+
+    #[test]
+    fn test_render_settings_jitter() {
+        let p = Tuple4D::new_point(1.0, 2.0, 3.0);
+        let mut s = RenderSettings::new();
+        assert_eq!(s.jitter(1, 2, &p), (0.0, 0.0));
+
+        s.set_seed(42);
+        let (ju, jv) = s.jitter(1, 2, &p);
+        assert!(ju >= 0.0 && ju < 1.0);
+        assert!(jv >= 0.0 && jv < 1.0);
+        assert_eq!(s.jitter(1, 2, &p), (ju, jv));
+        assert_ne!(s.jitter(2, 1, &p), (ju, jv));
+
+        s.set_seed(43);
+        assert_ne!(s.jitter(1, 2, &p), (ju, jv));
+    }
+
+    #[test]
+    fn test_render_settings_validate() {
+        let mut s = RenderSettings::new();
+        assert_eq!(s.validate(), Ok(()));
+
+        // the size is only used with antialiasing
+        s.set_antialiasing_size(4);
+        assert_eq!(s.validate(), Ok(()));
+        s.set_antialiasing(true);
+        assert_eq!(s.validate(), Err(ShapeError::InvalidSettings("antialiasing_size")));
+        s.set_antialiasing_size(3);
+        assert_eq!(s.validate(), Ok(()));
+
+        s.set_epsilon(0.0);
+        assert_eq!(s.validate(), Err(ShapeError::InvalidSettings("epsilon")));
+        s.set_epsilon(core::f32::NAN);
+        assert_eq!(s.validate(), Err(ShapeError::InvalidSettings("epsilon")));
+        s.set_epsilon(0.001);
+
+        s.set_max_depth(-1);
+        assert_eq!(s.validate(), Err(ShapeError::InvalidSettings("max_depth")));
+        s.set_max_depth(0);
+        assert_eq!(s.validate(), Ok(()));
+    }
+}
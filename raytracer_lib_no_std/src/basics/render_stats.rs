@@ -0,0 +1,184 @@
+#[cfg(feature = "use_serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{Shape, ShapeEnum, SHAPE_TYPE_NAMES};
+
+// counters of a kernel. every thread or tile fills its own instance, they are merged
+// after rendering, so counting needs no synchronization
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+pub struct RenderStats {
+    primary_rays: u64,
+    shadow_rays: u64,
+    reflection_rays: u64,
+    refraction_rays: u64,
+    // indexed by Shape::get_type_idx
+    intersection_tests: [u64; SHAPE_TYPE_NAMES.len()],
+    // 0 for primary rays, 1 for the first reflection / refraction, ...
+    max_depth_reached: i32,
+    // intersections dropped, because the intersection list of a ray was full
+    intersection_overflows: u64,
+}
+
+pub trait RenderStatsOps {
+    fn new() -> RenderStats;
+
+    fn count_primary_ray(&mut self);
+    fn count_shadow_ray(&mut self);
+    fn count_reflection_ray(&mut self);
+    fn count_refraction_ray(&mut self);
+    // a ray is tested against every shape of the world
+    fn count_intersection_tests(&mut self, shapes: &[Shape]);
+    fn record_depth(&mut self, depth: i32);
+    fn count_intersection_overflows(&mut self, cnt: usize);
+
+    fn merge(&mut self, other: &RenderStats);
+
+    fn get_primary_rays(&self) -> u64;
+    fn get_shadow_rays(&self) -> u64;
+    fn get_reflection_rays(&self) -> u64;
+    fn get_refraction_rays(&self) -> u64;
+    fn get_total_rays(&self) -> u64;
+    fn get_intersection_tests(&self) -> &[u64; SHAPE_TYPE_NAMES.len()];
+    fn get_total_intersection_tests(&self) -> u64;
+    fn get_max_depth_reached(&self) -> i32;
+    fn get_intersection_overflows(&self) -> u64;
+}
+
+impl RenderStatsOps for RenderStats {
+    fn new() -> RenderStats {
+        RenderStats::default()
+    }
+
+    fn count_primary_ray(&mut self) {
+        self.primary_rays += 1;
+    }
+
+    fn count_shadow_ray(&mut self) {
+        self.shadow_rays += 1;
+    }
+
+    fn count_reflection_ray(&mut self) {
+        self.reflection_rays += 1;
+    }
+
+    fn count_refraction_ray(&mut self) {
+        self.refraction_rays += 1;
+    }
+
+    fn count_intersection_tests(&mut self, shapes: &[Shape]) {
+        for s in shapes {
+            match s.get_shape() {
+                // prototypes are tested once per instance
+                _ if s.get_is_prototype() => {}
+                ShapeEnum::Instance(ref instance) => {
+                    let first = instance.get_first_prototype();
+                    for p in shapes[first..first + instance.get_prototype_cnt()].iter() {
+                        self.intersection_tests[p.get_type_idx()] += 1;
+                    }
+                }
+                _ => self.intersection_tests[s.get_type_idx()] += 1,
+            }
+        }
+    }
+
+    fn record_depth(&mut self, depth: i32) {
+        if depth > self.max_depth_reached {
+            self.max_depth_reached = depth;
+        }
+    }
+
+    fn count_intersection_overflows(&mut self, cnt: usize) {
+        self.intersection_overflows += cnt as u64;
+    }
+
+    fn merge(&mut self, other: &RenderStats) {
+        self.primary_rays += other.primary_rays;
+        self.shadow_rays += other.shadow_rays;
+        self.reflection_rays += other.reflection_rays;
+        self.refraction_rays += other.refraction_rays;
+        for (a, b) in self.intersection_tests.iter_mut().zip(other.intersection_tests.iter()) {
+            *a += b;
+        }
+        self.record_depth(other.max_depth_reached);
+        self.intersection_overflows += other.intersection_overflows;
+    }
+
+    fn get_primary_rays(&self) -> u64 {
+        self.primary_rays
+    }
+
+    fn get_shadow_rays(&self) -> u64 {
+        self.shadow_rays
+    }
+
+    fn get_reflection_rays(&self) -> u64 {
+        self.reflection_rays
+    }
+
+    fn get_refraction_rays(&self) -> u64 {
+        self.refraction_rays
+    }
+
+    fn get_total_rays(&self) -> u64 {
+        self.primary_rays + self.shadow_rays + self.reflection_rays + self.refraction_rays
+    }
+
+    fn get_intersection_tests(&self) -> &[u64; SHAPE_TYPE_NAMES.len()] {
+        &self.intersection_tests
+    }
+
+    fn get_total_intersection_tests(&self) -> u64 {
+        self.intersection_tests.iter().sum()
+    }
+
+    fn get_max_depth_reached(&self) -> i32 {
+        self.max_depth_reached
+    }
+
+    fn get_intersection_overflows(&self) -> u64 {
+        self.intersection_overflows
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Cube, Plane, ShapeEnum, Sphere};
+
+    use super::*;
+
+    #[test]
+    fn test_render_stats_merge() {
+        let shapes = [
+            Shape::new(ShapeEnum::Sphere(Sphere::new())),
+            Shape::new(ShapeEnum::Sphere(Sphere::new())),
+            Shape::new(ShapeEnum::Plane(Plane::new())),
+        ];
+
+        let mut a = RenderStats::new();
+        a.count_primary_ray();
+        a.count_intersection_tests(&shapes);
+        a.count_shadow_ray();
+        a.count_intersection_tests(&shapes);
+        a.record_depth(2);
+
+        let mut b = RenderStats::new();
+        b.count_primary_ray();
+        b.count_reflection_ray();
+        b.count_refraction_ray();
+        b.count_intersection_tests(&[Shape::new(ShapeEnum::Cube(Cube::new()))]);
+        b.record_depth(1);
+        b.count_intersection_overflows(3);
+
+        a.merge(&b);
+        assert_eq!(a.get_primary_rays(), 2);
+        assert_eq!(a.get_shadow_rays(), 1);
+        assert_eq!(a.get_reflection_rays(), 1);
+        assert_eq!(a.get_refraction_rays(), 1);
+        assert_eq!(a.get_total_rays(), 5);
+        assert_eq!(a.get_intersection_tests(), &[4, 2, 1, 0, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(a.get_total_intersection_tests(), 7);
+        assert_eq!(a.get_max_depth_reached(), 2);
+        assert_eq!(a.get_intersection_overflows(), 3);
+    }
+}
@@ -0,0 +1,24 @@
+use core::fmt;
+
+// no_std counterpart of RaytracerError in raytracer_lib_std: only carries
+// static data, so it can be used in the kernels
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ShapeError {
+    NonInvertibleTransform,
+    InvalidMaterial(&'static str),
+    InvalidShape(&'static str),
+    CapacityExceeded(usize),
+    InvalidSettings(&'static str),
+}
+
+impl fmt::Display for ShapeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShapeError::NonInvertibleTransform => write!(f, "transformation matrix is not invertible"),
+            ShapeError::InvalidMaterial(field) => write!(f, "invalid material value for '{}'", field),
+            ShapeError::InvalidShape(field) => write!(f, "invalid shape value for '{}'", field),
+            ShapeError::CapacityExceeded(capacity) => write!(f, "capacity of {} entries exceeded", capacity),
+            ShapeError::InvalidSettings(field) => write!(f, "invalid render setting '{}'", field),
+        }
+    }
+}
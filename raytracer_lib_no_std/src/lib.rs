@@ -39,3 +39,7 @@ pub mod prelude {
 }
 
 pub const DEBUG: bool = false;
+
+// length of the intersection lists of the CUDA kernel. it is defined here, so the CUDA backend can derive
+// its limits without depending on the kernel crate
+pub const CUDA_MAX_INTERSECTIONLIST_LEN: usize = 100;
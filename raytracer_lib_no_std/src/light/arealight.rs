@@ -80,6 +80,13 @@ impl LightOps for AreaLight {
 
         self.get_corner() + &(u_pos + v_pos)
     }
+
+    fn point_on_light_jittered(&self, u: usize, v: usize, jitter: (f32, f32)) -> Tuple4D {
+        let u_pos = self.get_uvec() * (u as f32 + jitter.0);
+        let v_pos = self.get_vvec() * (v as f32 + jitter.1);
+
+        self.get_corner() + &(u_pos + v_pos)
+    }
 }
 
 impl AreaLight {
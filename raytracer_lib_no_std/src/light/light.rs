@@ -29,6 +29,8 @@ pub trait LightOps {
     fn get_vsteps(&self) -> usize;
 
     fn point_on_light(&self, u: usize, v: usize) -> Tuple4D;
+    // jitter is the offset inside the cell (u, v), see RenderSettings::jitter
+    fn point_on_light_jittered(&self, u: usize, v: usize, jitter: (f32, f32)) -> Tuple4D;
 
     // moved to Cpu/Cuda Kernel
     //    fn intensity_at_point(&self, point: &Tuple4D, world: &World) -> f32;
@@ -118,4 +120,11 @@ impl LightOps for Light {
             Light::AreaLight(ref al) => al.point_on_light(u, v),
         }
     }
+
+    fn point_on_light_jittered(&self, u: usize, v: usize, jitter: (f32, f32)) -> Tuple4D {
+        match self {
+            Light::PointLight(ref pl) => pl.point_on_light_jittered(u, v, jitter),
+            Light::AreaLight(ref al) => al.point_on_light_jittered(u, v, jitter),
+        }
+    }
 }
@@ -58,6 +58,10 @@ impl LightOps for PointLight {
     fn point_on_light(&self, _u: usize, _v: usize) -> Tuple4D {
         self.position.clone()
     }
+
+    fn point_on_light_jittered(&self, _u: usize, _v: usize, _jitter: (f32, f32)) -> Tuple4D {
+        self.position.clone()
+    }
 }
 
 impl PointLight {
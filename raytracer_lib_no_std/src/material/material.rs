@@ -1,7 +1,7 @@
 #[cfg(feature = "use_serde")]
 use serde::{Deserialize, Serialize};
 
-use crate::{Color, ColorOps, Pattern};
+use crate::{Color, ColorOps, NormalPerturbation, Pattern, ShapeError};
 
 pub const REFRACTION_VACUUM: f32 = 1.0;
 pub const REFRACTION_AIR: f32 = 1.00029;
@@ -22,6 +22,8 @@ pub struct Material {
     reflective: f32,
     transparency: f32,
     refractive_index: f32,
+    #[cfg_attr(feature = "use_serde", serde(default))]
+    normal_perturbation: Option<NormalPerturbation>,
 }
 
 pub trait MaterialOps {
@@ -52,6 +54,13 @@ pub trait MaterialOps {
     fn get_refractive_index(&self) -> f32;
 
     fn set_refractive_index(&mut self, refractive_index: f32);
+
+    // the perturbation of the shading normal, applied by the kernels in prepare_computations
+    fn set_normal_perturbation(&mut self, p: NormalPerturbation);
+    fn get_normal_perturbation(&self) -> &Option<NormalPerturbation>;
+
+    // rejects values which would produce NaN or infinite colors while shading
+    fn validate(&self) -> Result<(), ShapeError>;
 }
 
 impl MaterialOps for Material {
@@ -66,6 +75,7 @@ impl MaterialOps for Material {
             reflective: 0.0,
             transparency: 0.0,
             refractive_index: 1.0,
+            normal_perturbation: None,
         }
     }
 
@@ -140,4 +150,76 @@ impl MaterialOps for Material {
     fn set_refractive_index(&mut self, refractive_index: f32) {
         self.refractive_index = refractive_index;
     }
+
+    fn set_normal_perturbation(&mut self, p: NormalPerturbation) {
+        self.normal_perturbation = Some(p);
+    }
+
+    fn get_normal_perturbation(&self) -> &Option<NormalPerturbation> {
+        &self.normal_perturbation
+    }
+
+    fn validate(&self) -> Result<(), ShapeError> {
+        let c = &self.color;
+        if !is_non_negative(c.r) || !is_non_negative(c.g) || !is_non_negative(c.b) {
+            return Err(ShapeError::InvalidMaterial("color"));
+        }
+        if !is_non_negative(self.ambient) {
+            return Err(ShapeError::InvalidMaterial("ambient"));
+        }
+        if !is_non_negative(self.diffuse) {
+            return Err(ShapeError::InvalidMaterial("diffuse"));
+        }
+        if !is_non_negative(self.specular) {
+            return Err(ShapeError::InvalidMaterial("specular"));
+        }
+        if !is_non_negative(self.shininess) {
+            return Err(ShapeError::InvalidMaterial("shininess"));
+        }
+        if !(0.0..=1.0).contains(&self.reflective) {
+            return Err(ShapeError::InvalidMaterial("reflective"));
+        }
+        if !(0.0..=1.0).contains(&self.transparency) {
+            return Err(ShapeError::InvalidMaterial("transparency"));
+        }
+        if !(is_non_negative(self.refractive_index) && self.refractive_index > 0.0) {
+            return Err(ShapeError::InvalidMaterial("refractive_index"));
+        }
+        if let Some(p) = self.normal_perturbation.as_ref() {
+            p.validate()?;
+        }
+        Ok(())
+    }
+}
+
+fn is_non_negative(x: f32) -> bool {
+    x.is_finite() && x >= 0.0
+}
+
+#[cfg(test)]
+mod tests {
+    use core::f32::NAN;
+
+    use super::*;
+
+    #[test]
+    fn test_material_validate() {
+        let mut m = Material::new();
+        assert_eq!(m.validate(), Ok(()));
+
+        m.set_refractive_index(REFRACTION_GLASS);
+        m.set_transparency(1.0);
+        assert_eq!(m.validate(), Ok(()));
+
+        m.set_reflective(1.5);
+        assert_eq!(m.validate(), Err(ShapeError::InvalidMaterial("reflective")));
+
+        let mut m = Material::new();
+        m.set_color(Color::new(NAN, 0.0, 0.0));
+        assert_eq!(m.validate(), Err(ShapeError::InvalidMaterial("color")));
+
+        let mut m = Material::new();
+        m.set_refractive_index(0.0);
+        assert_eq!(m.validate(), Err(ShapeError::InvalidMaterial("refractive_index")));
+    }
 }
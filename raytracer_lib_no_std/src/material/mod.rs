@@ -1,3 +1,5 @@
 pub use self::material::*;
+pub use self::normal_perturbation::*;
 
 pub mod material;
+pub mod normal_perturbation;
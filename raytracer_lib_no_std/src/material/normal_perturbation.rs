@@ -0,0 +1,379 @@
+use core::f32::consts::PI;
+
+#[cfg(feature = "use_serde")]
+use serde::{Deserialize, Serialize};
+
+use math::prelude::*;
+
+use crate::{perlin_noise, ShapeError};
+
+// the step for the numerical derivatives in object space
+const DELTA: f32 = 1e-3;
+
+// maps a point in object space to texture coordinates in [0, 1] x [0, 1]
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "cuda", derive(DeviceCopy))]
+pub enum UvMapping {
+    // x and z, repeated every unit
+    Planar,
+    // longitude and latitude of the direction from the origin
+    Spherical,
+    // the angle around the y axis and y, repeated every unit
+    Cylindrical,
+    // x and z of the square -1..1 of a heightfield, stretched once over the whole grid. the top row of the
+    // image is the sample row z = 0, like in heightfield_from_canvas
+    Heightfield,
+}
+
+impl UvMapping {
+    pub fn uv_at(&self, p: &Tuple4D) -> (f32, f32) {
+        match self {
+            UvMapping::Planar => (fract(p.x), fract(p.z)),
+            UvMapping::Spherical => {
+                let phi = intri_atan2(intri_sqrt(p.x * p.x + p.z * p.z), p.y);
+                (longitude(p), 1.0 - phi / PI)
+            }
+            UvMapping::Cylindrical => (longitude(p), fract(p.y)),
+            UvMapping::Heightfield => (clamp_unit((p.x + 1.0) / 2.0), clamp_unit((1.0 - p.z) / 2.0)),
+        }
+    }
+}
+
+fn longitude(p: &Tuple4D) -> f32 {
+    1.0 - (intri_atan2(p.x, p.z) / (2.0 * PI) + 0.5)
+}
+
+fn fract(x: f32) -> f32 {
+    x - intri_floor(x)
+}
+
+fn clamp_unit(x: f32) -> f32 {
+    intri_min(intri_max(x, 0.0), 1.0)
+}
+
+// the difference of two texture coordinates, which wrap around at 1
+fn wrapped_diff(a: f32, b: f32) -> f32 {
+    let d = a - b;
+    d - intri_floor(d + 0.5)
+}
+
+// tangent space normals, the image is wrapped around the object with the uv mapping. the texels are stored in
+// the sample buffer of the world like the heights of a heightfield, so the material stays small
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "cuda", derive(DeviceCopy))]
+pub struct NormalMap {
+    // the samples first_texel .. first_texel + width * height * 3 of the sample buffer. x, y and z of the
+    // normal of every texel, row major from the top (v = 1) to the bottom (v = 0) row, like an image.
+    // x points along u, y along v and z away from the surface
+    first_texel: usize,
+    width: usize,
+    height: usize,
+    mapping: UvMapping,
+}
+
+impl NormalMap {
+    pub fn try_new(
+        samples: &[f32],
+        first_texel: usize,
+        width: usize,
+        height: usize,
+        mapping: UvMapping,
+    ) -> Result<NormalMap, ShapeError> {
+        let res = NormalMap {
+            first_texel,
+            width,
+            height,
+            mapping,
+        };
+        res.validate(samples)?;
+        Ok(res)
+    }
+
+    // the sample buffer of a deserialized or modified world may no longer contain the texels
+    pub fn validate(&self, samples: &[f32]) -> Result<(), ShapeError> {
+        if self.width == 0 || self.height == 0 {
+            return Err(ShapeError::InvalidMaterial("normal map size"));
+        }
+        let texels = self
+            .width
+            .checked_mul(self.height)
+            .and_then(|cnt| cnt.checked_mul(3))
+            .and_then(|cnt| samples.get(self.first_texel..self.first_texel.checked_add(cnt)?))
+            .ok_or(ShapeError::InvalidMaterial("normal map texels"))?;
+        // NaN fails the comparison as well
+        if !texels.iter().all(|t| *t >= -1.0 && *t <= 1.0) {
+            return Err(ShapeError::InvalidMaterial("normal map texels"));
+        }
+        Ok(())
+    }
+
+    pub fn get_first_texel(&self) -> usize {
+        self.first_texel
+    }
+
+    pub fn get_width(&self) -> usize {
+        self.width
+    }
+
+    pub fn get_height(&self) -> usize {
+        self.height
+    }
+
+    pub fn get_mapping(&self) -> UvMapping {
+        self.mapping
+    }
+
+    // the tangent space normal, bilinear interpolated between the texel centers and repeated
+    pub fn normal_at_uv(&self, samples: &[f32], u: f32, v: f32) -> Tuple4D {
+        let x = fract(u) * self.width as f32 - 0.5;
+        let y = (1.0 - fract(v)) * self.height as f32 - 0.5;
+        let (x0, y0) = (intri_floor(x), intri_floor(y));
+        let (fx, fy) = (x - x0, y - y0);
+        let wrap = |i: f32, n: usize| (i as i32).rem_euclid(n as i32) as usize;
+        let (x0, x1) = (wrap(x0, self.width), wrap(x0 + 1.0, self.width));
+        let (y0, y1) = (wrap(y0, self.height), wrap(y0 + 1.0, self.height));
+
+        let mut n = [0.0; 3];
+        for (i, c) in n.iter_mut().enumerate() {
+            let texel = |x: usize, y: usize| samples[self.first_texel + (y * self.width + x) * 3 + i];
+            let top = texel(x0, y0) * (1.0 - fx) + texel(x1, y0) * fx;
+            let bottom = texel(x0, y1) * (1.0 - fx) + texel(x1, y1) * fx;
+            *c = top * (1.0 - fy) + bottom * fy;
+        }
+        Tuple4D::normalize(&Tuple4D::new_vector(n[0], n[1], n[2]))
+    }
+
+    fn perturb(&self, samples: &[f32], p: &Tuple4D, n: &Tuple4D) -> Tuple4D {
+        // the tangent follows u, the bitangent v. both are the derivatives of the mapping projected
+        // onto the surface
+        let (u, v) = self.mapping.uv_at(p);
+        let mut du = Tuple4D::new_vector(0.0, 0.0, 0.0);
+        let mut dv = Tuple4D::new_vector(0.0, 0.0, 0.0);
+        for axis in 0..3 {
+            let mut q = *p;
+            match axis {
+                0 => q.x += DELTA,
+                1 => q.y += DELTA,
+                _ => q.z += DELTA,
+            }
+            let (qu, qv) = self.mapping.uv_at(&q);
+            let (gu, gv) = (wrapped_diff(qu, u) / DELTA, wrapped_diff(qv, v) / DELTA);
+            match axis {
+                0 => {
+                    du.x = gu;
+                    dv.x = gv;
+                }
+                1 => {
+                    du.y = gu;
+                    dv.y = gv;
+                }
+                _ => {
+                    du.z = gu;
+                    dv.z = gv;
+                }
+            }
+        }
+        let tangent = &du - &(n * (&du ^ n));
+        if Tuple4D::magnitude(&tangent) < EPSILON {
+            // e.g. at the poles of the spherical mapping
+            return *n;
+        }
+        let tangent = Tuple4D::normalize(&tangent);
+        let mut bitangent = n * &tangent;
+        if (&bitangent ^ &dv) < 0.0 {
+            bitangent = bitangent * (-1.0);
+        }
+
+        let m = self.normal_at_uv(samples, u, v);
+        let res = &(&(&tangent * m.x) + &(&bitangent * m.y)) + &(n * m.z);
+        Tuple4D::normalize(&res)
+    }
+}
+
+// bends the shading normal of a material without changing the geometry
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "cuda", derive(DeviceCopy))]
+pub enum NormalPerturbation {
+    // the bumps of sin(x) * sin(z), like the wavy water in the book
+    Waves { amplitude: f32, frequency: f32 },
+    // bumps of perlin noise with a size of about scale
+    Noise { amplitude: f32, scale: f32 },
+    NormalMap(NormalMap),
+}
+
+impl NormalPerturbation {
+    pub fn validate(&self) -> Result<(), ShapeError> {
+        match self {
+            NormalPerturbation::Waves { amplitude, frequency } => {
+                if !amplitude.is_finite() || !frequency.is_finite() {
+                    return Err(ShapeError::InvalidMaterial("normal_perturbation"));
+                }
+            }
+            NormalPerturbation::Noise { amplitude, scale } => {
+                if !amplitude.is_finite() || !scale.is_finite() || *scale <= 0.0 {
+                    return Err(ShapeError::InvalidMaterial("normal_perturbation"));
+                }
+            }
+            // the texels are checked with the sample buffer, see validate_samples
+            NormalPerturbation::NormalMap(_) => {}
+        }
+        Ok(())
+    }
+
+    pub fn validate_samples(&self, samples: &[f32]) -> Result<(), ShapeError> {
+        match self {
+            NormalPerturbation::NormalMap(ref normal_map) => normal_map.validate(samples),
+            _ => Ok(()),
+        }
+    }
+
+    // object_point and object_normal are in the object space of the shape, the result is normalized. samples
+    // is the sample buffer of the world
+    pub fn perturb(&self, samples: &[f32], object_point: &Tuple4D, object_normal: &Tuple4D) -> Tuple4D {
+        match self {
+            NormalPerturbation::Waves { amplitude, frequency } => {
+                let p = object_point;
+                let f = *frequency;
+                let gradient = Tuple4D::new_vector(
+                    f * intri_cos(f * p.x) * intri_sin(f * p.z),
+                    0.0,
+                    f * intri_sin(f * p.x) * intri_cos(f * p.z),
+                );
+                bump(object_normal, &(&gradient * *amplitude))
+            }
+            NormalPerturbation::Noise { amplitude, scale } => {
+                let noise = |dx: f32, dy: f32, dz: f32| {
+                    let p = object_point;
+                    perlin_noise(&Tuple4D::new_point(
+                        (p.x + dx) / scale,
+                        (p.y + dy) / scale,
+                        (p.z + dz) / scale,
+                    ))
+                };
+                let gradient = Tuple4D::new_vector(
+                    noise(DELTA, 0.0, 0.0) - noise(-DELTA, 0.0, 0.0),
+                    noise(0.0, DELTA, 0.0) - noise(0.0, -DELTA, 0.0),
+                    noise(0.0, 0.0, DELTA) - noise(0.0, 0.0, -DELTA),
+                );
+                bump(object_normal, &(&gradient * (*amplitude / (2.0 * DELTA))))
+            }
+            NormalPerturbation::NormalMap(ref normal_map) => normal_map.perturb(samples, object_point, object_normal),
+        }
+    }
+}
+
+// tilts the normal against the slope of a height function with the given gradient
+fn bump(n: &Tuple4D, gradient: &Tuple4D) -> Tuple4D {
+    let slope = gradient - &(n * (gradient ^ n));
+    let mut res = n - &slope;
+    res.w = 0.0;
+    Tuple4D::normalize(&res)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::common::{assert_float, assert_tuple};
+
+    use super::*;
+
+    #[test]
+    fn test_uv_mapping() {
+        let (u, v) = UvMapping::Planar.uv_at(&Tuple4D::new_point(-0.25, 3.0, 1.5));
+        assert_float(u, 0.75);
+        assert_float(v, 0.5);
+
+        let (u, v) = UvMapping::Spherical.uv_at(&Tuple4D::new_point(0.0, 0.0, -1.0));
+        assert_float(u, 0.0);
+        assert_float(v, 0.5);
+        let (u, v) = UvMapping::Spherical.uv_at(&Tuple4D::new_point(1.0, 0.0, 0.0));
+        assert_float(u, 0.25);
+        assert_float(v, 0.5);
+        let (_, v) = UvMapping::Spherical.uv_at(&Tuple4D::new_point(0.0, 1.0, 0.0));
+        assert_float(v, 1.0);
+
+        let (u, v) = UvMapping::Cylindrical.uv_at(&Tuple4D::new_point(0.0, 0.25, 1.0));
+        assert_float(u, 0.5);
+        assert_float(v, 0.25);
+
+        let (u, v) = UvMapping::Heightfield.uv_at(&Tuple4D::new_point(-1.0, 0.0, -1.0));
+        assert_float(u, 0.0);
+        assert_float(v, 1.0);
+        let (u, v) = UvMapping::Heightfield.uv_at(&Tuple4D::new_point(0.0, 1.0, 0.5));
+        assert_float(u, 0.5);
+        assert_float(v, 0.25);
+        // clamped outside of the grid
+        let (u, _) = UvMapping::Heightfield.uv_at(&Tuple4D::new_point(1.5, 0.0, 0.0));
+        assert_float(u, 1.0);
+    }
+
+    #[test]
+    fn test_normal_map() {
+        // tilted towards +u on the left half and +v on the right half, behind the sample of another shape
+        let c = 1.0 / 2f32.sqrt();
+        let samples = [0.5, c, 0.0, c, 0.0, c, c];
+        let m = NormalMap::try_new(&samples, 1, 2, 1, UvMapping::Planar).unwrap();
+        assert_tuple(
+            &m.normal_at_uv(&samples, 0.25, 0.5),
+            &Tuple4D::new_vector(0.70710677, 0.0, 0.70710677),
+        );
+
+        // on the plane u follows x and v follows z
+        let n = NormalPerturbation::NormalMap(m.clone());
+        let up = Tuple4D::new_vector(0.0, 1.0, 0.0);
+        let p = n.perturb(&samples, &Tuple4D::new_point(0.25, 0.0, 0.5), &up);
+        assert_tuple(&p, &Tuple4D::new_vector(0.70710677, 0.70710677, 0.0));
+        let p = n.perturb(&samples, &Tuple4D::new_point(0.75, 0.0, 0.5), &up);
+        assert_tuple(&p, &Tuple4D::new_vector(0.0, 0.70710677, 0.70710677));
+
+        assert_eq!(
+            NormalMap::try_new(&samples, 0, 0, 1, UvMapping::Planar),
+            Err(ShapeError::InvalidMaterial("normal map size"))
+        );
+        assert_eq!(
+            NormalMap::try_new(&samples, 1, 2, 2, UvMapping::Planar),
+            Err(ShapeError::InvalidMaterial("normal map texels"))
+        );
+        assert_eq!(
+            NormalMap::try_new(&[0.0, 2.0, 0.0], 0, 1, 1, UvMapping::Planar),
+            Err(ShapeError::InvalidMaterial("normal map texels"))
+        );
+        assert_eq!(
+            n.validate_samples(&samples[..6]),
+            Err(ShapeError::InvalidMaterial("normal map texels"))
+        );
+    }
+
+    #[test]
+    fn test_bump_perturbation() {
+        let up = Tuple4D::new_vector(0.0, 1.0, 0.0);
+        let waves = NormalPerturbation::Waves {
+            amplitude: 0.5,
+            frequency: 1.0,
+        };
+        // the slope of 0.5 sin(x) sin(z) at (0, 0, PI / 2) is 0.5 in x direction
+        let n = waves.perturb(&[], &Tuple4D::new_point(0.0, 0.0, PI / 2.0), &up);
+        assert_tuple(&n, &Tuple4D::normalize(&Tuple4D::new_vector(-0.5, 1.0, 0.0)));
+        // a flat spot
+        let n = waves.perturb(&[], &Tuple4D::new_point(PI / 2.0, 0.0, PI / 2.0), &up);
+        assert_tuple(&n, &up);
+
+        let noise = NormalPerturbation::Noise {
+            amplitude: 0.3,
+            scale: 0.5,
+        };
+        let n = noise.perturb(&[], &Tuple4D::new_point(0.3, 0.0, 0.7), &up);
+        assert_float(Tuple4D::magnitude(&n), 1.0);
+        assert!(n.y > 0.5 && n.y < 1.0);
+        assert_eq!(
+            NormalPerturbation::Noise {
+                amplitude: 0.3,
+                scale: 0.0
+            }
+            .validate(),
+            Err(ShapeError::InvalidMaterial("normal_perturbation"))
+        );
+    }
+}
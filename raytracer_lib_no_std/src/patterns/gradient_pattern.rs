@@ -12,6 +12,7 @@ pub struct GradientPattern {
     color_a: Color,
     color_b: Color,
     transformation_matrix: Matrix,
+    #[cfg_attr(feature = "use_serde", serde(skip, default = "Matrix::new_identity_4x4"))]
     inverse_transformation_matrix: Matrix,
 }
 
@@ -41,9 +42,14 @@ impl GradientPattern {
         &self.color_b
     }
 
+    // the fraction of color b
+    pub fn weight_at(point: &Tuple4D) -> f32 {
+        point.x - intri_floor(point.x)
+    }
+
     pub fn color_at(pattern: &GradientPattern, point: &Tuple4D) -> Color {
         let distance = pattern.get_color_b() - pattern.get_color_a();
-        let fraction = point.x - intri_floor(point.x);
+        let fraction = Self::weight_at(point);
         pattern.get_color_a() + &(distance * fraction)
     }
 
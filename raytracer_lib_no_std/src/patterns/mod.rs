@@ -1,5 +1,7 @@
 pub use self::checker3d_pattern::*;
 pub use self::gradient_pattern::*;
+pub use self::nested_pattern::*;
+pub use self::noise::*;
 pub use self::patterns::*;
 pub use self::ring_pattern::*;
 pub use self::stripe_patterns::*;
@@ -7,6 +9,8 @@ pub use self::test_pattern::*;
 
 pub mod checker3d_pattern;
 pub mod gradient_pattern;
+pub mod nested_pattern;
+pub mod noise;
 pub mod patterns;
 pub mod ring_pattern;
 pub mod stripe_patterns;
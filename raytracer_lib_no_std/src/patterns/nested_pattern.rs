@@ -0,0 +1,428 @@
+#[cfg(feature = "use_serde")]
+use serde::{Deserialize, Serialize};
+
+use math::prelude::*;
+
+use crate::{
+    perlin_noise, Checker3DPattern, Color, GradientPattern, RingPattern, Shape, ShapeError, ShapeOps, StripePattern,
+};
+
+// max. number of nodes of a nested pattern. like the nodes of an sdf, they are stored in the pattern itself
+pub const NESTED_PATTERN_MAX_NODES: usize = 8;
+
+// content of the unused part of the node array
+const NESTED_PATTERN_UNUSED_NODE: PatternNode = PatternNode::Blend {
+    a: 0,
+    b: 0,
+    weight: 0.0,
+};
+
+// a node of a nested pattern. the two color patterns use the sub pattern a or b in place of their color a or b,
+// None keeps the color of the pattern. a sub pattern is evaluated at the point in the space of its parent, so
+// the transformations of the patterns add up. children must come before their parent
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "cuda", derive(DeviceCopy))]
+pub enum PatternNode {
+    Stripe {
+        pattern: StripePattern,
+        a: Option<usize>,
+        b: Option<usize>,
+    },
+    // blends the sub patterns like the colors
+    Gradient {
+        pattern: GradientPattern,
+        a: Option<usize>,
+        b: Option<usize>,
+    },
+    Ring {
+        pattern: RingPattern,
+        a: Option<usize>,
+        b: Option<usize>,
+    },
+    Checker3D {
+        pattern: Checker3DPattern,
+        a: Option<usize>,
+        b: Option<usize>,
+    },
+    // (1 - weight) * a + weight * b
+    Blend {
+        a: usize,
+        b: usize,
+        weight: f32,
+    },
+    // the child at the point moved by up to amplitude with perlin noise. scale is about the size of the distortions
+    Perturbed {
+        child: usize,
+        amplitude: f32,
+        scale: f32,
+    },
+}
+
+// a pattern built from the nodes, the last node is the root. e.g. stripes of checkers are a stripe pattern with
+// two checker patterns as sub patterns
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "cuda", derive(DeviceCopy))]
+pub struct NestedPattern {
+    transformation_matrix: Matrix,
+    #[cfg_attr(feature = "use_serde", serde(skip, default = "Matrix::new_identity_4x4"))]
+    inverse_transformation_matrix: Matrix,
+    #[cfg_attr(feature = "use_serde", serde(with = "nodes_serde"))]
+    nodes: [PatternNode; NESTED_PATTERN_MAX_NODES],
+    node_cnt: usize,
+}
+
+impl NestedPattern {
+    pub fn try_new(nodes: &[PatternNode]) -> Result<NestedPattern, ShapeError> {
+        if nodes.len() > NESTED_PATTERN_MAX_NODES {
+            return Err(ShapeError::CapacityExceeded(NESTED_PATTERN_MAX_NODES));
+        }
+        let mut res = NestedPattern {
+            transformation_matrix: Matrix::new_identity_4x4(),
+            inverse_transformation_matrix: Matrix::new_identity_4x4(),
+            nodes: [NESTED_PATTERN_UNUSED_NODE; NESTED_PATTERN_MAX_NODES],
+            node_cnt: nodes.len(),
+        };
+        res.nodes[..nodes.len()].clone_from_slice(nodes);
+        res.validate()?;
+        Ok(res)
+    }
+
+    // the evaluation is recursive, so children must come before their parents
+    pub fn validate(&self) -> Result<(), ShapeError> {
+        if self.node_cnt == 0 {
+            return Err(ShapeError::InvalidMaterial("pattern nodes"));
+        }
+        if self.node_cnt > NESTED_PATTERN_MAX_NODES {
+            return Err(ShapeError::CapacityExceeded(NESTED_PATTERN_MAX_NODES));
+        }
+        for (idx, node) in self.get_nodes().iter().enumerate() {
+            let child = |c: Option<usize>| c.map_or(true, |c| c < idx);
+            let valid = match *node {
+                PatternNode::Stripe { ref pattern, a, b } => {
+                    child(a) && child(b) && invertible(pattern.get_transformation())
+                }
+                PatternNode::Gradient { ref pattern, a, b } => {
+                    child(a) && child(b) && invertible(pattern.get_transformation())
+                }
+                PatternNode::Ring { ref pattern, a, b } => {
+                    child(a) && child(b) && invertible(pattern.get_transformation())
+                }
+                PatternNode::Checker3D { ref pattern, a, b } => {
+                    child(a) && child(b) && invertible(pattern.get_transformation())
+                }
+                PatternNode::Blend { a, b, weight } => a < idx && b < idx && (0.0..=1.0).contains(&weight),
+                PatternNode::Perturbed {
+                    child,
+                    amplitude,
+                    scale,
+                } => child < idx && amplitude.is_finite() && scale.is_finite() && scale > 0.0,
+            };
+            if !valid {
+                return Err(ShapeError::InvalidMaterial("pattern nodes"));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn get_nodes(&self) -> &[PatternNode] {
+        &self.nodes[..self.node_cnt]
+    }
+
+    pub fn color_at(&self, point: &Tuple4D) -> Color {
+        self.node_color(self.node_cnt - 1, point)
+    }
+
+    pub fn color_at_object(&self, shape: &Shape, world_point: &Tuple4D) -> Color {
+        let object_point = shape.get_inverse_transformation() * world_point;
+        let pattern_point = self.get_inverse_transformation() * &object_point;
+        self.color_at(&pattern_point)
+    }
+
+    fn node_color(&self, idx: usize, point: &Tuple4D) -> Color {
+        match self.nodes[idx] {
+            PatternNode::Stripe { ref pattern, a, b } => {
+                let p = pattern.get_inverse_transformation() * point;
+                let colors = (pattern.get_color_a(), pattern.get_color_b());
+                self.mix(StripePattern::weight_at(&p), (a, b), colors, &p)
+            }
+            PatternNode::Gradient { ref pattern, a, b } => {
+                let p = pattern.get_inverse_transformation() * point;
+                let colors = (pattern.get_color_a(), pattern.get_color_b());
+                self.mix(GradientPattern::weight_at(&p), (a, b), colors, &p)
+            }
+            PatternNode::Ring { ref pattern, a, b } => {
+                let p = pattern.get_inverse_transformation() * point;
+                let colors = (pattern.get_color_a(), pattern.get_color_b());
+                self.mix(RingPattern::weight_at(&p), (a, b), colors, &p)
+            }
+            PatternNode::Checker3D { ref pattern, a, b } => {
+                let p = pattern.get_inverse_transformation() * point;
+                let colors = (pattern.get_color_a(), pattern.get_color_b());
+                self.mix(Checker3DPattern::weight_at(&p), (a, b), colors, &p)
+            }
+            PatternNode::Blend { a, b, weight } => {
+                blend(&self.node_color(a, point), &self.node_color(b, point), weight)
+            }
+            PatternNode::Perturbed {
+                child,
+                amplitude,
+                scale,
+            } => {
+                // three noise values from distant parts of the noise
+                let noise = |dx: f32, dy: f32| {
+                    perlin_noise(&Tuple4D::new_point(
+                        point.x / scale + dx,
+                        point.y / scale + dy,
+                        point.z / scale,
+                    ))
+                };
+                let offset = Tuple4D::new_vector(noise(0.0, 0.0), noise(31.4, 0.0), noise(0.0, 47.2));
+                self.node_color(child, &(point + &(&offset * amplitude)))
+            }
+        }
+    }
+
+    fn mix(&self, weight: f32, subs: (Option<usize>, Option<usize>), colors: (&Color, &Color), p: &Tuple4D) -> Color {
+        let color = |sub: Option<usize>, color: &Color| sub.map_or(*color, |s| self.node_color(s, p));
+        if weight <= 0.0 {
+            color(subs.0, colors.0)
+        } else if weight >= 1.0 {
+            color(subs.1, colors.1)
+        } else {
+            blend(&color(subs.0, colors.0), &color(subs.1, colors.1), weight)
+        }
+    }
+
+    pub fn set_transformation(&mut self, m: Matrix) {
+        self.inverse_transformation_matrix =
+            Matrix::invert(&m).expect("NestedPattern::set_transformation: cant unwrap inverse matrix");
+        self.transformation_matrix = m;
+    }
+
+    // also recomputes the inverses of the node patterns, which are not serialized
+    pub fn try_set_transformation(&mut self, m: Matrix) -> Result<(), ShapeError> {
+        if !invertible(&m) {
+            return Err(ShapeError::NonInvertibleTransform);
+        }
+        self.validate()?;
+        for node in self.nodes[..self.node_cnt].iter_mut() {
+            match node {
+                PatternNode::Stripe { ref mut pattern, .. } => {
+                    pattern.set_transformation(pattern.get_transformation().clone())
+                }
+                PatternNode::Gradient { ref mut pattern, .. } => {
+                    pattern.set_transformation(pattern.get_transformation().clone())
+                }
+                PatternNode::Ring { ref mut pattern, .. } => {
+                    pattern.set_transformation(pattern.get_transformation().clone())
+                }
+                PatternNode::Checker3D { ref mut pattern, .. } => {
+                    pattern.set_transformation(pattern.get_transformation().clone())
+                }
+                PatternNode::Blend { .. } | PatternNode::Perturbed { .. } => {}
+            }
+        }
+        self.set_transformation(m);
+        Ok(())
+    }
+
+    pub fn get_transformation(&self) -> &Matrix {
+        &self.transformation_matrix
+    }
+
+    pub fn get_inverse_transformation(&self) -> &Matrix {
+        &self.inverse_transformation_matrix
+    }
+}
+
+fn invertible(m: &Matrix) -> bool {
+    Matrix::invert(m).is_some()
+}
+
+fn blend(a: &Color, b: &Color, weight: f32) -> Color {
+    &(a * (1.0 - weight)) + &(b * weight)
+}
+
+// serde only implements arrays up to 32 elements, and most of the array is unused. like the nodes of an sdf,
+// the trailing unused nodes are not written
+#[cfg(feature = "use_serde")]
+mod nodes_serde {
+    use core::fmt;
+
+    use serde::de::{Error, SeqAccess, Visitor};
+    use serde::{Deserializer, Serializer};
+
+    use super::{PatternNode, NESTED_PATTERN_MAX_NODES, NESTED_PATTERN_UNUSED_NODE};
+
+    pub fn serialize<S: Serializer>(nodes: &[PatternNode; NESTED_PATTERN_MAX_NODES], s: S) -> Result<S::Ok, S::Error> {
+        let len = nodes
+            .iter()
+            .rposition(|n| *n != NESTED_PATTERN_UNUSED_NODE)
+            .map_or(0, |i| i + 1);
+        s.collect_seq(nodes[..len].iter())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<[PatternNode; NESTED_PATTERN_MAX_NODES], D::Error> {
+        d.deserialize_seq(NodesVisitor)
+    }
+
+    struct NodesVisitor;
+
+    impl<'de> Visitor<'de> for NodesVisitor {
+        type Value = [PatternNode; NESTED_PATTERN_MAX_NODES];
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "at most {} pattern nodes", NESTED_PATTERN_MAX_NODES)
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            let mut res = [NESTED_PATTERN_UNUSED_NODE; NESTED_PATTERN_MAX_NODES];
+            let mut len = 0;
+            while let Some(n) = seq.next_element()? {
+                if len == NESTED_PATTERN_MAX_NODES {
+                    return Err(A::Error::invalid_length(len + 1, &self));
+                }
+                res[len] = n;
+                len += 1;
+            }
+            Ok(res)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{assert_color, ColorOps, Pattern, ShapeEnum, Sphere, BLACK, WHITE};
+
+    use super::*;
+
+    fn checker(a: Color, b: Color) -> Checker3DPattern {
+        let mut p = Checker3DPattern::new();
+        p.set_color_a(a);
+        p.set_color_b(b);
+        p.set_transformation(Matrix::scale(0.25, 0.25, 0.25));
+        p
+    }
+
+    #[test]
+    fn test_stripes_of_checkers() {
+        let red = Color::new(1.0, 0.0, 0.0);
+        let blue = Color::new(0.0, 0.0, 1.0);
+        let nodes = [
+            PatternNode::Checker3D {
+                pattern: checker(WHITE, BLACK),
+                a: None,
+                b: None,
+            },
+            PatternNode::Checker3D {
+                pattern: checker(red, blue),
+                a: None,
+                b: None,
+            },
+            PatternNode::Stripe {
+                pattern: StripePattern::new(),
+                a: Some(0),
+                b: Some(1),
+            },
+        ];
+        let mut p = NestedPattern::try_new(&nodes).unwrap();
+        p.set_transformation(Matrix::translation(0.0, 0.0, 10.0));
+
+        let shape = Shape::new(ShapeEnum::Sphere(Sphere::new()));
+        let p = Pattern::NestedPattern(p);
+        // first stripe, the checkers have a size of 0.25
+        assert_color(&p.color_at_object(&shape, &Tuple4D::new_point(0.1, 0.0, 10.0)), &WHITE);
+        assert_color(&p.color_at_object(&shape, &Tuple4D::new_point(0.3, 0.0, 10.0)), &BLACK);
+        // second stripe
+        assert_color(&p.color_at_object(&shape, &Tuple4D::new_point(1.1, 0.0, 10.0)), &red);
+        assert_color(&p.color_at_object(&shape, &Tuple4D::new_point(1.3, 0.0, 10.0)), &blue);
+    }
+
+    #[test]
+    fn test_blend_and_perturbed() {
+        let mut stripes = StripePattern::new();
+        stripes.set_transformation(Matrix::rotate_y(core::f32::consts::PI / 2.0));
+        let nodes = [
+            PatternNode::Stripe {
+                pattern: StripePattern::new(),
+                a: None,
+                b: None,
+            },
+            PatternNode::Stripe {
+                pattern: stripes,
+                a: None,
+                b: None,
+            },
+            PatternNode::Blend {
+                a: 0,
+                b: 1,
+                weight: 0.25,
+            },
+        ];
+        let p = NestedPattern::try_new(&nodes).unwrap();
+        // the rotated stripes are white for z < 0
+        assert_color(&p.color_at(&Tuple4D::new_point(0.5, 0.0, -0.5)), &WHITE);
+        assert_color(
+            &p.color_at(&Tuple4D::new_point(1.5, 0.0, -0.5)),
+            &Color::new(0.25, 0.25, 0.25),
+        );
+        assert_color(
+            &p.color_at(&Tuple4D::new_point(0.5, 0.0, 0.5)),
+            &Color::new(0.75, 0.75, 0.75),
+        );
+
+        let nodes = [
+            PatternNode::Stripe {
+                pattern: StripePattern::new(),
+                a: None,
+                b: None,
+            },
+            PatternNode::Perturbed {
+                child: 0,
+                amplitude: 0.5,
+                scale: 0.3,
+            },
+        ];
+        let p = NestedPattern::try_new(&nodes).unwrap();
+        // some points close to the stripe border change their color
+        let changed = (0..100)
+            .map(|i| Tuple4D::new_point(0.9 + i as f32 * 0.002, i as f32 * 0.37, 0.0))
+            .filter(|point| p.color_at(point) != StripePattern::stripe_at(&StripePattern::new(), point))
+            .count();
+        assert!(changed > 0 && changed < 100);
+    }
+
+    #[test]
+    fn test_nested_pattern_validate() {
+        let stripe = PatternNode::Stripe {
+            pattern: StripePattern::new(),
+            a: None,
+            b: Some(0),
+        };
+        assert_eq!(
+            NestedPattern::try_new(&[stripe.clone()]),
+            Err(ShapeError::InvalidMaterial("pattern nodes"))
+        );
+        assert_eq!(
+            NestedPattern::try_new(&[]),
+            Err(ShapeError::InvalidMaterial("pattern nodes"))
+        );
+        let nodes = [
+            NESTED_PATTERN_UNUSED_NODE,
+            NESTED_PATTERN_UNUSED_NODE,
+            NESTED_PATTERN_UNUSED_NODE,
+            NESTED_PATTERN_UNUSED_NODE,
+            NESTED_PATTERN_UNUSED_NODE,
+            NESTED_PATTERN_UNUSED_NODE,
+            NESTED_PATTERN_UNUSED_NODE,
+            NESTED_PATTERN_UNUSED_NODE,
+            NESTED_PATTERN_UNUSED_NODE,
+        ];
+        assert_eq!(
+            NestedPattern::try_new(&nodes),
+            Err(ShapeError::CapacityExceeded(NESTED_PATTERN_MAX_NODES))
+        );
+    }
+}
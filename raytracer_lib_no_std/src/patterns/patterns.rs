@@ -3,7 +3,9 @@ use serde::{Deserialize, Serialize};
 
 use math::prelude::*;
 
-use crate::{Checker3DPattern, Color, GradientPattern, RingPattern, Shape, StripePattern, TestPattern};
+use crate::{
+    Checker3DPattern, Color, GradientPattern, NestedPattern, RingPattern, Shape, ShapeError, StripePattern, TestPattern,
+};
 
 #[derive(Clone, Debug, PartialEq)]
 #[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
@@ -14,6 +16,7 @@ pub enum Pattern {
     RingPattern(RingPattern),
     Checker3DPattern(Checker3DPattern),
     TestPattern(TestPattern),
+    NestedPattern(NestedPattern),
 }
 
 impl Pattern {
@@ -30,6 +33,7 @@ impl Pattern {
                 Checker3DPattern::color_at_object(checker3d_pattern, shape, world_point)
             }
             Pattern::TestPattern(ref test_pattern) => TestPattern::color_at_object(test_pattern, shape, world_point),
+            Pattern::NestedPattern(ref nested_pattern) => nested_pattern.color_at_object(shape, world_point),
         }
     }
 
@@ -40,9 +44,21 @@ impl Pattern {
             Pattern::RingPattern(ref mut ring_pattern) => ring_pattern.set_transformation(m),
             Pattern::Checker3DPattern(ref mut checker3d_pattern) => checker3d_pattern.set_transformation(m),
             Pattern::TestPattern(ref mut test_pattern) => test_pattern.set_transformation(m),
+            Pattern::NestedPattern(ref mut nested_pattern) => nested_pattern.set_transformation(m),
         }
     }
 
+    pub fn try_set_transformation(&mut self, m: Matrix) -> Result<(), ShapeError> {
+        if let Pattern::NestedPattern(ref mut nested_pattern) = self {
+            return nested_pattern.try_set_transformation(m);
+        }
+        if Matrix::invert(&m).is_none() {
+            return Err(ShapeError::NonInvertibleTransform);
+        }
+        self.set_transformation(m);
+        Ok(())
+    }
+
     pub fn get_transformation(&self) -> &Matrix {
         match self {
             Pattern::StripePattern(ref stripe_pattern) => stripe_pattern.get_transformation(),
@@ -50,6 +66,7 @@ impl Pattern {
             Pattern::RingPattern(ref ring_pattern) => ring_pattern.get_transformation(),
             Pattern::Checker3DPattern(ref checker3d_pattern) => checker3d_pattern.get_transformation(),
             Pattern::TestPattern(ref test_pattern) => test_pattern.get_transformation(),
+            Pattern::NestedPattern(ref nested_pattern) => nested_pattern.get_transformation(),
         }
     }
 
@@ -60,6 +77,7 @@ impl Pattern {
             Pattern::RingPattern(ref ring_pattern) => ring_pattern.get_inverse_transformation(),
             Pattern::Checker3DPattern(ref checker3d_pattern) => checker3d_pattern.get_inverse_transformation(),
             Pattern::TestPattern(ref test_pattern) => test_pattern.get_inverse_transformation(),
+            Pattern::NestedPattern(ref nested_pattern) => nested_pattern.get_inverse_transformation(),
         }
     }
 }
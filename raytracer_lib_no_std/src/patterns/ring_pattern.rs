@@ -12,6 +12,7 @@ pub struct RingPattern {
     color_a: Color,
     color_b: Color,
     transformation_matrix: Matrix,
+    #[cfg_attr(feature = "use_serde", serde(skip, default = "Matrix::new_identity_4x4"))]
     inverse_transformation_matrix: Matrix,
 }
 
@@ -41,8 +42,17 @@ impl RingPattern {
         &self.color_b
     }
 
-    pub fn color_at(pattern: &RingPattern, point: &Tuple4D) -> Color {
+    // 0 where the pattern has color a, 1 where it has color b
+    pub fn weight_at(point: &Tuple4D) -> f32 {
         if intri_floor(intri_sqrt(intri_powi(point.x, 2) + intri_powi(point.z, 2))) as i32 % 2 == 0 {
+            0.0
+        } else {
+            1.0
+        }
+    }
+
+    pub fn color_at(pattern: &RingPattern, point: &Tuple4D) -> Color {
+        if Self::weight_at(point) == 0.0 {
             Color::from_color(&pattern.get_color_a())
         } else {
             Color::from_color(&pattern.get_color_b())
@@ -12,6 +12,7 @@ pub struct StripePattern {
     color_a: Color,
     color_b: Color,
     transformation_matrix: Matrix,
+    #[cfg_attr(feature = "use_serde", serde(skip, default = "Matrix::new_identity_4x4"))]
     inverse_transformation_matrix: Matrix,
 }
 
@@ -41,9 +42,18 @@ impl StripePattern {
         &self.color_b
     }
 
+    // 0 where the pattern has color a, 1 where it has color b
+    pub fn weight_at(point: &Tuple4D) -> f32 {
+        if intri_floor(point.x) as i32 % 2 == 0 {
+            0.0
+        } else {
+            1.0
+        }
+    }
+
     pub fn stripe_at(pattern: &StripePattern, point: &Tuple4D) -> Color {
         // TODO: we copy here colors all the way -> may be there is a chance to returen references?
-        if intri_floor(point.x) as i32 % 2 == 0 {
+        if Self::weight_at(point) == 0.0 {
             Color::from_color(&pattern.get_color_a())
         } else {
             Color::from_color(&pattern.get_color_b())
@@ -12,6 +12,7 @@ pub struct TestPattern {
     color_a: Color,
     color_b: Color,
     transformation_matrix: Matrix,
+    #[cfg_attr(feature = "use_serde", serde(skip, default = "Matrix::new_identity_4x4"))]
     inverse_transformation_matrix: Matrix,
 }
 
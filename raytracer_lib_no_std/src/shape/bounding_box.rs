@@ -0,0 +1,214 @@
+use core::f32::INFINITY;
+
+#[cfg(feature = "use_serde")]
+use serde::{Deserialize, Serialize};
+
+use math::prelude::*;
+
+// axis aligned bounding box. planes and open cylinders have infinite components
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "cuda", derive(DeviceCopy))]
+pub struct BoundingBox {
+    min: Tuple4D,
+    max: Tuple4D,
+}
+
+pub trait BoundingBoxOps {
+    fn new(min: Tuple4D, max: Tuple4D) -> BoundingBox;
+    // contains no point at all, the neutral element of merge()
+    fn empty() -> BoundingBox;
+
+    fn get_min(&self) -> &Tuple4D;
+    fn get_max(&self) -> &Tuple4D;
+
+    fn add_point(&mut self, p: &Tuple4D);
+    fn merge(&mut self, other: &BoundingBox);
+
+    fn is_empty(&self) -> bool;
+    fn is_infinite(&self) -> bool;
+    fn contains_point(&self, p: &Tuple4D) -> bool;
+
+    // only meaningful for finite, non empty boxes
+    fn get_center(&self) -> Tuple4D;
+    fn get_size(&self) -> Tuple4D;
+
+    // the box around the eight transformed corners
+    fn transform(&self, m: &Matrix) -> BoundingBox;
+}
+
+impl BoundingBoxOps for BoundingBox {
+    fn new(min: Tuple4D, max: Tuple4D) -> BoundingBox {
+        BoundingBox { min, max }
+    }
+
+    fn empty() -> BoundingBox {
+        BoundingBox {
+            min: Tuple4D::new_point(INFINITY, INFINITY, INFINITY),
+            max: Tuple4D::new_point(-INFINITY, -INFINITY, -INFINITY),
+        }
+    }
+
+    fn get_min(&self) -> &Tuple4D {
+        &self.min
+    }
+
+    fn get_max(&self) -> &Tuple4D {
+        &self.max
+    }
+
+    fn add_point(&mut self, p: &Tuple4D) {
+        add_component(&mut self.min.x, &mut self.max.x, p.x);
+        add_component(&mut self.min.y, &mut self.max.y, p.y);
+        add_component(&mut self.min.z, &mut self.max.z, p.z);
+    }
+
+    fn merge(&mut self, other: &BoundingBox) {
+        if !other.is_empty() {
+            self.add_point(&other.min);
+            self.add_point(&other.max);
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.min.x > self.max.x || self.min.y > self.max.y || self.min.z > self.max.z
+    }
+
+    fn is_infinite(&self) -> bool {
+        !self.is_empty()
+            && (self.min.x == -INFINITY
+                || self.min.y == -INFINITY
+                || self.min.z == -INFINITY
+                || self.max.x == INFINITY
+                || self.max.y == INFINITY
+                || self.max.z == INFINITY)
+    }
+
+    fn contains_point(&self, p: &Tuple4D) -> bool {
+        self.min.x <= p.x
+            && p.x <= self.max.x
+            && self.min.y <= p.y
+            && p.y <= self.max.y
+            && self.min.z <= p.z
+            && p.z <= self.max.z
+    }
+
+    fn get_center(&self) -> Tuple4D {
+        Tuple4D::new_point(
+            (self.min.x + self.max.x) / 2.0,
+            (self.min.y + self.max.y) / 2.0,
+            (self.min.z + self.max.z) / 2.0,
+        )
+    }
+
+    fn get_size(&self) -> Tuple4D {
+        &self.max - &self.min
+    }
+
+    fn transform(&self, m: &Matrix) -> BoundingBox {
+        let mut res = BoundingBox::empty();
+        if self.is_empty() {
+            return res;
+        }
+        for x in [self.min.x, self.max.x].iter() {
+            for y in [self.min.y, self.max.y].iter() {
+                for z in [self.min.z, self.max.z].iter() {
+                    res.add_point(&transform_point(m, &Tuple4D::new_point(*x, *y, *z)));
+                }
+            }
+        }
+        res
+    }
+}
+
+// a NaN comes from adding +INFINITY and -INFINITY, e.g. a rotated plane, so the axis is unbounded
+fn add_component(min: &mut f32, max: &mut f32, v: f32) {
+    if v.is_nan() {
+        *min = -INFINITY;
+        *max = INFINITY;
+    } else {
+        *min = min.min(v);
+        *max = max.max(v);
+    }
+}
+
+// m * p, but 0 * INFINITY is 0 instead of NaN, so a translated plane stays flat
+fn transform_point(m: &Matrix, p: &Tuple4D) -> Tuple4D {
+    let v = [p.x, p.y, p.z, p.w];
+    let mut res = [0.0; 4];
+    for (row, r) in res.iter_mut().enumerate() {
+        for (col, c) in v.iter().enumerate() {
+            if m[row][col] != 0.0 {
+                *r += m[row][col] * c;
+            }
+        }
+    }
+    Tuple4D::new(res[0], res[1], res[2], res[3])
+}
+
+#[cfg(test)]
+mod tests {
+    use core::f32::consts::PI;
+
+    use crate::math::common::assert_tuple;
+    use crate::{Cylinder, Plane, Shape, ShapeEnum, ShapeOps, Sphere, Triangle};
+
+    use super::*;
+
+    #[test]
+    fn test_bounding_box_local_bounds() {
+        let b = Sphere::new().local_bounds();
+        assert_eq!(b.get_min(), &Tuple4D::new_point(-1.0, -1.0, -1.0));
+        assert_eq!(b.get_max(), &Tuple4D::new_point(1.0, 1.0, 1.0));
+
+        let b = Plane::new().local_bounds();
+        assert!(b.is_infinite());
+        assert_eq!(b.get_min(), &Tuple4D::new_point(-INFINITY, 0.0, -INFINITY));
+        assert_eq!(b.get_max(), &Tuple4D::new_point(INFINITY, 0.0, INFINITY));
+
+        let mut c = Cylinder::new();
+        assert!(c.local_bounds().is_infinite());
+        c.set_minimum(-5.0);
+        c.set_maximum(3.0);
+        assert_eq!(c.local_bounds().get_min(), &Tuple4D::new_point(-1.0, -5.0, -1.0));
+        assert_eq!(c.local_bounds().get_max(), &Tuple4D::new_point(1.0, 3.0, 1.0));
+
+        let t = Triangle::new(
+            Tuple4D::new_point(-3.0, 7.0, 2.0),
+            Tuple4D::new_point(6.0, 2.0, -4.0),
+            Tuple4D::new_point(2.0, -1.0, -1.0),
+        );
+        assert_eq!(t.local_bounds().get_min(), &Tuple4D::new_point(-3.0, -1.0, -4.0));
+        assert_eq!(t.local_bounds().get_max(), &Tuple4D::new_point(6.0, 7.0, 2.0));
+    }
+
+    #[test]
+    fn test_bounding_box_world_bounds() {
+        let mut s = Shape::new(ShapeEnum::Sphere(Sphere::new()));
+        s.set_transformation(&Matrix::translation(1.0, 2.0, 3.0) * &Matrix::rotate_y(PI / 4.0));
+        let b = s.bounds();
+        let r = 2.0f32.sqrt();
+        assert_tuple(b.get_min(), &Tuple4D::new_point(1.0 - r, 1.0, 3.0 - r));
+        assert_tuple(b.get_max(), &Tuple4D::new_point(1.0 + r, 3.0, 3.0 + r));
+        assert!(b.contains_point(&Tuple4D::new_point(1.0, 2.0, 3.0)));
+        assert_tuple(&b.get_center(), &Tuple4D::new_point(1.0, 2.0, 3.0));
+
+        // translated planes stay flat, rotated planes are unbounded in every direction
+        let mut p = Shape::new(ShapeEnum::Plane(Plane::new()));
+        p.set_transformation(Matrix::translation(0.0, -1.0, 0.0));
+        assert_eq!(p.bounds().get_min().y, -1.0);
+        assert_eq!(p.bounds().get_max().y, -1.0);
+        p.set_transformation(Matrix::rotate_z(PI / 4.0));
+        assert_eq!(
+            p.bounds().get_min(),
+            &Tuple4D::new_point(-INFINITY, -INFINITY, -INFINITY)
+        );
+
+        let mut b = BoundingBox::empty();
+        assert!(b.is_empty());
+        b.merge(&BoundingBox::empty());
+        assert!(b.is_empty());
+        b.merge(&s.bounds());
+        assert_eq!(b, s.bounds());
+    }
+}
@@ -5,13 +5,16 @@ use serde::{Deserialize, Serialize};
 
 use math::prelude::*;
 
-use crate::{Material, MaterialOps, Ray, RayOps, ShapeIntersectionResult, ShapeOps};
+use crate::{
+    BoundingBox, BoundingBoxOps, Material, MaterialOps, Ray, RayOps, ShapeError, ShapeIntersectionResult, ShapeOps,
+};
 
 #[derive(Clone, Debug, PartialEq)]
-#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
 #[cfg_attr(feature = "cuda", derive(DeviceCopy))]
 pub struct Cube {
     transformation_matrix: Matrix,
+    #[cfg_attr(feature = "use_serde", serde(skip, default = "Matrix::new_identity_4x4"))]
     inverse_transformation_matrix: Matrix,
     material: Material,
 }
@@ -70,10 +73,14 @@ impl ShapeOps for Cube {
         Tuple4D::new_vector(0.0, 0.0, local_point.z)
     }
 
-    fn set_transformation(&mut self, m: Matrix) {
-        self.inverse_transformation_matrix =
-            Matrix::invert(&m).expect("Cube::set_transofrmation: cant unwrap inverse matrix");
+    fn local_bounds(&self) -> BoundingBox {
+        BoundingBox::new(Tuple4D::new_point(-1.0, -1.0, -1.0), Tuple4D::new_point(1.0, 1.0, 1.0))
+    }
+
+    fn try_set_transformation(&mut self, m: Matrix) -> Result<(), ShapeError> {
+        self.inverse_transformation_matrix = Matrix::invert(&m).ok_or(ShapeError::NonInvertibleTransform)?;
         self.transformation_matrix = m;
+        Ok(())
     }
 
     fn get_transformation(&self) -> &Matrix {
@@ -0,0 +1,195 @@
+#[cfg(feature = "use_serde")]
+use serde::{Deserialize, Serialize};
+
+use math::prelude::*;
+
+use crate::{
+    BoundingBox, BoundingBoxOps, Material, MaterialOps, Ray, RayOps, ShapeError, ShapeIntersectionResult, ShapeOps,
+};
+
+// a disk in the xz plane around the origin, facing +y. with an inner radius > 0 it is a ring
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "cuda", derive(DeviceCopy))]
+pub struct Disk {
+    transformation_matrix: Matrix,
+    #[cfg_attr(feature = "use_serde", serde(skip, default = "Matrix::new_identity_4x4"))]
+    inverse_transformation_matrix: Matrix,
+    material: Material,
+    radius: f32,
+    inner_radius: f32,
+    // a single sided disk is only hit by rays coming from +y, it is invisible and casts no shadow from below
+    two_sided: bool,
+}
+
+impl ShapeOps for Disk {
+    fn intersect(&self, r: &Ray) -> ShapeIntersectionResult {
+        let mut res = [0f32; 4];
+        let mut res_cnt = 0;
+
+        let t = match intersect_flat(r, self.two_sided) {
+            Some(t) => t,
+            None => return (res, res_cnt),
+        };
+        let x = r.get_origin().x + t * r.get_direction().x;
+        let z = r.get_origin().z + t * r.get_direction().z;
+        let dist = intri_powi(x, 2) + intri_powi(z, 2);
+        if intri_powi(self.inner_radius, 2) <= dist && dist <= intri_powi(self.radius, 2) {
+            res[0] = t;
+            res_cnt = 1;
+        }
+        (res, res_cnt)
+    }
+
+    fn normal_at(&self, world_point: &Tuple4D) -> Tuple4D {
+        let object_point = self.get_inverse_transformation() * world_point;
+        let local_normal = self.local_normal_at(&object_point);
+        let mut world_normal = &Matrix::transpose(self.get_inverse_transformation()) * &local_normal;
+        world_normal.w = 0.0;
+        Tuple4D::normalize(&world_normal)
+    }
+
+    fn local_normal_at(&self, _local_point: &Tuple4D) -> Tuple4D {
+        Tuple4D::new_vector(0.0, 1.0, 0.0)
+    }
+
+    fn local_bounds(&self) -> BoundingBox {
+        BoundingBox::new(
+            Tuple4D::new_point(-self.radius, 0.0, -self.radius),
+            Tuple4D::new_point(self.radius, 0.0, self.radius),
+        )
+    }
+
+    fn try_set_transformation(&mut self, m: Matrix) -> Result<(), ShapeError> {
+        self.inverse_transformation_matrix = Matrix::invert(&m).ok_or(ShapeError::NonInvertibleTransform)?;
+        self.transformation_matrix = m;
+        Ok(())
+    }
+
+    fn get_transformation(&self) -> &Matrix {
+        &self.transformation_matrix
+    }
+
+    fn get_inverse_transformation(&self) -> &Matrix {
+        &self.inverse_transformation_matrix
+    }
+
+    fn set_material(&mut self, m: Material) {
+        self.material = m;
+    }
+
+    fn get_material(&self) -> &Material {
+        &self.material
+    }
+
+    fn get_material_mut(&mut self) -> &mut Material {
+        &mut self.material
+    }
+}
+
+impl Disk {
+    pub fn new() -> Disk {
+        Disk {
+            transformation_matrix: Matrix::new_identity_4x4(),
+            inverse_transformation_matrix: Matrix::new_identity_4x4(),
+            material: Material::new(),
+            radius: 1.0,
+            inner_radius: 0.0,
+            two_sided: true,
+        }
+    }
+
+    pub fn get_radius(&self) -> f32 {
+        self.radius
+    }
+
+    pub fn set_radius(&mut self, radius: f32) {
+        self.radius = radius;
+    }
+
+    pub fn get_inner_radius(&self) -> f32 {
+        self.inner_radius
+    }
+
+    pub fn set_inner_radius(&mut self, inner_radius: f32) {
+        self.inner_radius = inner_radius;
+    }
+
+    pub fn get_two_sided(&self) -> bool {
+        self.two_sided
+    }
+
+    pub fn set_two_sided(&mut self, two_sided: bool) {
+        self.two_sided = two_sided;
+    }
+}
+
+// t of the intersection with the xz plane, None for rays parallel to the plane
+// and for rays from below, if the surface is single sided
+pub(crate) fn intersect_flat(r: &Ray, two_sided: bool) -> Option<f32> {
+    let dy = r.get_direction().y;
+    if intri_abs(dy) < EPSILON || (!two_sided && dy > 0.0) {
+        return None;
+    }
+    Some(-r.get_origin().y / dy)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::basics::ray::RayOps;
+    use crate::math::common::{assert_float, assert_tuple};
+
+    use super::*;
+
+    fn test_ray_disk_intersection_helper(d: &Disk, origin: Tuple4D, direction: Tuple4D, expected: Option<f32>) {
+        let r = Ray::new(origin, direction);
+        let (xs, cnt_hits) = d.intersect(&r);
+
+        match expected {
+            Some(t) => {
+                assert_eq!(cnt_hits, 1);
+                assert_float(xs[0], t);
+            }
+            None => assert_eq!(cnt_hits, 0),
+        }
+    }
+
+    #[test]
+    fn test_ray_disk_intersection() {
+        let mut d = Disk::new();
+        d.set_radius(2.0);
+        let down = Tuple4D::new_vector(0.0, -1.0, 0.0);
+
+        test_ray_disk_intersection_helper(&d, Tuple4D::new_point(0.0, 1.0, 0.0), down, Some(1.0));
+        test_ray_disk_intersection_helper(&d, Tuple4D::new_point(1.9, 3.0, 0.0), down, Some(3.0));
+        test_ray_disk_intersection_helper(&d, Tuple4D::new_point(1.5, 1.0, 1.5), down, None);
+        // parallel
+        let origin = Tuple4D::new_point(0.0, 0.0, -5.0);
+        test_ray_disk_intersection_helper(&d, origin, Tuple4D::new_vector(0.0, 0.0, 1.0), None);
+
+        // the hole of a ring
+        d.set_inner_radius(0.5);
+        test_ray_disk_intersection_helper(&d, Tuple4D::new_point(0.0, 1.0, 0.0), down, None);
+        test_ray_disk_intersection_helper(&d, Tuple4D::new_point(0.0, 1.0, 0.6), down, Some(1.0));
+    }
+
+    #[test]
+    fn test_ray_disk_single_sided() {
+        let mut d = Disk::new();
+        let up = Tuple4D::new_vector(0.0, 1.0, 0.0);
+        test_ray_disk_intersection_helper(&d, Tuple4D::new_point(0.0, -1.0, 0.0), up, Some(1.0));
+
+        d.set_two_sided(false);
+        test_ray_disk_intersection_helper(&d, Tuple4D::new_point(0.0, -1.0, 0.0), up, None);
+        let down = Tuple4D::new_vector(0.0, -1.0, 0.0);
+        test_ray_disk_intersection_helper(&d, Tuple4D::new_point(0.0, 1.0, 0.0), down, Some(1.0));
+    }
+
+    #[test]
+    fn test_ray_disk_normal_at() {
+        let mut d = Disk::new();
+        d.set_transformation(Matrix::rotate_x(core::f32::consts::PI / 2.0));
+        let n = d.normal_at(&Tuple4D::new_point(0.5, 0.0, 0.0));
+        assert_tuple(&n, &Tuple4D::new_vector(0.0, 0.0, 1.0));
+    }
+}
@@ -0,0 +1,487 @@
+#[cfg(feature = "use_serde")]
+use serde::{Deserialize, Serialize};
+
+use math::prelude::*;
+
+use crate::{
+    BoundingBox, BoundingBoxOps, Material, MaterialOps, Ray, RayOps, ShapeError, ShapeIntersectionResult, ShapeOps,
+};
+
+// a terrain over the square from (-1, 0, -1) to (1, 0, 1) with heights between 0 and 1, scaled with the
+// transformation. sample (x, z) of a width x depth grid is at (-1 + 2 x / (width - 1), h, -1 + 2 z / (depth - 1)),
+// every cell of the grid consists of two triangles. the samples are stored in the sample buffer of the world,
+// so the shape stays small and can be copied to the device
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "cuda", derive(DeviceCopy))]
+pub struct Heightfield {
+    transformation_matrix: Matrix,
+    #[cfg_attr(feature = "use_serde", serde(skip, default = "Matrix::new_identity_4x4"))]
+    inverse_transformation_matrix: Matrix,
+    material: Material,
+    // the samples first_sample .. first_sample + width * depth of the sample buffer, row major
+    first_sample: usize,
+    width: usize,
+    depth: usize,
+    min_height: f32,
+    max_height: f32,
+}
+
+impl ShapeOps for Heightfield {
+    // the hits depend on the samples, see Heightfield::intersect_samples
+    fn intersect(&self, _r: &Ray) -> ShapeIntersectionResult {
+        ([0f32; 4], 0)
+    }
+
+    fn normal_at(&self, world_point: &Tuple4D) -> Tuple4D {
+        let object_point = self.get_inverse_transformation() * world_point;
+        let local_normal = self.local_normal_at(&object_point);
+        let mut world_normal = &Matrix::transpose(self.get_inverse_transformation()) * &local_normal;
+        world_normal.w = 0.0;
+        Tuple4D::normalize(&world_normal)
+    }
+
+    // the normal depends on the samples, see Heightfield::local_normal_at_samples
+    fn local_normal_at(&self, _local_point: &Tuple4D) -> Tuple4D {
+        Tuple4D::new_vector(0.0, 1.0, 0.0)
+    }
+
+    fn local_bounds(&self) -> BoundingBox {
+        BoundingBox::new(
+            Tuple4D::new_point(-1.0, self.min_height, -1.0),
+            Tuple4D::new_point(1.0, self.max_height, 1.0),
+        )
+    }
+
+    fn try_set_transformation(&mut self, m: Matrix) -> Result<(), ShapeError> {
+        self.inverse_transformation_matrix = Matrix::invert(&m).ok_or(ShapeError::NonInvertibleTransform)?;
+        self.transformation_matrix = m;
+        Ok(())
+    }
+
+    fn get_transformation(&self) -> &Matrix {
+        &self.transformation_matrix
+    }
+
+    fn get_inverse_transformation(&self) -> &Matrix {
+        &self.inverse_transformation_matrix
+    }
+
+    fn set_material(&mut self, m: Material) {
+        self.material = m;
+    }
+
+    fn get_material(&self) -> &Material {
+        &self.material
+    }
+
+    fn get_material_mut(&mut self) -> &mut Material {
+        &mut self.material
+    }
+}
+
+impl Heightfield {
+    // a flat 2 x 2 grid, the samples still have to be added to the sample buffer
+    pub fn new() -> Heightfield {
+        Heightfield {
+            transformation_matrix: Matrix::new_identity_4x4(),
+            inverse_transformation_matrix: Matrix::new_identity_4x4(),
+            material: Material::new(),
+            first_sample: 0,
+            width: 2,
+            depth: 2,
+            min_height: 0.0,
+            max_height: 0.0,
+        }
+    }
+
+    // the heights are the samples first_sample .. first_sample + width * depth of the sample buffer, row major
+    // (x changes fastest) and between 0 and 1
+    pub fn try_new(
+        samples: &[f32],
+        first_sample: usize,
+        width: usize,
+        depth: usize,
+    ) -> Result<Heightfield, ShapeError> {
+        let mut res = Heightfield::new();
+        res.first_sample = first_sample;
+        res.width = width;
+        res.depth = depth;
+        res.min_height = 1.0;
+        res.max_height = 0.0;
+        for h in res.heights(samples)?.iter() {
+            res.min_height = intri_min(res.min_height, *h);
+            res.max_height = intri_max(res.max_height, *h);
+        }
+        res.validate(samples)?;
+        Ok(res)
+    }
+
+    // the sample buffer of a deserialized or modified world may no longer contain the samples
+    pub fn validate(&self, samples: &[f32]) -> Result<(), ShapeError> {
+        // NaN fails the comparison as well
+        if !self
+            .heights(samples)?
+            .iter()
+            .all(|h| *h >= self.min_height && *h <= self.max_height)
+            || self.min_height < 0.0
+            || self.max_height > 1.0
+        {
+            return Err(ShapeError::InvalidShape("heightfield heights"));
+        }
+        Ok(())
+    }
+
+    pub fn get_first_sample(&self) -> usize {
+        self.first_sample
+    }
+
+    pub fn get_width(&self) -> usize {
+        self.width
+    }
+
+    pub fn get_depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn get_height(&self, samples: &[f32], x: usize, z: usize) -> f32 {
+        samples[self.first_sample + z * self.width + x]
+    }
+
+    // walks the cells below the ray from the entry to the exit of the bounding box and tests their triangles
+    pub fn intersect_samples(&self, samples: &[f32], r: &Ray) -> ShapeIntersectionResult {
+        let mut res = [0f32; 4];
+        let mut res_cnt = 0;
+
+        let o = r.get_origin();
+        let d = r.get_direction();
+        let (t_min, t_max) = match self.intersect_bounds(o, d) {
+            Some(t) => t,
+            None => return (res, res_cnt),
+        };
+
+        let cell_x = 2.0 / (self.width - 1) as f32;
+        let cell_z = 2.0 / (self.depth - 1) as f32;
+        let start_x = o.x + t_min * d.x;
+        let start_z = o.z + t_min * d.z;
+        let mut x = clamp_cell((start_x + 1.0) / cell_x, self.width - 2);
+        let mut z = clamp_cell((start_z + 1.0) / cell_z, self.depth - 2);
+
+        // t of the next cell border and t between two borders in x and z direction
+        let (step_x, mut t_next_x, t_delta_x) = dda_setup(o.x, d.x, x, cell_x);
+        let (step_z, mut t_next_z, t_delta_z) = dda_setup(o.z, d.z, z, cell_z);
+
+        let mut t_cell = t_min;
+        loop {
+            let t_cell_exit = intri_min(intri_min(t_next_x, t_next_z), t_max);
+            let y0 = o.y + t_cell * d.y;
+            let y1 = o.y + t_cell_exit * d.y;
+            let (cell_min, cell_max) = self.cell_height_range(samples, x, z);
+            if intri_min(y0, y1) <= cell_max + EPSILON && intri_max(y0, y1) >= cell_min - EPSILON {
+                let (xs, cnt) = self.intersect_cell(samples, r, x, z);
+                for t in xs[..cnt].iter() {
+                    // a ray through the diagonal or a border hits two triangles at the same t
+                    if res_cnt < 4 && (res_cnt == 0 || intri_abs(res[res_cnt - 1] - *t) > EPSILON) {
+                        res[res_cnt] = *t;
+                        res_cnt += 1;
+                    }
+                }
+            }
+            if res_cnt == 4 || t_cell_exit >= t_max {
+                break;
+            }
+            if t_next_x < t_next_z {
+                if (step_x < 0 && x == 0) || (step_x > 0 && x == self.width - 2) {
+                    break;
+                }
+                x = (x as isize + step_x) as usize;
+                t_cell = t_next_x;
+                t_next_x += t_delta_x;
+            } else {
+                if (step_z < 0 && z == 0) || (step_z > 0 && z == self.depth - 2) {
+                    break;
+                }
+                z = (z as isize + step_z) as usize;
+                t_cell = t_next_z;
+                t_next_z += t_delta_z;
+            }
+        }
+        (res, res_cnt)
+    }
+
+    // bilinear interpolation of the normals at the corners of the cell
+    pub fn local_normal_at_samples(&self, samples: &[f32], local_point: &Tuple4D) -> Tuple4D {
+        let fx = (local_point.x + 1.0) / 2.0 * (self.width - 1) as f32;
+        let fz = (local_point.z + 1.0) / 2.0 * (self.depth - 1) as f32;
+        let x = clamp_cell(fx, self.width - 2);
+        let z = clamp_cell(fz, self.depth - 2);
+        let u = intri_min(intri_max(fx - x as f32, 0.0), 1.0);
+        let v = intri_min(intri_max(fz - z as f32, 0.0), 1.0);
+
+        let n00 = self.vertex_normal(samples, x, z);
+        let n10 = self.vertex_normal(samples, x + 1, z);
+        let n01 = self.vertex_normal(samples, x, z + 1);
+        let n11 = self.vertex_normal(samples, x + 1, z + 1);
+        let n = n00 * ((1.0 - u) * (1.0 - v)) + n10 * (u * (1.0 - v)) + n01 * ((1.0 - u) * v) + n11 * (u * v);
+        Tuple4D::normalize(&n)
+    }
+
+    fn heights<'a>(&self, samples: &'a [f32]) -> Result<&'a [f32], ShapeError> {
+        if self.width < 2 || self.depth < 2 {
+            return Err(ShapeError::InvalidShape("heightfield size"));
+        }
+        self.width
+            .checked_mul(self.depth)
+            .and_then(|cnt| samples.get(self.first_sample..self.first_sample.checked_add(cnt)?))
+            .ok_or(ShapeError::InvalidShape("heightfield samples"))
+    }
+
+    fn sample_point(&self, samples: &[f32], x: usize, z: usize) -> Tuple4D {
+        Tuple4D::new_point(
+            -1.0 + 2.0 * x as f32 / (self.width - 1) as f32,
+            self.get_height(samples, x, z),
+            -1.0 + 2.0 * z as f32 / (self.depth - 1) as f32,
+        )
+    }
+
+    // central differences, one sided at the border
+    fn vertex_normal(&self, samples: &[f32], x: usize, z: usize) -> Tuple4D {
+        let (x0, x1) = (
+            if x > 0 { x - 1 } else { x },
+            if x < self.width - 1 { x + 1 } else { x },
+        );
+        let (z0, z1) = (
+            if z > 0 { z - 1 } else { z },
+            if z < self.depth - 1 { z + 1 } else { z },
+        );
+        let cell_x = 2.0 / (self.width - 1) as f32;
+        let cell_z = 2.0 / (self.depth - 1) as f32;
+        let dx = (self.get_height(samples, x1, z) - self.get_height(samples, x0, z)) / ((x1 - x0) as f32 * cell_x);
+        let dz = (self.get_height(samples, x, z1) - self.get_height(samples, x, z0)) / ((z1 - z0) as f32 * cell_z);
+        Tuple4D::new_vector(-dx, 1.0, -dz)
+    }
+
+    fn cell_height_range(&self, samples: &[f32], x: usize, z: usize) -> (f32, f32) {
+        let h = [
+            self.get_height(samples, x, z),
+            self.get_height(samples, x + 1, z),
+            self.get_height(samples, x, z + 1),
+            self.get_height(samples, x + 1, z + 1),
+        ];
+        (
+            intri_min(intri_min(h[0], h[1]), intri_min(h[2], h[3])),
+            intri_max(intri_max(h[0], h[1]), intri_max(h[2], h[3])),
+        )
+    }
+
+    fn intersect_cell(&self, samples: &[f32], r: &Ray, x: usize, z: usize) -> ([f32; 2], usize) {
+        let p00 = self.sample_point(samples, x, z);
+        let p10 = self.sample_point(samples, x + 1, z);
+        let p01 = self.sample_point(samples, x, z + 1);
+        let p11 = self.sample_point(samples, x + 1, z + 1);
+
+        let mut res = [0f32; 2];
+        let mut res_cnt = 0;
+        for (p1, p2, p3) in [(&p00, &p10, &p11), (&p00, &p11, &p01)].iter() {
+            if let Some(t) = intersect_triangle(r, p1, p2, p3) {
+                res[res_cnt] = t;
+                res_cnt += 1;
+            }
+        }
+        if res_cnt == 2 && res[1] < res[0] {
+            res.swap(0, 1);
+        }
+        (res, res_cnt)
+    }
+
+    // t range of the ray inside the bounding box
+    fn intersect_bounds(&self, o: &Tuple4D, d: &Tuple4D) -> Option<(f32, f32)> {
+        let mut t_min = -core::f32::INFINITY;
+        let mut t_max = core::f32::INFINITY;
+        for (origin, direction, min, max) in [
+            (o.x, d.x, -1.0, 1.0),
+            (o.y, d.y, self.min_height - EPSILON, self.max_height + EPSILON),
+            (o.z, d.z, -1.0, 1.0),
+        ]
+        .iter()
+        {
+            if intri_abs(*direction) < EPSILON {
+                if origin < min || origin > max {
+                    return None;
+                }
+                continue;
+            }
+            let t0 = (min - origin) / direction;
+            let t1 = (max - origin) / direction;
+            t_min = intri_max(t_min, intri_min(t0, t1));
+            t_max = intri_min(t_max, intri_max(t0, t1));
+        }
+        if t_min > t_max {
+            return None;
+        }
+        Some((t_min, t_max))
+    }
+}
+
+// Moeller - Trumbore, the edges are included, so there are no cracks between the triangles
+fn intersect_triangle(r: &Ray, p1: &Tuple4D, p2: &Tuple4D, p3: &Tuple4D) -> Option<f32> {
+    let e1 = p2 - p1;
+    let e2 = p3 - p1;
+    let dir_cross_e2 = r.get_direction() * &e2;
+    let det = &e1 ^ &dir_cross_e2;
+    if intri_abs(det) < EPSILON * EPSILON {
+        return None;
+    }
+    let f = 1.0 / det;
+    let p1_to_origin = r.get_origin() - p1;
+    let u = f * (&p1_to_origin ^ &dir_cross_e2);
+    if u < -EPSILON || u > 1.0 + EPSILON {
+        return None;
+    }
+    let origin_cross_e1 = &p1_to_origin * &e1;
+    let v = f * (r.get_direction() ^ &origin_cross_e1);
+    if v < -EPSILON || u + v > 1.0 + EPSILON {
+        return None;
+    }
+    Some(f * (&e2 ^ &origin_cross_e1))
+}
+
+fn clamp_cell(f: f32, max: usize) -> usize {
+    if f < 0.0 || f.is_nan() {
+        0
+    } else {
+        let c = intri_floor(f) as usize;
+        if c > max {
+            max
+        } else {
+            c
+        }
+    }
+}
+
+// step direction, t of the first cell border and t between two borders
+fn dda_setup(origin: f32, direction: f32, cell: usize, cell_size: f32) -> (isize, f32, f32) {
+    if direction > 0.0 {
+        let border = -1.0 + (cell + 1) as f32 * cell_size;
+        (1, (border - origin) / direction, cell_size / direction)
+    } else if direction < 0.0 {
+        let border = -1.0 + cell as f32 * cell_size;
+        (-1, (border - origin) / direction, -cell_size / direction)
+    } else {
+        (0, core::f32::INFINITY, core::f32::INFINITY)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::basics::ray::RayOps;
+    use crate::math::common::{assert_float, assert_tuple};
+
+    use super::*;
+
+    // a ridge along z: 0 at x = -1 and x = 1, 1 at x = 0. the samples start behind the sample of another shape
+    const RIDGE: [f32; 10] = [0.5, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0];
+
+    fn ridge() -> Heightfield {
+        Heightfield::try_new(&RIDGE, 1, 3, 3).unwrap()
+    }
+
+    #[test]
+    fn test_heightfield_try_new() {
+        let h = ridge();
+        assert_eq!(h.get_first_sample(), 1);
+        assert_eq!(h.get_width(), 3);
+        assert_eq!(h.get_depth(), 3);
+        assert_float(h.get_height(&RIDGE, 1, 2), 1.0);
+        assert_eq!(h.local_bounds().get_max(), &Tuple4D::new_point(1.0, 1.0, 1.0));
+
+        assert_eq!(
+            Heightfield::try_new(&[0.0; 3], 0, 1, 3),
+            Err(ShapeError::InvalidShape("heightfield size"))
+        );
+        assert_eq!(
+            Heightfield::try_new(&RIDGE, 2, 3, 3),
+            Err(ShapeError::InvalidShape("heightfield samples"))
+        );
+        assert_eq!(
+            Heightfield::try_new(&[0.0, 1.5, 0.0, 0.0], 0, 2, 2),
+            Err(ShapeError::InvalidShape("heightfield heights"))
+        );
+        assert_eq!(
+            Heightfield::try_new(&[0.0, core::f32::NAN, 0.0, 0.0], 0, 2, 2),
+            Err(ShapeError::InvalidShape("heightfield heights"))
+        );
+
+        // the samples of the heightfield were changed or removed
+        assert!(h.validate(&RIDGE).is_ok());
+        assert_eq!(
+            h.validate(&RIDGE[..9]),
+            Err(ShapeError::InvalidShape("heightfield samples"))
+        );
+        let mut changed = RIDGE;
+        changed[3] = 0.75;
+        assert!(h.validate(&changed).is_ok());
+        changed[3] = -0.25;
+        assert_eq!(
+            h.validate(&changed),
+            Err(ShapeError::InvalidShape("heightfield heights"))
+        );
+    }
+
+    #[test]
+    fn test_heightfield_intersection() {
+        let h = ridge();
+
+        // straight down onto the ridge and onto the slope
+        let r = Ray::new(Tuple4D::new_point(0.0, 5.0, 0.3), Tuple4D::new_vector(0.0, -1.0, 0.0));
+        let (xs, cnt) = h.intersect_samples(&RIDGE, &r);
+        assert_eq!(cnt, 1);
+        assert_float(xs[0], 4.0);
+
+        let r = Ray::new(Tuple4D::new_point(0.5, 5.0, -0.7), Tuple4D::new_vector(0.0, -1.0, 0.0));
+        let (xs, cnt) = h.intersect_samples(&RIDGE, &r);
+        assert_eq!(cnt, 1);
+        assert_float(xs[0], 4.5);
+
+        // horizontally through the ridge at y = 0.5, in and out on both slopes
+        let r = Ray::new(Tuple4D::new_point(-5.0, 0.5, 0.1), Tuple4D::new_vector(1.0, 0.0, 0.0));
+        let (xs, cnt) = h.intersect_samples(&RIDGE, &r);
+        assert_eq!(cnt, 2);
+        assert_float(xs[0], 4.5);
+        assert_float(xs[1], 5.5);
+
+        // above the ridge and next to the grid
+        let r = Ray::new(Tuple4D::new_point(-5.0, 1.5, 0.0), Tuple4D::new_vector(1.0, 0.0, 0.0));
+        assert_eq!(h.intersect_samples(&RIDGE, &r).1, 0);
+        let r = Ray::new(Tuple4D::new_point(1.5, 5.0, 0.0), Tuple4D::new_vector(0.0, -1.0, 0.0));
+        assert_eq!(h.intersect_samples(&RIDGE, &r).1, 0);
+
+        // diagonal, crossing cells in x and z
+        let r = Ray::new(Tuple4D::new_point(-2.0, 0.25, -2.0), Tuple4D::new_vector(1.0, 0.0, 1.0));
+        let (xs, cnt) = h.intersect_samples(&RIDGE, &r);
+        assert_eq!(cnt, 2);
+        assert_float(xs[0], 1.25);
+        assert_float(xs[1], 2.75);
+    }
+
+    #[test]
+    fn test_heightfield_normal_at() {
+        let h = ridge();
+        // the vertex normals on the ridge point straight up, on the border they follow the slope
+        assert_tuple(
+            &h.local_normal_at_samples(&RIDGE, &Tuple4D::new_point(0.0, 1.0, 0.0)),
+            &Tuple4D::new_vector(0.0, 1.0, 0.0),
+        );
+        let n = Tuple4D::normalize(&Tuple4D::new_vector(-1.0, 1.0, 0.0));
+        assert_tuple(
+            &h.local_normal_at_samples(&RIDGE, &Tuple4D::new_point(-1.0, 0.0, 0.5)),
+            &n,
+        );
+        // interpolated in between
+        let n = Tuple4D::normalize(&Tuple4D::new_vector(-0.5, 1.0, 0.0));
+        assert_tuple(
+            &h.local_normal_at_samples(&RIDGE, &Tuple4D::new_point(-0.5, 0.5, 0.0)),
+            &n,
+        );
+    }
+}
@@ -0,0 +1,210 @@
+#[cfg(feature = "use_serde")]
+use serde::{Deserialize, Serialize};
+
+use math::prelude::*;
+
+use crate::{
+    BoundingBox, BoundingBoxOps, Material, Ray, RayOps, Shape, ShapeEnum, ShapeError, ShapeIdx,
+    ShapeIntersectionResult, ShapeOps,
+};
+
+// a copy of the prototypes first_prototype .. first_prototype + prototype_cnt of the shape list, moved with
+// its own transformation. the prototypes are only stored once, no matter how many instances use them.
+// the instance can't look up the prototypes itself, the kernels intersect them
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "cuda", derive(DeviceCopy))]
+pub struct Instance {
+    transformation_matrix: Matrix,
+    #[cfg_attr(feature = "use_serde", serde(skip, default = "Matrix::new_identity_4x4"))]
+    inverse_transformation_matrix: Matrix,
+    // without override_material it is only used for the refractive index, like the material of the first prototype
+    material: Material,
+    override_material: bool,
+    first_prototype: ShapeIdx,
+    prototype_cnt: usize,
+    // object space bounds of the prototypes
+    prototype_bounds: BoundingBox,
+}
+
+impl ShapeOps for Instance {
+    // the hits are the hits of the prototypes, see Instance::intersect_prototype
+    fn intersect(&self, _r: &Ray) -> ShapeIntersectionResult {
+        ([0f32; 4], 0)
+    }
+
+    fn normal_at(&self, world_point: &Tuple4D) -> Tuple4D {
+        let object_point = self.get_inverse_transformation() * world_point;
+        let local_normal = self.local_normal_at(&object_point);
+        let mut world_normal = &Matrix::transpose(self.get_inverse_transformation()) * &local_normal;
+        world_normal.w = 0.0;
+        Tuple4D::normalize(&world_normal)
+    }
+
+    // the normal depends on the prototype, see Instance::normal_at_prototype
+    fn local_normal_at(&self, _local_point: &Tuple4D) -> Tuple4D {
+        Tuple4D::new_vector(0.0, 1.0, 0.0)
+    }
+
+    fn local_bounds(&self) -> BoundingBox {
+        self.prototype_bounds
+    }
+
+    fn try_set_transformation(&mut self, m: Matrix) -> Result<(), ShapeError> {
+        self.inverse_transformation_matrix = Matrix::invert(&m).ok_or(ShapeError::NonInvertibleTransform)?;
+        self.transformation_matrix = m;
+        Ok(())
+    }
+
+    fn get_transformation(&self) -> &Matrix {
+        &self.transformation_matrix
+    }
+
+    fn get_inverse_transformation(&self) -> &Matrix {
+        &self.inverse_transformation_matrix
+    }
+
+    fn set_material(&mut self, m: Material) {
+        self.material = m;
+    }
+
+    fn get_material(&self) -> &Material {
+        &self.material
+    }
+
+    fn get_material_mut(&mut self) -> &mut Material {
+        &mut self.material
+    }
+}
+
+impl Instance {
+    // the prototypes must be marked with Shape::set_is_prototype, instances of instances are not supported
+    pub fn try_new(shapes: &[Shape], first_prototype: ShapeIdx, prototype_cnt: usize) -> Result<Instance, ShapeError> {
+        let prototypes = check_prototypes(shapes, first_prototype, prototype_cnt)?;
+        let mut prototype_bounds = BoundingBox::empty();
+        for p in prototypes.iter() {
+            prototype_bounds.merge(&p.bounds());
+        }
+        Ok(Instance {
+            transformation_matrix: Matrix::new_identity_4x4(),
+            inverse_transformation_matrix: Matrix::new_identity_4x4(),
+            material: prototypes[0].get_material().clone(),
+            override_material: false,
+            first_prototype,
+            prototype_cnt,
+            prototype_bounds,
+        })
+    }
+
+    // the shape list of a deserialized or modified world may no longer contain the prototypes
+    pub fn validate(&self, shapes: &[Shape]) -> Result<(), ShapeError> {
+        check_prototypes(shapes, self.first_prototype, self.prototype_cnt)?;
+        Ok(())
+    }
+
+    pub fn get_first_prototype(&self) -> ShapeIdx {
+        self.first_prototype
+    }
+
+    pub fn get_prototype_cnt(&self) -> usize {
+        self.prototype_cnt
+    }
+
+    pub fn get_override_material(&self) -> bool {
+        self.override_material
+    }
+
+    // with override_material all prototypes are rendered with the material of the instance
+    pub fn set_override_material(&mut self, override_material: bool) {
+        self.override_material = override_material;
+    }
+
+    // local_ray is the ray in the space of the instance, samples the sample buffer of the world
+    pub fn intersect_prototype(&self, prototype: &Shape, samples: &[f32], local_ray: &Ray) -> ShapeIntersectionResult {
+        prototype.intersect_samples(
+            samples,
+            &Ray::transform(local_ray, prototype.get_inverse_transformation()),
+        )
+    }
+
+    pub fn normal_at_prototype(&self, prototype: &Shape, samples: &[f32], world_point: &Tuple4D) -> Tuple4D {
+        let object_point = self.get_inverse_transformation() * world_point;
+        let object_normal = prototype.normal_at_samples(samples, &object_point);
+        let mut world_normal = &Matrix::transpose(self.get_inverse_transformation()) * &object_normal;
+        world_normal.w = 0.0;
+        Tuple4D::normalize(&world_normal)
+    }
+}
+
+fn check_prototypes(shapes: &[Shape], first_prototype: ShapeIdx, prototype_cnt: usize) -> Result<&[Shape], ShapeError> {
+    let prototypes = shapes
+        .get(first_prototype..first_prototype + prototype_cnt)
+        .ok_or(ShapeError::InvalidShape("instance prototypes"))?;
+    if prototypes.is_empty() {
+        return Err(ShapeError::InvalidShape("instance prototypes"));
+    }
+    for p in prototypes.iter() {
+        if !p.get_is_prototype() {
+            return Err(ShapeError::InvalidShape("instance prototypes"));
+        }
+        if let ShapeEnum::Instance(_) = p.get_shape() {
+            return Err(ShapeError::InvalidShape("instance prototypes"));
+        }
+    }
+    Ok(prototypes)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::basics::ray::RayOps;
+    use crate::math::common::{assert_float, assert_tuple};
+    use crate::{Color, ColorOps, MaterialOps, Sphere};
+
+    use super::*;
+
+    fn prototypes() -> [Shape; 2] {
+        let mut s = Shape::new(ShapeEnum::Sphere(Sphere::new()));
+        s.set_is_prototype(true);
+        s.get_material_mut().set_color(Color::new(1.0, 0.0, 0.0));
+        let mut s2 = s.clone();
+        s2.set_transformation(Matrix::translation(0.0, 2.0, 0.0));
+        [s, s2]
+    }
+
+    #[test]
+    fn test_instance_try_new() {
+        let shapes = prototypes();
+        let i = Instance::try_new(&shapes, 0, 2).unwrap();
+        assert_eq!(i.get_material().get_color(), &Color::new(1.0, 0.0, 0.0));
+        assert_eq!(i.local_bounds().get_min(), &Tuple4D::new_point(-1.0, -1.0, -1.0));
+        assert_eq!(i.local_bounds().get_max(), &Tuple4D::new_point(1.0, 3.0, 1.0));
+
+        assert_eq!(
+            Instance::try_new(&shapes, 1, 2),
+            Err(ShapeError::InvalidShape("instance prototypes"))
+        );
+        let not_a_prototype = [Shape::new(ShapeEnum::Sphere(Sphere::new()))];
+        assert_eq!(
+            Instance::try_new(&not_a_prototype, 0, 1),
+            Err(ShapeError::InvalidShape("instance prototypes"))
+        );
+    }
+
+    #[test]
+    fn test_instance_intersect_and_normal() {
+        let shapes = prototypes();
+        let mut i = Instance::try_new(&shapes, 0, 2).unwrap();
+        i.set_transformation(Matrix::translation(5.0, 0.0, 0.0));
+
+        let r = Ray::new(Tuple4D::new_point(5.0, 2.0, -5.0), Tuple4D::new_vector(0.0, 0.0, 1.0));
+        let local_ray = Ray::transform(&r, i.get_inverse_transformation());
+        let (_, cnt_hits) = i.intersect_prototype(&shapes[0], &[], &local_ray);
+        assert_eq!(cnt_hits, 0);
+        let (xs, cnt_hits) = i.intersect_prototype(&shapes[1], &[], &local_ray);
+        assert_eq!(cnt_hits, 2);
+        assert_float(xs[0], 4.0);
+
+        let n = i.normal_at_prototype(&shapes[1], &[], &Tuple4D::new_point(6.0, 2.0, 0.0));
+        assert_tuple(&n, &Tuple4D::new_vector(1.0, 0.0, 0.0));
+    }
+}
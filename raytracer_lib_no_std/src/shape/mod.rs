@@ -1,13 +1,27 @@
+pub use self::bounding_box::*;
 pub use self::cube::*;
 pub use self::cylinder::*;
+pub use self::disk::*;
+pub use self::heightfield::*;
+pub use self::instance::*;
 pub use self::plane::*;
+pub use self::rectangle::*;
+pub use self::sdf::*;
 pub use self::shape::*;
 pub use self::sphere::*;
+pub use self::torus::*;
 pub use self::triangle::*;
 
+pub mod bounding_box;
 pub mod cube;
 pub mod cylinder;
+pub mod disk;
+pub mod heightfield;
+pub mod instance;
 pub mod plane;
+pub mod rectangle;
+pub mod sdf;
 pub mod shape;
 pub mod sphere;
+pub mod torus;
 pub mod triangle;
@@ -0,0 +1,161 @@
+#[cfg(feature = "use_serde")]
+use serde::{Deserialize, Serialize};
+
+use math::prelude::*;
+
+use crate::shape::disk::intersect_flat;
+use crate::{
+    AreaLight, BoundingBox, BoundingBoxOps, Color, Material, MaterialOps, Ray, RayOps, ShapeError,
+    ShapeIntersectionResult, ShapeOps,
+};
+
+// the square from (-1, 0, -1) to (1, 0, 1) in the xz plane, facing +y. like the cube, the size is set
+// with the transformation
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "cuda", derive(DeviceCopy))]
+pub struct Rectangle {
+    transformation_matrix: Matrix,
+    #[cfg_attr(feature = "use_serde", serde(skip, default = "Matrix::new_identity_4x4"))]
+    inverse_transformation_matrix: Matrix,
+    material: Material,
+    // a single sided rectangle is only hit by rays coming from +y, it is invisible and casts no shadow from below
+    two_sided: bool,
+}
+
+impl ShapeOps for Rectangle {
+    fn intersect(&self, r: &Ray) -> ShapeIntersectionResult {
+        let mut res = [0f32; 4];
+        let mut res_cnt = 0;
+
+        let t = match intersect_flat(r, self.two_sided) {
+            Some(t) => t,
+            None => return (res, res_cnt),
+        };
+        let x = r.get_origin().x + t * r.get_direction().x;
+        let z = r.get_origin().z + t * r.get_direction().z;
+        if intri_abs(x) <= 1.0 && intri_abs(z) <= 1.0 {
+            res[0] = t;
+            res_cnt = 1;
+        }
+        (res, res_cnt)
+    }
+
+    fn normal_at(&self, world_point: &Tuple4D) -> Tuple4D {
+        let object_point = self.get_inverse_transformation() * world_point;
+        let local_normal = self.local_normal_at(&object_point);
+        let mut world_normal = &Matrix::transpose(self.get_inverse_transformation()) * &local_normal;
+        world_normal.w = 0.0;
+        Tuple4D::normalize(&world_normal)
+    }
+
+    fn local_normal_at(&self, _local_point: &Tuple4D) -> Tuple4D {
+        Tuple4D::new_vector(0.0, 1.0, 0.0)
+    }
+
+    fn local_bounds(&self) -> BoundingBox {
+        BoundingBox::new(Tuple4D::new_point(-1.0, 0.0, -1.0), Tuple4D::new_point(1.0, 0.0, 1.0))
+    }
+
+    fn try_set_transformation(&mut self, m: Matrix) -> Result<(), ShapeError> {
+        self.inverse_transformation_matrix = Matrix::invert(&m).ok_or(ShapeError::NonInvertibleTransform)?;
+        self.transformation_matrix = m;
+        Ok(())
+    }
+
+    fn get_transformation(&self) -> &Matrix {
+        &self.transformation_matrix
+    }
+
+    fn get_inverse_transformation(&self) -> &Matrix {
+        &self.inverse_transformation_matrix
+    }
+
+    fn set_material(&mut self, m: Material) {
+        self.material = m;
+    }
+
+    fn get_material(&self) -> &Material {
+        &self.material
+    }
+
+    fn get_material_mut(&mut self) -> &mut Material {
+        &mut self.material
+    }
+}
+
+impl Rectangle {
+    pub fn new() -> Rectangle {
+        Rectangle {
+            transformation_matrix: Matrix::new_identity_4x4(),
+            inverse_transformation_matrix: Matrix::new_identity_4x4(),
+            material: Material::new(),
+            two_sided: true,
+        }
+    }
+
+    pub fn get_two_sided(&self) -> bool {
+        self.two_sided
+    }
+
+    pub fn set_two_sided(&mut self, two_sided: bool) {
+        self.two_sided = two_sided;
+    }
+
+    // an area light covering the rectangle. to see the emitter, add the rectangle to the world
+    // with a bright ambient material and set_casts_shadow(false)
+    pub fn to_area_light(&self, usteps: usize, vsteps: usize, intensity: Color) -> AreaLight {
+        let m = self.get_transformation();
+        let corner = m * &Tuple4D::new_point(-1.0, 0.0, -1.0);
+        let v1 = m * &Tuple4D::new_vector(2.0, 0.0, 0.0);
+        let v2 = m * &Tuple4D::new_vector(0.0, 0.0, 2.0);
+        AreaLight::new(corner, v1, usteps, v2, vsteps, intensity)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::f32::consts::PI;
+
+    use crate::basics::ray::RayOps;
+    use crate::math::common::{assert_float, assert_tuple};
+    use crate::{ColorOps, LightOps};
+
+    use super::*;
+
+    #[test]
+    fn test_ray_rectangle_intersection() {
+        let mut rect = Rectangle::new();
+        rect.set_transformation(&Matrix::translation(0.0, 3.0, 40.0) * &Matrix::rotate_x(-PI / 2.0));
+
+        let r = Ray::new(Tuple4D::new_point(0.5, 3.9, 0.0), Tuple4D::new_vector(0.0, 0.0, 1.0));
+        let (xs, cnt_hits) = rect.intersect(&Ray::transform(&r, rect.get_inverse_transformation()));
+        assert_eq!(cnt_hits, 1);
+        assert_float(xs[0], 40.0);
+
+        let r = Ray::new(Tuple4D::new_point(1.1, 3.0, 0.0), Tuple4D::new_vector(0.0, 0.0, 1.0));
+        let (_, cnt_hits) = rect.intersect(&Ray::transform(&r, rect.get_inverse_transformation()));
+        assert_eq!(cnt_hits, 0);
+
+        // the front faces -z, a single sided rectangle can't be seen from behind
+        let n = rect.normal_at(&Tuple4D::new_point(0.0, 3.0, 40.0));
+        assert_tuple(&n, &Tuple4D::new_vector(0.0, 0.0, -1.0));
+        rect.set_two_sided(false);
+        let r = Ray::new(Tuple4D::new_point(0.0, 3.0, 50.0), Tuple4D::new_vector(0.0, 0.0, -1.0));
+        let (_, cnt_hits) = rect.intersect(&Ray::transform(&r, rect.get_inverse_transformation()));
+        assert_eq!(cnt_hits, 0);
+    }
+
+    #[test]
+    fn test_rectangle_to_area_light() {
+        let mut rect = Rectangle::new();
+        rect.set_transformation(&Matrix::translation(0.0, 5.0, 0.0) * &Matrix::scale(2.0, 1.0, 0.5));
+
+        let l = rect.to_area_light(4, 2, Color::new(1.0, 1.0, 1.0));
+        assert_tuple(l.get_corner(), &Tuple4D::new_point(-2.0, 5.0, -0.5));
+        assert_tuple(l.get_uvec(), &Tuple4D::new_vector(1.0, 0.0, 0.0));
+        assert_tuple(l.get_vvec(), &Tuple4D::new_vector(0.0, 0.0, 0.5));
+        assert_tuple(l.get_position(), &Tuple4D::new_point(0.0, 5.0, 0.0));
+        assert_eq!(l.get_samples(), 8);
+    }
+}
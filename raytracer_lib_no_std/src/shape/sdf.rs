@@ -0,0 +1,616 @@
+use core::f32::INFINITY;
+use core::fmt;
+
+#[cfg(feature = "use_serde")]
+use serde::{Deserialize, Serialize};
+
+use math::prelude::*;
+
+use crate::{
+    BoundingBox, BoundingBoxOps, Material, MaterialOps, Ray, RayOps, ShapeError, ShapeIntersectionResult, ShapeOps,
+};
+
+// max. number of nodes of a signed distance field. the nodes are stored in the shape itself, 32 is the
+// largest array that is still DeviceCopy
+pub const SDF_MAX_NODES: usize = 32;
+
+// limits of the sphere tracing, in object space
+const SDF_MAX_STEPS: usize = 512;
+const SDF_MIN_STEP: f32 = 0.0001;
+const SDF_MAX_DISTANCE: f32 = 1000.0;
+const SDF_NORMAL_DELTA: f32 = 0.001;
+
+// content of the unused part of the node array
+const SDF_UNUSED_NODE: SdfNode = SdfNode::Sphere { radius: 1.0 };
+
+// a node of the distance field. operations and domain transformations refer to their children by index,
+// a child must come before its parent
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "cuda", derive(DeviceCopy))]
+pub enum SdfNode {
+    Sphere { radius: f32 },
+    // a box from -half_size to half_size, the edges are rounded with the radius
+    RoundBox { half_size: Tuple4D, radius: f32 },
+    // around the y axis like the Torus shape
+    Torus { major_radius: f32, minor_radius: f32 },
+    Union { a: usize, b: usize },
+    Intersection { a: usize, b: usize },
+    // a without b
+    Subtraction { a: usize, b: usize },
+    // a union with a fillet of about the size k between a and b
+    SmoothUnion { a: usize, b: usize, k: f32 },
+    Translate { offset: Tuple4D, child: usize },
+    // rotates the child around the y axis by angle y * k
+    Twist { k: f32, child: usize },
+    // infinite copies of the child in a grid with the cell size period, a period of 0 disables the repetition
+    // along the axis. the child should fit into the cell around the origin
+    Repeat { period: Tuple4D, child: usize },
+}
+
+// a shape defined by the distance function of its nodes, the last node is the root. it is rendered with sphere
+// tracing, so it works with any combination of nodes, but is a lot slower than the analytic shapes
+#[derive(Clone, PartialEq)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "cuda", derive(DeviceCopy))]
+pub struct Sdf {
+    transformation_matrix: Matrix,
+    #[cfg_attr(feature = "use_serde", serde(skip, default = "Matrix::new_identity_4x4"))]
+    inverse_transformation_matrix: Matrix,
+    material: Material,
+    #[cfg_attr(feature = "use_serde", serde(with = "nodes_serde"))]
+    nodes: [SdfNode; SDF_MAX_NODES],
+    node_cnt: usize,
+}
+
+impl ShapeOps for Sdf {
+    // marches along the ray inside the bounds and records every sign change of the distance, so the exit
+    // points are found as well. for unbounded fields the march starts at the ray origin
+    fn intersect(&self, r: &Ray) -> ShapeIntersectionResult {
+        let mut res = [0f32; 4];
+        let mut res_cnt = 0;
+
+        let o = r.get_origin();
+        let d = r.get_direction();
+        let dir_len = Tuple4D::magnitude(d);
+        if dir_len < EPSILON {
+            return (res, res_cnt);
+        }
+        let (mut t, t_max) = match intersect_box(&self.local_bounds(), o, d) {
+            Some((t_min, t_max)) => {
+                let t_min = if t_min == -INFINITY { 0.0 } else { t_min };
+                (t_min, intri_min(t_max, t_min + SDF_MAX_DISTANCE / dir_len))
+            }
+            None => return (res, res_cnt),
+        };
+        let step_factor = 1.0 / self.lipschitz(self.root());
+
+        let mut t_prev = t;
+        let mut inside = self.distance(&Ray::position(r, t)) < 0.0;
+        for _ in 0..SDF_MAX_STEPS {
+            let dist = self.distance(&Ray::position(r, t));
+            if (dist < 0.0) != inside {
+                res[res_cnt] = self.refine(r, t_prev, t, inside);
+                res_cnt += 1;
+                inside = !inside;
+                if res_cnt == 4 {
+                    break;
+                }
+            }
+            if t >= t_max {
+                break;
+            }
+            t_prev = t;
+            t = intri_min(
+                t + intri_max(intri_abs(dist) * step_factor, SDF_MIN_STEP) / dir_len,
+                t_max,
+            );
+        }
+        (res, res_cnt)
+    }
+
+    fn normal_at(&self, world_point: &Tuple4D) -> Tuple4D {
+        let object_point = self.get_inverse_transformation() * world_point;
+        let local_normal = self.local_normal_at(&object_point);
+        let mut world_normal = &Matrix::transpose(self.get_inverse_transformation()) * &local_normal;
+        world_normal.w = 0.0;
+        Tuple4D::normalize(&world_normal)
+    }
+
+    // gradient of the distance with the tetrahedron technique, 4 instead of 6 evaluations
+    fn local_normal_at(&self, local_point: &Tuple4D) -> Tuple4D {
+        let h = SDF_NORMAL_DELTA;
+        let mut n = Tuple4D::new_vector(0.0, 0.0, 0.0);
+        for (x, y, z) in [(1.0, -1.0, -1.0), (-1.0, -1.0, 1.0), (-1.0, 1.0, -1.0), (1.0, 1.0, 1.0)].iter() {
+            let p = Tuple4D::new_point(local_point.x + x * h, local_point.y + y * h, local_point.z + z * h);
+            let dist = self.distance(&p);
+            n.x += x * dist;
+            n.y += y * dist;
+            n.z += z * dist;
+        }
+        n
+    }
+
+    fn local_bounds(&self) -> BoundingBox {
+        self.node_bounds(self.root())
+    }
+
+    fn try_set_transformation(&mut self, m: Matrix) -> Result<(), ShapeError> {
+        self.inverse_transformation_matrix = Matrix::invert(&m).ok_or(ShapeError::NonInvertibleTransform)?;
+        self.transformation_matrix = m;
+        Ok(())
+    }
+
+    fn get_transformation(&self) -> &Matrix {
+        &self.transformation_matrix
+    }
+
+    fn get_inverse_transformation(&self) -> &Matrix {
+        &self.inverse_transformation_matrix
+    }
+
+    fn set_material(&mut self, m: Material) {
+        self.material = m;
+    }
+
+    fn get_material(&self) -> &Material {
+        &self.material
+    }
+
+    fn get_material_mut(&mut self) -> &mut Material {
+        &mut self.material
+    }
+}
+
+impl Sdf {
+    // a unit sphere
+    pub fn new() -> Sdf {
+        Sdf {
+            transformation_matrix: Matrix::new_identity_4x4(),
+            inverse_transformation_matrix: Matrix::new_identity_4x4(),
+            material: Material::new(),
+            nodes: [SDF_UNUSED_NODE; SDF_MAX_NODES],
+            node_cnt: 1,
+        }
+    }
+
+    pub fn try_new(nodes: &[SdfNode]) -> Result<Sdf, ShapeError> {
+        if nodes.len() > SDF_MAX_NODES {
+            return Err(ShapeError::CapacityExceeded(SDF_MAX_NODES));
+        }
+        let mut res = Sdf::new();
+        res.nodes[..nodes.len()].copy_from_slice(nodes);
+        res.node_cnt = nodes.len();
+        res.validate()?;
+        Ok(res)
+    }
+
+    // the evaluation is recursive, so children must come before their parents
+    pub fn validate(&self) -> Result<(), ShapeError> {
+        if self.node_cnt == 0 {
+            return Err(ShapeError::InvalidShape("sdf nodes"));
+        }
+        if self.node_cnt > SDF_MAX_NODES {
+            return Err(ShapeError::CapacityExceeded(SDF_MAX_NODES));
+        }
+        for (idx, node) in self.get_nodes().iter().enumerate() {
+            let valid = match *node {
+                SdfNode::Sphere { .. } | SdfNode::RoundBox { .. } | SdfNode::Torus { .. } => true,
+                SdfNode::Union { a, b } | SdfNode::Intersection { a, b } | SdfNode::Subtraction { a, b } => {
+                    a < idx && b < idx
+                }
+                SdfNode::SmoothUnion { a, b, k } => a < idx && b < idx && k > 0.0,
+                SdfNode::Translate { child, .. } | SdfNode::Twist { child, .. } => child < idx,
+                SdfNode::Repeat { period, child } => {
+                    child < idx && period.x >= 0.0 && period.y >= 0.0 && period.z >= 0.0
+                }
+            };
+            if !valid {
+                return Err(ShapeError::InvalidShape("sdf nodes"));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn get_nodes(&self) -> &[SdfNode] {
+        &self.nodes[..self.node_cnt]
+    }
+
+    // signed distance of a point in object space, negative inside
+    pub fn distance(&self, local_point: &Tuple4D) -> f32 {
+        self.node_distance(self.root(), local_point)
+    }
+
+    fn root(&self) -> usize {
+        self.node_cnt - 1
+    }
+
+    fn node_distance(&self, idx: usize, p: &Tuple4D) -> f32 {
+        match self.nodes[idx] {
+            SdfNode::Sphere { radius } => intri_sqrt(p.x * p.x + p.y * p.y + p.z * p.z) - radius,
+            SdfNode::RoundBox { half_size, radius } => {
+                let qx = intri_abs(p.x) - half_size.x + radius;
+                let qy = intri_abs(p.y) - half_size.y + radius;
+                let qz = intri_abs(p.z) - half_size.z + radius;
+                let outside = intri_sqrt(
+                    intri_powi(intri_max(qx, 0.0), 2)
+                        + intri_powi(intri_max(qy, 0.0), 2)
+                        + intri_powi(intri_max(qz, 0.0), 2),
+                );
+                outside + intri_min(intri_max(qx, intri_max(qy, qz)), 0.0) - radius
+            }
+            SdfNode::Torus {
+                major_radius,
+                minor_radius,
+            } => {
+                let qx = intri_sqrt(p.x * p.x + p.z * p.z) - major_radius;
+                intri_sqrt(qx * qx + p.y * p.y) - minor_radius
+            }
+            SdfNode::Union { a, b } => intri_min(self.node_distance(a, p), self.node_distance(b, p)),
+            SdfNode::Intersection { a, b } => intri_max(self.node_distance(a, p), self.node_distance(b, p)),
+            SdfNode::Subtraction { a, b } => intri_max(self.node_distance(a, p), -self.node_distance(b, p)),
+            SdfNode::SmoothUnion { a, b, k } => {
+                let da = self.node_distance(a, p);
+                let db = self.node_distance(b, p);
+                let h = intri_min(intri_max(0.5 + 0.5 * (db - da) / k, 0.0), 1.0);
+                db + (da - db) * h - k * h * (1.0 - h)
+            }
+            SdfNode::Translate { offset, child } => {
+                let q = Tuple4D::new_point(p.x - offset.x, p.y - offset.y, p.z - offset.z);
+                self.node_distance(child, &q)
+            }
+            SdfNode::Twist { k, child } => {
+                let c = intri_cos(k * p.y);
+                let s = intri_sin(k * p.y);
+                let q = Tuple4D::new_point(c * p.x - s * p.z, p.y, s * p.x + c * p.z);
+                self.node_distance(child, &q)
+            }
+            SdfNode::Repeat { period, child } => {
+                let q = Tuple4D::new_point(repeat(p.x, period.x), repeat(p.y, period.y), repeat(p.z, period.z));
+                self.node_distance(child, &q)
+            }
+        }
+    }
+
+    fn node_bounds(&self, idx: usize) -> BoundingBox {
+        match self.nodes[idx] {
+            SdfNode::Sphere { radius } => symmetric_bounds(radius, radius, radius),
+            SdfNode::RoundBox { half_size, .. } => symmetric_bounds(half_size.x, half_size.y, half_size.z),
+            SdfNode::Torus {
+                major_radius,
+                minor_radius,
+            } => {
+                let r = major_radius + minor_radius;
+                symmetric_bounds(r, minor_radius, r)
+            }
+            SdfNode::Union { a, b } => {
+                let mut res = self.node_bounds(a);
+                res.merge(&self.node_bounds(b));
+                res
+            }
+            SdfNode::Intersection { a, b } => {
+                let (a, b) = (self.node_bounds(a), self.node_bounds(b));
+                BoundingBox::new(
+                    Tuple4D::new_point(
+                        intri_max(a.get_min().x, b.get_min().x),
+                        intri_max(a.get_min().y, b.get_min().y),
+                        intri_max(a.get_min().z, b.get_min().z),
+                    ),
+                    Tuple4D::new_point(
+                        intri_min(a.get_max().x, b.get_max().x),
+                        intri_min(a.get_max().y, b.get_max().y),
+                        intri_min(a.get_max().z, b.get_max().z),
+                    ),
+                )
+            }
+            SdfNode::Subtraction { a, .. } => self.node_bounds(a),
+            SdfNode::SmoothUnion { a, b, k } => {
+                // the fillet adds at most k / 4 to the union
+                let mut res = self.node_bounds(a);
+                res.merge(&self.node_bounds(b));
+                let grow = Tuple4D::new_vector(k / 4.0, k / 4.0, k / 4.0);
+                BoundingBox::new(res.get_min() - &grow, res.get_max() + &grow)
+            }
+            SdfNode::Translate { offset, child } => {
+                let b = self.node_bounds(child);
+                let offset = Tuple4D::new_vector(offset.x, offset.y, offset.z);
+                BoundingBox::new(b.get_min() + &offset, b.get_max() + &offset)
+            }
+            SdfNode::Twist { child, .. } => {
+                let b = self.node_bounds(child);
+                let r = self.twist_radius(&b);
+                BoundingBox::new(
+                    Tuple4D::new_point(-r, b.get_min().y, -r),
+                    Tuple4D::new_point(r, b.get_max().y, r),
+                )
+            }
+            SdfNode::Repeat { period, child } => {
+                let b = self.node_bounds(child);
+                let unbounded = |p: f32, v: f32, inf: f32| if p > 0.0 { inf } else { v };
+                BoundingBox::new(
+                    Tuple4D::new_point(
+                        unbounded(period.x, b.get_min().x, -INFINITY),
+                        unbounded(period.y, b.get_min().y, -INFINITY),
+                        unbounded(period.z, b.get_min().z, -INFINITY),
+                    ),
+                    Tuple4D::new_point(
+                        unbounded(period.x, b.get_max().x, INFINITY),
+                        unbounded(period.y, b.get_max().y, INFINITY),
+                        unbounded(period.z, b.get_max().z, INFINITY),
+                    ),
+                )
+            }
+        }
+    }
+
+    // how much faster than 1 the distance can change. the twist stretches space, the sphere tracing has
+    // to take smaller steps to not step through the surface
+    fn lipschitz(&self, idx: usize) -> f32 {
+        match self.nodes[idx] {
+            SdfNode::Sphere { .. } | SdfNode::RoundBox { .. } | SdfNode::Torus { .. } => 1.0,
+            SdfNode::Union { a, b }
+            | SdfNode::Intersection { a, b }
+            | SdfNode::Subtraction { a, b }
+            | SdfNode::SmoothUnion { a, b, .. } => intri_max(self.lipschitz(a), self.lipschitz(b)),
+            SdfNode::Translate { child, .. } | SdfNode::Repeat { child, .. } => self.lipschitz(child),
+            SdfNode::Twist { k, child } => {
+                // an unbounded child gets a fixed, hopefully small enough step size
+                let r = intri_min(self.twist_radius(&self.node_bounds(child)), 10.0);
+                self.lipschitz(child) * intri_sqrt(1.0 + intri_powi(k * r, 2))
+            }
+        }
+    }
+
+    fn twist_radius(&self, b: &BoundingBox) -> f32 {
+        let x = intri_max(intri_abs(b.get_min().x), intri_abs(b.get_max().x));
+        let z = intri_max(intri_abs(b.get_min().z), intri_abs(b.get_max().z));
+        intri_sqrt(x * x + z * z)
+    }
+
+    // bisection between the last point on one side of the surface and the first one on the other side
+    fn refine(&self, r: &Ray, mut t0: f32, mut t1: f32, inside: bool) -> f32 {
+        for _ in 0..24 {
+            let t = 0.5 * (t0 + t1);
+            if (self.distance(&Ray::position(r, t)) < 0.0) == inside {
+                t0 = t;
+            } else {
+                t1 = t;
+            }
+        }
+        0.5 * (t0 + t1)
+    }
+}
+
+impl fmt::Debug for Sdf {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Sdf")
+            .field("transformation_matrix", &self.transformation_matrix)
+            .field("material", &self.material)
+            .field("nodes", &self.get_nodes())
+            .finish()
+    }
+}
+
+fn repeat(v: f32, period: f32) -> f32 {
+    if period > 0.0 {
+        v - period * intri_floor(v / period + 0.5)
+    } else {
+        v
+    }
+}
+
+fn symmetric_bounds(x: f32, y: f32, z: f32) -> BoundingBox {
+    BoundingBox::new(Tuple4D::new_point(-x, -y, -z), Tuple4D::new_point(x, y, z))
+}
+
+// slab test, the t range can be infinite for unbounded boxes
+fn intersect_box(b: &BoundingBox, o: &Tuple4D, d: &Tuple4D) -> Option<(f32, f32)> {
+    if b.is_empty() {
+        return None;
+    }
+    let mut t_min = -INFINITY;
+    let mut t_max = INFINITY;
+    for (origin, direction, min, max) in [
+        (o.x, d.x, b.get_min().x, b.get_max().x),
+        (o.y, d.y, b.get_min().y, b.get_max().y),
+        (o.z, d.z, b.get_min().z, b.get_max().z),
+    ]
+    .iter()
+    {
+        if intri_abs(*direction) < EPSILON {
+            if origin < min || origin > max {
+                return None;
+            }
+            continue;
+        }
+        let t0 = (min - origin) / direction;
+        let t1 = (max - origin) / direction;
+        t_min = intri_max(t_min, intri_min(t0, t1));
+        t_max = intri_min(t_max, intri_max(t0, t1));
+    }
+    if t_min > t_max || t_max < 0.0 {
+        return None;
+    }
+    Some((t_min, t_max))
+}
+
+// serde only implements arrays up to 32 elements, and most of the array is unused. the trailing unused
+// nodes are not written. node_cnt is serialized as well, so trimming a used node that equals the unused one
+// doesn't change the shape
+#[cfg(feature = "use_serde")]
+mod nodes_serde {
+    use core::fmt;
+
+    use serde::de::{Error, SeqAccess, Visitor};
+    use serde::{Deserializer, Serializer};
+
+    use super::{SdfNode, SDF_MAX_NODES, SDF_UNUSED_NODE};
+
+    pub fn serialize<S: Serializer>(nodes: &[SdfNode; SDF_MAX_NODES], s: S) -> Result<S::Ok, S::Error> {
+        let len = nodes.iter().rposition(|n| *n != SDF_UNUSED_NODE).map_or(0, |i| i + 1);
+        s.collect_seq(nodes[..len].iter())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<[SdfNode; SDF_MAX_NODES], D::Error> {
+        d.deserialize_seq(NodesVisitor)
+    }
+
+    struct NodesVisitor;
+
+    impl<'de> Visitor<'de> for NodesVisitor {
+        type Value = [SdfNode; SDF_MAX_NODES];
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "at most {} sdf nodes", SDF_MAX_NODES)
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            let mut res = [SDF_UNUSED_NODE; SDF_MAX_NODES];
+            let mut len = 0;
+            while let Some(n) = seq.next_element()? {
+                if len == SDF_MAX_NODES {
+                    return Err(A::Error::invalid_length(len + 1, &self));
+                }
+                res[len] = n;
+                len += 1;
+            }
+            Ok(res)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::f32::consts::PI;
+
+    use crate::basics::ray::RayOps;
+    use crate::math::common::{assert_float, assert_tuple};
+
+    use super::*;
+
+    fn test_ray_sdf_intersection_helper(sdf: &Sdf, origin: Tuple4D, direction: Tuple4D, expected: &[f32]) {
+        let r = Ray::new(origin, direction);
+        let (xs, cnt_hits) = sdf.intersect(&r);
+
+        assert_eq!(cnt_hits, expected.len());
+        for (actual, expected) in xs.iter().zip(expected.iter()) {
+            assert_float(*actual, *expected);
+        }
+    }
+
+    #[test]
+    fn test_ray_sdf_sphere() {
+        let sdf = Sdf::new();
+        let origin = Tuple4D::new_point(0.0, 0.0, -5.0);
+        test_ray_sdf_intersection_helper(&sdf, origin, Tuple4D::new_vector(0.0, 0.0, 1.0), &[4.0, 6.0]);
+        // the direction of a transformed ray isn't normalized
+        test_ray_sdf_intersection_helper(&sdf, origin, Tuple4D::new_vector(0.0, 0.0, 2.0), &[2.0, 3.0]);
+        // inside
+        let origin = Tuple4D::new_point(0.0, 0.0, 0.0);
+        test_ray_sdf_intersection_helper(&sdf, origin, Tuple4D::new_vector(0.0, 0.0, 1.0), &[-1.0, 1.0]);
+        let origin = Tuple4D::new_point(0.0, 2.0, -5.0);
+        test_ray_sdf_intersection_helper(&sdf, origin, Tuple4D::new_vector(0.0, 0.0, 1.0), &[]);
+
+        let n = sdf.normal_at(&Tuple4D::new_point(0.0, 0.0, -1.0));
+        assert_tuple(&n, &Tuple4D::new_vector(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn test_ray_sdf_subtraction() {
+        // a unit cube with rounded edges and a hole through the middle
+        let sdf = Sdf::try_new(&[
+            SdfNode::RoundBox {
+                half_size: Tuple4D::new_vector(1.0, 1.0, 1.0),
+                radius: 0.1,
+            },
+            SdfNode::Sphere { radius: 0.5 },
+            SdfNode::Subtraction { a: 0, b: 1 },
+        ])
+        .unwrap();
+
+        let direction = Tuple4D::new_vector(0.0, 0.0, 1.0);
+        let origin = Tuple4D::new_point(0.0, 0.0, -5.0);
+        test_ray_sdf_intersection_helper(&sdf, origin, direction, &[4.0, 4.5, 5.5, 6.0]);
+        let origin = Tuple4D::new_point(0.8, 0.0, -5.0);
+        test_ray_sdf_intersection_helper(&sdf, origin, direction, &[4.0, 6.0]);
+
+        let n = sdf.normal_at(&Tuple4D::new_point(0.0, 0.0, -0.5));
+        assert_tuple(&n, &Tuple4D::new_vector(0.0, 0.0, 1.0));
+        assert_eq!(sdf.local_bounds().get_max(), &Tuple4D::new_point(1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn test_ray_sdf_twist_and_repeat() {
+        // a thin box rotated by 90 degrees at the top
+        let sdf = Sdf::try_new(&[
+            SdfNode::RoundBox {
+                half_size: Tuple4D::new_vector(0.5, 2.0, 0.1),
+                radius: 0.0,
+            },
+            SdfNode::Twist { k: PI / 4.0, child: 0 },
+        ])
+        .unwrap();
+        let direction = Tuple4D::new_vector(0.0, 0.0, 1.0);
+        test_ray_sdf_intersection_helper(&sdf, Tuple4D::new_point(0.0, 0.0, -5.0), direction, &[4.9, 5.1]);
+        // at y = 1.9 the box is rotated by 85.5 degrees
+        let half_width = 0.5 / intri_sin(1.9 * PI / 4.0);
+        test_ray_sdf_intersection_helper(
+            &sdf,
+            Tuple4D::new_point(0.0, 1.9, -5.0),
+            direction,
+            &[5.0 - half_width, 5.0 + half_width],
+        );
+
+        // spheres at x = ..., -2, 0, 2, 4, ... the march starts at the origin of the ray
+        let sdf = Sdf::try_new(&[
+            SdfNode::Sphere { radius: 0.25 },
+            SdfNode::Repeat {
+                period: Tuple4D::new_vector(2.0, 0.0, 0.0),
+                child: 0,
+            },
+        ])
+        .unwrap();
+        assert!(sdf.local_bounds().is_infinite());
+        let origin = Tuple4D::new_point(1.0, 0.0, 0.0);
+        test_ray_sdf_intersection_helper(
+            &sdf,
+            origin,
+            Tuple4D::new_vector(1.0, 0.0, 0.0),
+            &[0.75, 1.25, 2.75, 3.25],
+        );
+    }
+
+    #[test]
+    fn test_sdf_smooth_union() {
+        let nodes = [
+            SdfNode::Sphere { radius: 1.0 },
+            SdfNode::Translate {
+                offset: Tuple4D::new_vector(2.0, 0.0, 0.0),
+                child: 0,
+            },
+            SdfNode::SmoothUnion { a: 0, b: 1, k: 0.5 },
+        ];
+        let sdf = Sdf::try_new(&nodes).unwrap();
+        // the spheres touch at x = 1, the fillet fills the gap around it
+        assert!(sdf.distance(&Tuple4D::new_point(1.0, 0.2, 0.0)) < 0.0);
+        assert_float(sdf.distance(&Tuple4D::new_point(-2.0, 0.0, 0.0)), 1.0);
+        assert_eq!(
+            sdf.local_bounds().get_min(),
+            &Tuple4D::new_point(-1.125, -1.125, -1.125)
+        );
+        assert_eq!(sdf.local_bounds().get_max(), &Tuple4D::new_point(3.125, 1.125, 1.125));
+    }
+
+    #[test]
+    fn test_sdf_try_new_rejects_invalid_nodes() {
+        // a child after its parent
+        let nodes = [SdfNode::Union { a: 0, b: 1 }, SdfNode::Sphere { radius: 1.0 }];
+        assert_eq!(Sdf::try_new(&nodes), Err(ShapeError::InvalidShape("sdf nodes")));
+        assert_eq!(Sdf::try_new(&[]), Err(ShapeError::InvalidShape("sdf nodes")));
+        let too_many = [SdfNode::Sphere { radius: 1.0 }; SDF_MAX_NODES + 1];
+        assert_eq!(
+            Sdf::try_new(&too_many),
+            Err(ShapeError::CapacityExceeded(SDF_MAX_NODES))
+        );
+    }
+}
@@ -5,10 +5,30 @@ use serde::{Deserialize, Serialize};
 
 use math::prelude::*;
 
-use crate::{Cube, Cylinder, Material, Plane, Ray, Sphere, Triangle};
+use crate::{
+    BoundingBox, BoundingBoxOps, Cube, Cylinder, Disk, Heightfield, Instance, Material, MaterialOps, Plane, Ray, Rectangle,
+    Sdf, ShapeError, Sphere, Torus, Triangle,
+};
 
 pub type ShapeIdx = usize;
-pub type ShapeIntersectionResult = ([f32; 4], usize);
+// max. number of intersections of a ray with a single shape, e.g. a torus
+pub const SHAPE_MAX_INTERSECTIONS: usize = 4;
+pub type ShapeIntersectionResult = ([f32; SHAPE_MAX_INTERSECTIONS], usize);
+
+// names of the variants of ShapeEnum, indexed by Shape::get_type_idx
+pub const SHAPE_TYPE_NAMES: [&str; 11] = [
+    "sphere",
+    "plane",
+    "cube",
+    "cylinder",
+    "triangle",
+    "torus",
+    "disk",
+    "rectangle",
+    "heightfield",
+    "sdf",
+    "instance",
+];
 
 #[derive(Clone, Debug, PartialEq)]
 #[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
@@ -19,6 +39,12 @@ pub enum ShapeEnum {
     Cube(Cube),
     Cylinder(Cylinder),
     Triangle(Triangle),
+    Torus(Torus),
+    Disk(Disk),
+    Rectangle(Rectangle),
+    Heightfield(Heightfield),
+    Sdf(Sdf),
+    Instance(Instance),
     //  Group(Group),
 }
 
@@ -29,6 +55,9 @@ pub struct Shape {
     shape: ShapeEnum,
     parent: Option<ShapeIdx>,
     casts_shadow: bool,
+    // prototypes are only rendered through an Instance
+    #[cfg_attr(feature = "use_serde", serde(default))]
+    is_prototype: bool,
 }
 
 pub trait ShapeOps {
@@ -39,13 +68,28 @@ pub trait ShapeOps {
     // TODO: intersect and normal_at are individual implementatiosn for each shape
     // but the setters / getters are all identical for all shapes, groups, CSG (if ever implemented)
     // move to a "BaseShape" and make a compose struct of a BaseShape and the individual componentes
-    fn set_transformation(&mut self, m: Matrix);
+    fn try_set_transformation(&mut self, m: Matrix) -> Result<(), ShapeError>;
     fn get_transformation(&self) -> &Matrix;
     fn get_inverse_transformation(&self) -> &Matrix;
 
     fn set_material(&mut self, m: Material);
     fn get_material(&self) -> &Material;
     fn get_material_mut(&mut self) -> &mut Material;
+
+    // panics if m is not invertible - use try_set_transformation for matrices from user input
+    fn set_transformation(&mut self, m: Matrix) {
+        if let Err(e) = self.try_set_transformation(m) {
+            panic!("set_transformation: {}", e);
+        }
+    }
+
+    // bounding box in object space
+    fn local_bounds(&self) -> BoundingBox;
+
+    // bounding box in world space
+    fn bounds(&self) -> BoundingBox {
+        self.local_bounds().transform(self.get_transformation())
+    }
 }
 
 impl ShapeOps for Shape {
@@ -56,6 +100,12 @@ impl ShapeOps for Shape {
             ShapeEnum::Cube(ref cube) => cube.intersect(r),
             ShapeEnum::Cylinder(ref cylinder) => cylinder.intersect(r),
             ShapeEnum::Triangle(ref triangle) => triangle.intersect(r),
+            ShapeEnum::Torus(ref torus) => torus.intersect(r),
+            ShapeEnum::Disk(ref disk) => disk.intersect(r),
+            ShapeEnum::Rectangle(ref rectangle) => rectangle.intersect(r),
+            ShapeEnum::Heightfield(ref heightfield) => heightfield.intersect(r),
+            ShapeEnum::Sdf(ref sdf) => sdf.intersect(r),
+            ShapeEnum::Instance(ref instance) => instance.intersect(r),
             // ShapeEnum::Group(_) => panic!("Group::normal_at should never be called "),
         }
     }
@@ -68,6 +118,12 @@ impl ShapeOps for Shape {
             ShapeEnum::Cube(ref cube) => cube.local_normal_at(&object_point),
             ShapeEnum::Cylinder(ref cylinder) => cylinder.local_normal_at(&object_point),
             ShapeEnum::Triangle(ref triangle) => triangle.local_normal_at(&object_point),
+            ShapeEnum::Torus(ref torus) => torus.local_normal_at(&object_point),
+            ShapeEnum::Disk(ref disk) => disk.local_normal_at(&object_point),
+            ShapeEnum::Rectangle(ref rectangle) => rectangle.local_normal_at(&object_point),
+            ShapeEnum::Heightfield(ref heightfield) => heightfield.local_normal_at(&object_point),
+            ShapeEnum::Sdf(ref sdf) => sdf.local_normal_at(&object_point),
+            ShapeEnum::Instance(ref instance) => instance.local_normal_at(&object_point),
             // ShapeEnum::Group(_) => panic!("Group::normal_at should never be called "),
         };
         let mut world_normal = &Matrix::transpose(self.get_inverse_transformation()) * &local_normal;
@@ -79,15 +135,37 @@ impl ShapeOps for Shape {
         unreachable!("should never get here ");
     }
 
-    fn set_transformation(&mut self, m: Matrix) {
+    fn local_bounds(&self) -> BoundingBox {
+        match self.shape {
+            ShapeEnum::Sphere(ref s) => s.local_bounds(),
+            ShapeEnum::Plane(ref plane) => plane.local_bounds(),
+            ShapeEnum::Cube(ref cube) => cube.local_bounds(),
+            ShapeEnum::Cylinder(ref cylinder) => cylinder.local_bounds(),
+            ShapeEnum::Triangle(ref triangle) => triangle.local_bounds(),
+            ShapeEnum::Torus(ref torus) => torus.local_bounds(),
+            ShapeEnum::Disk(ref disk) => disk.local_bounds(),
+            ShapeEnum::Rectangle(ref rectangle) => rectangle.local_bounds(),
+            ShapeEnum::Heightfield(ref heightfield) => heightfield.local_bounds(),
+            ShapeEnum::Sdf(ref sdf) => sdf.local_bounds(),
+            ShapeEnum::Instance(ref instance) => instance.local_bounds(),
+        }
+    }
+
+    fn try_set_transformation(&mut self, m: Matrix) -> Result<(), ShapeError> {
         match self.shape {
-            ShapeEnum::Sphere(ref mut sphere) => sphere.set_transformation(m),
-            ShapeEnum::Plane(ref mut plane) => plane.set_transformation(m),
-            ShapeEnum::Cube(ref mut cube) => cube.set_transformation(m),
-            ShapeEnum::Cylinder(ref mut cylinder) => cylinder.set_transformation(m),
-            ShapeEnum::Triangle(ref mut triangle) => triangle.set_transformation(m),
+            ShapeEnum::Sphere(ref mut sphere) => sphere.try_set_transformation(m),
+            ShapeEnum::Plane(ref mut plane) => plane.try_set_transformation(m),
+            ShapeEnum::Cube(ref mut cube) => cube.try_set_transformation(m),
+            ShapeEnum::Cylinder(ref mut cylinder) => cylinder.try_set_transformation(m),
+            ShapeEnum::Triangle(ref mut triangle) => triangle.try_set_transformation(m),
+            ShapeEnum::Torus(ref mut torus) => torus.try_set_transformation(m),
+            ShapeEnum::Disk(ref mut disk) => disk.try_set_transformation(m),
+            ShapeEnum::Rectangle(ref mut rectangle) => rectangle.try_set_transformation(m),
+            ShapeEnum::Heightfield(ref mut heightfield) => heightfield.try_set_transformation(m),
+            ShapeEnum::Sdf(ref mut sdf) => sdf.try_set_transformation(m),
+            ShapeEnum::Instance(ref mut instance) => instance.try_set_transformation(m),
             // ShapeEnum::Group(ref mut group) => group.set_transformation(m),
-        };
+        }
     }
 
     fn get_transformation(&self) -> &Matrix {
@@ -97,6 +175,12 @@ impl ShapeOps for Shape {
             ShapeEnum::Cube(ref c) => c.get_transformation(),
             ShapeEnum::Cylinder(ref cylinder) => cylinder.get_transformation(),
             ShapeEnum::Triangle(ref triangle) => triangle.get_transformation(),
+            ShapeEnum::Torus(ref torus) => torus.get_transformation(),
+            ShapeEnum::Disk(ref disk) => disk.get_transformation(),
+            ShapeEnum::Rectangle(ref rectangle) => rectangle.get_transformation(),
+            ShapeEnum::Heightfield(ref heightfield) => heightfield.get_transformation(),
+            ShapeEnum::Sdf(ref sdf) => sdf.get_transformation(),
+            ShapeEnum::Instance(ref instance) => instance.get_transformation(),
             // ShapeEnum::Group(ref group) => group.get_transformation(),
         }
     }
@@ -108,6 +192,12 @@ impl ShapeOps for Shape {
             ShapeEnum::Cube(ref c) => c.get_inverse_transformation(),
             ShapeEnum::Cylinder(ref cylinder) => cylinder.get_inverse_transformation(),
             ShapeEnum::Triangle(ref triangle) => triangle.get_inverse_transformation(),
+            ShapeEnum::Torus(ref torus) => torus.get_inverse_transformation(),
+            ShapeEnum::Disk(ref disk) => disk.get_inverse_transformation(),
+            ShapeEnum::Rectangle(ref rectangle) => rectangle.get_inverse_transformation(),
+            ShapeEnum::Heightfield(ref heightfield) => heightfield.get_inverse_transformation(),
+            ShapeEnum::Sdf(ref sdf) => sdf.get_inverse_transformation(),
+            ShapeEnum::Instance(ref instance) => instance.get_inverse_transformation(),
             // ShapeEnum::Group(ref group) => group.get_inverse_transformation(),
         }
     }
@@ -119,6 +209,12 @@ impl ShapeOps for Shape {
             ShapeEnum::Cube(ref mut c) => c.set_material(m),
             ShapeEnum::Cylinder(ref mut cylinder) => cylinder.set_material(m),
             ShapeEnum::Triangle(ref mut triangle) => triangle.set_material(m),
+            ShapeEnum::Torus(ref mut torus) => torus.set_material(m),
+            ShapeEnum::Disk(ref mut disk) => disk.set_material(m),
+            ShapeEnum::Rectangle(ref mut rectangle) => rectangle.set_material(m),
+            ShapeEnum::Heightfield(ref mut heightfield) => heightfield.set_material(m),
+            ShapeEnum::Sdf(ref mut sdf) => sdf.set_material(m),
+            ShapeEnum::Instance(ref mut instance) => instance.set_material(m),
             // ShapeEnum::Group(ref mut group) => group.set_material(m),
         };
     }
@@ -130,6 +226,12 @@ impl ShapeOps for Shape {
             ShapeEnum::Cube(ref c) => c.get_material(),
             ShapeEnum::Cylinder(ref cylinder) => cylinder.get_material(),
             ShapeEnum::Triangle(ref triangle) => triangle.get_material(),
+            ShapeEnum::Torus(ref torus) => torus.get_material(),
+            ShapeEnum::Disk(ref disk) => disk.get_material(),
+            ShapeEnum::Rectangle(ref rectangle) => rectangle.get_material(),
+            ShapeEnum::Heightfield(ref heightfield) => heightfield.get_material(),
+            ShapeEnum::Sdf(ref sdf) => sdf.get_material(),
+            ShapeEnum::Instance(ref instance) => instance.get_material(),
             // ShapeEnum::Group(_) => panic!("Group::get_material should never be called "),
         }
     }
@@ -141,6 +243,12 @@ impl ShapeOps for Shape {
             ShapeEnum::Cube(ref mut c) => c.get_material_mut(),
             ShapeEnum::Cylinder(ref mut cylinder) => cylinder.get_material_mut(),
             ShapeEnum::Triangle(ref mut triangle) => triangle.get_material_mut(),
+            ShapeEnum::Torus(ref mut torus) => torus.get_material_mut(),
+            ShapeEnum::Disk(ref mut disk) => disk.get_material_mut(),
+            ShapeEnum::Rectangle(ref mut rectangle) => rectangle.get_material_mut(),
+            ShapeEnum::Heightfield(ref mut heightfield) => heightfield.get_material_mut(),
+            ShapeEnum::Sdf(ref mut sdf) => sdf.get_material_mut(),
+            ShapeEnum::Instance(ref mut instance) => instance.get_material_mut(),
             // ShapeEnum::Group(_) => panic!("Group::get_material should never be called "),
         }
     }
@@ -152,13 +260,52 @@ impl Shape {
             shape,
             parent: None,
             casts_shadow: true,
+            is_prototype: false,
         }
     }
 
+    // like new(), but checks the transformation and the material of the shape
+    pub fn try_new(shape: ShapeEnum) -> Result<Shape, ShapeError> {
+        let shape = Shape::new(shape);
+        shape.validate()?;
+        Ok(shape)
+    }
+
+    pub fn validate(&self) -> Result<(), ShapeError> {
+        if Matrix::invert(self.get_transformation()).is_none() {
+            return Err(ShapeError::NonInvertibleTransform);
+        }
+        if let Some(p) = self.get_material().get_pattern() {
+            if Matrix::invert(p.get_transformation()).is_none() {
+                return Err(ShapeError::NonInvertibleTransform);
+            }
+        }
+        if let ShapeEnum::Sdf(ref sdf) = self.shape {
+            sdf.validate()?;
+        }
+        self.get_material().validate()
+    }
+
     pub fn get_shape(&self) -> &ShapeEnum {
         &self.shape
     }
 
+    pub fn get_type_idx(&self) -> usize {
+        match self.shape {
+            ShapeEnum::Sphere(_) => 0,
+            ShapeEnum::Plane(_) => 1,
+            ShapeEnum::Cube(_) => 2,
+            ShapeEnum::Cylinder(_) => 3,
+            ShapeEnum::Triangle(_) => 4,
+            ShapeEnum::Torus(_) => 5,
+            ShapeEnum::Disk(_) => 6,
+            ShapeEnum::Rectangle(_) => 7,
+            ShapeEnum::Heightfield(_) => 8,
+            ShapeEnum::Sdf(_) => 9,
+            ShapeEnum::Instance(_) => 10,
+        }
+    }
+
     pub fn get_casts_shadow(&self) -> bool {
         self.casts_shadow
     }
@@ -166,6 +313,88 @@ impl Shape {
     pub fn set_casts_shadow(&mut self, casts_shadow: bool) {
         self.casts_shadow = casts_shadow;
     }
+
+    pub fn get_is_prototype(&self) -> bool {
+        self.is_prototype
+    }
+
+    pub fn set_is_prototype(&mut self, is_prototype: bool) {
+        self.is_prototype = is_prototype;
+    }
+
+    // the heights of a heightfield and the texels of a normal map are in the sample buffer of the world. like
+    // the prototypes of an instance, they can only be checked together with it
+    pub fn validate_samples(&self, samples: &[f32]) -> Result<(), ShapeError> {
+        if let ShapeEnum::Heightfield(ref heightfield) = self.shape {
+            heightfield.validate(samples)?;
+        }
+        match self.get_material().get_normal_perturbation() {
+            Some(p) => p.validate_samples(samples),
+            None => Ok(()),
+        }
+    }
+
+    // like intersect, with the sample buffer of the world for heightfields
+    pub fn intersect_samples(&self, samples: &[f32], r: &Ray) -> ShapeIntersectionResult {
+        match self.shape {
+            ShapeEnum::Heightfield(ref heightfield) => heightfield.intersect_samples(samples, r),
+            _ => self.intersect(r),
+        }
+    }
+
+    // like normal_at, with the sample buffer of the world for heightfields
+    pub fn normal_at_samples(&self, samples: &[f32], world_point: &Tuple4D) -> Tuple4D {
+        let heightfield = match self.shape {
+            ShapeEnum::Heightfield(ref heightfield) => heightfield,
+            _ => return self.normal_at(world_point),
+        };
+        let object_point = self.get_inverse_transformation() * world_point;
+        let local_normal = heightfield.local_normal_at_samples(samples, &object_point);
+        let mut world_normal = &Matrix::transpose(self.get_inverse_transformation()) * &local_normal;
+        world_normal.w = 0.0;
+        Tuple4D::normalize(&world_normal)
+    }
+
+    // the shape with the material of a hit and the point to look up its pattern with. hits on the prototype
+    // of an instance use the material of the prototype, unless the instance overrides it
+    pub fn material_source<'a>(&'a self, prototype: Option<&'a Shape>, world_point: &Tuple4D) -> (&'a Shape, Tuple4D) {
+        match (&self.shape, prototype) {
+            (ShapeEnum::Instance(ref instance), Some(prototype)) if !instance.get_override_material() => {
+                (prototype, instance.get_inverse_transformation() * world_point)
+            }
+            _ => (self, *world_point),
+        }
+    }
+
+    // the normal to shade a hit with: the geometric world_normal, bent by the normal perturbation of the
+    // material. the perturbation is evaluated in the object space of the hit shape or prototype
+    pub fn perturbed_normal(
+        &self,
+        prototype: Option<&Shape>,
+        samples: &[f32],
+        world_point: &Tuple4D,
+        world_normal: &Tuple4D,
+    ) -> Tuple4D {
+        let (source, _) = self.material_source(prototype, world_point);
+        let perturbation = match source.get_material().get_normal_perturbation() {
+            Some(p) => p,
+            None => return *world_normal,
+        };
+        let (m, m_inv) = match (&self.shape, prototype) {
+            (ShapeEnum::Instance(ref instance), Some(prototype)) => (
+                instance.get_transformation() * prototype.get_transformation(),
+                prototype.get_inverse_transformation() * instance.get_inverse_transformation(),
+            ),
+            _ => (self.get_transformation().clone(), self.get_inverse_transformation().clone()),
+        };
+        let object_point = &m_inv * world_point;
+        let mut object_normal = &Matrix::transpose(&m) * world_normal;
+        object_normal.w = 0.0;
+        let n = perturbation.perturb(samples, &object_point, &Tuple4D::normalize(&object_normal));
+        let mut res = &Matrix::transpose(&m_inv) * &n;
+        res.w = 0.0;
+        Tuple4D::normalize(&res)
+    }
 }
 
 impl fmt::Debug for Shape {
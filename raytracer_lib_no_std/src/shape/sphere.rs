@@ -3,13 +3,16 @@ use serde::{Deserialize, Serialize};
 
 use math::prelude::*;
 
-use crate::{Material, MaterialOps, Ray, RayOps, ShapeIntersectionResult, ShapeOps};
+use crate::{
+    BoundingBox, BoundingBoxOps, Material, MaterialOps, Ray, RayOps, ShapeError, ShapeIntersectionResult, ShapeOps,
+};
 
 #[derive(Clone, Debug, PartialEq)]
 #[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
 #[cfg_attr(feature = "cuda", derive(DeviceCopy))]
 pub struct Sphere {
     transformation_matrix: Matrix,
+    #[cfg_attr(feature = "use_serde", serde(skip, default = "Matrix::new_identity_4x4"))]
     inverse_transformation_matrix: Matrix,
     material: Material,
 }
@@ -38,10 +41,10 @@ impl ShapeOps for Sphere {
         (res, res_cnt)
     }
 
-    fn set_transformation(&mut self, m: Matrix) {
-        self.inverse_transformation_matrix =
-            Matrix::invert(&m).expect("Sphere::set_transofrmation:  cant unwrap inverted matrix ");
+    fn try_set_transformation(&mut self, m: Matrix) -> Result<(), ShapeError> {
+        self.inverse_transformation_matrix = Matrix::invert(&m).ok_or(ShapeError::NonInvertibleTransform)?;
         self.transformation_matrix = m;
+        Ok(())
     }
 
     fn get_transformation(&self) -> &Matrix {
@@ -66,6 +69,10 @@ impl ShapeOps for Sphere {
         local_point - &o
     }
 
+    fn local_bounds(&self) -> BoundingBox {
+        BoundingBox::new(Tuple4D::new_point(-1.0, -1.0, -1.0), Tuple4D::new_point(1.0, 1.0, 1.0))
+    }
+
     fn set_material(&mut self, m: Material) {
         self.material = m;
     }
@@ -205,6 +212,20 @@ mod tests {
         assert_matrix(&s.get_inverse_transformation(), &m_inv);
     }
 
+    #[test]
+    fn test_sphere_non_invertible_transformation() {
+        let mut s = Sphere::new();
+        let res = s.try_set_transformation(Matrix::scale(1.0, 0.0, 1.0));
+        assert_eq!(res, Err(ShapeError::NonInvertibleTransform));
+        // the shape is unchanged
+        assert_matrix(&s.get_transformation(), &Matrix::new_identity_4x4());
+
+        let mut shape = Shape::new(ShapeEnum::Sphere(Sphere::new()));
+        shape.get_material_mut().set_transparency(-1.0);
+        assert_eq!(shape.validate(), Err(ShapeError::InvalidMaterial("transparency")));
+        assert!(Shape::try_new(ShapeEnum::Sphere(Sphere::new())).is_ok());
+    }
+
     // page 69 bottom
     #[test]
     fn test_sphere_scale() {
@@ -225,7 +246,7 @@ mod tests {
             raytracer_lib_no_std::shape::shape::Shape::new(raytracer_lib_no_std::shape::shape::ShapeEnum::Sphere(s));
         let shapes = vec![sphere_shape];
 
-        let is = Intersection::intersect(0, &r, &shapes);
+        let is = Intersection::intersect(0, &r, &shapes, &vec![]);
 
         let intersections = is.get_intersections();
 
@@ -257,7 +278,7 @@ mod tests {
             raytracer_lib_no_std::shape::shape::Shape::new(raytracer_lib_no_std::shape::shape::ShapeEnum::Sphere(s));
         let shapes = vec![sphere_shape];
 
-        let is = Intersection::intersect(0, &r, &shapes);
+        let is = Intersection::intersect(0, &r, &shapes, &vec![]);
 
         let intersections = is.get_intersections();
         assert_eq!(is.len(), 0);
@@ -382,7 +403,14 @@ mod tests {
         xs.push(Intersection::new(5.25, 2));
         xs.push(Intersection::new(6.0, 0));
 
-        let comps = Intersection::prepare_computations(&xs.get_intersections()[index], &r, &xs, &shapes);
+        let comps = Intersection::prepare_computations(
+            &xs.get_intersections()[index],
+            &r,
+            &xs,
+            &shapes,
+            &vec![],
+            EPSILON_OVER_UNDER,
+        );
 
         println!("n1 = {}   n1_expected = {}", comps.get_n1(), n1_expected);
         println!("n2 = {}   n2_expected = {}", comps.get_n2(), n2_expected);
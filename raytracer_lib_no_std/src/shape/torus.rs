@@ -0,0 +1,238 @@
+#[cfg(feature = "use_serde")]
+use serde::{Deserialize, Serialize};
+
+use math::prelude::*;
+
+use crate::{
+    BoundingBox, BoundingBoxOps, Material, MaterialOps, Ray, RayOps, ShapeError, ShapeIntersectionResult, ShapeOps,
+};
+
+// the y axis goes through the hole, the center of the tube is a circle in the xz plane
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "cuda", derive(DeviceCopy))]
+pub struct Torus {
+    transformation_matrix: Matrix,
+    #[cfg_attr(feature = "use_serde", serde(skip, default = "Matrix::new_identity_4x4"))]
+    inverse_transformation_matrix: Matrix,
+    material: Material,
+    // radius of the circle through the center of the tube
+    major_radius: f32,
+    // radius of the tube
+    minor_radius: f32,
+}
+
+impl ShapeOps for Torus {
+    fn intersect(&self, r: &Ray) -> ShapeIntersectionResult {
+        let mut res = [0f32; 4];
+        let mut res_cnt = 0;
+
+        let d = r.get_direction();
+        let (dx, dy, dz) = (d.x as f64, d.y as f64, d.z as f64);
+        let dd = dx * dx + dy * dy + dz * dz;
+        if dd == 0.0 {
+            return (res, res_cnt);
+        }
+
+        // start at the point of the ray closest to the center, the quartic is badly conditioned
+        // for an origin far away from the torus
+        let o = r.get_origin();
+        let t_shift = -(o.x as f64 * dx + o.y as f64 * dy + o.z as f64 * dz) / dd;
+        let ox = o.x as f64 + t_shift * dx;
+        let oy = o.y as f64 + t_shift * dy;
+        let oz = o.z as f64 + t_shift * dz;
+
+        // (|p|^2 + R^2 - r^2)^2 = 4 R^2 (p.x^2 + p.z^2) for p = o + t * d
+        let major2 = self.major_radius as f64 * self.major_radius as f64;
+        let minor2 = self.minor_radius as f64 * self.minor_radius as f64;
+        let od = ox * dx + oy * dy + oz * dz;
+        let k = ox * ox + oy * oy + oz * oz + major2 - minor2;
+        let c = [
+            k * k - 4.0 * major2 * (ox * ox + oz * oz),
+            4.0 * od * k - 8.0 * major2 * (ox * dx + oz * dz),
+            2.0 * dd * k + 4.0 * od * od - 4.0 * major2 * (dx * dx + dz * dz),
+            4.0 * dd * od,
+            dd * dd,
+        ];
+
+        let (t, t_cnt) = solve_quartic(&c);
+        for i in 0..t_cnt {
+            res[res_cnt] = (t[i] + t_shift) as f32;
+            res_cnt += 1;
+        }
+        (res, res_cnt)
+    }
+
+    fn normal_at(&self, world_point: &Tuple4D) -> Tuple4D {
+        let object_point = self.get_inverse_transformation() * world_point;
+        let local_normal = self.local_normal_at(&object_point);
+        let mut world_normal = &Matrix::transpose(self.get_inverse_transformation()) * &local_normal;
+        world_normal.w = 0.0;
+        Tuple4D::normalize(&world_normal)
+    }
+
+    // points away from the closest point on the center circle of the tube
+    fn local_normal_at(&self, local_point: &Tuple4D) -> Tuple4D {
+        let dist = intri_sqrt(intri_powi(local_point.x, 2) + intri_powi(local_point.z, 2));
+        if dist < EPSILON {
+            // only possible for a minor radius >= major radius
+            let y = if local_point.y < 0.0 { -1.0 } else { 1.0 };
+            return Tuple4D::new_vector(0.0, y, 0.0);
+        }
+        let scale = 1.0 - self.major_radius / dist;
+        Tuple4D::new_vector(local_point.x * scale, local_point.y, local_point.z * scale)
+    }
+
+    fn local_bounds(&self) -> BoundingBox {
+        let r = self.major_radius + self.minor_radius;
+        BoundingBox::new(
+            Tuple4D::new_point(-r, -self.minor_radius, -r),
+            Tuple4D::new_point(r, self.minor_radius, r),
+        )
+    }
+
+    fn try_set_transformation(&mut self, m: Matrix) -> Result<(), ShapeError> {
+        self.inverse_transformation_matrix = Matrix::invert(&m).ok_or(ShapeError::NonInvertibleTransform)?;
+        self.transformation_matrix = m;
+        Ok(())
+    }
+
+    fn get_transformation(&self) -> &Matrix {
+        &self.transformation_matrix
+    }
+
+    fn get_inverse_transformation(&self) -> &Matrix {
+        &self.inverse_transformation_matrix
+    }
+
+    fn set_material(&mut self, m: Material) {
+        self.material = m;
+    }
+
+    fn get_material(&self) -> &Material {
+        &self.material
+    }
+
+    fn get_material_mut(&mut self) -> &mut Material {
+        &mut self.material
+    }
+}
+
+impl Torus {
+    pub fn new() -> Torus {
+        Torus {
+            transformation_matrix: Matrix::new_identity_4x4(),
+            inverse_transformation_matrix: Matrix::new_identity_4x4(),
+            material: Material::new(),
+            major_radius: 1.0,
+            minor_radius: 0.25,
+        }
+    }
+
+    pub fn get_major_radius(&self) -> f32 {
+        self.major_radius
+    }
+
+    pub fn get_minor_radius(&self) -> f32 {
+        self.minor_radius
+    }
+
+    pub fn set_major_radius(&mut self, major_radius: f32) {
+        self.major_radius = major_radius;
+    }
+
+    pub fn set_minor_radius(&mut self, minor_radius: f32) {
+        self.minor_radius = minor_radius;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::basics::ray::RayOps;
+    use crate::math::common::{assert_float, assert_tuple};
+
+    use super::*;
+
+    fn test_ray_torus_intersection_helper(origin: Tuple4D, direction: Tuple4D, expected: &[f32]) {
+        let t = Torus::new();
+        let r = Ray::new(origin, direction);
+
+        let (xs, cnt_hits) = t.intersect(&r);
+
+        assert_eq!(cnt_hits, expected.len());
+        for (actual, expected) in xs.iter().zip(expected.iter()) {
+            assert_float(*actual, *expected);
+        }
+    }
+
+    #[test]
+    fn test_ray_torus_intersection() {
+        // through both sides of the ring
+        let origin = Tuple4D::new_point(-5.0, 0.0, 0.0);
+        let direction = Tuple4D::new_vector(1.0, 0.0, 0.0);
+        test_ray_torus_intersection_helper(origin, direction, &[3.75, 4.25, 5.75, 6.25]);
+
+        // through the hole
+        let origin = Tuple4D::new_point(0.0, 5.0, 0.0);
+        let direction = Tuple4D::new_vector(0.0, -1.0, 0.0);
+        test_ray_torus_intersection_helper(origin, direction, &[]);
+
+        // down through the tube
+        let origin = Tuple4D::new_point(1.0, 5.0, 0.0);
+        let direction = Tuple4D::new_vector(0.0, -2.0, 0.0);
+        test_ray_torus_intersection_helper(origin, direction, &[2.375, 2.625]);
+
+        // inside the tube
+        let origin = Tuple4D::new_point(0.0, 0.0, -1.0);
+        let direction = Tuple4D::new_vector(0.0, 0.0, 1.0);
+        test_ray_torus_intersection_helper(origin, direction, &[-0.25, 0.25, 1.75, 2.25]);
+
+        // miss
+        let origin = Tuple4D::new_point(-5.0, 0.5, 0.0);
+        let direction = Tuple4D::new_vector(1.0, 0.0, 0.0);
+        test_ray_torus_intersection_helper(origin, direction, &[]);
+    }
+
+    #[test]
+    fn test_ray_torus_intersection_far_away() {
+        let mut t = Torus::new();
+        t.set_minor_radius(0.01);
+        let r = Ray::new(
+            Tuple4D::new_point(-1000.0, 0.0, 0.0),
+            Tuple4D::new_vector(1.0, 0.0, 0.0),
+        );
+        let (xs, cnt_hits) = t.intersect(&r);
+
+        assert_eq!(cnt_hits, 4);
+        assert_float(xs[0], 998.99);
+        assert_float(xs[1], 999.01);
+        assert_float(xs[2], 1000.99);
+        assert_float(xs[3], 1001.01);
+    }
+
+    #[test]
+    fn test_ray_torus_normal_at() {
+        let t = Torus::new();
+
+        let n = t.normal_at(&Tuple4D::new_point(1.25, 0.0, 0.0));
+        assert_tuple(&n, &Tuple4D::new_vector(1.0, 0.0, 0.0));
+
+        let n = t.normal_at(&Tuple4D::new_point(-0.75, 0.0, 0.0));
+        assert_tuple(&n, &Tuple4D::new_vector(1.0, 0.0, 0.0));
+
+        let n = t.normal_at(&Tuple4D::new_point(0.0, 0.25, 1.0));
+        assert_tuple(&n, &Tuple4D::new_vector(0.0, 1.0, 0.0));
+
+        let n = t.normal_at(&Tuple4D::new_point(0.0, 0.0, -1.25));
+        assert_tuple(&n, &Tuple4D::new_vector(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn test_ray_torus_bounds() {
+        let mut t = Torus::new();
+        t.set_major_radius(2.0);
+        t.set_minor_radius(0.5);
+        assert_eq!(t.local_bounds().get_min(), &Tuple4D::new_point(-2.5, -0.5, -2.5));
+        assert_eq!(t.local_bounds().get_max(), &Tuple4D::new_point(2.5, 0.5, 2.5));
+    }
+}
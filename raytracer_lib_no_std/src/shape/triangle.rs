@@ -3,13 +3,16 @@ use serde::{Deserialize, Serialize};
 
 use math::prelude::*;
 
-use crate::{Material, MaterialOps, Ray, RayOps, ShapeIntersectionResult, ShapeOps};
+use crate::{
+    BoundingBox, BoundingBoxOps, Material, MaterialOps, Ray, RayOps, ShapeError, ShapeIntersectionResult, ShapeOps,
+};
 
 #[derive(Clone, Debug, PartialEq)]
 #[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
 #[cfg_attr(feature = "cuda", derive(DeviceCopy))]
 pub struct Triangle {
     transformation_matrix: Matrix,
+    #[cfg_attr(feature = "use_serde", serde(skip, default = "Matrix::new_identity_4x4"))]
     inverse_transformation_matrix: Matrix,
     material: Material,
     p1: Tuple4D,
@@ -63,10 +66,18 @@ impl ShapeOps for Triangle {
         self.normal.clone()
     }
 
-    fn set_transformation(&mut self, m: Matrix) {
-        self.inverse_transformation_matrix =
-            Matrix::invert(&m).expect("Cube::set_transofrmation: cant unwrap inverse matrix");
+    fn local_bounds(&self) -> BoundingBox {
+        let mut res = BoundingBox::empty();
+        res.add_point(&self.p1);
+        res.add_point(&self.p2);
+        res.add_point(&self.p3);
+        res
+    }
+
+    fn try_set_transformation(&mut self, m: Matrix) -> Result<(), ShapeError> {
+        self.inverse_transformation_matrix = Matrix::invert(&m).ok_or(ShapeError::NonInvertibleTransform)?;
         self.transformation_matrix = m;
+        Ok(())
     }
 
     fn get_transformation(&self) -> &Matrix {
@@ -108,17 +119,15 @@ impl Triangle {
         }
     }
 
-    fn get_p1(&self) -> &Tuple4D {
+    pub fn get_p1(&self) -> &Tuple4D {
         &self.p1
     }
 
-    #[allow(dead_code)]
-    fn get_p2(&self) -> &Tuple4D {
+    pub fn get_p2(&self) -> &Tuple4D {
         &self.p2
     }
 
-    #[allow(dead_code)]
-    fn get_p3(&self) -> &Tuple4D {
+    pub fn get_p3(&self) -> &Tuple4D {
         &self.p3
     }
 
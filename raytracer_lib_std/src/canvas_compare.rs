@@ -0,0 +1,478 @@
+use core::fmt;
+use std::error::Error;
+
+use raytracer_lib_no_std::{Color, ColorOps};
+
+use crate::{Canvas, CanvasOps};
+
+// per channel differences of two canvases, colors are clamped to [0, 1] before comparing
+#[derive(Clone, Debug, PartialEq)]
+pub struct CanvasDifference {
+    max_abs_diff: f32,
+    mse: f32,
+    // number of pixels with a channel difference above 1/255
+    differing_pixels: usize,
+    pixels: usize,
+}
+
+// per pixel perceptual error in [0, 1] of compare_flip
+#[derive(Clone, Debug, PartialEq)]
+pub struct PerceptualError {
+    width: usize,
+    height: usize,
+    errors: Vec<f32>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum CanvasCompareError {
+    DifferentSize {
+        expected: (usize, usize),
+        actual: (usize, usize),
+    },
+}
+
+impl CanvasDifference {
+    pub fn get_max_abs_diff(&self) -> f32 {
+        self.max_abs_diff
+    }
+
+    pub fn get_mse(&self) -> f32 {
+        self.mse
+    }
+
+    pub fn get_rmse(&self) -> f32 {
+        self.mse.sqrt()
+    }
+
+    // peak signal to noise ratio in dB with a peak of 1.0, infinite for identical images
+    pub fn get_psnr(&self) -> f32 {
+        if self.mse == 0.0 {
+            f32::INFINITY
+        } else {
+            -10.0 * self.mse.log10()
+        }
+    }
+
+    pub fn get_differing_pixels(&self) -> usize {
+        self.differing_pixels
+    }
+
+    pub fn get_pixels(&self) -> usize {
+        self.pixels
+    }
+}
+
+impl PerceptualError {
+    pub fn get_mean(&self) -> f32 {
+        if self.errors.is_empty() {
+            0.0
+        } else {
+            self.errors.iter().sum::<f32>() / self.errors.len() as f32
+        }
+    }
+
+    pub fn get_max(&self) -> f32 {
+        self.errors.iter().cloned().fold(0.0, f32::max)
+    }
+
+    pub fn error_at(&self, x: usize, y: usize) -> f32 {
+        self.errors[y * self.width + x]
+    }
+
+    // false color image of the error, black for no visible difference
+    pub fn to_heatmap(&self) -> Canvas {
+        let mut heatmap = Canvas::new(self.width, self.height);
+        for (h, e) in heatmap.get_pixels_mut().iter_mut().zip(self.errors.iter()) {
+            h.color = heat_color(*e);
+        }
+        heatmap
+    }
+}
+
+pub fn compare_canvas(expected: &Canvas, actual: &Canvas) -> Result<CanvasDifference, CanvasCompareError> {
+    check_size(expected, actual)?;
+
+    let mut max_abs_diff: f32 = 0.0;
+    let mut sum_sq = 0.0f64;
+    let mut differing_pixels = 0;
+    for (e, a) in expected.get_pixels().iter().zip(actual.get_pixels().iter()) {
+        let d = channel_diff(&e.color, &a.color);
+        let max = d[0].max(d[1]).max(d[2]);
+        if max > 1.0 / 255.0 {
+            differing_pixels += 1;
+        }
+        max_abs_diff = max_abs_diff.max(max);
+        sum_sq += d.iter().map(|d| (*d as f64) * (*d as f64)).sum::<f64>();
+    }
+
+    let pixels = expected.get_width() * expected.get_height();
+    Ok(CanvasDifference {
+        max_abs_diff,
+        mse: if pixels > 0 {
+            (sum_sq / (pixels * 3) as f64) as f32
+        } else {
+            0.0
+        },
+        differing_pixels,
+        pixels,
+    })
+}
+
+// black where both images agree, then red, yellow and white for growing differences.
+// differences of max_diff or more are white
+pub fn diff_heatmap(expected: &Canvas, actual: &Canvas, max_diff: f32) -> Result<Canvas, CanvasCompareError> {
+    check_size(expected, actual)?;
+
+    let mut heatmap = Canvas::new(expected.get_width(), expected.get_height());
+    for (h, (e, a)) in heatmap
+        .get_pixels_mut()
+        .iter_mut()
+        .zip(expected.get_pixels().iter().zip(actual.get_pixels().iter()))
+    {
+        let d = channel_diff(&e.color, &a.color);
+        let t = (d[0].max(d[1]).max(d[2]) / max_diff).min(1.0);
+        h.color = heat_color(t);
+    }
+    Ok(heatmap)
+}
+
+// mean structural similarity of the luminance, computed over 8x8 windows. 1.0 for identical images
+pub fn compare_ssim(expected: &Canvas, actual: &Canvas) -> Result<f32, CanvasCompareError> {
+    check_size(expected, actual)?;
+    let (w, h) = (expected.get_width(), expected.get_height());
+    if w == 0 || h == 0 {
+        return Ok(1.0);
+    }
+
+    let e = luminance(expected);
+    let a = luminance(actual);
+    let window_w = SSIM_WINDOW.min(w);
+    let window_h = SSIM_WINDOW.min(h);
+    let n = (window_w * window_h) as f32;
+
+    let mut sum = 0.0f64;
+    let mut windows = 0;
+    for y0 in 0..=(h - window_h) {
+        for x0 in 0..=(w - window_w) {
+            let (mut mean_e, mut mean_a) = (0.0, 0.0);
+            for y in y0..y0 + window_h {
+                for x in x0..x0 + window_w {
+                    mean_e += e[y * w + x];
+                    mean_a += a[y * w + x];
+                }
+            }
+            mean_e /= n;
+            mean_a /= n;
+
+            let (mut var_e, mut var_a, mut cov) = (0.0, 0.0, 0.0);
+            for y in y0..y0 + window_h {
+                for x in x0..x0 + window_w {
+                    let de = e[y * w + x] - mean_e;
+                    let da = a[y * w + x] - mean_a;
+                    var_e += de * de;
+                    var_a += da * da;
+                    cov += de * da;
+                }
+            }
+            // sample variances, like the reference implementation of the SSIM paper
+            let norm = (n - 1.0).max(1.0);
+            var_e /= norm;
+            var_a /= norm;
+            cov /= norm;
+
+            let ssim = ((2.0 * mean_e * mean_a + SSIM_C1) * (2.0 * cov + SSIM_C2))
+                / ((mean_e * mean_e + mean_a * mean_a + SSIM_C1) * (var_e + var_a + SSIM_C2));
+            sum += ssim as f64;
+            windows += 1;
+        }
+    }
+    Ok((sum / windows as f64) as f32)
+}
+
+// perceptual error in the spirit of NVIDIA's FLIP: the color difference is measured in CIELAB with the
+// HyAB distance on slightly blurred images and amplified where the edges of the two images differ
+pub fn compare_flip(expected: &Canvas, actual: &Canvas) -> Result<PerceptualError, CanvasCompareError> {
+    check_size(expected, actual)?;
+    let (w, h) = (expected.get_width(), expected.get_height());
+
+    let lab_e = blur(&to_lab(expected), w, h);
+    let lab_a = blur(&to_lab(actual), w, h);
+    let edges_e = edges(&lab_e, w, h);
+    let edges_a = edges(&lab_a, w, h);
+
+    let c_max = hyab(
+        &srgb_to_lab(&Color::new(0.0, 1.0, 0.0)),
+        &srgb_to_lab(&Color::new(0.0, 0.0, 1.0)),
+    )
+    .powf(FLIP_COLOR_EXPONENT);
+    let errors = (0..w * h)
+        .map(|i| {
+            let color = hyab(&lab_e[i], &lab_a[i]).powf(FLIP_COLOR_EXPONENT);
+            // map into [0, 1], the first part of the range is compressed into [0, FLIP_PT]
+            let color = if color < FLIP_PC * c_max {
+                FLIP_PT * color / (FLIP_PC * c_max)
+            } else {
+                (FLIP_PT + (color - FLIP_PC * c_max) / (c_max - FLIP_PC * c_max) * (1.0 - FLIP_PT)).min(1.0)
+            };
+            let feature = ((edges_e[i] - edges_a[i]).abs() / 2.0f32.sqrt()).min(1.0).sqrt();
+            color.powf(1.0 - feature)
+        })
+        .collect();
+
+    Ok(PerceptualError {
+        width: w,
+        height: h,
+        errors,
+    })
+}
+
+// "hot" color map for t in [0, 1]
+pub fn heat_color(t: f32) -> Color {
+    Color::new(
+        (3.0 * t).max(0.0).min(1.0),
+        (3.0 * t - 1.0).max(0.0).min(1.0),
+        (3.0 * t - 2.0).max(0.0).min(1.0),
+    )
+}
+
+fn check_size(expected: &Canvas, actual: &Canvas) -> Result<(), CanvasCompareError> {
+    if expected.get_width() != actual.get_width() || expected.get_height() != actual.get_height() {
+        return Err(CanvasCompareError::DifferentSize {
+            expected: (expected.get_width(), expected.get_height()),
+            actual: (actual.get_width(), actual.get_height()),
+        });
+    }
+    Ok(())
+}
+
+const SSIM_WINDOW: usize = 8;
+const SSIM_C1: f32 = 0.01 * 0.01;
+const SSIM_C2: f32 = 0.03 * 0.03;
+
+const FLIP_COLOR_EXPONENT: f32 = 0.7;
+const FLIP_PC: f32 = 0.4;
+const FLIP_PT: f32 = 0.95;
+
+fn luminance(c: &Canvas) -> Vec<f32> {
+    c.get_pixels()
+        .iter()
+        .map(|p| {
+            0.299 * p.color.r.max(0.0).min(1.0)
+                + 0.587 * p.color.g.max(0.0).min(1.0)
+                + 0.114 * p.color.b.max(0.0).min(1.0)
+        })
+        .collect()
+}
+
+fn to_lab(c: &Canvas) -> Vec<[f32; 3]> {
+    c.get_pixels().iter().map(|p| srgb_to_lab(&p.color)).collect()
+}
+
+// sRGB with D65 white point to CIELAB
+fn srgb_to_lab(c: &Color) -> [f32; 3] {
+    let linear = |v: f32| {
+        let v = v.max(0.0).min(1.0);
+        if v <= 0.04045 {
+            v / 12.92
+        } else {
+            ((v + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    let (r, g, b) = (linear(c.r), linear(c.g), linear(c.b));
+    let x = (0.4124 * r + 0.3576 * g + 0.1805 * b) / 0.950_47;
+    let y = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+    let z = (0.0193 * r + 0.1192 * g + 0.9505 * b) / 1.088_83;
+
+    let f = |t: f32| {
+        if t > 0.008_856 {
+            t.cbrt()
+        } else {
+            7.787 * t + 16.0 / 116.0
+        }
+    };
+    let (fx, fy, fz) = (f(x), f(y), f(z));
+    [116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)]
+}
+
+fn hyab(a: &[f32; 3], b: &[f32; 3]) -> f32 {
+    (a[0] - b[0]).abs() + ((a[1] - b[1]).powi(2) + (a[2] - b[2]).powi(2)).sqrt()
+}
+
+// 3x3 binomial filter, a cheap stand in for the contrast sensitivity filter of FLIP
+fn blur(lab: &[[f32; 3]], w: usize, h: usize) -> Vec<[f32; 3]> {
+    let weights = [1.0, 2.0, 1.0];
+    let mut res = vec![[0.0; 3]; lab.len()];
+    for y in 0..h {
+        for x in 0..w {
+            let mut sum = [0.0; 3];
+            let mut total = 0.0;
+            for (dy, wy) in weights.iter().enumerate() {
+                for (dx, wx) in weights.iter().enumerate() {
+                    let xx = (x + dx).wrapping_sub(1);
+                    let yy = (y + dy).wrapping_sub(1);
+                    if xx < w && yy < h {
+                        let v = &lab[yy * w + xx];
+                        let weight = wx * wy;
+                        sum[0] += weight * v[0];
+                        sum[1] += weight * v[1];
+                        sum[2] += weight * v[2];
+                        total += weight;
+                    }
+                }
+            }
+            res[y * w + x] = [sum[0] / total, sum[1] / total, sum[2] / total];
+        }
+    }
+    res
+}
+
+// sobel gradient magnitude of the lightness, about 1.0 for a black/white edge
+fn edges(lab: &[[f32; 3]], w: usize, h: usize) -> Vec<f32> {
+    let l = |x: usize, y: usize| lab[y.min(h - 1) * w + x.min(w - 1)][0] / 100.0;
+    let mut res = vec![0.0; lab.len()];
+    for y in 0..h {
+        for x in 0..w {
+            let (xm, ym) = (x.saturating_sub(1), y.saturating_sub(1));
+            let gx = (l(x + 1, ym) + 2.0 * l(x + 1, y) + l(x + 1, y + 1)) - (l(xm, ym) + 2.0 * l(xm, y) + l(xm, y + 1));
+            let gy = (l(xm, y + 1) + 2.0 * l(x, y + 1) + l(x + 1, y + 1)) - (l(xm, ym) + 2.0 * l(x, ym) + l(x + 1, ym));
+            res[y * w + x] = (gx * gx + gy * gy).sqrt() / 4.0;
+        }
+    }
+    res
+}
+
+fn channel_diff(a: &Color, b: &Color) -> [f32; 3] {
+    let clamp = |v: f32| v.max(0.0).min(1.0);
+    [
+        (clamp(a.r) - clamp(b.r)).abs(),
+        (clamp(a.g) - clamp(b.g)).abs(),
+        (clamp(a.b) - clamp(b.b)).abs(),
+    ]
+}
+
+impl Error for CanvasCompareError {}
+
+impl fmt::Display for CanvasCompareError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CanvasCompareError::DifferentSize { expected, actual } => write!(
+                f,
+                "canvas sizes differ: expected {}x{}, got {}x{}",
+                expected.0, expected.1, actual.0, actual.1
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use math::prelude::*;
+    use raytracer_lib_no_std::{assert_color, BLACK, WHITE};
+
+    use super::*;
+
+    #[test]
+    fn test_compare_identical() {
+        let mut c = Canvas::new(3, 2);
+        c.write_pixel(1, 1, Color::new(0.2, 0.4, 0.6));
+        let d = compare_canvas(&c, &c.clone()).unwrap();
+        assert_float(d.get_max_abs_diff(), 0.0);
+        assert_float(d.get_rmse(), 0.0);
+        assert!(d.get_psnr().is_infinite());
+        assert_eq!(d.get_differing_pixels(), 0);
+    }
+
+    #[test]
+    fn test_compare_metrics() {
+        let a = Canvas::new(2, 2);
+        let mut b = Canvas::new(2, 2);
+        b.write_pixel(0, 0, Color::new(1.0, 0.0, 0.0));
+        // values above 1.0 are clamped like in the written images
+        b.write_pixel(1, 0, Color::new(0.0, 0.0, 2.0));
+
+        let d = compare_canvas(&a, &b).unwrap();
+        assert_float(d.get_max_abs_diff(), 1.0);
+        // 2 of 12 channel values differ by 1.0
+        assert_float(d.get_mse(), 2.0 / 12.0);
+        assert_float(d.get_psnr(), 10.0 * (6.0f32).log10());
+        assert_eq!(d.get_differing_pixels(), 2);
+        assert_eq!(d.get_pixels(), 4);
+    }
+
+    #[test]
+    fn test_compare_different_size() {
+        let err = compare_canvas(&Canvas::new(2, 2), &Canvas::new(2, 3)).unwrap_err();
+        assert_eq!(
+            err,
+            CanvasCompareError::DifferentSize {
+                expected: (2, 2),
+                actual: (2, 3)
+            }
+        );
+    }
+
+    #[test]
+    fn test_diff_heatmap() {
+        let a = Canvas::new(3, 1);
+        let mut b = Canvas::new(3, 1);
+        b.write_pixel(1, 0, Color::new(0.05, 0.0, 0.0));
+        b.write_pixel(2, 0, WHITE);
+
+        let h = diff_heatmap(&a, &b, 0.1).unwrap();
+        assert_color(&h.pixel_at(0, 0).color, &BLACK);
+        assert_color(&h.pixel_at(1, 0).color, &Color::new(1.0, 0.5, 0.0));
+        assert_color(&h.pixel_at(2, 0).color, &WHITE);
+    }
+
+    fn gradient_canvas(w: usize, h: usize) -> Canvas {
+        let mut c = Canvas::new(w, h);
+        for y in 0..h {
+            for x in 0..w {
+                let v = (x + y) as f32 / (w + h) as f32;
+                c.write_pixel(x, y, Color::new(v, 0.5 * v, 1.0 - v));
+            }
+        }
+        c
+    }
+
+    #[test]
+    fn test_ssim() {
+        let a = gradient_canvas(16, 12);
+        assert_float(compare_ssim(&a, &a.clone()).unwrap(), 1.0);
+
+        let mut noisy = a.clone();
+        for i in (0..16 * 12).step_by(7) {
+            let c = noisy.pixel_at(i % 16, i / 16).color;
+            noisy.write_pixel(i % 16, i / 16, Color::new(c.r + 0.05, c.g, c.b));
+        }
+        let mut inverted = Canvas::new(16, 12);
+        for p in a.get_pixels().iter() {
+            inverted.write_pixel(p.x, p.y, Color::new(1.0 - p.color.r, 1.0 - p.color.g, 1.0 - p.color.b));
+        }
+
+        let ssim_noisy = compare_ssim(&a, &noisy).unwrap();
+        let ssim_inverted = compare_ssim(&a, &inverted).unwrap();
+        assert!(ssim_noisy < 1.0 && ssim_noisy > 0.8);
+        assert!(ssim_inverted < ssim_noisy);
+    }
+
+    #[test]
+    fn test_flip() {
+        let a = gradient_canvas(16, 12);
+        let e = compare_flip(&a, &a.clone()).unwrap();
+        assert_float(e.get_mean(), 0.0);
+        assert_float(e.get_max(), 0.0);
+
+        let mut b = a.clone();
+        b.write_pixel(8, 6, Color::new(0.0, 1.0, 0.0));
+        let e = compare_flip(&a, &b).unwrap();
+        assert!(e.error_at(8, 6) > 0.5);
+        // blurring spreads the error a bit, far away pixels are untouched
+        assert!(e.error_at(9, 6) > 0.0);
+        assert_float(e.error_at(0, 0), 0.0);
+        assert!(e.get_max() <= 1.0);
+
+        let h = e.to_heatmap();
+        assert_color(&h.pixel_at(0, 0).color, &BLACK);
+    }
+}
@@ -1,16 +1,50 @@
-use crate::{Canvas, CanvasOps};
+use raytracer_lib_no_std::{Color, ColorOps, Heightfield, NormalMap, UvMapping};
+
+use crate::{heightfield_from_canvas, normal_map_from_canvas, Canvas, CanvasOps, RaytracerError, World};
 use image::ImageBuffer;
 use image::RgbImage;
 use std::fs::File;
-use std::io::{Error, Write};
+use std::io::{Error, ErrorKind, Write};
 
 pub trait CanvasOpsStd<'a> {
-    fn write_ppm(&self, filename: &'a str) -> Result<(), Error>;
-    fn write_png(&self, filename: &'a str) -> Result<(), Error>;
+    fn write_ppm(&self, filename: &'a str) -> Result<(), RaytracerError>;
+    fn write_png(&self, filename: &'a str) -> Result<(), RaytracerError>;
+}
+
+// loads a PNG (or any other format the image crate knows) into a canvas, colors are in [0, 1]
+pub fn read_png(filename: &str) -> Result<Canvas, Error> {
+    let image = image::open(filename)
+        .map_err(|e| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("can't read image '{}': {}", filename, e),
+            )
+        })?
+        .to_rgb();
+
+    let mut canvas = Canvas::new(image.width() as usize, image.height() as usize);
+    for (x, y, p) in image.enumerate_pixels() {
+        canvas.write_pixel(
+            x as usize,
+            y as usize,
+            Color::new(p[0] as f32 / 255.0, p[1] as f32 / 255.0, p[2] as f32 / 255.0),
+        );
+    }
+    Ok(canvas)
+}
+
+// loads a grayscale image as terrain, see heightfield_from_canvas
+pub fn read_heightfield(world: &mut World, filename: &str) -> Result<Heightfield, RaytracerError> {
+    heightfield_from_canvas(world, &read_png(filename)?)
+}
+
+// loads a tangent space normal map image, see normal_map_from_canvas
+pub fn read_normal_map(world: &mut World, filename: &str, mapping: UvMapping) -> Result<NormalMap, RaytracerError> {
+    normal_map_from_canvas(world, &read_png(filename)?, mapping)
 }
 
 impl<'a> CanvasOpsStd<'a> for Canvas {
-    fn write_ppm(&self, filename: &'a str) -> Result<(), Error> {
+    fn write_ppm(&self, filename: &'a str) -> Result<(), RaytracerError> {
         let mut file = File::create(filename)?;
 
         let new_line = "\n";
@@ -46,7 +80,7 @@ impl<'a> CanvasOpsStd<'a> for Canvas {
         Ok(())
     }
 
-    fn write_png(&self, filename: &'a str) -> Result<(), Error> {
+    fn write_png(&self, filename: &'a str) -> Result<(), RaytracerError> {
         let mut x = 0;
         let mut y = 0;
         let mut idx = 0;
@@ -67,6 +101,7 @@ impl<'a> CanvasOpsStd<'a> for Canvas {
                 x = 0;
             }
         }
-        image.save(filename)
+        image.save(filename)?;
+        Ok(())
     }
 }
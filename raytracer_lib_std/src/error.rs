@@ -0,0 +1,91 @@
+use core::fmt;
+use std::error::Error;
+use std::io;
+
+use raytracer_lib_no_std::ShapeError;
+
+#[derive(Debug)]
+pub enum RaytracerError {
+    NonInvertibleTransform,
+    InvalidMaterial(&'static str),
+    InvalidShape(&'static str),
+    CapacityExceeded(usize),
+    InvalidSettings(&'static str),
+    Backend(String),
+    Io(io::Error),
+    // a feature of a scene, which the target of a conversion can not represent
+    Unsupported(&'static str),
+    // a scene file with invalid values, the message names the part of the scene
+    InvalidScene(String),
+    #[cfg(feature = "use_serde")]
+    Json(serde_json::Error),
+    #[cfg(feature = "use_serde")]
+    Binary(bincode::Error),
+}
+
+impl Error for RaytracerError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            RaytracerError::Io(e) => Some(e),
+            #[cfg(feature = "use_serde")]
+            RaytracerError::Json(e) => Some(e),
+            #[cfg(feature = "use_serde")]
+            RaytracerError::Binary(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for RaytracerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RaytracerError::NonInvertibleTransform => write!(f, "transformation matrix is not invertible"),
+            RaytracerError::InvalidMaterial(field) => write!(f, "invalid material value for '{}'", field),
+            RaytracerError::InvalidShape(field) => write!(f, "invalid shape value for '{}'", field),
+            RaytracerError::CapacityExceeded(capacity) => {
+                write!(f, "capacity of {} entries exceeded", capacity)
+            }
+            RaytracerError::InvalidSettings(field) => write!(f, "invalid render setting '{}'", field),
+            RaytracerError::Backend(msg) => write!(f, "backend failure: {}", msg),
+            RaytracerError::Io(e) => write!(f, "I/O error: {}", e),
+            RaytracerError::Unsupported(feature) => write!(f, "not supported: {}", feature),
+            RaytracerError::InvalidScene(msg) => write!(f, "invalid scene: {}", msg),
+            #[cfg(feature = "use_serde")]
+            RaytracerError::Json(e) => write!(f, "invalid JSON scene: {}", e),
+            #[cfg(feature = "use_serde")]
+            RaytracerError::Binary(e) => write!(f, "invalid binary scene: {}", e),
+        }
+    }
+}
+
+impl From<ShapeError> for RaytracerError {
+    fn from(e: ShapeError) -> Self {
+        match e {
+            ShapeError::NonInvertibleTransform => RaytracerError::NonInvertibleTransform,
+            ShapeError::InvalidMaterial(field) => RaytracerError::InvalidMaterial(field),
+            ShapeError::InvalidShape(field) => RaytracerError::InvalidShape(field),
+            ShapeError::CapacityExceeded(capacity) => RaytracerError::CapacityExceeded(capacity),
+            ShapeError::InvalidSettings(field) => RaytracerError::InvalidSettings(field),
+        }
+    }
+}
+
+impl From<io::Error> for RaytracerError {
+    fn from(e: io::Error) -> Self {
+        RaytracerError::Io(e)
+    }
+}
+
+#[cfg(feature = "use_serde")]
+impl From<serde_json::Error> for RaytracerError {
+    fn from(e: serde_json::Error) -> Self {
+        RaytracerError::Json(e)
+    }
+}
+
+#[cfg(feature = "use_serde")]
+impl From<bincode::Error> for RaytracerError {
+    fn from(e: bincode::Error) -> Self {
+        RaytracerError::Binary(e)
+    }
+}
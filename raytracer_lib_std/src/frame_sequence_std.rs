@@ -0,0 +1,541 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Error, ErrorKind, Write};
+
+use crate::{Canvas, CanvasOps};
+
+const PNG_SIGNATURE: [u8; 8] = [137, 80, 78, 71, 13, 10, 26, 10];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Looping {
+    Infinite,
+    // number of times the animation is played, 1 = no repetition
+    Count(u16),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FrameSequenceOptions {
+    frame_delay_ms: u16,
+    looping: Looping,
+    // GIF only: palette size and Floyd-Steinberg dithering
+    max_colors: usize,
+    dithering: bool,
+}
+
+impl FrameSequenceOptions {
+    pub fn new() -> FrameSequenceOptions {
+        FrameSequenceOptions {
+            frame_delay_ms: 40,
+            looping: Looping::Infinite,
+            max_colors: 256,
+            dithering: false,
+        }
+    }
+
+    pub fn set_frame_delay_ms(&mut self, frame_delay_ms: u16) {
+        self.frame_delay_ms = frame_delay_ms;
+    }
+
+    pub fn set_fps(&mut self, fps: f32) {
+        self.frame_delay_ms = (1000.0 / fps).round() as u16;
+    }
+
+    pub fn get_frame_delay_ms(&self) -> u16 {
+        self.frame_delay_ms
+    }
+
+    pub fn set_looping(&mut self, looping: Looping) {
+        self.looping = looping;
+    }
+
+    pub fn get_looping(&self) -> Looping {
+        self.looping
+    }
+
+    pub fn set_max_colors(&mut self, max_colors: usize) {
+        self.max_colors = max_colors;
+    }
+
+    pub fn get_max_colors(&self) -> usize {
+        self.max_colors
+    }
+
+    pub fn set_dithering(&mut self, dithering: bool) {
+        self.dithering = dithering;
+    }
+
+    pub fn get_dithering(&self) -> bool {
+        self.dithering
+    }
+}
+
+impl Default for FrameSequenceOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub fn write_gif(frames: &[Canvas], filename: &str, options: &FrameSequenceOptions) -> Result<(), Error> {
+    let file = File::create(filename)?;
+    encode_gif(frames, BufWriter::new(file), options)
+}
+
+pub fn write_apng(frames: &[Canvas], filename: &str, options: &FrameSequenceOptions) -> Result<(), Error> {
+    let file = File::create(filename)?;
+    encode_apng(frames, BufWriter::new(file), options)
+}
+
+// all frames share one global palette, so colors do not flicker between frames
+pub fn encode_gif<W: Write>(frames: &[Canvas], w: W, options: &FrameSequenceOptions) -> Result<(), Error> {
+    let (width, height) = check_frames(frames)?;
+    if width > u16::MAX as usize || height > u16::MAX as usize {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "GIF frames must not be larger than 65535x65535",
+        ));
+    }
+    if options.max_colors < 2 || options.max_colors > 256 {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("GIF palette size must be between 2 and 256, got {}", options.max_colors),
+        ));
+    }
+
+    let rgb_frames: Vec<Vec<[u8; 3]>> = frames.iter().map(to_rgb).collect();
+    let palette = median_cut(&rgb_frames, options.max_colors);
+    let flat_palette: Vec<u8> = palette.iter().flat_map(|c| c.iter().cloned()).collect();
+
+    let mut encoder = gif::Encoder::new(w, width as u16, height as u16, &flat_palette)?;
+    match options.looping {
+        Looping::Infinite => encoder.write_extension(gif::ExtensionData::Repetitions(gif::Repeat::Infinite))?,
+        Looping::Count(n) if n > 1 => {
+            encoder.write_extension(gif::ExtensionData::Repetitions(gif::Repeat::Finite(n - 1)))?
+        }
+        Looping::Count(_) => {}
+    }
+
+    // GIF delays are given in 1/100 s
+    let delay = (options.frame_delay_ms as u32 + 5) / 10;
+    for rgb in rgb_frames.iter() {
+        let indices = map_to_palette(rgb, width, height, &palette, options.dithering);
+        let frame = gif::Frame {
+            width: width as u16,
+            height: height as u16,
+            delay: delay as u16,
+            buffer: Cow::Owned(indices),
+            ..gif::Frame::default()
+        };
+        encoder.write_frame(&frame)?;
+    }
+    Ok(())
+}
+
+// APNG: the first frame is stored as regular IDAT, so viewers without APNG support show it
+pub fn encode_apng<W: Write>(frames: &[Canvas], mut w: W, options: &FrameSequenceOptions) -> Result<(), Error> {
+    let (width, height) = check_frames(frames)?;
+
+    w.write_all(&PNG_SIGNATURE)?;
+
+    let mut ihdr = Vec::with_capacity(13);
+    ihdr.extend_from_slice(&(width as u32).to_be_bytes());
+    ihdr.extend_from_slice(&(height as u32).to_be_bytes());
+    // 8 bit RGB, deflate, no filter method extensions, no interlacing
+    ihdr.extend_from_slice(&[8, 2, 0, 0, 0]);
+    write_chunk(&mut w, b"IHDR", &ihdr)?;
+
+    let num_plays = match options.looping {
+        Looping::Infinite => 0,
+        Looping::Count(n) => n.max(1) as u32,
+    };
+    let mut actl = Vec::with_capacity(8);
+    actl.extend_from_slice(&(frames.len() as u32).to_be_bytes());
+    actl.extend_from_slice(&num_plays.to_be_bytes());
+    write_chunk(&mut w, b"acTL", &actl)?;
+
+    // fcTL and fdAT chunks share one sequence
+    let mut sequence: u32 = 0;
+    for (idx, canvas) in frames.iter().enumerate() {
+        let mut fctl = Vec::with_capacity(26);
+        fctl.extend_from_slice(&sequence.to_be_bytes());
+        fctl.extend_from_slice(&(width as u32).to_be_bytes());
+        fctl.extend_from_slice(&(height as u32).to_be_bytes());
+        fctl.extend_from_slice(&0u32.to_be_bytes());
+        fctl.extend_from_slice(&0u32.to_be_bytes());
+        fctl.extend_from_slice(&options.frame_delay_ms.to_be_bytes());
+        fctl.extend_from_slice(&1000u16.to_be_bytes());
+        // dispose op none, blend op source
+        fctl.extend_from_slice(&[0, 0]);
+        write_chunk(&mut w, b"fcTL", &fctl)?;
+        sequence += 1;
+
+        let data = compress_scanlines(canvas);
+        if idx == 0 {
+            write_chunk(&mut w, b"IDAT", &data)?;
+        } else {
+            let mut fdat = Vec::with_capacity(data.len() + 4);
+            fdat.extend_from_slice(&sequence.to_be_bytes());
+            fdat.extend_from_slice(&data);
+            write_chunk(&mut w, b"fdAT", &fdat)?;
+            sequence += 1;
+        }
+    }
+
+    write_chunk(&mut w, b"IEND", &[])?;
+    w.flush()
+}
+
+fn check_frames(frames: &[Canvas]) -> Result<(usize, usize), Error> {
+    let first = frames
+        .first()
+        .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "frame sequence is empty"))?;
+    let (width, height) = (first.get_width(), first.get_height());
+    if width == 0 || height == 0 {
+        return Err(Error::new(ErrorKind::InvalidInput, "frames must not be empty"));
+    }
+    if let Some(idx) = frames
+        .iter()
+        .position(|c| c.get_width() != width || c.get_height() != height)
+    {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "frame {} has size {}x{}, expected {}x{}",
+                idx,
+                frames[idx].get_width(),
+                frames[idx].get_height(),
+                width,
+                height
+            ),
+        ));
+    }
+    Ok((width, height))
+}
+
+// same conversion as write_png
+fn to_rgb(c: &Canvas) -> Vec<[u8; 3]> {
+    c.get_pixels()
+        .iter()
+        .map(|p| {
+            [
+                (p.color.r * 255.0) as u8,
+                (p.color.g * 255.0) as u8,
+                (p.color.b * 255.0) as u8,
+            ]
+        })
+        .collect()
+}
+
+fn write_chunk<W: Write>(w: &mut W, chunk_type: &[u8; 4], data: &[u8]) -> Result<(), Error> {
+    let mut crc = crc32fast::Hasher::new();
+    crc.update(chunk_type);
+    crc.update(data);
+
+    w.write_all(&(data.len() as u32).to_be_bytes())?;
+    w.write_all(chunk_type)?;
+    w.write_all(data)?;
+    w.write_all(&crc.finalize().to_be_bytes())
+}
+
+// every scanline gets filter type 0 (none) in front
+fn compress_scanlines(c: &Canvas) -> Vec<u8> {
+    let rgb = to_rgb(c);
+    let mut raw = Vec::with_capacity(c.get_height() * (c.get_width() * 3 + 1));
+    for row in rgb.chunks(c.get_width()) {
+        raw.push(0);
+        for p in row.iter() {
+            raw.extend_from_slice(p);
+        }
+    }
+    deflate::deflate_bytes_zlib(&raw)
+}
+
+// median cut on the histogram of all frames. if there are not more colors than max_colors
+// the colors are used as they are
+fn median_cut(frames: &[Vec<[u8; 3]>], max_colors: usize) -> Vec<[u8; 3]> {
+    let mut histogram: HashMap<[u8; 3], u32> = HashMap::new();
+    for c in frames.iter().flat_map(|f| f.iter()) {
+        *histogram.entry(*c).or_insert(0) += 1;
+    }
+    let mut colors: Vec<([u8; 3], u32)> = histogram.into_iter().collect();
+    if colors.len() <= max_colors {
+        colors.sort();
+        return colors.into_iter().map(|(c, _)| c).collect();
+    }
+
+    let mut boxes = vec![colors];
+    while boxes.len() < max_colors {
+        let (idx, channel, range) = boxes
+            .iter()
+            .enumerate()
+            .map(|(idx, b)| {
+                let (channel, range) = widest_channel(b);
+                (idx, channel, range)
+            })
+            .max_by_key(|(_, _, range)| *range)
+            .unwrap();
+        if range == 0 {
+            break;
+        }
+
+        let mut b = boxes.swap_remove(idx);
+        b.sort_by_key(|(c, _)| c[channel]);
+        let total: u64 = b.iter().map(|(_, n)| *n as u64).sum();
+        let mut acc = 0;
+        let mut split = b.len() / 2;
+        for (i, (_, n)) in b.iter().enumerate() {
+            acc += *n as u64;
+            if acc * 2 >= total {
+                split = i + 1;
+                break;
+            }
+        }
+        // a box with a range > 0 has at least 2 colors
+        let split = split.max(1).min(b.len() - 1);
+        let rest = b.split_off(split);
+        boxes.push(b);
+        boxes.push(rest);
+    }
+
+    boxes
+        .iter()
+        .map(|b| {
+            let total: u64 = b.iter().map(|(_, n)| *n as u64).sum();
+            let mut sum = [0u64; 3];
+            for (c, n) in b.iter() {
+                for i in 0..3 {
+                    sum[i] += c[i] as u64 * *n as u64;
+                }
+            }
+            [
+                ((sum[0] + total / 2) / total) as u8,
+                ((sum[1] + total / 2) / total) as u8,
+                ((sum[2] + total / 2) / total) as u8,
+            ]
+        })
+        .collect()
+}
+
+fn widest_channel(colors: &[([u8; 3], u32)]) -> (usize, u8) {
+    (0..3)
+        .map(|channel| {
+            let min = colors.iter().map(|(c, _)| c[channel]).min().unwrap_or(0);
+            let max = colors.iter().map(|(c, _)| c[channel]).max().unwrap_or(0);
+            (channel, max - min)
+        })
+        .max_by_key(|(_, range)| *range)
+        .unwrap()
+}
+
+fn nearest_color(c: &[u8; 3], palette: &[[u8; 3]]) -> u8 {
+    let dist = |p: &[u8; 3]| -> i32 {
+        (0..3)
+            .map(|i| (c[i] as i32 - p[i] as i32) * (c[i] as i32 - p[i] as i32))
+            .sum()
+    };
+    let mut best = 0;
+    for (idx, p) in palette.iter().enumerate() {
+        if dist(p) < dist(&palette[best]) {
+            best = idx;
+        }
+    }
+    best as u8
+}
+
+fn map_to_palette(rgb: &[[u8; 3]], width: usize, height: usize, palette: &[[u8; 3]], dithering: bool) -> Vec<u8> {
+    let mut cache: HashMap<[u8; 3], u8> = HashMap::new();
+    let mut lookup = |c: [u8; 3]| *cache.entry(c).or_insert_with(|| nearest_color(&c, palette));
+
+    if !dithering {
+        return rgb.iter().map(|c| lookup(*c)).collect();
+    }
+
+    // Floyd-Steinberg: distribute the quantization error to the neighbours not visited yet
+    let mut errors = vec![[0.0f32; 3]; width * height];
+    let mut indices = Vec::with_capacity(width * height);
+    for y in 0..height {
+        for x in 0..width {
+            let pos = y * width + x;
+            let mut c = [0u8; 3];
+            for i in 0..3 {
+                c[i] = (rgb[pos][i] as f32 + errors[pos][i]).round().max(0.0).min(255.0) as u8;
+            }
+            let idx = lookup(c);
+            indices.push(idx);
+
+            let p = palette[idx as usize];
+            let err = [
+                c[0] as f32 - p[0] as f32,
+                c[1] as f32 - p[1] as f32,
+                c[2] as f32 - p[2] as f32,
+            ];
+            let mut spread = |x: usize, y: usize, factor: f32| {
+                let pos = y * width + x;
+                for i in 0..3 {
+                    errors[pos][i] += err[i] * factor;
+                }
+            };
+            if x + 1 < width {
+                spread(x + 1, y, 7.0 / 16.0);
+            }
+            if y + 1 < height {
+                if x > 0 {
+                    spread(x - 1, y + 1, 3.0 / 16.0);
+                }
+                spread(x, y + 1, 5.0 / 16.0);
+                if x + 1 < width {
+                    spread(x + 1, y + 1, 1.0 / 16.0);
+                }
+            }
+        }
+    }
+    indices
+}
+
+#[cfg(test)]
+mod tests {
+    use raytracer_lib_no_std::{Color, ColorOps};
+
+    use super::*;
+
+    fn test_frames() -> Vec<Canvas> {
+        (0..3)
+            .map(|f| {
+                let mut c = Canvas::new(4, 2);
+                for y in 0..2 {
+                    for x in 0..4 {
+                        c.write_pixel(x, y, Color::new(f as f32 / 2.0, x as f32 / 3.0, y as f32));
+                    }
+                }
+                c
+            })
+            .collect()
+    }
+
+    // returns (type, data) of all chunks after the signature
+    fn read_chunks(png: &[u8]) -> Vec<(String, Vec<u8>)> {
+        assert_eq!(&png[0..8], &PNG_SIGNATURE);
+        let mut chunks = Vec::new();
+        let mut pos = 8;
+        while pos < png.len() {
+            let len = u32::from_be_bytes([png[pos], png[pos + 1], png[pos + 2], png[pos + 3]]) as usize;
+            let chunk_type = String::from_utf8(png[pos + 4..pos + 8].to_vec()).unwrap();
+            let data = png[pos + 8..pos + 8 + len].to_vec();
+
+            let mut crc = crc32fast::Hasher::new();
+            crc.update(&png[pos + 4..pos + 8 + len]);
+            let expected = &png[pos + 8 + len..pos + 12 + len];
+            assert_eq!(crc.finalize().to_be_bytes(), expected);
+
+            chunks.push((chunk_type, data));
+            pos += 12 + len;
+        }
+        chunks
+    }
+
+    #[test]
+    fn test_median_cut_keeps_few_colors() {
+        let frames = vec![vec![[1, 2, 3], [4, 5, 6], [1, 2, 3]]];
+        assert_eq!(median_cut(&frames, 256), vec![[1, 2, 3], [4, 5, 6]]);
+    }
+
+    #[test]
+    fn test_median_cut_reduces_colors() {
+        let frame: Vec<[u8; 3]> = (0..=255).map(|v| [v as u8, 0, 255 - v as u8]).collect();
+        let palette = median_cut(std::slice::from_ref(&frame), 16);
+        assert_eq!(palette.len(), 16);
+
+        let indices = map_to_palette(&frame, 256, 1, &palette, false);
+        for (c, idx) in frame.iter().zip(indices.iter()) {
+            let p = palette[*idx as usize];
+            assert!((c[0] as i32 - p[0] as i32).abs() <= 8);
+        }
+    }
+
+    #[test]
+    fn test_dithering_preserves_average() {
+        // a flat grey between black and white becomes a mix of both
+        let frame = vec![[100u8, 100, 100]; 64];
+        let palette = vec![[0, 0, 0], [255, 255, 255]];
+
+        let plain = map_to_palette(&frame, 8, 8, &palette, false);
+        assert!(plain.iter().all(|idx| *idx == 0));
+
+        let dithered = map_to_palette(&frame, 8, 8, &palette, true);
+        let white = dithered.iter().filter(|idx| **idx == 1).count();
+        assert!((white as i32 - 25).abs() <= 2);
+    }
+
+    #[test]
+    fn test_encode_gif() {
+        let mut options = FrameSequenceOptions::new();
+        options.set_fps(10.0);
+        options.set_dithering(true);
+        let mut gif = Vec::new();
+        encode_gif(&test_frames(), &mut gif, &options).unwrap();
+
+        assert_eq!(&gif[0..6], b"GIF89a");
+        assert_eq!(u16::from_le_bytes([gif[6], gif[7]]), 4);
+        assert_eq!(u16::from_le_bytes([gif[8], gif[9]]), 2);
+        assert!(gif.windows(11).any(|w| w == b"NETSCAPE2.0"));
+        // one graphic control extension with a delay of 10/100 s per frame
+        let delays = gif
+            .windows(6)
+            .filter(|w| w[0] == 0x21 && w[1] == 0xF9 && w[2] == 4 && w[4] == 10 && w[5] == 0)
+            .count();
+        assert_eq!(delays, 3);
+        assert_eq!(*gif.last().unwrap(), 0x3B);
+    }
+
+    #[test]
+    fn test_encode_gif_play_once() {
+        let mut options = FrameSequenceOptions::new();
+        options.set_looping(Looping::Count(1));
+        let mut gif = Vec::new();
+        encode_gif(&test_frames(), &mut gif, &options).unwrap();
+        assert!(!gif.windows(11).any(|w| w == b"NETSCAPE2.0"));
+    }
+
+    #[test]
+    fn test_encode_apng() {
+        let mut options = FrameSequenceOptions::new();
+        options.set_frame_delay_ms(250);
+        options.set_looping(Looping::Count(3));
+        let mut png = Vec::new();
+        encode_apng(&test_frames(), &mut png, &options).unwrap();
+
+        let chunks = read_chunks(&png);
+        let types: Vec<&str> = chunks.iter().map(|(t, _)| t.as_str()).collect();
+        assert_eq!(
+            types,
+            vec!["IHDR", "acTL", "fcTL", "IDAT", "fcTL", "fdAT", "fcTL", "fdAT", "IEND"]
+        );
+        assert_eq!(&chunks[1].1, &[0, 0, 0, 3, 0, 0, 0, 3]);
+        // sequence numbers of fcTL and fdAT count up together
+        let sequence: Vec<u32> = chunks
+            .iter()
+            .filter(|(t, _)| t == "fcTL" || t == "fdAT")
+            .map(|(_, d)| u32::from_be_bytes([d[0], d[1], d[2], d[3]]))
+            .collect();
+        assert_eq!(sequence, vec![0, 1, 2, 3, 4]);
+        // delay 250/1000
+        assert_eq!(&chunks[2].1[20..24], &[0, 250, 3, 232]);
+
+        // regular decoders see the first frame
+        let image = image::load_from_memory(&png).unwrap().to_rgb();
+        assert_eq!(image.dimensions(), (4, 2));
+        assert_eq!(image.get_pixel(3, 1).0, [0, 255, 255]);
+    }
+
+    #[test]
+    fn test_encode_rejects_invalid_frames() {
+        let options = FrameSequenceOptions::new();
+        let mut out = Vec::new();
+        assert!(encode_gif(&[], &mut out, &options).is_err());
+        assert!(encode_apng(&[Canvas::new(2, 2), Canvas::new(3, 2)], &mut out, &options).is_err());
+
+        let mut options = FrameSequenceOptions::new();
+        options.set_max_colors(300);
+        assert!(encode_gif(&test_frames(), &mut out, &options).is_err());
+    }
+}
@@ -0,0 +1,61 @@
+use raytracer_lib_no_std::Heightfield;
+
+use crate::{Canvas, CanvasOps, RaytracerError, World, WorldOps};
+
+// builds a heightfield from the brightness of a grayscale (or color) image and adds its samples to the sample
+// buffer of the world. pixel column x is the sample x, pixel row y the sample z. the heightfield itself still
+// has to be added with World::add_shape
+pub fn heightfield_from_canvas(world: &mut World, canvas: &Canvas) -> Result<Heightfield, RaytracerError> {
+    let width = canvas.get_width();
+    let depth = canvas.get_height();
+    if width < 2 || depth < 2 {
+        return Err(RaytracerError::InvalidShape("heightfield size"));
+    }
+
+    let mut heights = Vec::with_capacity(width * depth);
+    for z in 0..depth {
+        for x in 0..width {
+            heights.push(luminance(canvas, x, z).max(0.0).min(1.0));
+        }
+    }
+    let first = world.add_samples(&heights);
+    Ok(Heightfield::try_new(world.get_samples(), first, width, depth)?)
+}
+
+fn luminance(canvas: &Canvas, x: usize, y: usize) -> f32 {
+    let c = &canvas.pixel_at(x, y).color;
+    0.2126 * c.r + 0.7152 * c.g + 0.0722 * c.b
+}
+
+#[cfg(test)]
+mod tests {
+    use raytracer_lib_no_std::{Color, ColorOps, Shape, ShapeEnum};
+
+    use math::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn test_heightfield_from_canvas() {
+        let mut c = Canvas::new(3, 2);
+        c.write_pixel(1, 0, Color::new(1.0, 1.0, 1.0));
+        c.write_pixel(2, 1, Color::new(0.0, 1.0, 0.0));
+        c.write_pixel(0, 1, Color::new(2.0, 2.0, 2.0));
+
+        let mut w = World::new();
+        w.add_samples(&[0.5]);
+        let h = heightfield_from_canvas(&mut w, &c).unwrap();
+        assert_eq!(h.get_first_sample(), 1);
+        assert_eq!(h.get_width(), 3);
+        assert_eq!(h.get_depth(), 2);
+        assert_eq!(w.get_samples().len(), 7);
+        assert_float(h.get_height(w.get_samples(), 0, 0), 0.0);
+        assert_float(h.get_height(w.get_samples(), 1, 0), 1.0);
+        assert_float(h.get_height(w.get_samples(), 2, 1), 0.7152);
+        // brighter than white is clamped
+        assert_float(h.get_height(w.get_samples(), 0, 1), 1.0);
+        assert!(w.add_shape(Shape::new(ShapeEnum::Heightfield(h))).is_ok());
+
+        assert!(heightfield_from_canvas(&mut w, &Canvas::new(1, 5)).is_err());
+    }
+}
@@ -7,10 +7,22 @@ extern crate math;
 pub use raytracer_lib_no_std;
 
 pub use self::canvas::*;
+pub use self::canvas_compare::*;
+pub use self::error::*;
+pub use self::heightfield::*;
+pub use self::mesh::*;
+pub use self::normal_map::*;
+pub use self::render_statistics::*;
 pub use self::scene::*;
 pub use self::world::*;
 
 pub mod canvas;
+pub mod canvas_compare;
+pub mod error;
+pub mod heightfield;
+pub mod mesh;
+pub mod normal_map;
+pub mod render_statistics;
 pub mod scene;
 pub mod world;
 
@@ -20,5 +32,17 @@ pub mod canvas_std;
 #[cfg(not(feature = "wasm"))]
 pub use self::canvas_std::*;
 
+#[cfg(not(feature = "wasm"))]
+pub mod frame_sequence_std;
+
+#[cfg(not(feature = "wasm"))]
+pub use self::frame_sequence_std::*;
+
+#[cfg(not(feature = "wasm"))]
+extern crate crc32fast;
+#[cfg(not(feature = "wasm"))]
+extern crate deflate;
+#[cfg(not(feature = "wasm"))]
+extern crate gif;
 #[cfg(not(feature = "wasm"))]
 extern crate image;
@@ -0,0 +1,573 @@
+use std::fs;
+use std::io::{Error, ErrorKind};
+
+use math::prelude::*;
+use raytracer_lib_no_std::{Color, ColorOps, Material, MaterialOps, Shape, ShapeEnum, ShapeOps, Triangle};
+
+use crate::{RaytracerError, World, WorldOps};
+
+// STL and PLY import and export. a mesh is read as a list of triangle shapes with the given material, a vertex
+// (or facet) color replaces the color of the material. the triangles have flat normals, vertex normals in the
+// files are ignored. add the shapes with WorldOps::add_shape or, to place the mesh several times, with
+// WorldOps::add_prototypes and instances
+
+const STL_HEADER_SIZE: usize = 80;
+const STL_TRIANGLE_SIZE: usize = 50;
+// VisCAM / SolidView: bit 15 marks a valid color, bits 10-14 red, 5-9 green, 0-4 blue
+const STL_COLOR_VALID: u16 = 0x8000;
+
+// loads a binary or ASCII STL file
+pub fn read_stl(filename: &str, material: &Material) -> Result<Vec<Shape>, RaytracerError> {
+    stl_from_bytes(&fs::read(filename)?, material)
+}
+
+// loads an ASCII or binary (little or big endian) PLY file
+pub fn read_ply(filename: &str, material: &Material) -> Result<Vec<Shape>, RaytracerError> {
+    ply_from_bytes(&fs::read(filename)?, material)
+}
+
+// writes the triangles of the world as binary STL, with the material colors as VisCAM facet colors
+pub fn write_stl(world: &World, filename: &str) -> Result<(), RaytracerError> {
+    fs::write(filename, world_to_stl(world))?;
+    Ok(())
+}
+
+// writes the triangles of the world as ASCII PLY with vertex colors
+pub fn write_ply(world: &World, filename: &str) -> Result<(), RaytracerError> {
+    fs::write(filename, world_to_ply(world))?;
+    Ok(())
+}
+
+pub fn stl_from_bytes(data: &[u8], material: &Material) -> Result<Vec<Shape>, RaytracerError> {
+    // an ASCII file may also start with "solid", the size of a binary file is exact
+    if data.len() >= STL_HEADER_SIZE + 4 {
+        let cnt = read_u32_le(data, STL_HEADER_SIZE) as usize;
+        if cnt.checked_mul(STL_TRIANGLE_SIZE).map(|s| s + STL_HEADER_SIZE + 4) == Some(data.len()) {
+            return Ok(binary_stl(data, cnt, material));
+        }
+    }
+    if data.starts_with(b"solid") {
+        return ascii_stl(data, material);
+    }
+    Err(invalid_data("not an STL file"))
+}
+
+fn binary_stl(data: &[u8], cnt: usize, material: &Material) -> Vec<Shape> {
+    let mut res = Vec::with_capacity(cnt);
+    for i in 0..cnt {
+        // skip the facet normal
+        let offset = STL_HEADER_SIZE + 4 + i * STL_TRIANGLE_SIZE + 12;
+        let point = |v: usize| {
+            let o = offset + v * 12;
+            Tuple4D::new_point(read_f32_le(data, o), read_f32_le(data, o + 4), read_f32_le(data, o + 8))
+        };
+        let attribute = u16::from_le_bytes([data[offset + 36], data[offset + 37]]);
+        let color = if attribute & STL_COLOR_VALID != 0 {
+            let channel = |shift: u16| ((attribute >> shift) & 0x1f) as f32 / 31.0;
+            Some(Color::new(channel(10), channel(5), channel(0)))
+        } else {
+            None
+        };
+        if let Some(s) = triangle_shape([point(0), point(1), point(2)], color, material) {
+            res.push(s);
+        }
+    }
+    res
+}
+
+fn ascii_stl(data: &[u8], material: &Material) -> Result<Vec<Shape>, RaytracerError> {
+    let text = std::str::from_utf8(data).map_err(|_| invalid_data("STL file is neither binary nor ASCII"))?;
+    let mut points = Vec::new();
+    let mut tokens = text.split_whitespace();
+    while let Some(token) = tokens.next() {
+        if token == "vertex" {
+            let mut coord = || -> Result<f32, RaytracerError> {
+                tokens
+                    .next()
+                    .and_then(|t| t.parse().ok())
+                    .ok_or_else(|| invalid_data("STL vertex"))
+            };
+            points.push(Tuple4D::new_point(coord()?, coord()?, coord()?));
+        }
+    }
+    if points.len() % 3 != 0 {
+        return Err(invalid_data("STL facet without 3 vertices"));
+    }
+    Ok(points
+        .chunks(3)
+        .filter_map(|p| triangle_shape([p[0], p[1], p[2]], None, material))
+        .collect())
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum PlyType {
+    Int8,
+    UInt8,
+    Int16,
+    UInt16,
+    Int32,
+    UInt32,
+    Float32,
+    Float64,
+}
+
+impl PlyType {
+    fn parse(name: &str) -> Option<PlyType> {
+        match name {
+            "char" | "int8" => Some(PlyType::Int8),
+            "uchar" | "uint8" => Some(PlyType::UInt8),
+            "short" | "int16" => Some(PlyType::Int16),
+            "ushort" | "uint16" => Some(PlyType::UInt16),
+            "int" | "int32" => Some(PlyType::Int32),
+            "uint" | "uint32" => Some(PlyType::UInt32),
+            "float" | "float32" => Some(PlyType::Float32),
+            "double" | "float64" => Some(PlyType::Float64),
+            _ => None,
+        }
+    }
+
+    fn size(self) -> usize {
+        match self {
+            PlyType::Int8 | PlyType::UInt8 => 1,
+            PlyType::Int16 | PlyType::UInt16 => 2,
+            PlyType::Int32 | PlyType::UInt32 | PlyType::Float32 => 4,
+            PlyType::Float64 => 8,
+        }
+    }
+
+    // integer colors use the full range of the type, float colors are in [0, 1]
+    fn color_scale(self) -> f64 {
+        match self {
+            PlyType::UInt16 => 65535.0,
+            PlyType::Float32 | PlyType::Float64 => 1.0,
+            _ => 255.0,
+        }
+    }
+}
+
+struct PlyProperty {
+    name: String,
+    value_type: PlyType,
+    // the type of the element count of a list property
+    count_type: Option<PlyType>,
+}
+
+struct PlyElement {
+    name: String,
+    count: usize,
+    properties: Vec<PlyProperty>,
+}
+
+enum PlyBody<'a> {
+    Ascii(std::str::SplitWhitespace<'a>),
+    Binary {
+        data: &'a [u8],
+        pos: usize,
+        little_endian: bool,
+    },
+}
+
+impl<'a> PlyBody<'a> {
+    fn read(&mut self, value_type: PlyType) -> Result<f64, RaytracerError> {
+        match self {
+            PlyBody::Ascii(tokens) => tokens
+                .next()
+                .and_then(|t| t.parse().ok())
+                .ok_or_else(|| invalid_data("PLY value")),
+            PlyBody::Binary {
+                data,
+                pos,
+                little_endian,
+            } => {
+                let size = value_type.size();
+                let bytes = data
+                    .get(*pos..*pos + size)
+                    .ok_or_else(|| invalid_data("PLY file truncated"))?;
+                *pos += size;
+                let mut b = [0u8; 8];
+                b[..size].copy_from_slice(bytes);
+                if !*little_endian {
+                    b[..size].reverse();
+                }
+                Ok(match value_type {
+                    PlyType::Int8 => b[0] as i8 as f64,
+                    PlyType::UInt8 => b[0] as f64,
+                    PlyType::Int16 => i16::from_le_bytes([b[0], b[1]]) as f64,
+                    PlyType::UInt16 => u16::from_le_bytes([b[0], b[1]]) as f64,
+                    PlyType::Int32 => i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+                    PlyType::UInt32 => u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+                    PlyType::Float32 => f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+                    PlyType::Float64 => f64::from_le_bytes(b),
+                })
+            }
+        }
+    }
+}
+
+pub fn ply_from_bytes(data: &[u8], material: &Material) -> Result<Vec<Shape>, RaytracerError> {
+    let (elements, mut body) = ply_header(data)?;
+
+    let mut vertices: Vec<(Tuple4D, Option<Color>)> = Vec::new();
+    let mut res = Vec::new();
+    let mut values = Vec::new();
+    for element in elements.iter() {
+        let idx = |name: &str| element.properties.iter().position(|p| p.name == name);
+        let (x, y, z) = (idx("x"), idx("y"), idx("z"));
+        let (red, green, blue) = (idx("red"), idx("green"), idx("blue"));
+        let indices = idx("vertex_indices").or_else(|| idx("vertex_index"));
+
+        for _ in 0..element.count {
+            values.clear();
+            let mut face = Vec::new();
+            for (i, p) in element.properties.iter().enumerate() {
+                match p.count_type {
+                    Some(count_type) => {
+                        let cnt = body.read(count_type)? as usize;
+                        for _ in 0..cnt {
+                            let v = body.read(p.value_type)?;
+                            if Some(i) == indices {
+                                face.push(v as usize);
+                            }
+                        }
+                        values.push(0.0);
+                    }
+                    None => values.push(body.read(p.value_type)?),
+                }
+            }
+
+            if element.name == "vertex" {
+                let coord = |i: Option<usize>| i.map(|i| values[i] as f32).ok_or_else(|| invalid_data("PLY vertex"));
+                let point = Tuple4D::new_point(coord(x)?, coord(y)?, coord(z)?);
+                let color = match (red, green, blue) {
+                    (Some(r), Some(g), Some(b)) => {
+                        let channel = |i: usize| (values[i] / element.properties[i].value_type.color_scale()) as f32;
+                        Some(Color::new(channel(r), channel(g), channel(b)))
+                    }
+                    _ => None,
+                };
+                vertices.push((point, color));
+            } else if element.name == "face" {
+                if face.iter().any(|&i| i >= vertices.len()) {
+                    return Err(invalid_data("PLY face vertex index"));
+                }
+                // polygons are split into a fan of triangles
+                for i in 1..face.len().saturating_sub(1) {
+                    let v = [&vertices[face[0]], &vertices[face[i]], &vertices[face[i + 1]]];
+                    let color = match (&v[0].1, &v[1].1, &v[2].1) {
+                        (Some(c1), Some(c2), Some(c3)) => Some(Color::new(
+                            (c1.r + c2.r + c3.r) / 3.0,
+                            (c1.g + c2.g + c3.g) / 3.0,
+                            (c1.b + c2.b + c3.b) / 3.0,
+                        )),
+                        _ => None,
+                    };
+                    let points = [v[0].0, v[1].0, v[2].0];
+                    if let Some(s) = triangle_shape(points, color, material) {
+                        res.push(s);
+                    }
+                }
+            }
+        }
+    }
+    Ok(res)
+}
+
+fn ply_header(data: &[u8]) -> Result<(Vec<PlyElement>, PlyBody<'_>), RaytracerError> {
+    const END_HEADER: &[u8] = b"end_header";
+    let end = data
+        .windows(END_HEADER.len())
+        .position(|w| w == END_HEADER)
+        .ok_or_else(|| invalid_data("PLY header without end_header"))?;
+    let body_start = data[end..]
+        .iter()
+        .position(|&b| b == b'\n')
+        .map(|p| end + p + 1)
+        .unwrap_or_else(|| data.len());
+    let header = std::str::from_utf8(&data[..end]).map_err(|_| invalid_data("PLY header"))?;
+
+    let mut lines = header.lines().map(|l| l.split_whitespace().collect::<Vec<_>>());
+    if lines.next().map(|l| l == ["ply"]) != Some(true) {
+        return Err(invalid_data("not a PLY file"));
+    }
+    let mut format = None;
+    let mut elements: Vec<PlyElement> = Vec::new();
+    for l in lines {
+        match l.as_slice() {
+            ["format", f, _] => format = Some(*f),
+            ["element", name, count] => elements.push(PlyElement {
+                name: name.to_string(),
+                count: count.parse().map_err(|_| invalid_data("PLY element count"))?,
+                properties: Vec::new(),
+            }),
+            ["property", "list", count_type, value_type, name] => {
+                let element = elements.last_mut().ok_or_else(|| invalid_data("PLY property"))?;
+                element.properties.push(PlyProperty {
+                    name: name.to_string(),
+                    value_type: PlyType::parse(value_type).ok_or_else(|| invalid_data("PLY property type"))?,
+                    count_type: Some(PlyType::parse(count_type).ok_or_else(|| invalid_data("PLY property type"))?),
+                });
+            }
+            ["property", value_type, name] => {
+                let element = elements.last_mut().ok_or_else(|| invalid_data("PLY property"))?;
+                element.properties.push(PlyProperty {
+                    name: name.to_string(),
+                    value_type: PlyType::parse(value_type).ok_or_else(|| invalid_data("PLY property type"))?,
+                    count_type: None,
+                });
+            }
+            _ => {}
+        }
+    }
+
+    let body = &data[body_start..];
+    let body = match format {
+        Some("ascii") => PlyBody::Ascii(
+            std::str::from_utf8(body)
+                .map_err(|_| invalid_data("PLY body"))?
+                .split_whitespace(),
+        ),
+        Some("binary_little_endian") => PlyBody::Binary {
+            data: body,
+            pos: 0,
+            little_endian: true,
+        },
+        Some("binary_big_endian") => PlyBody::Binary {
+            data: body,
+            pos: 0,
+            little_endian: false,
+        },
+        _ => return Err(invalid_data("PLY format")),
+    };
+    Ok((elements, body))
+}
+
+// binary STL
+pub fn world_to_stl(world: &World) -> Vec<u8> {
+    let triangles = world_triangles(world);
+    let mut res = Vec::with_capacity(STL_HEADER_SIZE + 4 + triangles.len() * STL_TRIANGLE_SIZE);
+    let mut header = [0u8; STL_HEADER_SIZE];
+    let name = b"raytracer export";
+    header[..name.len()].copy_from_slice(name);
+    res.extend_from_slice(&header);
+    res.extend_from_slice(&(triangles.len() as u32).to_le_bytes());
+
+    for (points, color) in triangles.iter() {
+        // STL facets are counter clockwise seen from the outside
+        let n = &(&points[1] - &points[0]) * &(&points[2] - &points[0]);
+        let n = if Tuple4D::magnitude(&n) > 0.0 {
+            Tuple4D::normalize(&n)
+        } else {
+            n
+        };
+        for v in [n.x, n.y, n.z].iter() {
+            res.extend_from_slice(&v.to_le_bytes());
+        }
+        for p in points.iter() {
+            for v in [p.x, p.y, p.z].iter() {
+                res.extend_from_slice(&v.to_le_bytes());
+            }
+        }
+        let channel = |c: f32| (c.max(0.0).min(1.0) * 31.0).round() as u16;
+        let attribute = STL_COLOR_VALID | channel(color.r) << 10 | channel(color.g) << 5 | channel(color.b);
+        res.extend_from_slice(&attribute.to_le_bytes());
+    }
+    res
+}
+
+// ASCII PLY, every triangle has its own 3 vertices
+pub fn world_to_ply(world: &World) -> Vec<u8> {
+    let triangles = world_triangles(world);
+    let mut res = String::new();
+    res.push_str("ply\nformat ascii 1.0\ncomment raytracer export\n");
+    res.push_str(&format!("element vertex {}\n", triangles.len() * 3));
+    res.push_str("property float x\nproperty float y\nproperty float z\n");
+    res.push_str("property uchar red\nproperty uchar green\nproperty uchar blue\n");
+    res.push_str(&format!("element face {}\n", triangles.len()));
+    res.push_str("property list uchar int vertex_indices\nend_header\n");
+
+    let channel = |c: f32| (c.max(0.0).min(1.0) * 255.0).round() as u8;
+    for (points, color) in triangles.iter() {
+        for p in points.iter() {
+            res.push_str(&format!(
+                "{} {} {} {} {} {}\n",
+                p.x,
+                p.y,
+                p.z,
+                channel(color.r),
+                channel(color.g),
+                channel(color.b)
+            ));
+        }
+    }
+    for i in 0..triangles.len() {
+        res.push_str(&format!("3 {} {} {}\n", i * 3, i * 3 + 1, i * 3 + 2));
+    }
+    res.into_bytes()
+}
+
+// the world space corners and the material color of all triangles, including the triangles of instances
+fn world_triangles(world: &World) -> Vec<([Tuple4D; 3], Color)> {
+    let mut res = Vec::new();
+    let shapes = world.get_shapes();
+    for s in shapes.iter().filter(|s| !s.get_is_prototype()) {
+        match s.get_shape() {
+            ShapeEnum::Triangle(ref t) => {
+                res.push((
+                    transform_triangle(t, s.get_transformation()),
+                    *s.get_material().get_color(),
+                ));
+            }
+            ShapeEnum::Instance(ref instance) => {
+                let first = instance.get_first_prototype();
+                let prototypes = shapes.get(first..first + instance.get_prototype_cnt()).unwrap_or(&[]);
+                for p in prototypes.iter() {
+                    if let ShapeEnum::Triangle(ref t) = p.get_shape() {
+                        let m = instance.get_transformation() * p.get_transformation();
+                        let material = if instance.get_override_material() {
+                            s.get_material()
+                        } else {
+                            p.get_material()
+                        };
+                        res.push((transform_triangle(t, &m), *material.get_color()));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    res
+}
+
+fn transform_triangle(t: &Triangle, m: &Matrix) -> [Tuple4D; 3] {
+    [m * t.get_p1(), m * t.get_p2(), m * t.get_p3()]
+}
+
+// degenerated triangles, which are common in scanned meshes, have no normal and are skipped
+fn triangle_shape(points: [Tuple4D; 3], color: Option<Color>, material: &Material) -> Option<Shape> {
+    let [p1, p2, p3] = points;
+    let n = &(&p2 - &p1) * &(&p3 - &p1);
+    let len = Tuple4D::magnitude(&n);
+    if len.is_nan() || len <= 0.0 {
+        return None;
+    }
+    let mut shape = Shape::new(ShapeEnum::Triangle(Triangle::new(p1, p2, p3)));
+    let mut m = material.clone();
+    if let Some(c) = color {
+        m.set_color(c);
+    }
+    shape.set_material(m);
+    Some(shape)
+}
+
+fn read_u32_le(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]])
+}
+
+fn read_f32_le(data: &[u8], offset: usize) -> f32 {
+    f32::from_bits(read_u32_le(data, offset))
+}
+
+fn invalid_data(msg: &str) -> RaytracerError {
+    RaytracerError::Io(Error::new(ErrorKind::InvalidData, msg))
+}
+
+#[cfg(test)]
+mod tests {
+    use raytracer_lib_no_std::Instance;
+
+    use super::*;
+
+    fn color_of(s: &Shape) -> &Color {
+        s.get_material().get_color()
+    }
+
+    #[test]
+    fn test_ascii_stl() {
+        let stl = "solid test\n\
+                   facet normal 0 0 1\n outer loop\n  vertex 0 0 0\n  vertex 1 0 0\n  vertex 0 1 0\n endloop\nendfacet\n\
+                   facet normal 0 0 1\n outer loop\n  vertex 0 0 0\n  vertex 1 0 0\n  vertex 2 0 0\n endloop\nendfacet\n\
+                   endsolid test\n";
+        let shapes = stl_from_bytes(stl.as_bytes(), &Material::new()).unwrap();
+        // the second facet is degenerated
+        assert_eq!(shapes.len(), 1);
+        match shapes[0].get_shape() {
+            ShapeEnum::Triangle(ref t) => assert_tuple(t.get_p2(), &Tuple4D::new_point(1.0, 0.0, 0.0)),
+            _ => panic!("expected a triangle"),
+        }
+
+        assert!(stl_from_bytes(b"solid x\n vertex 0 0 0\n", &Material::new()).is_err());
+        assert!(stl_from_bytes(b"no mesh", &Material::new()).is_err());
+    }
+
+    #[test]
+    fn test_stl_round_trip() {
+        let mut w = World::new();
+        let mut t = Shape::new(ShapeEnum::Triangle(Triangle::new(
+            Tuple4D::new_point(0.0, 0.0, 0.0),
+            Tuple4D::new_point(1.0, 0.0, 0.0),
+            Tuple4D::new_point(0.0, 1.0, 0.0),
+        )));
+        t.get_material_mut().set_color(Color::new(1.0, 0.0, 0.0));
+        w.add_prototypes(vec![t]).unwrap();
+        let mut instance = Shape::new(ShapeEnum::Instance(Instance::try_new(w.get_shapes(), 0, 1).unwrap()));
+        instance.set_transformation(Matrix::translation(0.0, 0.0, 5.0));
+        w.add_shape(instance).unwrap();
+
+        let data = world_to_stl(&w);
+        assert_eq!(data.len(), 84 + 50);
+        let shapes = stl_from_bytes(&data, &Material::new()).unwrap();
+        assert_eq!(shapes.len(), 1);
+        assert_eq!(color_of(&shapes[0]), &Color::new(1.0, 0.0, 0.0));
+        match shapes[0].get_shape() {
+            ShapeEnum::Triangle(ref t) => assert_tuple(t.get_p3(), &Tuple4D::new_point(0.0, 1.0, 5.0)),
+            _ => panic!("expected a triangle"),
+        }
+    }
+
+    #[test]
+    fn test_ply() {
+        let ply = "ply\nformat ascii 1.0\nelement vertex 4\nproperty float x\nproperty float y\nproperty float z\n\
+                   property float nx\nproperty float ny\nproperty float nz\n\
+                   property uchar red\nproperty uchar green\nproperty uchar blue\n\
+                   element face 1\nproperty list uchar int vertex_indices\nend_header\n\
+                   0 0 0 0 0 1 255 0 0\n1 0 0 0 0 1 0 255 0\n1 1 0 0 0 1 0 0 255\n0 1 0 0 0 1 0 0 255\n\
+                   4 0 1 2 3\n";
+        let shapes = ply_from_bytes(ply.as_bytes(), &Material::new()).unwrap();
+        assert_eq!(shapes.len(), 2);
+        assert_color(color_of(&shapes[0]), &Color::new(1.0 / 3.0, 1.0 / 3.0, 1.0 / 3.0));
+        assert_color(color_of(&shapes[1]), &Color::new(1.0 / 3.0, 0.0, 2.0 / 3.0));
+
+        // the same mesh as binary big endian, without colors
+        let mut data = b"ply\nformat binary_big_endian 1.0\nelement vertex 3\nproperty float x\nproperty float y\n\
+                         property float z\nelement face 1\nproperty list uchar ushort vertex_indices\nend_header\n"
+            .to_vec();
+        for v in [0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0].iter() {
+            data.extend_from_slice(&v.to_be_bytes());
+        }
+        data.push(3);
+        for i in [0u16, 1, 2].iter() {
+            data.extend_from_slice(&i.to_be_bytes());
+        }
+        let shapes = ply_from_bytes(&data, &Material::new()).unwrap();
+        assert_eq!(shapes.len(), 1);
+        assert_eq!(color_of(&shapes[0]), Material::new().get_color());
+
+        // round trip through the exporter
+        let mut w = World::new();
+        for s in ply_from_bytes(ply.as_bytes(), &Material::new()).unwrap() {
+            w.add_shape(s).unwrap();
+        }
+        let shapes = ply_from_bytes(&world_to_ply(&w), &Material::new()).unwrap();
+        assert_eq!(shapes.len(), 2);
+        assert_color(color_of(&shapes[1]), &Color::new(85.0 / 255.0, 0.0, 170.0 / 255.0));
+
+        data.truncate(data.len() - 1);
+        assert!(ply_from_bytes(&data, &Material::new()).is_err());
+    }
+
+    fn assert_color(actual: &Color, expected: &Color) {
+        assert_float(actual.r, expected.r);
+        assert_float(actual.g, expected.g);
+        assert_float(actual.b, expected.b);
+    }
+}
@@ -0,0 +1,58 @@
+use raytracer_lib_no_std::{NormalMap, UvMapping};
+
+use crate::{Canvas, CanvasOps, RaytracerError, World, WorldOps};
+
+// builds a normal map from a tangent space normal map image and adds its texels to the sample buffer of the
+// world. the colors encode the normals like the usual normal map images: (r, g, b) = (x, y, z) * 0.5 + 0.5
+pub fn normal_map_from_canvas(
+    world: &mut World,
+    canvas: &Canvas,
+    mapping: UvMapping,
+) -> Result<NormalMap, RaytracerError> {
+    let width = canvas.get_width();
+    let height = canvas.get_height();
+    if width == 0 || height == 0 {
+        return Err(RaytracerError::InvalidMaterial("normal map size"));
+    }
+
+    let mut texels = Vec::with_capacity(width * height * 3);
+    for y in 0..height {
+        for x in 0..width {
+            let c = &canvas.pixel_at(x, y).color;
+            for v in [c.r, c.g, c.b].iter() {
+                texels.push((v * 2.0 - 1.0).max(-1.0).min(1.0));
+            }
+        }
+    }
+    let first = world.add_samples(&texels);
+    Ok(NormalMap::try_new(world.get_samples(), first, width, height, mapping)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use raytracer_lib_no_std::{Color, ColorOps};
+
+    use super::*;
+
+    #[test]
+    fn test_normal_map_from_canvas() {
+        let mut c = Canvas::new(2, 1);
+        c.write_pixel(0, 0, Color::new(0.5, 0.5, 1.0));
+        c.write_pixel(1, 0, Color::new(1.0, 0.5, 0.5));
+
+        let mut w = World::new();
+        w.add_samples(&[0.5]);
+        let m = normal_map_from_canvas(&mut w, &c, UvMapping::Spherical).unwrap();
+        assert_eq!(m.get_first_texel(), 1);
+        assert_eq!(m.get_width(), 2);
+        assert_eq!(m.get_height(), 1);
+        assert_eq!(m.get_mapping(), UvMapping::Spherical);
+        assert_eq!(w.get_samples().len(), 7);
+        let n = m.normal_at_uv(w.get_samples(), 0.25, 0.5);
+        assert!(n.z > 0.99);
+        let n = m.normal_at_uv(w.get_samples(), 0.75, 0.5);
+        assert!(n.x > 0.99);
+
+        assert!(normal_map_from_canvas(&mut w, &Canvas::new(0, 3), UvMapping::Planar).is_err());
+    }
+}
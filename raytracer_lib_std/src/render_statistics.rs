@@ -0,0 +1,109 @@
+use core::fmt;
+use std::time::Duration;
+
+#[cfg(feature = "use_serde")]
+use serde::{Deserialize, Serialize};
+
+use raytracer_lib_no_std::{RenderStats, RenderStatsOps, SHAPE_TYPE_NAMES};
+
+// the merged counters of all kernel invocations and the time spent on each tile
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+pub struct RenderStatistics {
+    counters: RenderStats,
+    tile_durations: Vec<Duration>,
+}
+
+pub trait RenderStatisticsOps {
+    fn new() -> RenderStatistics;
+
+    // adds the counters and the duration of one tile, tiles are numbered in the order they are added
+    fn add_tile(&mut self, counters: &RenderStats, duration: Duration);
+
+    fn get_counters(&self) -> &RenderStats;
+    fn get_tile_durations(&self) -> &Vec<Duration>;
+    // index and duration of the tile, which took the longest
+    fn get_slowest_tile(&self) -> Option<(usize, Duration)>;
+}
+
+impl RenderStatisticsOps for RenderStatistics {
+    fn new() -> RenderStatistics {
+        RenderStatistics {
+            counters: RenderStats::new(),
+            tile_durations: Vec::new(),
+        }
+    }
+
+    fn add_tile(&mut self, counters: &RenderStats, duration: Duration) {
+        self.counters.merge(counters);
+        self.tile_durations.push(duration);
+    }
+
+    fn get_counters(&self) -> &RenderStats {
+        &self.counters
+    }
+
+    fn get_tile_durations(&self) -> &Vec<Duration> {
+        &self.tile_durations
+    }
+
+    fn get_slowest_tile(&self) -> Option<(usize, Duration)> {
+        self.tile_durations.iter().cloned().enumerate().max_by_key(|(_, d)| *d)
+    }
+}
+
+impl fmt::Display for RenderStatistics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let c = &self.counters;
+        writeln!(f, "primary rays:       {}", c.get_primary_rays())?;
+        writeln!(f, "shadow rays:        {}", c.get_shadow_rays())?;
+        writeln!(f, "reflection rays:    {}", c.get_reflection_rays())?;
+        writeln!(f, "refraction rays:    {}", c.get_refraction_rays())?;
+        writeln!(f, "intersection tests: {}", c.get_total_intersection_tests())?;
+        for (name, cnt) in SHAPE_TYPE_NAMES.iter().zip(c.get_intersection_tests().iter()) {
+            if *cnt > 0 {
+                writeln!(f, "    {:<15} {}", name, cnt)?;
+            }
+        }
+        writeln!(f, "max depth reached:  {}", c.get_max_depth_reached())?;
+        writeln!(f, "list overflows:     {}", c.get_intersection_overflows())?;
+        match self.get_slowest_tile() {
+            Some((idx, d)) => write!(
+                f,
+                "tiles:              {}, slowest: #{} {:?}",
+                self.tile_durations.len(),
+                idx,
+                d
+            ),
+            None => write!(f, "tiles:              0"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_statistics_add_tile() {
+        let mut a = RenderStats::new();
+        a.count_primary_ray();
+        a.count_shadow_ray();
+        a.record_depth(1);
+        let mut b = RenderStats::new();
+        b.count_primary_ray();
+        b.record_depth(3);
+
+        let mut s = RenderStatistics::new();
+        assert_eq!(s.get_slowest_tile(), None);
+        s.add_tile(&a, Duration::from_millis(5));
+        s.add_tile(&b, Duration::from_millis(9));
+        s.add_tile(&a, Duration::from_millis(2));
+
+        assert_eq!(s.get_counters().get_primary_rays(), 3);
+        assert_eq!(s.get_counters().get_shadow_rays(), 2);
+        assert_eq!(s.get_counters().get_max_depth_reached(), 3);
+        assert_eq!(s.get_tile_durations().len(), 3);
+        assert_eq!(s.get_slowest_tile(), Some((1, Duration::from_millis(9))));
+    }
+}
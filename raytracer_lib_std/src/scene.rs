@@ -1,9 +1,22 @@
+use std::f32::consts::PI;
+use std::time::Duration;
+
 #[cfg(feature = "use_serde")]
 use serde::{Deserialize, Serialize};
+#[cfg(feature = "use_serde")]
+use std::fs::File;
+#[cfg(feature = "use_serde")]
+use std::io::{BufReader, BufWriter, Read, Write};
 
-use crate::{Canvas, World};
-use raytracer_lib_no_std::Camera;
-use std::time::Duration;
+use crate::{Canvas, RaytracerError, RenderStatistics, World, WorldOps};
+use math::prelude::*;
+use raytracer_lib_no_std::{Camera, CameraOps, Light, LightOps, MaterialOps, ShapeEnum, ShapeOps};
+
+// magic bytes and version at the start of a binary scene file
+#[cfg(feature = "use_serde")]
+const SCENE_BINARY_MAGIC: [u8; 4] = *b"RTSC";
+#[cfg(feature = "use_serde")]
+const SCENE_BINARY_VERSION: u8 = 1;
 
 #[derive(Clone, Debug)]
 #[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
@@ -17,6 +30,10 @@ pub struct Scene {
 pub struct RenderedScene {
     canvas: Canvas,
     duration: Duration,
+    // only collected, if RenderSettings::statistics is set and the backend supports it
+    statistics: Option<RenderStatistics>,
+    // intersections dropped because of a full intersection list, counted independent of the statistics
+    intersection_overflows: u64,
 }
 
 pub trait SceneOps {
@@ -25,6 +42,10 @@ pub trait SceneOps {
     fn get_world(&self) -> &World;
     fn get_world_mut(&mut self) -> &mut World;
     fn get_camera(&self) -> &Camera;
+    fn get_camera_mut(&mut self) -> &mut Camera;
+
+    // checks the scene and recomputes the derived data (inverse transformations, camera pixel size)
+    fn validate(&mut self) -> Result<(), RaytracerError>;
 }
 
 impl SceneOps for Scene {
@@ -42,25 +63,351 @@ impl SceneOps for Scene {
     fn get_camera(&self) -> &Camera {
         &self.camera
     }
+    fn get_camera_mut(&mut self) -> &mut Camera {
+        &mut self.camera
+    }
+
+    fn validate(&mut self) -> Result<(), RaytracerError> {
+        let c = &mut self.camera;
+        if c.get_hsize() == 0 || c.get_vsize() == 0 {
+            return Err(RaytracerError::InvalidScene(format!(
+                "camera size must not be 0, got {}x{}",
+                c.get_hsize(),
+                c.get_vsize()
+            )));
+        }
+        if !(c.get_field_of_view() > 0.0 && c.get_field_of_view() < PI) {
+            return Err(RaytracerError::InvalidScene(format!(
+                "field of view of the camera must be between 0 and PI, got {}",
+                c.get_field_of_view()
+            )));
+        }
+        if c.get_antialiasing() && c.get_antialiasing_size() != 2 && c.get_antialiasing_size() != 3 {
+            return Err(RaytracerError::InvalidScene(format!(
+                "antialiasing size of the camera must be 2 or 3, got {}",
+                c.get_antialiasing_size()
+            )));
+        }
+        if Matrix::invert(c.get_transform()).is_none() {
+            return Err(not_invertible("camera"));
+        }
+        c.calc_pixel_size();
+
+        if let Light::AreaLight(ref al) = self.world.get_light() {
+            if al.get_samples() == 0 {
+                return Err(RaytracerError::InvalidScene(format!(
+                    "area light needs at least 1 step in u and v, got {}x{}",
+                    al.get_usteps(),
+                    al.get_vsteps()
+                )));
+            }
+        }
+
+        for (idx, shape) in self.world.get_shapes_mut().iter_mut().enumerate() {
+            // recomputes the inverse, which isn't serialized
+            let m = shape.get_transformation().clone();
+            shape
+                .try_set_transformation(m)
+                .map_err(|_| not_invertible(&format!("shape {}", idx)))?;
+
+            if let Some(mut p) = shape.get_material().get_pattern().clone() {
+                let m = p.get_transformation().clone();
+                p.try_set_transformation(m)
+                    .map_err(|_| not_invertible(&format!("pattern of shape {}", idx)))?;
+                shape.get_material_mut().set_pattern(p);
+            }
+
+            if let Err(e) = shape.get_material().validate() {
+                return Err(RaytracerError::InvalidScene(format!("shape {}: {}", idx, e)));
+            }
+
+            if let ShapeEnum::Sdf(ref sdf) = shape.get_shape() {
+                sdf.validate()
+                    .map_err(|e| RaytracerError::InvalidScene(format!("shape {}: {}", idx, e)))?;
+            }
+        }
+
+        // the samples and the prototypes are only complete after the loading, so they are checked afterwards
+        let shapes = self.world.get_shapes();
+        for (idx, shape) in shapes.iter().enumerate() {
+            let mut valid = shape.validate_samples(self.world.get_samples());
+            if let ShapeEnum::Instance(ref instance) = shape.get_shape() {
+                valid = valid.and(instance.validate(shapes));
+            }
+            valid.map_err(|e| RaytracerError::InvalidScene(format!("shape {}: {}", idx, e)))?;
+        }
+        Ok(())
+    }
+}
+
+fn not_invertible(what: &str) -> RaytracerError {
+    RaytracerError::InvalidScene(format!("transformation matrix of {} is not invertible", what))
+}
+
+#[cfg(feature = "use_serde")]
+impl Scene {
+    pub fn save_json(&self, filename: &str) -> Result<(), RaytracerError> {
+        let file = File::create(filename)?;
+        self.write_json(BufWriter::new(file))
+    }
+
+    pub fn load_json(filename: &str) -> Result<Scene, RaytracerError> {
+        let file = File::open(filename)?;
+        Scene::read_json(BufReader::new(file))
+    }
+
+    pub fn write_json<W: Write>(&self, writer: W) -> Result<(), RaytracerError> {
+        serde_json::to_writer_pretty(writer, self)?;
+        Ok(())
+    }
+
+    pub fn read_json<R: Read>(reader: R) -> Result<Scene, RaytracerError> {
+        let mut scene: Scene = serde_json::from_reader(reader)?;
+        scene.validate()?;
+        Ok(scene)
+    }
+
+    pub fn save_binary(&self, filename: &str) -> Result<(), RaytracerError> {
+        let file = File::create(filename)?;
+        let mut writer = BufWriter::new(file);
+        self.write_binary(&mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    pub fn load_binary(filename: &str) -> Result<Scene, RaytracerError> {
+        let file = File::open(filename)?;
+        Scene::read_binary(BufReader::new(file))
+    }
+
+    pub fn write_binary<W: Write>(&self, mut writer: W) -> Result<(), RaytracerError> {
+        writer.write_all(&SCENE_BINARY_MAGIC)?;
+        writer.write_all(&[SCENE_BINARY_VERSION])?;
+        bincode::serialize_into(writer, self)?;
+        Ok(())
+    }
+
+    pub fn read_binary<R: Read>(mut reader: R) -> Result<Scene, RaytracerError> {
+        let mut header = [0u8; 5];
+        reader.read_exact(&mut header)?;
+        if header[0..4] != SCENE_BINARY_MAGIC {
+            return Err(RaytracerError::InvalidScene("not a binary scene file".to_string()));
+        }
+        if header[4] != SCENE_BINARY_VERSION {
+            return Err(RaytracerError::InvalidScene(format!(
+                "unsupported binary scene version {}, expected {}",
+                header[4], SCENE_BINARY_VERSION
+            )));
+        }
+        let mut scene: Scene = bincode::deserialize_from(reader)?;
+        scene.validate()?;
+        Ok(scene)
+    }
 }
 
 pub trait RenderedSceneOps {
     fn new(canvas: Canvas, duration: Duration) -> RenderedScene;
 
     fn get_canvas(&self) -> &Canvas;
+    fn into_canvas(self) -> Canvas;
     fn get_duration(&self) -> &Duration;
+
+    fn get_statistics(&self) -> Option<&RenderStatistics>;
+    fn set_statistics(&mut self, statistics: Option<RenderStatistics>);
+
+    fn get_intersection_overflows(&self) -> u64;
+    fn set_intersection_overflows(&mut self, cnt: u64);
 }
 
 impl RenderedSceneOps for RenderedScene {
     fn new(canvas: Canvas, duration: Duration) -> RenderedScene {
-        RenderedScene { canvas, duration }
+        RenderedScene {
+            canvas,
+            duration,
+            statistics: None,
+            intersection_overflows: 0,
+        }
     }
 
     fn get_canvas(&self) -> &Canvas {
         &self.canvas
     }
 
+    fn into_canvas(self) -> Canvas {
+        self.canvas
+    }
+
     fn get_duration(&self) -> &Duration {
         &self.duration
     }
+
+    fn get_statistics(&self) -> Option<&RenderStatistics> {
+        self.statistics.as_ref()
+    }
+
+    fn set_statistics(&mut self, statistics: Option<RenderStatistics>) {
+        self.statistics = statistics;
+    }
+
+    fn get_intersection_overflows(&self) -> u64 {
+        self.intersection_overflows
+    }
+
+    fn set_intersection_overflows(&mut self, cnt: u64) {
+        self.intersection_overflows = cnt;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use raytracer_lib_no_std::{
+        Checker3DPattern, Color, ColorOps, Cube, Pattern, PointLight, Shape, ShapeEnum, Sphere,
+    };
+
+    use super::*;
+
+    fn test_scene() -> Scene {
+        let mut sphere = Sphere::new();
+        sphere.set_transformation(&Matrix::translation(1.0, 2.0, 3.0) * &Matrix::scale(0.5, 0.5, 0.5));
+
+        let mut checker = Checker3DPattern::new();
+        checker.set_color_b(Color::new(0.2, 0.3, 0.4));
+        checker.set_transformation(Matrix::scale(2.0, 2.0, 2.0));
+        let mut cube = Cube::new();
+        cube.set_transformation(Matrix::rotate_y(PI / 3.0));
+        cube.get_material_mut().set_pattern(Pattern::Checker3DPattern(checker));
+
+        let mut w = World::new();
+        w.set_light(Light::PointLight(PointLight::new(
+            Tuple4D::new_point(-1.0, 10.0, -10.0),
+            Color::new(1.0, 1.0, 1.0),
+        )));
+        w.add_shape(Shape::new(ShapeEnum::Sphere(sphere))).unwrap();
+        w.add_shape(Shape::new(ShapeEnum::Cube(cube))).unwrap();
+
+        let mut c = Camera::new(40, 30, PI / 3.0);
+        c.calc_pixel_size();
+        c.set_transformation(Matrix::view_transform(
+            &Tuple4D::new_point(0.0, 1.5, -5.0),
+            &Tuple4D::new_point(0.0, 1.0, 0.0),
+            &Tuple4D::new_vector(0.0, 1.0, 0.0),
+        ));
+        Scene::new(w, c)
+    }
+
+    #[cfg(feature = "use_serde")]
+    fn assert_same_scene(actual: &Scene, expected: &Scene) {
+        assert_float(
+            actual.get_camera().get_pixel_size(),
+            expected.get_camera().get_pixel_size(),
+        );
+        assert_float(
+            actual.get_camera().get_half_width(),
+            expected.get_camera().get_half_width(),
+        );
+        assert_float(
+            actual.get_camera().get_half_height(),
+            expected.get_camera().get_half_height(),
+        );
+        assert_matrix(
+            actual.get_camera().get_transform(),
+            expected.get_camera().get_transform(),
+        );
+
+        let actual_shapes = actual.get_world().get_shapes();
+        let expected_shapes = expected.get_world().get_shapes();
+        assert_eq!(actual_shapes.len(), expected_shapes.len());
+        for (a, e) in actual_shapes.iter().zip(expected_shapes.iter()) {
+            assert_matrix(a.get_transformation(), e.get_transformation());
+            assert_matrix(a.get_inverse_transformation(), e.get_inverse_transformation());
+        }
+        let a = actual_shapes[1].get_material().get_pattern().as_ref().unwrap();
+        let e = expected_shapes[1].get_material().get_pattern().as_ref().unwrap();
+        assert_matrix(a.get_inverse_transformation(), e.get_inverse_transformation());
+    }
+
+    #[test]
+    fn test_scene_validate_rejects_invalid_camera() {
+        let mut scene = test_scene();
+        assert!(scene.validate().is_ok());
+
+        let c = Camera::new(0, 30, PI / 3.0);
+        let mut scene = Scene::new(scene.get_world().clone(), c);
+        assert!(scene.validate().is_err());
+
+        let c = Camera::new(40, 30, PI);
+        let mut scene = Scene::new(scene.get_world().clone(), c);
+        assert!(scene.validate().is_err());
+    }
+
+    #[cfg(feature = "use_serde")]
+    #[test]
+    fn test_scene_load_rejects_non_invertible_transformation() {
+        let mut w = World::new();
+        w.add_shape(Shape::new(ShapeEnum::Sphere(Sphere::new()))).unwrap();
+        let scene = Scene::new(w, Camera::new(40, 30, PI / 3.0));
+
+        let mut json = serde_json::to_value(&scene).unwrap();
+        json["world"]["shapes"][0]["shape"]["Sphere"]["transformation_matrix"]["m"][0] = serde_json::json!(0.0);
+
+        match Scene::read_json(json.to_string().as_bytes()) {
+            Err(RaytracerError::InvalidScene(msg)) => assert!(msg.contains("not invertible")),
+            r => panic!("expected InvalidScene, got {:?}", r),
+        }
+    }
+
+    #[cfg(feature = "use_serde")]
+    #[test]
+    fn test_scene_load_rejects_invalid_material() {
+        let scene = test_scene();
+
+        let mut json = serde_json::to_value(&scene).unwrap();
+        json["world"]["shapes"][1]["shape"]["Cube"]["material"]["transparency"] = serde_json::json!(2.0);
+
+        match Scene::read_json(json.to_string().as_bytes()) {
+            Err(RaytracerError::InvalidScene(msg)) => assert!(msg.contains("shape 1") && msg.contains("transparency")),
+            r => panic!("expected InvalidScene, got {:?}", r),
+        }
+    }
+
+    #[cfg(feature = "use_serde")]
+    #[test]
+    fn test_scene_json_round_trip() {
+        let scene = test_scene();
+        let mut buf = Vec::new();
+        scene.write_json(&mut buf).unwrap();
+
+        let json = String::from_utf8(buf.clone()).unwrap();
+        assert!(!json.contains("inverse_transformation_matrix"));
+        assert!(!json.contains("pixel_size"));
+
+        let loaded = Scene::read_json(&buf[..]).unwrap();
+        assert_same_scene(&loaded, &scene);
+    }
+
+    #[cfg(feature = "use_serde")]
+    #[test]
+    fn test_scene_binary_round_trip() {
+        let scene = test_scene();
+        let mut buf = Vec::new();
+        scene.write_binary(&mut buf).unwrap();
+
+        let loaded = Scene::read_binary(&buf[..]).unwrap();
+        assert_same_scene(&loaded, &scene);
+
+        buf[0] = b'X';
+        assert!(Scene::read_binary(&buf[..]).is_err());
+    }
+
+    #[cfg(feature = "use_serde")]
+    #[test]
+    fn test_scene_save_load_file() {
+        let scene = test_scene();
+        let filename = std::env::temp_dir().join("raytracer_test_scene_save_load.json");
+        let filename = filename.to_str().unwrap();
+        scene.save_json(filename).unwrap();
+        let loaded = Scene::load_json(filename).unwrap();
+        std::fs::remove_file(filename).unwrap();
+        assert_same_scene(&loaded, &scene);
+    }
 }
@@ -4,13 +4,20 @@
 use serde::{Deserialize, Serialize};
 
 use math::prelude::*;
-use raytracer_lib_no_std::{Color, ColorOps, Light, PointLight, Shape};
+use raytracer_lib_no_std::{
+    BoundingBox, BoundingBoxOps, Color, ColorOps, Light, PointLight, Shape, ShapeEnum, ShapeIdx, ShapeOps,
+};
+
+use crate::RaytracerError;
 
 #[derive(Clone, Debug)]
 #[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
 pub struct World {
     shapes: Vec<Shape>,
     light: Light,
+    // the heights of the heightfields and the texels of the normal maps, referenced by offset and count
+    #[cfg_attr(feature = "use_serde", serde(default))]
+    samples: Vec<f32>,
 }
 
 pub trait WorldOps {
@@ -18,9 +25,21 @@ pub trait WorldOps {
     fn set_light(&mut self, light: Light);
     fn get_light(&self) -> &Light;
 
-    fn add_shape(&mut self, shape: Shape);
+    // rejects shapes with a non invertible transformation or an invalid material
+    fn add_shape(&mut self, shape: Shape) -> Result<(), RaytracerError>;
+    // adds shapes which are only rendered through instances, e.g. the triangles of a mesh. returns the
+    // index of the first one for Instance::try_new
+    fn add_prototypes(&mut self, shapes: Vec<Shape>) -> Result<ShapeIdx, RaytracerError>;
     fn get_shapes(&self) -> &Vec<Shape>;
     fn get_shapes_mut(&mut self) -> &mut Vec<Shape>;
+
+    // appends samples to the sample buffer and returns the index of the first one, e.g. for Heightfield::try_new
+    // or NormalMap::try_new
+    fn add_samples(&mut self, samples: &[f32]) -> usize;
+    fn get_samples(&self) -> &Vec<f32>;
+
+    // world space bounds of all shapes, infinite if the world contains a plane
+    fn bounds(&self) -> BoundingBox;
 }
 
 impl WorldOps for World {
@@ -30,6 +49,7 @@ impl WorldOps for World {
         World {
             shapes: Vec::new(),
             light: Light::PointLight(pl),
+            samples: Vec::new(),
         }
     }
 
@@ -41,8 +61,27 @@ impl WorldOps for World {
         &self.light
     }
 
-    fn add_shape(&mut self, shape: Shape) {
+    fn add_shape(&mut self, shape: Shape) -> Result<(), RaytracerError> {
+        shape.validate()?;
+        shape.validate_samples(&self.samples)?;
+        if let ShapeEnum::Instance(ref instance) = shape.get_shape() {
+            instance.validate(&self.shapes)?;
+        }
         self.shapes.push(shape);
+        Ok(())
+    }
+
+    fn add_prototypes(&mut self, shapes: Vec<Shape>) -> Result<ShapeIdx, RaytracerError> {
+        for s in shapes.iter() {
+            s.validate()?;
+            s.validate_samples(&self.samples)?;
+        }
+        let first = self.shapes.len();
+        for mut s in shapes {
+            s.set_is_prototype(true);
+            self.shapes.push(s);
+        }
+        Ok(first)
     }
 
     fn get_shapes(&self) -> &Vec<Shape> {
@@ -52,4 +91,107 @@ impl WorldOps for World {
     fn get_shapes_mut(&mut self) -> &mut Vec<Shape> {
         &mut self.shapes
     }
+
+    fn add_samples(&mut self, samples: &[f32]) -> usize {
+        let first = self.samples.len();
+        self.samples.extend_from_slice(samples);
+        first
+    }
+
+    fn get_samples(&self) -> &Vec<f32> {
+        &self.samples
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        let mut res = BoundingBox::empty();
+        for s in self.shapes.iter().filter(|s| !s.get_is_prototype()) {
+            res.merge(&s.bounds());
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use raytracer_lib_no_std::{Cube, Heightfield, Instance, MaterialOps, Plane, Sphere};
+
+    use super::*;
+
+    #[test]
+    fn test_add_shape_rejects_invalid_shapes() {
+        let mut w = World::new();
+        assert!(w.add_shape(Shape::new(ShapeEnum::Sphere(Sphere::new()))).is_ok());
+
+        let mut s = Shape::new(ShapeEnum::Sphere(Sphere::new()));
+        s.get_material_mut().set_reflective(-0.5);
+        match w.add_shape(s) {
+            Err(RaytracerError::InvalidMaterial("reflective")) => {}
+            r => panic!("expected InvalidMaterial, got {:?}", r),
+        }
+        assert_eq!(w.get_shapes().len(), 1);
+    }
+
+    #[test]
+    fn test_world_bounds() {
+        let mut w = World::new();
+        assert!(w.bounds().is_empty());
+
+        let mut s = Shape::new(ShapeEnum::Sphere(Sphere::new()));
+        s.set_transformation(Matrix::translation(-3.0, 0.0, 0.0));
+        w.add_shape(s).unwrap();
+        let mut c = Shape::new(ShapeEnum::Cube(Cube::new()));
+        c.set_transformation(&Matrix::translation(2.0, 1.0, 0.5) * &Matrix::scale(0.5, 2.0, 0.5));
+        w.add_shape(c).unwrap();
+
+        assert_eq!(w.bounds().get_min(), &Tuple4D::new_point(-4.0, -1.0, -1.0));
+        assert_eq!(w.bounds().get_max(), &Tuple4D::new_point(2.5, 3.0, 1.0));
+        assert!(!w.bounds().is_infinite());
+
+        w.add_shape(Shape::new(ShapeEnum::Plane(Plane::new()))).unwrap();
+        assert!(w.bounds().is_infinite());
+    }
+
+    #[test]
+    fn test_world_instances() {
+        let mut w = World::new();
+        let mut s = Shape::new(ShapeEnum::Sphere(Sphere::new()));
+        s.set_transformation(Matrix::translation(0.0, 1.0, 0.0));
+        let first = w.add_prototypes(vec![s]).unwrap();
+
+        for x in 0..3 {
+            let mut i = Instance::try_new(w.get_shapes(), first, 1).unwrap();
+            i.set_transformation(Matrix::translation(x as f32 * 10.0, 0.0, 0.0));
+            w.add_shape(Shape::new(ShapeEnum::Instance(i))).unwrap();
+        }
+        assert_eq!(w.get_shapes().len(), 4);
+        // the prototype itself isn't rendered
+        assert_eq!(w.bounds().get_min(), &Tuple4D::new_point(-1.0, 0.0, -1.0));
+        assert_eq!(w.bounds().get_max(), &Tuple4D::new_point(21.0, 2.0, 1.0));
+
+        let mut other = World::new();
+        let i = Instance::try_new(w.get_shapes(), first, 1).unwrap();
+        match other.add_shape(Shape::new(ShapeEnum::Instance(i))) {
+            Err(RaytracerError::InvalidShape("instance prototypes")) => {}
+            r => panic!("expected InvalidShape, got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_world_samples() {
+        let mut w = World::new();
+        assert_eq!(w.add_samples(&[0.5]), 0);
+        let first = w.add_samples(&[0.0, 1.0, 0.0, 1.0]);
+        assert_eq!(first, 1);
+        assert_eq!(w.get_samples().len(), 5);
+
+        let h = Heightfield::try_new(w.get_samples(), first, 2, 2).unwrap();
+        w.add_shape(Shape::new(ShapeEnum::Heightfield(h.clone()))).unwrap();
+
+        // the samples are only in the sample buffer of w
+        let mut other = World::new();
+        match other.add_shape(Shape::new(ShapeEnum::Heightfield(h))) {
+            Err(RaytracerError::InvalidShape("heightfield samples")) => {}
+            r => panic!("expected InvalidShape, got {:?}", r),
+        }
+    }
 }